@@ -41,6 +41,7 @@ extern "C" {
 #include "version.h"
 
 typedef struct Dav1dContext Dav1dContext;
+typedef struct Dav1dStateSnapshot Dav1dStateSnapshot;
 typedef struct Dav1dRef Dav1dRef;
 
 #define DAV1D_MAX_THREADS 256
@@ -58,6 +59,122 @@ typedef struct Dav1dLogger {
     void (*callback)(void *cookie, const char *format, va_list ap);
 } Dav1dLogger;
 
+typedef struct Dav1dRowProgress {
+    void *cookie; ///< Custom data to pass to the callback.
+    /**
+     * Sub-frame progress callback. May be NULL to disable notifications.
+     *
+     * Called while a frame that will be output is being decoded, each time
+     * more rows of its picture have been fully reconstructed and
+     * post-filtered. Rows are those before film grain application. May be
+     * called from any decoder thread, and must not call into the decoder.
+     *
+     * @param cookie Custom pointer passed to all calls.
+     * @param    pic The picture being decoded, only valid during the call.
+     *               It holds no references, so it can't be referenced.
+     * @param   rows Number of luma rows, from the top of pic, that are final.
+     */
+    void (*callback)(void *cookie, const Dav1dPicture *pic, int rows);
+} Dav1dRowProgress;
+
+enum Dav1dSyntaxSource {
+    DAV1D_SYNTAX_HEADER = 0, ///< bits read from an OBU header
+    DAV1D_SYNTAX_LABEL  = 1, ///< name of the header bits read since the previous label
+    DAV1D_SYNTAX_TILE   = 2, ///< symbol decoded from tile data
+};
+
+typedef struct Dav1dSyntaxElement {
+    enum Dav1dSyntaxSource source;
+    /**
+     * For tile symbols, the name of the syntax element, as in the AV1
+     * specification where it has one (e.g. "is_inter"). Only valid during
+     * the callback.
+     */
+    const char *name;
+    int64_t value;
+    int n_bits; ///< number of bits read for header elements, 0 otherwise
+    int64_t bit_pos; ///< bit position in the OBU for header elements and labels, -1 otherwise
+    int tile; ///< tile index in raster order, -1 for header elements and labels
+    int sbx, sby; ///< superblock position, -1 for header elements and labels
+    int bx, by; ///< block position in 4px units, -1 for header elements and labels
+} Dav1dSyntaxElement;
+
+typedef struct Dav1dSyntaxTrace {
+    void *cookie; ///< Custom data to pass to the callback.
+    /**
+     * Syntax trace callback. May be NULL to disable the trace.
+     *
+     * Called for every header bit field and every tile symbol read from the
+     * bitstream, in bitstream order within each OBU and each tile. Tiles and
+     * frames may be decoded concurrently by different threads, in which case
+     * calls for them are interleaved; use a single thread for a trace in
+     * bitstream order. Must not call into the decoder.
+     *
+     * Only available if the library was built with the syntax_trace
+     * feature, otherwise dav1d_open() fails with DAV1D_ERR(ENOPROTOOPT)
+     * if this is set.
+     *
+     * @param  cookie Custom pointer passed to all calls.
+     * @param element The decoded element, only valid during the call.
+     */
+    void (*callback)(void *cookie, const Dav1dSyntaxElement *element);
+} Dav1dSyntaxTrace;
+
+enum Dav1dReconStage {
+    DAV1D_RECON_STAGE_PREFILTER   = 0, ///< after reconstruction, before any in-loop filter
+    DAV1D_RECON_STAGE_DEBLOCK     = 1, ///< after the deblocking filter
+    DAV1D_RECON_STAGE_CDEF        = 2, ///< after CDEF
+    DAV1D_RECON_STAGE_SUPERRES    = 3, ///< after super-resolution upscaling
+    DAV1D_RECON_STAGE_RESTORATION = 4, ///< after loop restoration
+};
+
+enum Dav1dExportFlags {
+    DAV1D_EXPORT_STATS         = 1 << 0, ///< attach Dav1dFrameStats to each output picture
+    DAV1D_EXPORT_CDF           = 1 << 1, ///< attach the Dav1dCdfContext at the end of the frame
+    DAV1D_EXPORT_PRED_RESIDUAL = 1 << 2, ///< keep the prediction and residual of every frame, see
+                                         ///< dav1d_picture_get_prediction()
+    DAV1D_EXPORT_BLOCK_INFO    = 1 << 3, ///< attach the Dav1dBlockInfoMap of the frame
+    DAV1D_EXPORT_SB_BITS       = 1 << 4, ///< attach the Dav1dSbBitsMap of the frame
+    DAV1D_EXPORT_TILE_INFO     = 1 << 5, ///< attach the Dav1dTileLayout of the frame
+};
+
+/**
+ * Export flag to keep a copy of every frame after the given Dav1dReconStage,
+ * see dav1d_picture_get_recon_stage().
+ */
+#define DAV1D_EXPORT_RECON_STAGE(stage) (1U << (8 + (stage)))
+
+typedef struct Dav1dCallbacks {
+    Dav1dRowProgress row_progress; ///< Sub-frame row progress callback.
+    /**
+     * Allocate the picture buffer per plane. May be NULL. If set, this is
+     * used instead of Dav1dSettings.allocator.alloc_picture_callback, which
+     * may then be NULL, and pictures are released with
+     * Dav1dSettings.allocator.release_picture_callback.
+     *
+     * Each plane may be placed in a separate buffer, but data[1] and data[2]
+     * must still share the same stride[1]. The filled in data, stride and
+     * size are checked against req; if they don't satisfy it, the picture is
+     * released again and decoding fails with DAV1D_ERR(EINVAL).
+     *
+     * @param    pic The picture to allocate the buffer for, as for
+     *               alloc_picture_callback().
+     * @param    req The requirements per plane, only valid during the call.
+     * @param   size Output, the size in bytes of the buffer of each plane,
+     *               from its lowest address, i.e. from data[i] or, for a
+     *               negative stride, from the start of its last row. It must
+     *               be at least |stride| * height + padding of req.
+     * @param cookie Dav1dSettings.allocator.cookie.
+     *
+     * @return 0 on success. A negative DAV1D_ERR value on error.
+     */
+    int (*alloc_picture_planes)(Dav1dPicture *pic,
+                                const Dav1dPictureRequirements *req,
+                                size_t size[3], void *cookie);
+    Dav1dSyntaxTrace syntax_trace; ///< Trace of all decoded syntax elements.
+    uintptr_t reserved[4]; ///< reserved for future use, must be zero
+} Dav1dCallbacks;
+
 enum Dav1dInloopFilterType {
     DAV1D_INLOOPFILTER_NONE        = 0,
     DAV1D_INLOOPFILTER_DEBLOCK     = 1 << 0,
@@ -95,7 +212,10 @@ typedef struct Dav1dSettings {
                                                ///< DAV1D_INLOOPFILTER_ALL)
     enum Dav1dDecodeFrameType decode_frame_type; ///< frame types to decode (default
                                                  ///< DAV1D_DECODEFRAMETYPE_ALL)
-    uint8_t reserved[16]; ///< reserved for future use
+    unsigned export_flags; ///< bitmask of Dav1dExportFlags for the data to keep with each
+                           ///< output picture (default 0)
+    uint8_t reserved[12 - sizeof(void *)]; ///< reserved for future use
+    const Dav1dCallbacks *callbacks; ///< optional callbacks, only read by dav1d_open() (default NULL)
 } Dav1dSettings;
 
 /**
@@ -145,6 +265,43 @@ DAV1D_API int dav1d_open(Dav1dContext **c_out, const Dav1dSettings *s);
 DAV1D_API int dav1d_parse_sequence_header(Dav1dSequenceHeader *out,
                                           const uint8_t *buf, const size_t sz);
 
+enum Dav1dRandomAccessType {
+    DAV1D_RANDOM_ACCESS_NONE        = 0, ///< decoding can't start at this temporal unit
+    DAV1D_RANDOM_ACCESS_KEY         = 1, ///< key frame with show_frame set
+    DAV1D_RANDOM_ACCESS_FORWARD_KEY = 2, ///< key frame shown later with show_existing_frame
+};
+
+typedef struct Dav1dRandomAccessPoint {
+    enum Dav1dRandomAccessType type; ///< kind of frame the temporal unit starts with
+    int seq_hdr_present; ///< whether the temporal unit contains a sequence header
+} Dav1dRandomAccessPoint;
+
+/**
+ * Inspect a temporal unit and report whether decoding can start at it,
+ * without decoding anything. Only a key frame is a random access point,
+ * either shown right away or later with show_existing_frame. OBUs outside
+ * of the selected operating point are ignored.
+ *
+ * @param             out Output random access point information.
+ * @param             buf The temporal unit to inspect.
+ * @param              sz Size of the data.
+ * @param         seq_hdr Sequence header to use if the temporal unit doesn't
+ *                        contain one, may be NULL.
+ * @param operating_point The operating point to decode, as in
+ *                        Dav1dSettings.operating_point (0 - 31).
+ *
+ * @return
+ *                  0: Success, and out is filled.
+ *  DAV1D_ERR(ENOENT): No sequence header was available to parse the frame
+ *                     header with.
+ *  Other negative DAV1D_ERR codes: Invalid data in the buffer, invalid passed-in
+ *                                  arguments, and other errors during parsing.
+ */
+DAV1D_API int dav1d_parse_random_access_point(Dav1dRandomAccessPoint *out,
+                                              const uint8_t *buf, const size_t sz,
+                                              const Dav1dSequenceHeader *seq_hdr,
+                                              int operating_point);
+
 /**
  * Feed bitstream data to the decoder, in the form of one or multiple AV1
  * Open Bitstream Units (OBUs).
@@ -256,6 +413,162 @@ DAV1D_API void dav1d_close(Dav1dContext **c_out);
  */
 DAV1D_API void dav1d_flush(Dav1dContext *c);
 
+/**
+ * Skip output until the first picture with m.timestamp >= target_timestamp.
+ * To be called right after dav1d_flush(), before feeding data from a seek
+ * point in front of the target.
+ *
+ * Pictures before the target are not returned by dav1d_get_picture(), and
+ * non-reference frames before it are not reconstructed. Frames shown later
+ * with show_existing_frame (e.g. forward key frames) take the timestamp of
+ * the data showing them. The hint is cleared once a picture at or after the
+ * target is output, or by dav1d_flush().
+ *
+ * @param                c Input decoder instance.
+ * @param target_timestamp Timestamp of the first picture to output.
+ *
+ * @return 0 on success, or < 0 (a negative DAV1D_ERR code) on error.
+ */
+DAV1D_API int dav1d_seek_hint(Dav1dContext *c, int64_t target_timestamp);
+
+/**
+ * Take a snapshot of the reference state of the decoder: reference pictures,
+ * segmentation maps, motion vectors, CDFs and the current headers.
+ *
+ * Frames still being decoded are waited for first. Data not yet consumed by
+ * dav1d_send_data() and pictures not yet returned by dav1d_get_picture() are
+ * not part of the snapshot. Buffers are shared with the decoder, not copied.
+ *
+ * @param   c Input decoder instance.
+ * @param out Output snapshot, to be released with dav1d_state_snapshot_unref().
+ *
+ * @return 0 on success, or < 0 (a negative DAV1D_ERR code) on error.
+ */
+DAV1D_API int dav1d_state_snapshot(Dav1dContext *c, Dav1dStateSnapshot **out);
+
+/**
+ * Flush the decoder and restore the reference state from a snapshot, so that
+ * decoding can resume with the temporal unit following the snapshot. The
+ * snapshot may come from a different decoder instance with a different number
+ * of threads, but the same settings otherwise.
+ *
+ * @param c Input decoder instance.
+ * @param s Snapshot to restore.
+ *
+ * @return
+ *                  0: Success.
+ *  DAV1D_ERR(EINVAL): The snapshot doesn't fit the decoder: it was taken with
+ *                     different settings, with a different sequence header
+ *                     than the last one the decoder has seen, or it has
+ *                     reference pictures that don't fit that sequence header
+ *                     or the frame size limit. The decoder is left untouched.
+ */
+DAV1D_API int dav1d_state_restore(Dav1dContext *c, const Dav1dStateSnapshot *s);
+
+/**
+ * Add a reference to a snapshot.
+ *
+ * @return s
+ */
+DAV1D_API Dav1dStateSnapshot *dav1d_state_snapshot_ref(const Dav1dStateSnapshot *s);
+
+/**
+ * Release a reference to a snapshot and set *s to NULL.
+ */
+DAV1D_API void dav1d_state_snapshot_unref(Dav1dStateSnapshot **s);
+
+/**
+ * Motion vector saved with a reference frame, for the motion field projection
+ * of the frames using it, see Dav1dRefSlot.
+ */
+typedef struct Dav1dSavedMv {
+    int16_t y, x; ///< in 1/8 luma pixels
+    int8_t ref; ///< reference frame the motion vector points into, from 1 (LAST_FRAME)
+                ///< to 7 (ALTREF_FRAME), or 0 if the block has none to save
+} Dav1dSavedMv;
+
+/**
+ * State saved with a frame in a reference slot, which the frames using it as
+ * a reference are decoded with, see dav1d_decode_frame_stateless().
+ *
+ * All of it is plain data that can be serialized and filled in again by the
+ * caller. segment_ids and mvs have one entry per 4x4 and 8x8 luma unit, in
+ * raster order, of the frame before superres upscaling, whose size in 4x4
+ * units w4 and h4 is rounded up to a multiple of 2.
+ */
+typedef struct Dav1dRefSlot {
+    Dav1dPicture pic; ///< reference picture; only its planes, stride, p and
+                      ///< frame_hdr are read
+    Dav1dCdfContext cdf; ///< CDFs at the end of the frame, as exported with
+                         ///< DAV1D_EXPORT_CDF
+    int showable; ///< whether the frame can still be shown with show_existing_frame
+    unsigned refpoc[7]; ///< frame_offset of each reference of the frame
+                        ///< (LAST_FRAME to ALTREF_FRAME), or 0s for intra frames
+    int w4, h4;
+    uint8_t *segment_ids; ///< w4 * h4 segment ids, or NULL without segmentation map
+    Dav1dSavedMv *mvs; ///< w4 / 2 * h4 / 2 motion vectors, or NULL without any to save
+} Dav1dRefSlot;
+
+/**
+ * Decode the frame at the start of data from explicit reference state,
+ * ignoring the state of the decoder from previous calls.
+ *
+ * The decoder instance must be opened with max_frame_delay 1. It is flushed
+ * before and after decoding the frame, so pictures not yet returned by
+ * dav1d_get_picture() are dropped.
+ *
+ * @param       c Input decoder instance.
+ * @param seq_hdr Sequence header of the frame.
+ * @param    data Input data; data following the frame is left in it.
+ * @param    refs Array of 8 reference slots, as returned in out by previous
+ *                calls or by dav1d_state_snapshot_get_ref_slot(), or filled
+ *                in by the caller. Slots not referenced by the frame may be
+ *                NULL.
+ * @param frame_hdr Header of the frame.
+ * @param     out State to save with the frame, to be released with
+ *                dav1d_ref_slot_unref(). out->pic is the reconstructed
+ *                picture without film grain applied (see
+ *                dav1d_apply_grain()). The state is to be stored in each slot
+ *                in frame_hdr->refresh_frame_flags or, for a
+ *                show_existing_frame of a key frame, in all 8 slots. For
+ *                frames that refresh no slots, only out->pic is set.
+ *
+ * @return
+ *                  0: Success.
+ *  DAV1D_ERR(EINVAL): The decoder uses frame threading, a reference slot
+ *                     doesn't fit the sequence header, data ends before the
+ *                     frame, the frame was skipped (see decode_frame_type in
+ *                     Dav1dSettings), or other invalid data.
+ *  Other negative DAV1D_ERR codes: Errors during decoding.
+ */
+DAV1D_API int dav1d_decode_frame_stateless(Dav1dContext *c,
+                                           const Dav1dSequenceHeader *seq_hdr,
+                                           Dav1dData *data,
+                                           const Dav1dRefSlot *const *refs,
+                                           Dav1dFrameHeader *frame_hdr,
+                                           Dav1dRefSlot *out);
+
+/**
+ * Get one of the reference slots of a snapshot, to decode with
+ * dav1d_decode_frame_stateless().
+ *
+ * @param    s Input snapshot.
+ * @param slot Reference slot (0 to 7).
+ * @param  out Output reference slot, to be released with
+ *             dav1d_ref_slot_unref().
+ *
+ * @return 0 on success, DAV1D_ERR(ENOENT) if the slot is empty, or another
+ *         negative DAV1D_ERR code on error.
+ */
+DAV1D_API int dav1d_state_snapshot_get_ref_slot(const Dav1dStateSnapshot *s,
+                                                int slot, Dav1dRefSlot *out);
+
+/**
+ * Release the buffers of a reference slot filled in by the decoder, and clear
+ * it. Slots filled in by the caller must not be passed here.
+ */
+DAV1D_API void dav1d_ref_slot_unref(Dav1dRefSlot *slot);
+
 enum Dav1dEventFlags {
     /**
      * The last returned picture contains a reference to a new Sequence Header,
@@ -298,6 +611,60 @@ DAV1D_API int dav1d_get_event_flags(Dav1dContext *c, enum Dav1dEventFlags *flags
  */
 DAV1D_API int dav1d_get_decode_error_data_props(Dav1dContext *c, Dav1dDataProps *out);
 
+/**
+ * Get a copy of a picture as it was after one of the in-loop filtering stages,
+ * if DAV1D_EXPORT_RECON_STAGE(stage) was set. Stages that are
+ * disabled for a frame leave it unchanged, so e.g. the picture after
+ * DAV1D_RECON_STAGE_CDEF equals the one after DAV1D_RECON_STAGE_DEBLOCK for
+ * frames without CDEF. Pictures are before film grain application.
+ *
+ * @param     p Input picture, as returned by dav1d_get_picture().
+ * @param stage Filtering stage.
+ * @param   out Output picture. On success, the caller assumes ownership of the
+ *              returned reference, and must release it with
+ *              dav1d_picture_unref().
+ *
+ * @return
+ *                  0: Success, and out is filled with the picture.
+ *  DAV1D_ERR(ENOENT): The stage wasn't captured for this picture.
+ *  Other negative DAV1D_ERR codes: Invalid passed-in arguments.
+ */
+DAV1D_API int dav1d_picture_get_recon_stage(const Dav1dPicture *p,
+                                            enum Dav1dReconStage stage,
+                                            Dav1dPicture *out);
+
+/**
+ * Get the intra/inter prediction of a picture, before the residual is added,
+ * if DAV1D_EXPORT_PRED_RESIDUAL was set. The picture has the size of
+ * the frame before super-resolution upscaling.
+ *
+ * @param   p Input picture, as returned by dav1d_get_picture().
+ * @param out Output picture. On success, the caller assumes ownership of the
+ *            returned reference, and must release it with dav1d_picture_unref().
+ *
+ * @return
+ *                  0: Success, and out is filled with the prediction.
+ *  DAV1D_ERR(ENOENT): The prediction wasn't kept for this picture.
+ *  Other negative DAV1D_ERR codes: Invalid passed-in arguments.
+ */
+DAV1D_API int dav1d_picture_get_prediction(const Dav1dPicture *p, Dav1dPicture *out);
+
+/**
+ * Get the residual of a picture, i.e. the reconstruction before in-loop
+ * filtering minus the prediction, offset by 1 << (bpc - 1) and clipped to the
+ * pixel range, if DAV1D_EXPORT_PRED_RESIDUAL was set.
+ *
+ * @param   p Input picture, as returned by dav1d_get_picture().
+ * @param out Output picture. On success, the caller assumes ownership of the
+ *            returned reference, and must release it with dav1d_picture_unref().
+ *
+ * @return
+ *                  0: Success, and out is filled with the residual.
+ *  DAV1D_ERR(ENOENT): The residual wasn't kept for this picture.
+ *  Other negative DAV1D_ERR codes: Invalid passed-in arguments.
+ */
+DAV1D_API int dav1d_picture_get_residual(const Dav1dPicture *p, Dav1dPicture *out);
+
 /**
  * Get the decoder delay, which is the number of internally buffered frames, not
  * including reference frames.
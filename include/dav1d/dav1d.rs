@@ -1,16 +1,27 @@
+use crate::include::common::validate::validate_input;
+use crate::include::dav1d::picture::Dav1dAllocPicturePlanesCallback;
+use crate::include::dav1d::picture::Dav1dCdfContext;
 use crate::include::dav1d::picture::Dav1dPicAllocator;
+use crate::include::dav1d::picture::Dav1dPicture;
 use crate::include::dav1d::picture::Rav1dPicAllocator;
 use crate::src::error::Rav1dError;
+use crate::src::error::Rav1dError::EINVAL;
 use crate::src::internal::Rav1dContext;
 pub use crate::src::log::Dav1dLogger;
 use crate::src::log::Rav1dLogger;
 use crate::src::r#ref::Rav1dRef;
+use crate::src::snapshot::Rav1dStateSnapshot;
 use bitflags::bitflags;
+use std::ffi::c_char;
 use std::ffi::c_int;
 use std::ffi::c_uint;
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
 
 pub type Dav1dContext = Rav1dContext;
 pub type Dav1dRef = Rav1dRef;
+pub type Dav1dStateSnapshot = Rav1dStateSnapshot;
 
 pub type Dav1dInloopFilterType = c_uint;
 pub const DAV1D_INLOOPFILTER_ALL: Dav1dInloopFilterType = 7;
@@ -40,6 +51,106 @@ pub(crate) const RAV1D_DECODEFRAMETYPE_REFERENCE: Rav1dDecodeFrameType =
     DAV1D_DECODEFRAMETYPE_REFERENCE;
 pub(crate) const RAV1D_DECODEFRAMETYPE_ALL: Rav1dDecodeFrameType = DAV1D_DECODEFRAMETYPE_ALL;
 
+pub type Dav1dRandomAccessType = c_uint;
+pub const DAV1D_RANDOM_ACCESS_FORWARD_KEY: Dav1dRandomAccessType =
+    Rav1dRandomAccessType::ForwardKey as Dav1dRandomAccessType;
+pub const DAV1D_RANDOM_ACCESS_KEY: Dav1dRandomAccessType =
+    Rav1dRandomAccessType::Key as Dav1dRandomAccessType;
+pub const DAV1D_RANDOM_ACCESS_NONE: Dav1dRandomAccessType =
+    Rav1dRandomAccessType::None as Dav1dRandomAccessType;
+
+/// The kind of frame a temporal unit starts with,
+/// as far as starting to decode at it is concerned.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Rav1dRandomAccessType {
+    /// Decoding can't start here, e.g. an inter or intra-only frame,
+    /// whose following frames may still reference frames from before it,
+    /// or a `show_existing_frame`.
+    #[default]
+    None = 0,
+    /// A key frame with `show_frame` set.
+    Key = 1,
+    /// A key frame without `show_frame` but with `showable_frame` set,
+    /// to be shown later with `show_existing_frame`.
+    ForwardKey = 2,
+}
+
+impl From<Rav1dRandomAccessType> for Dav1dRandomAccessType {
+    fn from(value: Rav1dRandomAccessType) -> Self {
+        value as Dav1dRandomAccessType
+    }
+}
+
+#[derive(Default)]
+#[repr(C)]
+pub struct Dav1dRandomAccessPoint {
+    pub r#type: Dav1dRandomAccessType,
+    /// Whether the temporal unit contains a sequence header.
+    pub seq_hdr_present: c_int,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Rav1dRandomAccessPoint {
+    pub r#type: Rav1dRandomAccessType,
+    pub seq_hdr_present: bool,
+}
+
+impl From<Rav1dRandomAccessPoint> for Dav1dRandomAccessPoint {
+    fn from(value: Rav1dRandomAccessPoint) -> Self {
+        let Rav1dRandomAccessPoint {
+            r#type,
+            seq_hdr_present,
+        } = value;
+        Self {
+            r#type: r#type.into(),
+            seq_hdr_present: seq_hdr_present as c_int,
+        }
+    }
+}
+
+/// A motion vector saved with a reference frame,
+/// for the motion field projection of the frames using it, see [`Dav1dRefSlot::mvs`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[repr(C)]
+pub struct Dav1dSavedMv {
+    /// In 1/8 luma pixels.
+    pub y: i16,
+    pub x: i16,
+    /// The reference frame the motion vector points into,
+    /// from 1 (`LAST_FRAME`) to 7 (`ALTREF_FRAME`), or 0 if the block has none to save.
+    pub r#ref: i8,
+}
+
+/// The state saved with a frame in a reference slot,
+/// which the frames using it as a reference are decoded with,
+/// see [`dav1d_decode_frame_stateless`].
+///
+/// All of it is plain data that can be serialized and filled in again by the caller.
+/// `segment_ids` and `mvs` have one entry per 4x4 and 8x8 luma unit, in raster order,
+/// of the frame before superres upscaling,
+/// whose size in 4x4 units `w4` and `h4` is rounded up to a multiple of 2.
+///
+/// [`dav1d_decode_frame_stateless`]: crate::src::lib::dav1d_decode_frame_stateless
+#[repr(C)]
+pub struct Dav1dRefSlot {
+    /// The reference picture.
+    /// Only its planes, `stride`, `p` and `frame_hdr` are read.
+    pub pic: Dav1dPicture,
+    /// The CDFs at the end of the frame, like [`DAV1D_EXPORT_CDF`] exports them.
+    pub cdf: Dav1dCdfContext,
+    /// Whether the frame can still be shown with `show_existing_frame`.
+    pub showable: c_int,
+    /// The `frame_offset` of each reference of the frame (`LAST_FRAME` to `ALTREF_FRAME`),
+    /// or 0s for intra frames.
+    pub refpoc: [c_uint; 7],
+    pub w4: c_int,
+    pub h4: c_int,
+    /// `w4 * h4` segment ids, or null if the frame has no segmentation map.
+    pub segment_ids: *mut u8,
+    /// `w4 / 2 * h4 / 2` motion vectors, or null if the frame has none to save.
+    pub mvs: *mut Dav1dSavedMv,
+}
+
 pub type Dav1dEventFlags = c_uint;
 pub const DAV1D_EVENT_FLAG_NEW_SEQUENCE: Dav1dEventFlags =
     Rav1dEventFlags::NEW_SEQUENCE.bits() as Dav1dEventFlags;
@@ -79,6 +190,264 @@ impl From<Dav1dEventFlags> for Rav1dEventFlags {
     }
 }
 
+pub type Dav1dRowProgressCallback = unsafe extern "C" fn(
+    // The `cookie` field of [`Dav1dRowProgress`].
+    cookie: *mut c_void,
+    // The picture being decoded.  Only valid for the duration of the call,
+    // and it holds no references, so it can't be referenced with `dav1d_picture_ref`.
+    pic: *const Dav1dPicture,
+    // The number of luma rows, counted from the top of `pic`, that are final.
+    rows: c_int,
+);
+
+/// Sub-frame progress notification.
+///
+/// When a callback is set, it is called while a frame that will be output
+/// is being decoded, each time more rows of its picture have been fully
+/// reconstructed and post-filtered (deblocking, CDEF, super-resolution and
+/// loop restoration).  The reported row count never decreases for a given
+/// picture and reaches the picture height once the frame is complete.
+///
+/// Film grain, if applied, is applied to a separate output picture later on,
+/// so the rows reported here are those of the picture before film grain.
+///
+/// The callback may be called from any of the decoder's worker threads,
+/// and for different pictures concurrently when frame threading is used.
+/// It must not call back into the decoder.
+#[derive(Clone)]
+#[repr(C)]
+pub struct Dav1dRowProgress {
+    /// A cookie that's passed as the first argument to the callback below.
+    pub cookie: *mut c_void,
+    /// May be [`None`]/`NULL` to disable progress notifications.
+    pub callback: Option<Dav1dRowProgressCallback>,
+}
+
+impl Default for Dav1dRowProgress {
+    fn default() -> Self {
+        Self {
+            cookie: ptr::null_mut(),
+            callback: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Rav1dRowProgress {
+    pub cookie: *mut c_void,
+    pub callback: Dav1dRowProgressCallback,
+}
+
+impl From<Dav1dRowProgress> for Option<Rav1dRowProgress> {
+    fn from(value: Dav1dRowProgress) -> Self {
+        let Dav1dRowProgress { cookie, callback } = value;
+        Some(Rav1dRowProgress {
+            cookie,
+            callback: callback?,
+        })
+    }
+}
+
+impl From<Option<Rav1dRowProgress>> for Dav1dRowProgress {
+    fn from(value: Option<Rav1dRowProgress>) -> Self {
+        match value {
+            None => Default::default(),
+            Some(Rav1dRowProgress { cookie, callback }) => Self {
+                cookie,
+                callback: Some(callback),
+            },
+        }
+    }
+}
+
+pub type Dav1dReconStage = c_uint;
+pub const DAV1D_RECON_STAGE_RESTORATION: Dav1dReconStage = 4;
+pub const DAV1D_RECON_STAGE_SUPERRES: Dav1dReconStage = 3;
+pub const DAV1D_RECON_STAGE_CDEF: Dav1dReconStage = 2;
+pub const DAV1D_RECON_STAGE_DEBLOCK: Dav1dReconStage = 1;
+pub const DAV1D_RECON_STAGE_PREFILTER: Dav1dReconStage = 0;
+
+pub(crate) type Rav1dReconStage = c_uint;
+pub(crate) const RAV1D_RECON_STAGE_COUNT: Rav1dReconStage = 5;
+pub(crate) const RAV1D_RECON_STAGE_RESTORATION: Rav1dReconStage = DAV1D_RECON_STAGE_RESTORATION;
+pub(crate) const RAV1D_RECON_STAGE_SUPERRES: Rav1dReconStage = DAV1D_RECON_STAGE_SUPERRES;
+pub(crate) const RAV1D_RECON_STAGE_CDEF: Rav1dReconStage = DAV1D_RECON_STAGE_CDEF;
+pub(crate) const RAV1D_RECON_STAGE_DEBLOCK: Rav1dReconStage = DAV1D_RECON_STAGE_DEBLOCK;
+pub(crate) const RAV1D_RECON_STAGE_PREFILTER: Rav1dReconStage = DAV1D_RECON_STAGE_PREFILTER;
+
+pub type Dav1dSyntaxSource = c_uint;
+pub const DAV1D_SYNTAX_TILE: Dav1dSyntaxSource = 2;
+pub const DAV1D_SYNTAX_LABEL: Dav1dSyntaxSource = 1;
+pub const DAV1D_SYNTAX_HEADER: Dav1dSyntaxSource = 0;
+
+#[cfg(feature = "syntax_trace")]
+pub(crate) type Rav1dSyntaxSource = c_uint;
+#[cfg(feature = "syntax_trace")]
+pub(crate) const RAV1D_SYNTAX_TILE: Rav1dSyntaxSource = DAV1D_SYNTAX_TILE;
+#[cfg(feature = "syntax_trace")]
+pub(crate) const RAV1D_SYNTAX_LABEL: Rav1dSyntaxSource = DAV1D_SYNTAX_LABEL;
+#[cfg(feature = "syntax_trace")]
+pub(crate) const RAV1D_SYNTAX_HEADER: Rav1dSyntaxSource = DAV1D_SYNTAX_HEADER;
+
+/// One decoded syntax element, see [`Dav1dSyntaxTrace`].
+#[repr(C)]
+pub struct Dav1dSyntaxElement {
+    /// [`DAV1D_SYNTAX_HEADER`] for bits read from OBU headers,
+    /// [`DAV1D_SYNTAX_LABEL`] for the name of the header bits read since the previous label,
+    /// [`DAV1D_SYNTAX_TILE`] for symbols decoded from tile data.
+    pub source: Dav1dSyntaxSource,
+    /// For tile symbols, the name of the syntax element,
+    /// as in the AV1 specification where it has one (e.g. `is_inter`).
+    /// Only valid for the duration of the call.
+    pub name: *const c_char,
+    pub value: i64,
+    /// Number of bits read for header elements, 0 otherwise.
+    pub n_bits: c_int,
+    /// Position in the OBU data, in bits, for header elements and labels, -1 otherwise.
+    pub bit_pos: i64,
+    /// Tile index in raster order, -1 for header elements and labels.
+    pub tile: c_int,
+    /// Position of the superblock, in superblock units, -1 for header elements and labels.
+    pub sbx: c_int,
+    pub sby: c_int,
+    /// Position of the block, in 4px units, -1 for header elements and labels.
+    /// Symbols decoded at the superblock level (e.g. partitions, CDEF indices)
+    /// take the position of the (sub-)block being partitioned.
+    pub bx: c_int,
+    pub by: c_int,
+}
+
+pub type Dav1dSyntaxTraceCallback = unsafe extern "C" fn(
+    // The `cookie` field of [`Dav1dSyntaxTrace`].
+    cookie: *mut c_void,
+    // Only valid for the duration of the call.
+    element: *const Dav1dSyntaxElement,
+);
+
+/// Trace of every syntax element read from the bitstream.
+///
+/// When a callback is set, it is called for every header bit field
+/// read by [`GetBits`] and every symbol decoded by [`MsacContext`],
+/// in bitstream order within each OBU and each tile.
+/// This is only compiled in with the `syntax_trace` feature,
+/// and `dav1d_open` fails with `ENOPROTOOPT` if a callback is set without it.
+///
+/// Tiles and frames may be decoded concurrently by different worker threads,
+/// in which case calls for them are interleaved.
+/// Use a single thread for a trace in bitstream order.
+/// The callback must not call back into the decoder.
+///
+/// [`GetBits`]: crate::src::getbits::GetBits
+/// [`MsacContext`]: crate::src::msac::MsacContext
+#[derive(Clone)]
+#[repr(C)]
+pub struct Dav1dSyntaxTrace {
+    /// A cookie that's passed as the first argument to the callback below.
+    pub cookie: *mut c_void,
+    /// May be [`None`]/`NULL` to disable the trace.
+    pub callback: Option<Dav1dSyntaxTraceCallback>,
+}
+
+impl Default for Dav1dSyntaxTrace {
+    fn default() -> Self {
+        Self {
+            cookie: ptr::null_mut(),
+            callback: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Rav1dSyntaxTrace {
+    pub cookie: *mut c_void,
+    pub callback: Dav1dSyntaxTraceCallback,
+}
+
+#[cfg(feature = "syntax_trace")]
+impl Rav1dSyntaxTrace {
+    pub fn emit(&self, element: &Dav1dSyntaxElement) {
+        // Safety: The callback is required to be valid for the lifetime of the decoder.
+        unsafe { (self.callback)(self.cookie, element) }
+    }
+}
+
+impl From<Dav1dSyntaxTrace> for Option<Rav1dSyntaxTrace> {
+    fn from(value: Dav1dSyntaxTrace) -> Self {
+        let Dav1dSyntaxTrace { cookie, callback } = value;
+        Some(Rav1dSyntaxTrace {
+            cookie,
+            callback: callback?,
+        })
+    }
+}
+
+impl From<Option<Rav1dSyntaxTrace>> for Dav1dSyntaxTrace {
+    fn from(value: Option<Rav1dSyntaxTrace>) -> Self {
+        match value {
+            None => Default::default(),
+            Some(Rav1dSyntaxTrace { cookie, callback }) => Self {
+                cookie,
+                callback: Some(callback),
+            },
+        }
+    }
+}
+
+pub type Dav1dExportFlags = c_uint;
+pub const DAV1D_EXPORT_TILE_INFO: Dav1dExportFlags = 1 << 5;
+pub const DAV1D_EXPORT_SB_BITS: Dav1dExportFlags = 1 << 4;
+pub const DAV1D_EXPORT_BLOCK_INFO: Dav1dExportFlags = 1 << 3;
+pub const DAV1D_EXPORT_PRED_RESIDUAL: Dav1dExportFlags = 1 << 2;
+pub const DAV1D_EXPORT_CDF: Dav1dExportFlags = 1 << 1;
+pub const DAV1D_EXPORT_STATS: Dav1dExportFlags = 1 << 0;
+
+/// The export flag to keep a copy of each frame after `stage`,
+/// see `dav1d_picture_get_recon_stage`.
+///
+/// This is `DAV1D_EXPORT_RECON_STAGE` in the C API.
+pub const fn dav1d_export_recon_stage(stage: Dav1dReconStage) -> Dav1dExportFlags {
+    1 << (8 + stage)
+}
+
+/// The callbacks of [`Dav1dSettings::callbacks`].
+///
+/// They are copied by `dav1d_open`,
+/// so this only needs to be valid for the duration of that call.
+#[derive(Clone, Default)]
+#[repr(C)]
+pub struct Dav1dCallbacks {
+    pub row_progress: Dav1dRowProgress,
+    /// Allocate the picture buffer per plane.
+    ///
+    /// If set, it is used instead of [`Dav1dPicAllocator::alloc_picture_callback`]
+    /// of [`Dav1dSettings::allocator`], which may then be [`None`]/`NULL`,
+    /// and the picture is released with [`Dav1dPicAllocator::release_picture_callback`].
+    /// It is passed [`Dav1dPicAllocator::cookie`] as its `cookie`.
+    ///
+    /// This is the same as [`Dav1dPicAllocator::alloc_picture_callback`],
+    /// except that the allocator is told the requirements per plane,
+    /// so it can e.g. hand out separate per-plane buffers from an external pool,
+    /// and that it also fills in the `size` in bytes of the buffer of each plane.
+    /// The size is counted from the lowest address of the plane,
+    /// i.e. from [`data`]`[i]` or, for a negative stride, from the start of its last row,
+    /// and must be at least `|stride| * height + padding` of the plane's requirements.
+    /// The filled in [`data`], [`stride`] and `size` are checked against `req`,
+    /// and if they don't satisfy it, the picture is released again
+    /// and decoding fails with `EINVAL`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Dav1dPicAllocator::alloc_picture_callback`].
+    /// `req` and `size` are only valid for the duration of the call.
+    ///
+    /// [`data`]: Dav1dPicture::data
+    /// [`stride`]: Dav1dPicture::stride
+    pub alloc_picture_planes: Option<Dav1dAllocPicturePlanesCallback>,
+    pub syntax_trace: Dav1dSyntaxTrace,
+    /// Reserved for future use, must be zero.
+    pub reserved: [usize; 4],
+}
+
 #[repr(C)]
 pub struct Dav1dSettings {
     pub n_threads: c_int,
@@ -93,7 +462,28 @@ pub struct Dav1dSettings {
     pub output_invisible_frames: c_int,
     pub inloop_filters: Dav1dInloopFilterType,
     pub decode_frame_type: Dav1dDecodeFrameType,
-    pub reserved: [u8; 16],
+    /// Bitmask of `DAV1D_EXPORT_*` flags for the data to keep with each output picture:
+    ///
+    /// * [`DAV1D_EXPORT_STATS`]: attach [`Dav1dFrameStats`].
+    /// * [`DAV1D_EXPORT_CDF`]: attach the [`Dav1dCdfContext`] at the end of the frame.
+    /// * [`DAV1D_EXPORT_PRED_RESIDUAL`]: keep the prediction and the residual,
+    ///   see `dav1d_picture_get_prediction` and `dav1d_picture_get_residual`.
+    /// * [`DAV1D_EXPORT_BLOCK_INFO`]: attach the [`Dav1dBlockInfoMap`].
+    /// * [`DAV1D_EXPORT_SB_BITS`]: attach the [`Dav1dSbBitsMap`].
+    /// * [`DAV1D_EXPORT_TILE_INFO`]: attach the [`Dav1dTileLayout`].
+    /// * [`dav1d_export_recon_stage`]`(stage)`: keep a copy of each frame after
+    ///   the [`Dav1dReconStage`], see `dav1d_picture_get_recon_stage`.
+    ///
+    /// [`Dav1dFrameStats`]: crate::include::dav1d::picture::Dav1dFrameStats
+    /// [`Dav1dCdfContext`]: crate::include::dav1d::picture::Dav1dCdfContext
+    /// [`Dav1dBlockInfoMap`]: crate::include::dav1d::picture::Dav1dBlockInfoMap
+    /// [`Dav1dSbBitsMap`]: crate::include::dav1d::picture::Dav1dSbBitsMap
+    /// [`Dav1dTileLayout`]: crate::include::dav1d::picture::Dav1dTileLayout
+    pub export_flags: Dav1dExportFlags,
+    /// Sized so that [`Self::callbacks`] takes the end of the original `reserved: [u8; 16]`.
+    pub reserved: [u8; 12 - mem::size_of::<*const c_void>()],
+    /// Optional callbacks, may be null.
+    pub callbacks: *const Dav1dCallbacks,
 }
 
 #[repr(C)]
@@ -110,6 +500,15 @@ pub(crate) struct Rav1dSettings {
     pub output_invisible_frames: bool,
     pub inloop_filters: Rav1dInloopFilterType,
     pub decode_frame_type: Rav1dDecodeFrameType,
+    pub row_progress: Option<Rav1dRowProgress>,
+    pub collect_stats: bool,
+    pub export_cdf: bool,
+    pub recon_stages: c_uint,
+    pub export_pred_residual: bool,
+    pub syntax_trace: Option<Rav1dSyntaxTrace>,
+    pub export_block_info: bool,
+    pub export_sb_bits: bool,
+    pub export_tile_info: bool,
 }
 
 impl TryFrom<Dav1dSettings> for Rav1dSettings {
@@ -129,8 +528,32 @@ impl TryFrom<Dav1dSettings> for Rav1dSettings {
             output_invisible_frames,
             inloop_filters,
             decode_frame_type,
+            export_flags,
             reserved: _,
+            callbacks,
         } = value;
+        let known_flags = DAV1D_EXPORT_STATS
+            | DAV1D_EXPORT_CDF
+            | DAV1D_EXPORT_PRED_RESIDUAL
+            | DAV1D_EXPORT_BLOCK_INFO
+            | DAV1D_EXPORT_SB_BITS
+            | DAV1D_EXPORT_TILE_INFO;
+        // The bits from 8 up are the recon stages, which are validated by `rav1d_open`.
+        validate_input!((export_flags & 0xff & !known_flags == 0, EINVAL))?;
+        let Dav1dCallbacks {
+            row_progress,
+            alloc_picture_planes,
+            syntax_trace,
+            reserved,
+        } = if callbacks.is_null() {
+            Default::default()
+        } else {
+            // Safety: `callbacks` is required to be valid for the duration of `dav1d_open`.
+            unsafe { &*callbacks }.clone()
+        };
+        // Callbacks added later take the place of `reserved`,
+        // so an application mustn't set it to anything yet.
+        validate_input!((reserved.iter().all(|&r| r == 0), EINVAL))?;
         Ok(Self {
             n_threads,
             max_frame_delay,
@@ -138,12 +561,21 @@ impl TryFrom<Dav1dSettings> for Rav1dSettings {
             operating_point,
             all_layers: all_layers != 0,
             frame_size_limit,
-            allocator: allocator.try_into()?,
+            allocator: Rav1dPicAllocator::try_with_planes(allocator, alloc_picture_planes)?,
             logger: logger.into(),
             strict_std_compliance: strict_std_compliance != 0,
             output_invisible_frames: output_invisible_frames != 0,
             inloop_filters,
             decode_frame_type,
+            row_progress: row_progress.into(),
+            collect_stats: export_flags & DAV1D_EXPORT_STATS != 0,
+            export_cdf: export_flags & DAV1D_EXPORT_CDF != 0,
+            recon_stages: export_flags >> 8,
+            export_pred_residual: export_flags & DAV1D_EXPORT_PRED_RESIDUAL != 0,
+            syntax_trace: syntax_trace.into(),
+            export_block_info: export_flags & DAV1D_EXPORT_BLOCK_INFO != 0,
+            export_sb_bits: export_flags & DAV1D_EXPORT_SB_BITS != 0,
+            export_tile_info: export_flags & DAV1D_EXPORT_TILE_INFO != 0,
         })
     }
 }
@@ -163,6 +595,15 @@ impl From<Rav1dSettings> for Dav1dSettings {
             output_invisible_frames,
             inloop_filters,
             decode_frame_type,
+            row_progress: _,
+            collect_stats,
+            export_cdf,
+            recon_stages,
+            export_pred_residual,
+            syntax_trace: _,
+            export_block_info,
+            export_sb_bits,
+            export_tile_info,
         } = value;
         Self {
             n_threads,
@@ -177,7 +618,16 @@ impl From<Rav1dSettings> for Dav1dSettings {
             output_invisible_frames: output_invisible_frames as c_int,
             inloop_filters,
             decode_frame_type,
+            export_flags: recon_stages << 8
+                | DAV1D_EXPORT_STATS * collect_stats as Dav1dExportFlags
+                | DAV1D_EXPORT_CDF * export_cdf as Dav1dExportFlags
+                | DAV1D_EXPORT_PRED_RESIDUAL * export_pred_residual as Dav1dExportFlags
+                | DAV1D_EXPORT_BLOCK_INFO * export_block_info as Dav1dExportFlags
+                | DAV1D_EXPORT_SB_BITS * export_sb_bits as Dav1dExportFlags
+                | DAV1D_EXPORT_TILE_INFO * export_tile_info as Dav1dExportFlags,
             reserved: Default::default(),
+            // The callbacks are copied by `dav1d_open`, so they can't be pointed to again.
+            callbacks: ptr::null(),
         }
     }
 }
@@ -46,6 +46,230 @@ typedef struct Dav1dPictureParameters {
     int bpc; ///< bits per pixel component (8 or 10)
 } Dav1dPictureParameters;
 
+/**
+ * Counts of the coding decisions made in a frame.
+ */
+typedef struct Dav1dBlockStats {
+    uint32_t block_size[22]; ///< blocks of each BlockSize
+    uint32_t partition[5][10]; ///< partitions of each BlockPartition, per BlockLevel,
+                               ///< including the ones implied at the frame edges
+    uint32_t intra; ///< intra blocks, including palette blocks, but not intrabc ones
+    uint32_t inter;
+    uint32_t intrabc;
+    uint32_t palette; ///< blocks with a luma or chroma palette
+    uint32_t skip; ///< blocks without residual
+    uint32_t tx_size[19]; ///< transform blocks with coefficients of each RectTxfmSize
+    uint32_t tx_type[17]; ///< transform blocks with coefficients of each TxfmType
+} Dav1dBlockStats;
+
+/**
+ * Statistics of a decoded frame, see DAV1D_EXPORT_STATS.
+ */
+typedef struct Dav1dFrameStats {
+    Dav1dBlockStats blocks;
+    int n_tiles;
+    const uint32_t *tile_bytes; ///< compressed size of each tile, in raster order
+} Dav1dFrameStats;
+
+/**
+ * Symbol probabilities of the mode syntax elements, see Dav1dCdfContext.
+ */
+typedef struct Dav1dCdfModeContext {
+    uint16_t y_mode[4][16];
+    uint16_t uv_mode[2][13][16];
+    uint16_t wedge_idx[9][16];
+    uint16_t partition[5][4][16];
+    uint16_t cfl_alpha[6][16];
+    uint16_t txtp_inter1[2][16];
+    uint16_t txtp_inter2[16];
+    uint16_t txtp_intra1[2][13][8];
+    uint16_t txtp_intra2[3][13][8];
+    uint16_t cfl_sign[8];
+    uint16_t angle_delta[8][8];
+    uint16_t filter_intra[8];
+    uint16_t comp_inter_mode[8][8];
+    uint16_t seg_id[3][8];
+    uint16_t pal_sz[2][7][8];
+    uint16_t color_map[2][7][5][8];
+    uint16_t filter[2][8][4];
+    uint16_t txsz[4][3][4];
+    uint16_t motion_mode[22][4];
+    uint16_t delta_q[4];
+    uint16_t delta_lf[5][4];
+    uint16_t interintra_mode[4][4];
+    uint16_t restore_switchable[4];
+    uint16_t restore_wiener[2];
+    uint16_t restore_sgrproj[2];
+    uint16_t interintra[7][2];
+    uint16_t interintra_wedge[7][2];
+    uint16_t txtp_inter3[4][2];
+    uint16_t use_filter_intra[22][2];
+    uint16_t newmv_mode[6][2];
+    uint16_t globalmv_mode[2][2];
+    uint16_t refmv_mode[6][2];
+    uint16_t drl_bit[3][2];
+    uint16_t intra[4][2];
+    uint16_t comp[5][2];
+    uint16_t comp_dir[5][2];
+    uint16_t jnt_comp[6][2];
+    uint16_t mask_comp[6][2];
+    uint16_t wedge_comp[9][2];
+    uint16_t ref[6][3][2];
+    uint16_t comp_fwd_ref[3][3][2];
+    uint16_t comp_bwd_ref[2][3][2];
+    uint16_t comp_uni_ref[3][3][2];
+    uint16_t txpart[7][3][2];
+    uint16_t skip[3][2];
+    uint16_t skip_mode[3][2];
+    uint16_t seg_pred[3][2];
+    uint16_t obmc[22][2];
+    uint16_t pal_y[7][3][2];
+    uint16_t pal_uv[2][2];
+    uint16_t intrabc[2];
+} Dav1dCdfModeContext;
+
+/**
+ * Symbol probabilities of the coefficient syntax elements, see Dav1dCdfContext.
+ */
+typedef struct Dav1dCdfCoefContext {
+    uint16_t eob_bin_16[2][2][8];
+    uint16_t eob_bin_32[2][2][8];
+    uint16_t eob_bin_64[2][2][8];
+    uint16_t eob_bin_128[2][2][8];
+    uint16_t eob_bin_256[2][2][16];
+    uint16_t eob_bin_512[2][16];
+    uint16_t eob_bin_1024[2][16];
+    uint16_t eob_base_tok[5][2][4][4];
+    uint16_t base_tok[5][2][41][4];
+    uint16_t br_tok[4][2][21][4];
+    uint16_t eob_hi_bit[5][2][11][2];
+    uint16_t skip[5][13][2];
+    uint16_t dc_sign[2][3][2];
+} Dav1dCdfCoefContext;
+
+/**
+ * Symbol probabilities of one motion vector component, see Dav1dCdfContext.
+ */
+typedef struct Dav1dCdfMvComponent {
+    uint16_t classes[16];
+    uint16_t class0_fp[2][4];
+    uint16_t classN_fp[4];
+    uint16_t class0_hp[2];
+    uint16_t classN_hp[2];
+    uint16_t class0[2];
+    uint16_t classN[10][2];
+    uint16_t sign[2];
+} Dav1dCdfMvComponent;
+
+typedef struct Dav1dCdfMvContext {
+    Dav1dCdfMvComponent comp[2]; ///< vertical [0] and horizontal [1] components
+    uint16_t joint[4];
+} Dav1dCdfMvContext;
+
+/**
+ * Symbol probabilities at the end of a frame, see DAV1D_EXPORT_CDF.
+ *
+ * These are the CDFs saved with the frame for the frames that use it as their
+ * primary reference, so they are the adapted ones if refresh_context is set,
+ * and the initial ones otherwise.
+ *
+ * Each CDF of N symbols is stored as the decoder uses it: N - 1 values of
+ * 32768 minus the cumulative probability, in units of 1/32768, then the
+ * adaptation counter, padded with zeros to the size of the array. The fields
+ * are named as in the syntax trace, e.g. "m.intra" or "coef.base_tok".
+ */
+typedef struct Dav1dCdfContext {
+    Dav1dCdfModeContext m;
+    uint16_t kfym[5][5][16]; ///< luma modes of intra frames, by the mode contexts above and left
+    Dav1dCdfCoefContext coef;
+    Dav1dCdfMvContext mv; ///< motion vectors
+    Dav1dCdfMvContext dmv; ///< intrabc block vectors
+} Dav1dCdfContext;
+
+/**
+ * Coding decisions of the block covering a 4x4 luma unit of a frame, see
+ * DAV1D_EXPORT_BLOCK_INFO.
+ */
+typedef struct Dav1dBlockInfo {
+    uint16_t bx, by; ///< top-left corner of the block, in 4x4 luma units
+    uint8_t bs; ///< BlockSize
+    uint8_t intra; ///< intra block, including palette blocks, but not intrabc ones
+    uint8_t intrabc;
+    uint8_t mode; ///< luma IntraPredMode of intra blocks, InterPredMode of inter blocks,
+                  ///< or CompInterPredMode if ref[1] >= 0
+    uint8_t pal_sz; ///< colors of the luma palette of intra blocks
+    uint8_t comp_type; ///< CompInterType of inter blocks
+    uint8_t motion_mode; ///< MotionMode of inter blocks
+    uint8_t skip; ///< block without residual
+    uint8_t seg_id;
+    uint8_t tx; ///< RectTxfmSize of the luma transform block covering the unit
+    int8_t ref[2]; ///< reference frames of inter blocks (0 = LAST_FRAME to 6 = ALTREF_FRAME,
+                   ///< -1 if unused)
+    struct {
+        int16_t y, x;
+    } mv[2]; ///< motion vectors of inter blocks, or the block vector of intrabc blocks
+             ///< in mv[0], in 1/8 luma pixels
+} Dav1dBlockInfo;
+
+/**
+ * Dav1dBlockInfo of each 4x4 luma unit of a frame, before superres upscaling.
+ */
+typedef struct Dav1dBlockInfoMap {
+    int w4, h4; ///< size of the frame in 4x4 luma units, rounded up to a multiple of 2
+    const Dav1dBlockInfo *info; ///< w4 * h4 units, in raster order
+} Dav1dBlockInfoMap;
+
+/**
+ * Approximate number of bits each superblock of a frame took to code, see
+ * DAV1D_EXPORT_SB_BITS.
+ */
+typedef struct Dav1dSbBitsMap {
+    int sb_size; ///< size of the superblocks in pixels, 64 or 128
+    int sbw, sbh; ///< superblocks in each row and column, before superres upscaling
+    const uint32_t *bits; ///< sbw * sbh bit counts, in raster order
+} Dav1dSbBitsMap;
+
+enum Dav1dTileStatus {
+    DAV1D_TILE_NOT_DECODED, ///< the frame was aborted before the tile was decoded
+    DAV1D_TILE_DECODED,
+    DAV1D_TILE_ERROR, ///< the tile data is corrupt or truncated
+};
+
+/**
+ * Where the data of a tile came from and whether it was decoded, see
+ * DAV1D_EXPORT_TILE_INFO.
+ */
+typedef struct Dav1dTileInfo {
+    size_t offset; ///< offset of the tile data from the start of the Dav1dData buffer it was
+                   ///< sent in, as created or wrapped
+    size_t size; ///< size of the tile data in bytes, without the tile_size_minus_1 field
+    int tile_group; ///< index of the tile group the tile was sent in, in the order of the frame
+    enum Dav1dTileStatus status;
+} Dav1dTileInfo;
+
+/**
+ * Tile grid of a frame and the Dav1dTileInfo of each tile.
+ */
+typedef struct Dav1dTileLayout {
+    int cols, rows; ///< number of tile columns and rows
+    const int *col_start; ///< cols + 1 tile boundaries in luma pixels, before superres upscaling,
+                          ///< with the last one at the frame edge
+    const int *row_start; ///< rows + 1 tile boundaries in luma pixels
+    const Dav1dTileInfo *tiles; ///< cols * rows tiles, in raster order
+} Dav1dTileLayout;
+
+/**
+ * Data exported with a picture. Each pointer is NULL unless the matching
+ * DAV1D_EXPORT_* flag is set in Dav1dSettings.export_flags.
+ */
+typedef struct Dav1dPictureExports {
+    Dav1dFrameStats *stats; ///< decoding statistics, DAV1D_EXPORT_STATS
+    Dav1dCdfContext *cdf; ///< symbol probabilities, DAV1D_EXPORT_CDF
+    Dav1dBlockInfoMap *block_info; ///< block-level coding decisions, DAV1D_EXPORT_BLOCK_INFO
+    Dav1dSbBitsMap *sb_bits; ///< bits of each superblock, DAV1D_EXPORT_SB_BITS
+    Dav1dTileLayout *tile_layout; ///< tile grid and tile data, DAV1D_EXPORT_TILE_INFO
+} Dav1dPictureExports;
+
 typedef struct Dav1dPicture {
     Dav1dSequenceHeader *seq_hdr;
     Dav1dFrameHeader *frame_hdr;
@@ -81,20 +305,44 @@ typedef struct Dav1dPicture {
      * ITU-T T.35 metadata as defined in section 5.8.2 and 6.7.2
      */
     Dav1dITUTT35 *itut_t35;
+    /**
+     * Data exported with Dav1dSettings.export_flags, or NULL if none is set
+     */
+    Dav1dPictureExports *exports;
 
-    uintptr_t reserved[4]; ///< reserved for future use
+    uintptr_t reserved[3]; ///< reserved for future use
 
     struct Dav1dRef *frame_hdr_ref; ///< Dav1dFrameHeader allocation origin
     struct Dav1dRef *seq_hdr_ref; ///< Dav1dSequenceHeader allocation origin
     struct Dav1dRef *content_light_ref; ///< Dav1dContentLightLevel allocation origin
     struct Dav1dRef *mastering_display_ref; ///< Dav1dMasteringDisplay allocation origin
     struct Dav1dRef *itut_t35_ref; ///< Dav1dITUTT35 allocation origin
-    uintptr_t reserved_ref[4]; ///< reserved for future use
+    struct Dav1dRef *exports_ref; ///< Dav1dPictureExports allocation origin
+    uintptr_t reserved_ref[3]; ///< reserved for future use
     struct Dav1dRef *ref; ///< Frame data allocation origin
 
     void *allocator_data; ///< pointer managed by the allocator
 } Dav1dPicture;
 
+/**
+ * The memory the decoder needs for one plane of a picture, passed to
+ * Dav1dCallbacks.alloc_picture_planes. These are the same fixed rules as
+ * documented for Dav1dPicAllocator.alloc_picture_callback, i.e. a size
+ * aligned to 128 pixels and DAV1D_PICTURE_ALIGNMENT bytes of alignment and
+ * padding, only spelled out per plane. No border around the visible picture
+ * is needed.
+ */
+typedef struct Dav1dPlaneRequirements {
+    int width; ///< bytes per row that may be written, i.e. the minimum |stride| (0 if unused)
+    int height; ///< number of rows that may be written
+    int alignment; ///< alignment in bytes of the data pointer and stride
+    int padding; ///< bytes after the last row that may be read
+} Dav1dPlaneRequirements;
+
+typedef struct Dav1dPictureRequirements {
+    Dav1dPlaneRequirements planes[3]; ///< Y is [0], U is [1], V is [2]
+} Dav1dPictureRequirements;
+
 typedef struct Dav1dPicAllocator {
     void *cookie; ///< custom data to pass to the allocator callbacks.
     /**
@@ -136,6 +384,28 @@ typedef struct Dav1dPicAllocator {
     void (*release_picture_callback)(Dav1dPicture *pic, void *cookie);
 } Dav1dPicAllocator;
 
+/**
+ * The memfd region a picture was allocated in by dav1d_memfd_picture_alloc(),
+ * pointed to by the picture's allocator_data. The fd can be sent to another
+ * process and mapped there; it is closed when the picture is released. Its
+ * size is sealed (F_SEAL_SHRINK | F_SEAL_GROW), so the receiver can rely on it.
+ */
+typedef struct Dav1dPictureMemfd {
+    int fd; ///< memfd file descriptor, owned by the picture
+    size_t size; ///< size in bytes of the region
+    size_t offset[3]; ///< offsets of data[0..2] from the start of the region
+} Dav1dPictureMemfd;
+
+#ifdef __linux__
+/**
+ * Built-in allocator placing each picture in its own memfd region (Linux
+ * only). Use together with dav1d_memfd_picture_release() as the
+ * Dav1dPicAllocator callbacks; the cookie is unused.
+ */
+DAV1D_API int dav1d_memfd_picture_alloc(Dav1dPicture *pic, void *cookie);
+DAV1D_API void dav1d_memfd_picture_release(Dav1dPicture *pic, void *cookie);
+#endif
+
 /**
  * Release reference to a picture.
  */
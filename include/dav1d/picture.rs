@@ -1,5 +1,6 @@
 use crate::include::common::validate::validate_input;
 use crate::include::dav1d::common::Dav1dDataProps;
+use crate::include::dav1d::common::Dav1dUserData;
 use crate::include::dav1d::common::Rav1dDataProps;
 use crate::include::dav1d::dav1d::Dav1dRef;
 use crate::include::dav1d::headers::DRav1d;
@@ -14,17 +15,32 @@ use crate::include::dav1d::headers::Rav1dMasteringDisplay;
 use crate::include::dav1d::headers::Rav1dPixelLayout;
 use crate::include::dav1d::headers::Rav1dSequenceHeader;
 use crate::src::c_arc::RawArc;
+use crate::src::cdf::CdfCoefContext;
+use crate::src::cdf::CdfContext;
+use crate::src::cdf::CdfModeContext;
+use crate::src::cdf::CdfMvComponent;
+use crate::src::cdf::CdfMvContext;
 use crate::src::error::Dav1dResult;
 use crate::src::error::Rav1dError;
 use crate::src::error::Rav1dError::EINVAL;
+use crate::src::error::Rav1dResult;
+use crate::src::levels::mv;
+use crate::src::levels::N_BL_LEVELS;
+use crate::src::levels::N_BS_SIZES;
+use crate::src::levels::N_PARTITIONS;
+use crate::src::levels::N_RECT_TX_SIZES;
+use crate::src::levels::N_TX_TYPES_PLUS_LL;
 use crate::src::r#ref::Rav1dRef;
+use crate::src::recon_stages::Rav1dReconStages;
 use libc::ptrdiff_t;
 use libc::uintptr_t;
 use std::ffi::c_int;
+use std::ffi::c_uint;
 use std::ffi::c_void;
 use std::ptr;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use std::sync::OnceLock;
 
 pub(crate) const RAV1D_PICTURE_ALIGNMENT: usize = 64;
 pub const DAV1D_PICTURE_ALIGNMENT: usize = RAV1D_PICTURE_ALIGNMENT;
@@ -72,6 +88,641 @@ impl From<Rav1dPictureParameters> for Dav1dPictureParameters {
     }
 }
 
+/// Counts of the coding decisions made in a frame.
+#[derive(Clone, Default)]
+#[repr(C)]
+pub struct Rav1dBlockStats {
+    /// Number of blocks of each `BlockSize`.
+    pub block_size: [u32; N_BS_SIZES],
+    /// Number of partitions of each `BlockPartition`, per `BlockLevel`,
+    /// including the ones implied at the frame edges.
+    pub partition: [[u32; N_PARTITIONS]; N_BL_LEVELS],
+    /// Number of intra blocks, including palette blocks, but not intrabc ones.
+    pub intra: u32,
+    pub inter: u32,
+    pub intrabc: u32,
+    /// Number of blocks with a luma or chroma palette.
+    pub palette: u32,
+    /// Number of blocks without residual.
+    pub skip: u32,
+    /// Number of transform blocks with coefficients,
+    /// of each `RectTxfmSize`, in all planes.
+    pub tx_size: [u32; N_RECT_TX_SIZES],
+    /// Number of transform blocks with coefficients,
+    /// of each `TxfmType`, in all planes.
+    pub tx_type: [u32; N_TX_TYPES_PLUS_LL],
+}
+
+pub type Dav1dBlockStats = Rav1dBlockStats;
+
+impl Rav1dBlockStats {
+    pub(crate) fn add(&mut self, other: &Self) {
+        fn add<const N: usize>(a: &mut [u32; N], b: &[u32; N]) {
+            for (a, b) in a.iter_mut().zip(b) {
+                *a += b;
+            }
+        }
+
+        add(&mut self.block_size, &other.block_size);
+        for (a, b) in self.partition.iter_mut().zip(&other.partition) {
+            add(a, b);
+        }
+        self.intra += other.intra;
+        self.inter += other.inter;
+        self.intrabc += other.intrabc;
+        self.palette += other.palette;
+        self.skip += other.skip;
+        add(&mut self.tx_size, &other.tx_size);
+        add(&mut self.tx_type, &other.tx_type);
+    }
+}
+
+/// Statistics of a decoded frame, see [`DAV1D_EXPORT_STATS`].
+///
+/// [`DAV1D_EXPORT_STATS`]: crate::include::dav1d::dav1d::DAV1D_EXPORT_STATS
+#[repr(C)]
+pub struct Dav1dFrameStats {
+    pub blocks: Dav1dBlockStats,
+    pub n_tiles: c_int,
+    /// Compressed size of each tile, in raster order, in bytes.
+    pub tile_bytes: *const u32,
+}
+
+pub struct Rav1dFrameStats {
+    pub blocks: Rav1dBlockStats,
+    pub tile_bytes: Box<[u32]>,
+}
+
+impl Rav1dFrameStats {
+    pub fn into_drav1d(self) -> DRav1d<Self, Dav1dFrameStats> {
+        let dav1d = Dav1dFrameStats {
+            blocks: self.blocks.clone(),
+            n_tiles: self.tile_bytes.len() as c_int,
+            tile_bytes: self.tile_bytes.as_ptr(),
+        };
+        DRav1d { rav1d: self, dav1d }
+    }
+}
+
+/// See [`Rav1dPictureExports`].
+pub type Rav1dFrameStatsCell = OnceLock<DRav1d<Rav1dFrameStats, Dav1dFrameStats>>;
+
+/// The symbol probabilities of the mode syntax elements, see [`Dav1dCdfContext`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dCdfModeContext {
+    pub y_mode: [[u16; 16]; 4],
+    pub uv_mode: [[[u16; 16]; 13]; 2],
+    pub wedge_idx: [[u16; 16]; 9],
+    pub partition: [[[u16; 16]; 4]; 5],
+    pub cfl_alpha: [[u16; 16]; 6],
+    pub txtp_inter1: [[u16; 16]; 2],
+    pub txtp_inter2: [u16; 16],
+    pub txtp_intra1: [[[u16; 8]; 13]; 2],
+    pub txtp_intra2: [[[u16; 8]; 13]; 3],
+    pub cfl_sign: [u16; 8],
+    pub angle_delta: [[u16; 8]; 8],
+    pub filter_intra: [u16; 8],
+    pub comp_inter_mode: [[u16; 8]; 8],
+    pub seg_id: [[u16; 8]; 3],
+    pub pal_sz: [[[u16; 8]; 7]; 2],
+    pub color_map: [[[[u16; 8]; 5]; 7]; 2],
+    pub filter: [[[u16; 4]; 8]; 2],
+    pub txsz: [[[u16; 4]; 3]; 4],
+    pub motion_mode: [[u16; 4]; 22],
+    pub delta_q: [u16; 4],
+    pub delta_lf: [[u16; 4]; 5],
+    pub interintra_mode: [[u16; 4]; 4],
+    pub restore_switchable: [u16; 4],
+    pub restore_wiener: [u16; 2],
+    pub restore_sgrproj: [u16; 2],
+    pub interintra: [[u16; 2]; 7],
+    pub interintra_wedge: [[u16; 2]; 7],
+    pub txtp_inter3: [[u16; 2]; 4],
+    pub use_filter_intra: [[u16; 2]; 22],
+    pub newmv_mode: [[u16; 2]; 6],
+    pub globalmv_mode: [[u16; 2]; 2],
+    pub refmv_mode: [[u16; 2]; 6],
+    pub drl_bit: [[u16; 2]; 3],
+    pub intra: [[u16; 2]; 4],
+    pub comp: [[u16; 2]; 5],
+    pub comp_dir: [[u16; 2]; 5],
+    pub jnt_comp: [[u16; 2]; 6],
+    pub mask_comp: [[u16; 2]; 6],
+    pub wedge_comp: [[u16; 2]; 9],
+    pub r#ref: [[[u16; 2]; 3]; 6],
+    pub comp_fwd_ref: [[[u16; 2]; 3]; 3],
+    pub comp_bwd_ref: [[[u16; 2]; 3]; 2],
+    pub comp_uni_ref: [[[u16; 2]; 3]; 3],
+    pub txpart: [[[u16; 2]; 3]; 7],
+    pub skip: [[u16; 2]; 3],
+    pub skip_mode: [[u16; 2]; 3],
+    pub seg_pred: [[u16; 2]; 3],
+    pub obmc: [[u16; 2]; 22],
+    pub pal_y: [[[u16; 2]; 3]; 7],
+    pub pal_uv: [[u16; 2]; 2],
+    pub intrabc: [u16; 2],
+}
+
+/// The symbol probabilities of the coefficient syntax elements, see [`Dav1dCdfContext`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dCdfCoefContext {
+    pub eob_bin_16: [[[u16; 8]; 2]; 2],
+    pub eob_bin_32: [[[u16; 8]; 2]; 2],
+    pub eob_bin_64: [[[u16; 8]; 2]; 2],
+    pub eob_bin_128: [[[u16; 8]; 2]; 2],
+    pub eob_bin_256: [[[u16; 16]; 2]; 2],
+    pub eob_bin_512: [[u16; 16]; 2],
+    pub eob_bin_1024: [[u16; 16]; 2],
+    pub eob_base_tok: [[[[u16; 4]; 4]; 2]; 5],
+    pub base_tok: [[[[u16; 4]; 41]; 2]; 5],
+    pub br_tok: [[[[u16; 4]; 21]; 2]; 4],
+    pub eob_hi_bit: [[[[u16; 2]; 11]; 2]; 5],
+    pub skip: [[[u16; 2]; 13]; 5],
+    pub dc_sign: [[[u16; 2]; 3]; 2],
+}
+
+/// The symbol probabilities of one motion vector component, see [`Dav1dCdfContext`].
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dCdfMvComponent {
+    pub classes: [u16; 16],
+    pub class0_fp: [[u16; 4]; 2],
+    pub classN_fp: [u16; 4],
+    pub class0_hp: [u16; 2],
+    pub classN_hp: [u16; 2],
+    pub class0: [u16; 2],
+    pub classN: [[u16; 2]; 10],
+    pub sign: [u16; 2],
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dCdfMvContext {
+    /// The vertical `[0]` and horizontal `[1]` components.
+    pub comp: [Dav1dCdfMvComponent; 2],
+    pub joint: [u16; 4],
+}
+
+/// The symbol probabilities at the end of a frame, see [`DAV1D_EXPORT_CDF`].
+///
+/// These are the CDFs saved with the frame for the frames that use it as their primary reference,
+/// so they are the adapted ones if `refresh_context` is set, and the initial ones otherwise.
+///
+/// Each CDF of `N` symbols is stored as the decoder uses it:
+/// `N - 1` values of 32768 minus the cumulative probability, in units of 1/32768,
+/// then the adaptation counter, padded with zeros to the size of the array.
+/// The fields are named as in the syntax trace, e.g. `"m.intra"` or `"coef.base_tok"`.
+///
+/// This is [`CdfContext`] without the alignment of its fields.
+///
+/// [`DAV1D_EXPORT_CDF`]: crate::include::dav1d::dav1d::DAV1D_EXPORT_CDF
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dCdfContext {
+    pub m: Dav1dCdfModeContext,
+    /// The luma modes of intra frames, by the mode contexts above and left.
+    pub kfym: [[[u16; 16]; 5]; 5],
+    pub coef: Dav1dCdfCoefContext,
+    /// Motion vectors.
+    pub mv: Dav1dCdfMvContext,
+    /// Block vectors of intrabc blocks.
+    pub dmv: Dav1dCdfMvContext,
+}
+
+/// Implements the conversions between a CDF struct and its `Dav1d` version,
+/// copying each field out of or into its `AlignN` wrapper.
+macro_rules! impl_from_unaligned {
+    ($T:ident, $DT:ident { $($field:ident),* $(,)? }) => {
+        impl From<&$T> for $DT {
+            fn from(value: &$T) -> Self {
+                Self {
+                    $($field: value.$field.0,)*
+                }
+            }
+        }
+
+        impl From<&$DT> for $T {
+            fn from(value: &$DT) -> Self {
+                Self {
+                    $($field: value.$field.into(),)*
+                }
+            }
+        }
+    };
+}
+
+impl_from_unaligned!(
+    CdfModeContext,
+    Dav1dCdfModeContext {
+        y_mode,
+        uv_mode,
+        wedge_idx,
+        partition,
+        cfl_alpha,
+        txtp_inter1,
+        txtp_inter2,
+        txtp_intra1,
+        txtp_intra2,
+        cfl_sign,
+        angle_delta,
+        filter_intra,
+        comp_inter_mode,
+        seg_id,
+        pal_sz,
+        color_map,
+        filter,
+        txsz,
+        motion_mode,
+        delta_q,
+        delta_lf,
+        interintra_mode,
+        restore_switchable,
+        restore_wiener,
+        restore_sgrproj,
+        interintra,
+        interintra_wedge,
+        txtp_inter3,
+        use_filter_intra,
+        newmv_mode,
+        globalmv_mode,
+        refmv_mode,
+        drl_bit,
+        intra,
+        comp,
+        comp_dir,
+        jnt_comp,
+        mask_comp,
+        wedge_comp,
+        r#ref,
+        comp_fwd_ref,
+        comp_bwd_ref,
+        comp_uni_ref,
+        txpart,
+        skip,
+        skip_mode,
+        seg_pred,
+        obmc,
+        pal_y,
+        pal_uv,
+        intrabc,
+    }
+);
+
+impl_from_unaligned!(
+    CdfCoefContext,
+    Dav1dCdfCoefContext {
+        eob_bin_16,
+        eob_bin_32,
+        eob_bin_64,
+        eob_bin_128,
+        eob_bin_256,
+        eob_bin_512,
+        eob_bin_1024,
+        eob_base_tok,
+        base_tok,
+        br_tok,
+        eob_hi_bit,
+        skip,
+        dc_sign,
+    }
+);
+
+impl_from_unaligned!(
+    CdfMvComponent,
+    Dav1dCdfMvComponent {
+        classes,
+        class0_fp,
+        classN_fp,
+        class0_hp,
+        classN_hp,
+        class0,
+        classN,
+        sign,
+    }
+);
+
+impl From<&CdfMvContext> for Dav1dCdfMvContext {
+    fn from(value: &CdfMvContext) -> Self {
+        let CdfMvContext { comp, joint } = value;
+        Self {
+            comp: [(&comp[0]).into(), (&comp[1]).into()],
+            joint: joint.0,
+        }
+    }
+}
+
+impl From<&Dav1dCdfMvContext> for CdfMvContext {
+    fn from(value: &Dav1dCdfMvContext) -> Self {
+        let Dav1dCdfMvContext { comp, joint } = value;
+        Self {
+            comp: [(&comp[0]).into(), (&comp[1]).into()],
+            joint: (*joint).into(),
+        }
+    }
+}
+
+impl From<&CdfContext> for Dav1dCdfContext {
+    fn from(value: &CdfContext) -> Self {
+        let CdfContext {
+            m,
+            kfym,
+            coef,
+            mv,
+            dmv,
+        } = value;
+        Self {
+            m: m.into(),
+            kfym: kfym.0,
+            coef: coef.into(),
+            mv: mv.into(),
+            dmv: dmv.into(),
+        }
+    }
+}
+
+impl From<&Dav1dCdfContext> for CdfContext {
+    fn from(value: &Dav1dCdfContext) -> Self {
+        let Dav1dCdfContext {
+            m,
+            kfym,
+            coef,
+            mv,
+            dmv,
+        } = value;
+        Self {
+            m: m.into(),
+            kfym: (*kfym).into(),
+            coef: coef.into(),
+            mv: mv.into(),
+            dmv: dmv.into(),
+        }
+    }
+}
+
+/// See [`Rav1dPictureExports`].
+pub type Rav1dCdfCell = OnceLock<Dav1dCdfContext>;
+
+/// The data exported with a picture.
+///
+/// Each field is [`None`] unless the matching `DAV1D_EXPORT_*` flag
+/// is set in [`Dav1dSettings::export_flags`].
+///
+/// [`Dav1dSettings::export_flags`]: crate::include::dav1d::dav1d::Dav1dSettings::export_flags
+#[derive(Default)]
+#[repr(C)]
+pub struct Dav1dPictureExports {
+    pub stats: Option<NonNull<Dav1dFrameStats>>,
+    pub cdf: Option<NonNull<Dav1dCdfContext>>,
+    pub block_info: Option<NonNull<Dav1dBlockInfoMap>>,
+    pub sb_bits: Option<NonNull<Dav1dSbBitsMap>>,
+    pub tile_layout: Option<NonNull<Dav1dTileLayout>>,
+}
+
+/// The exported data of a picture, kept alive by [`Dav1dPicture::exports_ref`].
+///
+/// Each export is a [`OnceLock`] cell shared with the frame being decoded,
+/// which fills it in once the frame is decoded, before it is output.
+/// The `Dav1d*` view of an export made by its `into_drav1d`
+/// points into the `Box`ed slices of its `Rav1d*` counterpart,
+/// which don't move when that is moved into the [`DRav1d`].
+pub struct Rav1dPictureExports {
+    pub stats: Option<Arc<Rav1dFrameStatsCell>>,
+    pub cdf: Option<Arc<Rav1dCdfCell>>,
+    pub block_info: Option<Arc<Rav1dBlockInfoCell>>,
+    pub sb_bits: Option<Arc<Rav1dSbBitsCell>>,
+    pub tile_layout: Option<Arc<Rav1dTileLayoutCell>>,
+    pub recon_stages: Option<Arc<Rav1dReconStages>>,
+}
+
+impl Rav1dPictureExports {
+    fn is_empty(&self) -> bool {
+        let Self {
+            stats,
+            cdf,
+            recon_stages,
+            block_info,
+            sb_bits,
+            tile_layout,
+        } = self;
+        stats.is_none()
+            && cdf.is_none()
+            && recon_stages.is_none()
+            && block_info.is_none()
+            && sb_bits.is_none()
+            && tile_layout.is_none()
+    }
+
+    pub fn into_drav1d(self) -> DRav1d<Self, Dav1dPictureExports> {
+        // The cells are behind [`Arc`]s, so they don't move with `self`.
+        let dav1d = Dav1dPictureExports {
+            stats: self
+                .stats
+                .as_ref()
+                .and_then(|arc| arc.get())
+                .map(|stats| (&stats.dav1d).into()),
+            cdf: self
+                .cdf
+                .as_ref()
+                .and_then(|arc| arc.get())
+                .map(|cdf| cdf.into()),
+            block_info: self
+                .block_info
+                .as_ref()
+                .and_then(|arc| arc.get())
+                .map(|block_info| (&block_info.dav1d).into()),
+            sb_bits: self
+                .sb_bits
+                .as_ref()
+                .and_then(|arc| arc.get())
+                .map(|sb_bits| (&sb_bits.dav1d).into()),
+            tile_layout: self
+                .tile_layout
+                .as_ref()
+                .and_then(|arc| arc.get())
+                .map(|tile_layout| (&tile_layout.dav1d).into()),
+        };
+        DRav1d { rav1d: self, dav1d }
+    }
+}
+
+/// The coding decisions of the block covering a 4x4 luma unit of a frame,
+/// see [`DAV1D_EXPORT_BLOCK_INFO`].
+///
+/// [`DAV1D_EXPORT_BLOCK_INFO`]: crate::include::dav1d::dav1d::DAV1D_EXPORT_BLOCK_INFO
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct Rav1dBlockInfo {
+    /// Position of the top-left corner of the block, in 4x4 luma units.
+    pub bx: u16,
+    pub by: u16,
+    /// `BlockSize`.
+    pub bs: u8,
+    /// 1 for intra blocks, including palette blocks, but not intrabc ones.
+    pub intra: u8,
+    pub intrabc: u8,
+    /// The luma `IntraPredMode` of intra blocks, and the `InterPredMode`,
+    /// or the `CompInterPredMode` if `ref[1] >= 0`, of inter blocks.
+    pub mode: u8,
+    /// Number of colors of the luma palette of intra blocks.
+    pub pal_sz: u8,
+    /// `CompInterType` of inter blocks.
+    pub comp_type: u8,
+    /// `MotionMode` of inter blocks.
+    pub motion_mode: u8,
+    /// 1 for blocks without residual.
+    pub skip: u8,
+    pub seg_id: u8,
+    /// `RectTxfmSize` of the luma transform block covering the unit.
+    pub tx: u8,
+    /// Reference frames of inter blocks, from 0 for `LAST_FRAME` to 6 for `ALTREF_FRAME`,
+    /// and -1 if unused.
+    pub r#ref: [i8; 2],
+    /// Motion vectors of inter blocks, and the block vector of intrabc blocks in `mv[0]`,
+    /// in 1/8 luma pixels.
+    pub mv: [mv; 2],
+}
+
+pub type Dav1dBlockInfo = Rav1dBlockInfo;
+
+/// The [`Dav1dBlockInfo`] of each 4x4 luma unit of a frame,
+/// before superres upscaling.
+#[repr(C)]
+pub struct Dav1dBlockInfoMap {
+    /// Size of the frame in 4x4 luma units, rounded up to a multiple of 2.
+    pub w4: c_int,
+    pub h4: c_int,
+    /// `w4 * h4` units, in raster order.
+    pub info: *const Dav1dBlockInfo,
+}
+
+pub struct Rav1dBlockInfoMap {
+    pub w4: c_int,
+    pub h4: c_int,
+    pub info: Box<[Rav1dBlockInfo]>,
+}
+
+impl Rav1dBlockInfoMap {
+    pub fn into_drav1d(self) -> DRav1d<Self, Dav1dBlockInfoMap> {
+        let dav1d = Dav1dBlockInfoMap {
+            w4: self.w4,
+            h4: self.h4,
+            info: self.info.as_ptr(),
+        };
+        DRav1d { rav1d: self, dav1d }
+    }
+}
+
+/// See [`Rav1dPictureExports`].
+pub type Rav1dBlockInfoCell = OnceLock<DRav1d<Rav1dBlockInfoMap, Dav1dBlockInfoMap>>;
+
+/// The approximate number of bits each superblock of a frame took to code,
+/// see [`DAV1D_EXPORT_SB_BITS`].
+///
+/// [`DAV1D_EXPORT_SB_BITS`]: crate::include::dav1d::dav1d::DAV1D_EXPORT_SB_BITS
+#[repr(C)]
+pub struct Dav1dSbBitsMap {
+    /// Size of the superblocks in pixels, 64 or 128.
+    pub sb_size: c_int,
+    /// Number of superblocks in each row and column, before superres upscaling.
+    pub sbw: c_int,
+    pub sbh: c_int,
+    /// `sbw * sbh` bit counts, in raster order.
+    pub bits: *const u32,
+}
+
+pub struct Rav1dSbBitsMap {
+    pub sb_size: c_int,
+    pub sbw: c_int,
+    pub sbh: c_int,
+    pub bits: Box<[u32]>,
+}
+
+impl Rav1dSbBitsMap {
+    pub fn into_drav1d(self) -> DRav1d<Self, Dav1dSbBitsMap> {
+        let dav1d = Dav1dSbBitsMap {
+            sb_size: self.sb_size,
+            sbw: self.sbw,
+            sbh: self.sbh,
+            bits: self.bits.as_ptr(),
+        };
+        DRav1d { rav1d: self, dav1d }
+    }
+}
+
+/// See [`Rav1dPictureExports`].
+pub type Rav1dSbBitsCell = OnceLock<DRav1d<Rav1dSbBitsMap, Dav1dSbBitsMap>>;
+
+pub type Dav1dTileStatus = c_uint;
+pub const DAV1D_TILE_ERROR: Dav1dTileStatus = 2;
+pub const DAV1D_TILE_DECODED: Dav1dTileStatus = 1;
+pub const DAV1D_TILE_NOT_DECODED: Dav1dTileStatus = 0;
+
+pub(crate) type Rav1dTileStatus = c_uint;
+pub(crate) const RAV1D_TILE_ERROR: Rav1dTileStatus = DAV1D_TILE_ERROR;
+pub(crate) const RAV1D_TILE_DECODED: Rav1dTileStatus = DAV1D_TILE_DECODED;
+pub(crate) const RAV1D_TILE_NOT_DECODED: Rav1dTileStatus = DAV1D_TILE_NOT_DECODED;
+
+/// Where the data of a tile came from and whether it was decoded,
+/// see [`DAV1D_EXPORT_TILE_INFO`].
+///
+/// [`DAV1D_EXPORT_TILE_INFO`]: crate::include::dav1d::dav1d::DAV1D_EXPORT_TILE_INFO
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct Rav1dTileInfo {
+    /// Offset of the tile data from the start of the [`Dav1dData`] buffer
+    /// it was sent in, as created or wrapped.
+    ///
+    /// [`Dav1dData`]: crate::include::dav1d::data::Dav1dData
+    pub offset: usize,
+    /// Size of the tile data in bytes, without the `tile_size_minus_1` field.
+    pub size: usize,
+    /// Index of the tile group the tile was sent in, in the order of the frame.
+    pub tile_group: c_int,
+    pub status: Dav1dTileStatus,
+}
+
+pub type Dav1dTileInfo = Rav1dTileInfo;
+
+/// The tile grid of a frame and the [`Dav1dTileInfo`] of each tile.
+#[repr(C)]
+pub struct Dav1dTileLayout {
+    /// Number of tile columns and rows.
+    pub cols: c_int,
+    pub rows: c_int,
+    /// `cols + 1` and `rows + 1` tile boundaries in luma pixels,
+    /// before superres upscaling, with the last one at the frame edge.
+    pub col_start: *const c_int,
+    pub row_start: *const c_int,
+    /// `cols * rows` tiles, in raster order.
+    pub tiles: *const Dav1dTileInfo,
+}
+
+pub struct Rav1dTileLayout {
+    pub cols: c_int,
+    pub rows: c_int,
+    pub col_start: Box<[c_int]>,
+    pub row_start: Box<[c_int]>,
+    pub tiles: Box<[Rav1dTileInfo]>,
+}
+
+impl Rav1dTileLayout {
+    pub fn into_drav1d(self) -> DRav1d<Self, Dav1dTileLayout> {
+        let dav1d = Dav1dTileLayout {
+            cols: self.cols,
+            rows: self.rows,
+            col_start: self.col_start.as_ptr(),
+            row_start: self.row_start.as_ptr(),
+            tiles: self.tiles.as_ptr(),
+        };
+        DRav1d { rav1d: self, dav1d }
+    }
+}
+
+/// See [`Rav1dPictureExports`].
+pub type Rav1dTileLayoutCell = OnceLock<DRav1d<Rav1dTileLayout, Dav1dTileLayout>>;
+
 #[derive(Default)]
 #[repr(C)]
 pub struct Dav1dPicture {
@@ -84,13 +735,15 @@ pub struct Dav1dPicture {
     pub content_light: Option<NonNull<Rav1dContentLightLevel>>,
     pub mastering_display: Option<NonNull<Rav1dMasteringDisplay>>,
     pub itut_t35: Option<NonNull<Dav1dITUTT35>>,
-    pub reserved: [uintptr_t; 4],
+    pub exports: Option<NonNull<Dav1dPictureExports>>,
+    pub reserved: [uintptr_t; 3],
     pub frame_hdr_ref: Option<RawArc<DRav1d<Rav1dFrameHeader, Dav1dFrameHeader>>>, // opaque, so we can change this
     pub seq_hdr_ref: Option<RawArc<DRav1d<Rav1dSequenceHeader, Dav1dSequenceHeader>>>, // opaque, so we can change this
     pub content_light_ref: Option<RawArc<Rav1dContentLightLevel>>, // opaque, so we can change this
     pub mastering_display_ref: Option<RawArc<Rav1dMasteringDisplay>>, // opaque, so we can change this
     pub itut_t35_ref: Option<RawArc<DRav1d<Rav1dITUTT35, Dav1dITUTT35>>>, // opaque, so we can change this
-    pub reserved_ref: [uintptr_t; 4],
+    pub exports_ref: Option<RawArc<DRav1d<Rav1dPictureExports, Dav1dPictureExports>>>, // opaque, so we can change this
+    pub reserved_ref: [uintptr_t; 3],
     pub r#ref: Option<NonNull<Dav1dRef>>,
     pub allocator_data: Option<NonNull<c_void>>,
 }
@@ -127,6 +780,12 @@ pub(crate) struct Rav1dPicture {
     pub content_light: Option<Arc<Rav1dContentLightLevel>>,
     pub mastering_display: Option<Arc<Rav1dMasteringDisplay>>,
     pub itut_t35: Option<Arc<DRav1d<Rav1dITUTT35, Dav1dITUTT35>>>,
+    pub stats: Option<Arc<Rav1dFrameStatsCell>>,
+    pub cdf: Option<Arc<Rav1dCdfCell>>,
+    pub recon_stages: Option<Arc<Rav1dReconStages>>,
+    pub block_info: Option<Arc<Rav1dBlockInfoCell>>,
+    pub sb_bits: Option<Arc<Rav1dSbBitsCell>>,
+    pub tile_layout: Option<Arc<Rav1dTileLayoutCell>>,
     pub r#ref: Option<NonNull<Rav1dRef>>,
 }
 
@@ -142,16 +801,21 @@ impl From<Dav1dPicture> for Rav1dPicture {
             content_light: _,
             mastering_display: _,
             itut_t35: _,
+            exports: _,
             reserved: _,
             frame_hdr_ref,
             seq_hdr_ref,
             content_light_ref,
             mastering_display_ref,
             itut_t35_ref,
+            exports_ref,
             reserved_ref: _,
             r#ref,
             allocator_data,
         } = value;
+        // Safety: `raw` came from [`RawArc::from_arc`].
+        let exports = exports_ref.map(|raw| unsafe { raw.into_arc() });
+        let exports = exports.as_ref().map(|arc| &arc.as_ref().rav1d);
         Self {
             // We don't `.update_rav1d()` [`Rav1dSequenceHeader`] because it's meant to be read-only.
             // Safety: `raw` came from [`RawArc::from_arc`].
@@ -173,6 +837,12 @@ impl From<Dav1dPicture> for Rav1dPicture {
             // We don't `.update_rav1d` [`Rav1dITUTT35`] because never read it.
             // Safety: `raw` came from [`RawArc::from_arc`].
             itut_t35: itut_t35_ref.map(|raw| unsafe { raw.into_arc() }),
+            stats: exports.and_then(|exports| exports.stats.clone()),
+            cdf: exports.and_then(|exports| exports.cdf.clone()),
+            block_info: exports.and_then(|exports| exports.block_info.clone()),
+            sb_bits: exports.and_then(|exports| exports.sb_bits.clone()),
+            tile_layout: exports.and_then(|exports| exports.tile_layout.clone()),
+            recon_stages: exports.and_then(|exports| exports.recon_stages.clone()),
             r#ref,
         }
     }
@@ -194,8 +864,23 @@ impl From<Rav1dPicture> for Dav1dPicture {
             content_light,
             mastering_display,
             itut_t35,
+            stats,
+            cdf,
+            recon_stages,
+            block_info,
+            sb_bits,
+            tile_layout,
             r#ref,
         } = value;
+        let exports = Rav1dPictureExports {
+            stats,
+            cdf,
+            recon_stages,
+            block_info,
+            sb_bits,
+            tile_layout,
+        };
+        let exports = (!exports.is_empty()).then(|| Arc::new(exports.into_drav1d()));
         Self {
             // [`DRav1d::from_rav1d`] is called right after [`parse_seq_hdr`].
             seq_hdr: seq_hdr.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
@@ -209,12 +894,14 @@ impl From<Rav1dPicture> for Dav1dPicture {
             mastering_display: mastering_display.as_ref().map(|arc| arc.as_ref().into()),
             // [`DRav1d::from_rav1d`] is called in [`rav1d_parse_obus`].
             itut_t35: itut_t35.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
+            exports: exports.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
             reserved: Default::default(),
             frame_hdr_ref: frame_hdr.map(RawArc::from_arc),
             seq_hdr_ref: seq_hdr.map(RawArc::from_arc),
             content_light_ref: content_light.map(RawArc::from_arc),
             mastering_display_ref: mastering_display.map(RawArc::from_arc),
             itut_t35_ref: itut_t35.map(RawArc::from_arc),
+            exports_ref: exports.map(RawArc::from_arc),
             reserved_ref: Default::default(),
             r#ref,
             allocator_data,
@@ -222,6 +909,146 @@ impl From<Rav1dPicture> for Dav1dPicture {
     }
 }
 
+impl Rav1dPicture {
+    /// A [`Dav1dPicture`] view of `self` that doesn't hold any references,
+    /// so it's only valid while `self` is borrowed and must not be unref'd.
+    ///
+    /// The exported data isn't included, as it's only filled in
+    /// once the frame is decoded.
+    pub(crate) fn dav1d_view(&self) -> Dav1dPicture {
+        let Self {
+            seq_hdr,
+            frame_hdr,
+            data:
+                Rav1dPictureData {
+                    data,
+                    allocator_data,
+                },
+            stride,
+            p,
+            m,
+            content_light,
+            mastering_display,
+            itut_t35,
+            stats: _,
+            cdf: _,
+            recon_stages: _,
+            block_info: _,
+            sb_bits: _,
+            tile_layout: _,
+            r#ref: _,
+        } = self;
+        let Rav1dDataProps {
+            timestamp,
+            duration,
+            offset,
+            size,
+            user_data,
+        } = m;
+        Dav1dPicture {
+            seq_hdr: seq_hdr.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
+            frame_hdr: frame_hdr.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
+            data: data.map(NonNull::new),
+            stride: *stride,
+            p: p.clone().into(),
+            m: Dav1dDataProps {
+                timestamp: *timestamp,
+                duration: *duration,
+                offset: *offset,
+                size: *size,
+                user_data: Dav1dUserData {
+                    data: user_data
+                        .as_ref()
+                        .map(|user_data| user_data.as_ref().into()),
+                    r#ref: None,
+                },
+            },
+            content_light: content_light.as_ref().map(|arc| arc.as_ref().into()),
+            mastering_display: mastering_display.as_ref().map(|arc| arc.as_ref().into()),
+            itut_t35: itut_t35.as_ref().map(|arc| (&arc.as_ref().dav1d).into()),
+            allocator_data: *allocator_data,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use crate::src::picture::dav1d_memfd_picture_alloc;
+#[cfg(target_os = "linux")]
+pub use crate::src::picture::dav1d_memfd_picture_release;
+
+/// The `memfd` region a picture was allocated in
+/// by [`dav1d_memfd_picture_alloc`],
+/// pointed to by the picture's [`Dav1dPicture::allocator_data`].
+///
+/// The file descriptor can be sent to another process
+/// (e.g. over a Unix socket with `SCM_RIGHTS`) and mapped there,
+/// as long as the receiver does so before the picture is released
+/// or keeps its own duplicate of the descriptor.
+/// Its size is sealed with `F_SEAL_SHRINK | F_SEAL_GROW`,
+/// so the receiver can map it without guarding against it being truncated.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dav1dPictureMemfd {
+    /// The `memfd` file descriptor, owned by the picture.
+    pub fd: c_int,
+    /// The size in bytes of the region.
+    pub size: usize,
+    /// The offsets in bytes of [`Dav1dPicture::data`]`[0..3]`
+    /// from the start of the region.
+    /// The strides are those of the picture.
+    pub offset: [usize; 3],
+}
+
+/// The memory the decoder needs for one plane of a picture.
+///
+/// These are the same fixed rules as documented for
+/// [`Dav1dPicAllocator::alloc_picture_callback`],
+/// i.e. a size aligned to 128 pixels
+/// and [`DAV1D_PICTURE_ALIGNMENT`] bytes of alignment and padding,
+/// only spelled out per plane.
+///
+/// No border around the visible picture is needed,
+/// as the decoder never extends edges in place.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Dav1dPlaneRequirements {
+    /// The number of bytes per row the decoder may write to,
+    /// i.e. the minimum absolute value of the plane's stride.
+    /// 0 if the plane is unused.
+    pub width: c_int,
+    /// The number of rows the decoder may write to.
+    pub height: c_int,
+    /// The alignment in bytes of the plane's data pointer and stride.
+    pub alignment: c_int,
+    /// The number of bytes after the last row, i.e. after `height` rows
+    /// in the direction of the stride, that may be read (but not written).
+    pub padding: c_int,
+}
+
+/// The memory the decoder needs for a picture, passed to
+/// [`Dav1dCallbacks::alloc_picture_planes`].
+///
+/// [`data`]`[1]` and [`data`]`[2]` must share the same [`stride`]`[1]`,
+/// but otherwise each plane may be placed in a separate buffer.
+///
+/// [`Dav1dCallbacks::alloc_picture_planes`]: crate::include::dav1d::dav1d::Dav1dCallbacks::alloc_picture_planes
+/// [`data`]: Dav1dPicture::data
+/// [`stride`]: Dav1dPicture::stride
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Dav1dPictureRequirements {
+    /// Y is `[0]`, U is `[1]`, V is `[2]`.
+    pub planes: [Dav1dPlaneRequirements; 3],
+}
+
+pub type Dav1dAllocPicturePlanesCallback = unsafe extern "C" fn(
+    pic: *mut Dav1dPicture,
+    req: *const Dav1dPictureRequirements,
+    size: *mut [usize; 3],
+    cookie: *mut c_void,
+) -> Dav1dResult;
+
 #[derive(Clone)]
 #[repr(C)]
 pub struct Dav1dPicAllocator {
@@ -322,12 +1149,15 @@ pub(crate) struct Rav1dPicAllocator {
 
     /// See [`Dav1dPicAllocator::alloc_picture_callback`].
     ///
+    /// Only [`None`] if [`Self::alloc_picture_planes_callback`] is set,
+    /// which then takes precedence.
+    ///
     /// # Safety
     ///
     /// If frame threading is used, accesses to [`Self::cookie`] must be thread-safe,
     /// i.e. [`Self::cookie`] must be [`Send`]` + `[`Sync`].
     pub alloc_picture_callback:
-        unsafe extern "C" fn(pic: *mut Dav1dPicture, cookie: *mut c_void) -> Dav1dResult,
+        Option<unsafe extern "C" fn(pic: *mut Dav1dPicture, cookie: *mut c_void) -> Dav1dResult>,
 
     /// See [`Dav1dPicAllocator::release_picture_callback`].
     ///
@@ -337,35 +1167,64 @@ pub(crate) struct Rav1dPicAllocator {
     /// i.e. [`Self::cookie`] must be [`Send`]` + `[`Sync`].
     pub release_picture_callback:
         unsafe extern "C" fn(pic: *mut Dav1dPicture, cookie: *mut c_void) -> (),
-}
 
-impl TryFrom<Dav1dPicAllocator> for Rav1dPicAllocator {
-    type Error = Rav1dError;
+    /// See [`Dav1dCallbacks::alloc_picture_planes`].
+    ///
+    /// [`Dav1dCallbacks::alloc_picture_planes`]: crate::include::dav1d::dav1d::Dav1dCallbacks::alloc_picture_planes
+    ///
+    /// # Safety
+    ///
+    /// If frame threading is used, accesses to [`Self::cookie`] must be thread-safe,
+    /// i.e. [`Self::cookie`] must be [`Send`]` + `[`Sync`].
+    pub alloc_picture_planes_callback: Option<Dav1dAllocPicturePlanesCallback>,
+}
 
-    fn try_from(value: Dav1dPicAllocator) -> Result<Self, Self::Error> {
+impl Rav1dPicAllocator {
+    /// Combines the [`Dav1dPicAllocator`] with the
+    /// [`Dav1dCallbacks::alloc_picture_planes`] callback, if any.
+    ///
+    /// [`Dav1dCallbacks::alloc_picture_planes`]: crate::include::dav1d::dav1d::Dav1dCallbacks::alloc_picture_planes
+    pub fn try_with_planes(
+        allocator: Dav1dPicAllocator,
+        alloc_picture_planes_callback: Option<Dav1dAllocPicturePlanesCallback>,
+    ) -> Rav1dResult<Self> {
         let Dav1dPicAllocator {
             cookie,
             alloc_picture_callback,
             release_picture_callback,
-        } = value;
+        } = allocator;
+        validate_input!((
+            alloc_picture_callback.is_some() || alloc_picture_planes_callback.is_some(),
+            EINVAL
+        ))?;
         Ok(Self {
             cookie,
-            alloc_picture_callback: validate_input!(alloc_picture_callback.ok_or(EINVAL))?,
+            alloc_picture_callback,
             release_picture_callback: validate_input!(release_picture_callback.ok_or(EINVAL))?,
+            alloc_picture_planes_callback,
         })
     }
 }
 
+impl TryFrom<Dav1dPicAllocator> for Rav1dPicAllocator {
+    type Error = Rav1dError;
+
+    fn try_from(value: Dav1dPicAllocator) -> Result<Self, Self::Error> {
+        Self::try_with_planes(value, None)
+    }
+}
+
 impl From<Rav1dPicAllocator> for Dav1dPicAllocator {
     fn from(value: Rav1dPicAllocator) -> Self {
         let Rav1dPicAllocator {
             cookie,
             alloc_picture_callback,
             release_picture_callback,
+            alloc_picture_planes_callback: _,
         } = value;
         Self {
             cookie,
-            alloc_picture_callback: Some(alloc_picture_callback),
+            alloc_picture_callback,
             release_picture_callback: Some(release_picture_callback),
         }
     }
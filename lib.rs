@@ -32,7 +32,7 @@ pub mod src {
     pub(crate) mod c_box;
     mod cdef;
     mod cdef_apply;
-    mod cdf;
+    pub(crate) mod cdf;
     mod const_fn;
     pub mod cpu;
     mod ctx;
@@ -57,7 +57,7 @@ pub mod src {
     #[cfg(feature = "bitdepth_16")]
     mod itx_tmpl_16;
     mod itx_tmpl_8;
-    mod levels;
+    pub(crate) mod levels;
     mod lf_apply;
     mod lf_mask;
     pub mod lib;
@@ -69,12 +69,15 @@ pub mod src {
     mod mem;
     mod msac;
     mod obu;
-    mod picture;
+    pub(crate) mod picture;
     mod qm;
     mod recon;
+    pub(crate) mod recon_stages;
     pub(crate) mod r#ref;
     mod refmvs;
     mod scan;
+    pub(crate) mod snapshot;
+    mod syntax_trace;
     mod tables;
     mod thread_data;
     mod thread_task;
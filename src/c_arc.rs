@@ -1,4 +1,5 @@
 use crate::src::c_box::CBox;
+use crate::src::error::Rav1dError::EINVAL;
 use crate::src::error::Rav1dResult;
 use std::marker::PhantomData;
 use std::mem;
@@ -153,6 +154,13 @@ impl<T> RawArc<T> {
         let raw = self.0.cast().as_ptr();
         Arc::from_raw(raw)
     }
+
+    /// # Safety
+    ///
+    /// The [`RawArc`] must be originally from [`Self::from_arc`].
+    pub unsafe fn as_ref(&self) -> &T {
+        self.0.cast().as_ref()
+    }
 }
 
 #[repr(transparent)]
@@ -187,6 +195,22 @@ impl<T> CArc<[T]> {
     {
         self.stable_ref = self.as_ref()[range].into();
     }
+
+    /// The offset of [`Self::stable_ref`] from the start of the slice owned by the [`Arc`],
+    /// i.e. how much has been sliced off by [`Self::slice_in_place`].
+    ///
+    /// Returns [`EINVAL`] if [`Self::stable_ref`] starts before the owned slice.
+    pub fn offset_in_owner(&self) -> Rav1dResult<usize> {
+        let base = (*self.owner).as_ref().get_ref();
+        // Safety: [`Self::stable_ref`] is always a sub-slice of `base`.
+        let offset = unsafe {
+            self.stable_ref
+                .as_ptr()
+                .cast::<T>()
+                .offset_from(base.as_ptr())
+        };
+        offset.try_into().map_err(|_| EINVAL)
+    }
 }
 
 impl<T> CArc<[T]>
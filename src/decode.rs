@@ -28,7 +28,18 @@ use crate::include::dav1d::headers::RAV1D_TX_SWITCHABLE;
 use crate::include::dav1d::headers::RAV1D_WM_TYPE_AFFINE;
 use crate::include::dav1d::headers::RAV1D_WM_TYPE_IDENTITY;
 use crate::include::dav1d::headers::RAV1D_WM_TYPE_TRANSLATION;
+use crate::include::dav1d::picture::Rav1dBlockInfo;
+use crate::include::dav1d::picture::Rav1dBlockInfoMap;
+use crate::include::dav1d::picture::Rav1dBlockStats;
+use crate::include::dav1d::picture::Rav1dFrameStats;
+use crate::include::dav1d::picture::Rav1dSbBitsMap;
+use crate::include::dav1d::picture::Rav1dTileInfo;
+use crate::include::dav1d::picture::Rav1dTileLayout;
+use crate::include::dav1d::picture::RAV1D_TILE_DECODED;
+use crate::include::dav1d::picture::RAV1D_TILE_ERROR;
+use crate::include::dav1d::picture::RAV1D_TILE_NOT_DECODED;
 use crate::src::align::Align16;
+use crate::src::c_arc::CArc;
 use crate::src::cdef::rav1d_cdef_dsp_init;
 use crate::src::cdf::rav1d_cdf_thread_alloc;
 use crate::src::cdf::rav1d_cdf_thread_copy;
@@ -36,6 +47,7 @@ use crate::src::cdf::rav1d_cdf_thread_init_static;
 use crate::src::cdf::rav1d_cdf_thread_ref;
 use crate::src::cdf::rav1d_cdf_thread_unref;
 use crate::src::cdf::rav1d_cdf_thread_update;
+use crate::src::cdf::CdfContext;
 use crate::src::cdf::CdfMvComponent;
 use crate::src::cdf::CdfMvContext;
 use crate::src::ctx::CaseSet;
@@ -179,6 +191,7 @@ use crate::src::picture::rav1d_picture_ref;
 use crate::src::picture::rav1d_picture_unref_internal;
 use crate::src::picture::rav1d_thread_picture_alloc;
 use crate::src::picture::rav1d_thread_picture_ref;
+use crate::src::picture::rav1d_thread_picture_row_progress;
 use crate::src::picture::rav1d_thread_picture_unref;
 use crate::src::picture::Rav1dThreadPicture;
 use crate::src::qm::dav1d_qm_tbl;
@@ -186,6 +199,7 @@ use crate::src::r#ref::rav1d_ref_create_using_pool;
 use crate::src::r#ref::rav1d_ref_dec;
 use crate::src::r#ref::rav1d_ref_inc;
 use crate::src::recon::DEBUG_BLOCK_INFO;
+use crate::src::recon_stages::rav1d_recon_stages_alloc;
 use crate::src::refmvs::rav1d_refmvs_find;
 use crate::src::refmvs::rav1d_refmvs_init_frame;
 use crate::src::refmvs::rav1d_refmvs_save_tmvs;
@@ -195,6 +209,9 @@ use crate::src::refmvs::refmvs_block_unaligned;
 use crate::src::refmvs::refmvs_mvpair;
 use crate::src::refmvs::refmvs_refpair;
 use crate::src::refmvs::refmvs_temporal_block;
+use crate::src::syntax_trace::trace_symbol;
+#[cfg(feature = "syntax_trace")]
+use crate::src::syntax_trace::TileTrace;
 use crate::src::tables::cfl_allowed_mask;
 use crate::src::tables::dav1d_al_part_ctx;
 use crate::src::tables::dav1d_block_dimensions;
@@ -223,6 +240,7 @@ use libc::malloc;
 use libc::ptrdiff_t;
 use libc::uintptr_t;
 use std::array;
+use std::cell::UnsafeCell;
 use std::cmp;
 use std::ffi::c_int;
 use std::ffi::c_uint;
@@ -233,7 +251,10 @@ use std::ptr;
 use std::ptr::addr_of_mut;
 use std::slice;
 use std::sync::atomic::AtomicI32;
+use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::OnceLock;
 
 #[cfg(feature = "bitdepth_8")]
 use crate::src::itx_tmpl_8::rav1d_itx_dsp_init_8bpc;
@@ -279,22 +300,42 @@ unsafe fn read_mv_component_diff(
     let ts = &mut *t.ts;
     let f = &*t.f;
     let have_hp = f.frame_hdr.as_ref().unwrap().hp != 0;
-    let sign = rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.sign.0);
-    let cl = rav1d_msac_decode_symbol_adapt16(&mut ts.msac, &mut mv_comp.classes.0, 10);
+    let sign = trace_symbol!(
+        ts,
+        "mv_sign",
+        rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.sign.0)
+    );
+    let cl = trace_symbol!(
+        ts,
+        "mv_class",
+        rav1d_msac_decode_symbol_adapt16(&mut ts.msac, &mut mv_comp.classes.0, 10)
+    );
     let mut up;
     let fp;
     let hp;
 
     if cl == 0 {
-        up = rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.class0.0) as c_uint;
+        up = trace_symbol!(
+            ts,
+            "mv_class0_bit",
+            rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.class0.0)
+        ) as c_uint;
         if have_fp {
-            fp = rav1d_msac_decode_symbol_adapt4(
-                &mut ts.msac,
-                &mut mv_comp.class0_fp[up as usize],
-                3,
+            fp = trace_symbol!(
+                ts,
+                "mv_class0_fr",
+                rav1d_msac_decode_symbol_adapt4(
+                    &mut ts.msac,
+                    &mut mv_comp.class0_fp[up as usize],
+                    3,
+                )
             );
             hp = if have_hp {
-                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.class0_hp.0)
+                trace_symbol!(
+                    ts,
+                    "mv_class0_hp",
+                    rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.class0_hp.0)
+                )
             } else {
                 true
             };
@@ -305,13 +346,25 @@ unsafe fn read_mv_component_diff(
     } else {
         up = 1 << cl;
         for n in 0..cl as usize {
-            up |=
-                (rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.classN[n]) as c_uint) << n;
+            up |= (trace_symbol!(
+                ts,
+                "mv_bit",
+                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.classN[n])
+            ) as c_uint)
+                << n;
         }
         if have_fp {
-            fp = rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut mv_comp.classN_fp.0, 3);
+            fp = trace_symbol!(
+                ts,
+                "mv_fr",
+                rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut mv_comp.classN_fp.0, 3)
+            );
             hp = if have_hp {
-                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.classN_hp.0)
+                trace_symbol!(
+                    ts,
+                    "mv_hp",
+                    rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut mv_comp.classN_hp.0)
+                )
             } else {
                 true
             };
@@ -338,10 +391,14 @@ unsafe fn read_mv_residual(
     have_fp: bool,
 ) {
     let ts = &mut *t.ts;
-    match rav1d_msac_decode_symbol_adapt4(
-        &mut ts.msac,
-        &mut ts.cdf.mv.joint.0,
-        N_MV_JOINTS as usize - 1,
+    match trace_symbol!(
+        ts,
+        "mv_joint",
+        rav1d_msac_decode_symbol_adapt4(
+            &mut ts.msac,
+            &mut ts.cdf.mv.joint.0,
+            N_MV_JOINTS as usize - 1,
+        )
     ) {
         MV_JOINT_HV => {
             ref_mv.y += read_mv_component_diff(t, &mut mv_cdf.comp[0], have_fp) as i16;
@@ -378,9 +435,13 @@ unsafe fn read_tx_tree(
         let a = ((*t.a).tx.0[bx4 as usize] < txw) as c_int;
         let l = (t.l.tx.0[by4 as usize] < txh) as c_int;
 
-        is_split = rav1d_msac_decode_bool_adapt(
-            &mut (*t.ts).msac,
-            &mut (*t.ts).cdf.m.txpart[cat as usize][(a + l) as usize],
+        is_split = trace_symbol!(
+            (*t.ts),
+            "txfm_split",
+            rav1d_msac_decode_bool_adapt(
+                &mut (*t.ts).msac,
+                &mut (*t.ts).cdf.m.txpart[cat as usize][(a + l) as usize],
+            )
         );
         if is_split {
             masks[depth as usize] |= 1 << (y_off * 4 + x_off);
@@ -698,10 +759,14 @@ unsafe fn read_pal_plane(
     // as well as the borrowck error here if `dbg` is not hoisted.
     let dbg = DEBUG_BLOCK_INFO(f, t);
 
-    let pal_sz = rav1d_msac_decode_symbol_adapt8(
-        &mut ts.msac,
-        &mut ts.cdf.m.pal_sz[pli][sz_ctx as usize],
-        6,
+    let pal_sz = trace_symbol!(
+        ts,
+        "palette_size_minus_2",
+        rav1d_msac_decode_symbol_adapt8(
+            &mut ts.msac,
+            &mut ts.cdf.m.pal_sz[pli][sz_ctx as usize],
+            6,
+        )
     ) as u8
         + 2;
     b.pal_sz_mut()[pli] = pal_sz;
@@ -785,7 +850,11 @@ unsafe fn read_pal_plane(
         if !(i < pal_sz) {
             break;
         }
-        if rav1d_msac_decode_bool_equi(&mut ts.msac) {
+        if trace_symbol!(
+            ts,
+            "use_palette_color_cache",
+            rav1d_msac_decode_bool_equi(&mut ts.msac)
+        ) {
             used_cache[i] = *cache;
             i += 1;
         }
@@ -803,16 +872,30 @@ unsafe fn read_pal_plane(
     };
     let pal = &mut pal[..pal_sz];
     if i < pal.len() {
-        let mut prev = rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as u32) as u16;
+        let mut prev = trace_symbol!(
+            ts,
+            "palette_colors",
+            rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as u32)
+        ) as u16;
         pal[i] = prev;
         i += 1;
 
         if i < pal.len() {
-            let mut bits = f.cur.p.bpc as u32 + rav1d_msac_decode_bools(&mut ts.msac, 2) - 3;
+            let mut bits = f.cur.p.bpc as u32
+                + trace_symbol!(
+                    ts,
+                    "palette_num_extra_bits",
+                    rav1d_msac_decode_bools(&mut ts.msac, 2)
+                )
+                - 3;
             let max = (1 << f.cur.p.bpc) - 1;
 
             loop {
-                let delta = rav1d_msac_decode_bools(&mut ts.msac, bits) as u16;
+                let delta = trace_symbol!(
+                    ts,
+                    "palette_delta",
+                    rav1d_msac_decode_bools(&mut ts.msac, bits)
+                ) as u16;
                 prev = cmp::min(prev + delta + not_pl, max);
                 pal[i] = prev;
                 i += 1;
@@ -890,21 +973,51 @@ unsafe fn read_pal_uv(
         &mut t.scratch.c2rust_unnamed_0.pal[2]
     };
     let pal = &mut pal[..b.pal_sz()[1] as usize];
-    if rav1d_msac_decode_bool_equi(&mut ts.msac) {
-        let bits = f.cur.p.bpc as u32 + rav1d_msac_decode_bools(&mut ts.msac, 2) - 4;
-        let mut prev = rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as c_uint) as u16;
+    if trace_symbol!(
+        ts,
+        "delta_encode_palette_colors_v",
+        rav1d_msac_decode_bool_equi(&mut ts.msac)
+    ) {
+        let bits = f.cur.p.bpc as u32
+            + trace_symbol!(
+                ts,
+                "palette_num_extra_bits_v",
+                rav1d_msac_decode_bools(&mut ts.msac, 2)
+            )
+            - 4;
+        let mut prev = trace_symbol!(
+            ts,
+            "palette_colors_v",
+            rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as c_uint)
+        ) as u16;
         pal[0] = prev;
         let max = (1 << f.cur.p.bpc) - 1;
         for pal in &mut pal[1..] {
-            let mut delta = rav1d_msac_decode_bools(&mut ts.msac, bits) as i16;
-            if delta != 0 && rav1d_msac_decode_bool_equi(&mut ts.msac) {
+            let mut delta = trace_symbol!(
+                ts,
+                "palette_delta_v",
+                rav1d_msac_decode_bools(&mut ts.msac, bits)
+            ) as i16;
+            if delta != 0
+                && trace_symbol!(
+                    ts,
+                    "palette_delta_sign_bit_v",
+                    rav1d_msac_decode_bool_equi(&mut ts.msac)
+                )
+            {
                 delta = -delta;
             }
             prev = ((prev as i16 + delta) as u16) & max;
             *pal = prev;
         }
     } else {
-        pal.fill_with(|| rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as c_uint) as u16);
+        pal.fill_with(|| {
+            trace_symbol!(
+                ts,
+                "palette_colors_v",
+                rav1d_msac_decode_bools(&mut ts.msac, f.cur.p.bpc as c_uint)
+            ) as u16
+        });
     }
     if dbg {
         print!("Post-pal[pl=2]: r={} ", ts.msac.rng);
@@ -1007,7 +1120,11 @@ unsafe fn read_pal_indices(
     let pal_sz = b.pal_sz()[pli] as usize;
 
     let stride = bw4 * 4;
-    pal_idx[0] = rav1d_msac_decode_uniform(&mut ts.msac, pal_sz as c_uint) as u8;
+    pal_idx[0] = trace_symbol!(
+        ts,
+        "color_index_map",
+        rav1d_msac_decode_uniform(&mut ts.msac, pal_sz as c_uint)
+    ) as u8;
     let color_map_cdf = &mut ts.cdf.m.color_map[pli][pal_sz - 2];
     let Rav1dTaskContext_scratch_pal {
         pal_order: order,
@@ -1019,10 +1136,14 @@ unsafe fn read_pal_indices(
         let last = (i + 1).checked_sub(h4 * 4).unwrap_or(0);
         order_palette(pal_idx, stride, i, first, last, order, ctx);
         for (m, j) in (last..=first).rev().enumerate() {
-            let color_idx = rav1d_msac_decode_symbol_adapt8(
-                &mut ts.msac,
-                &mut color_map_cdf[ctx[m] as usize],
-                pal_sz - 1,
+            let color_idx = trace_symbol!(
+                ts,
+                "palette_color_idx",
+                rav1d_msac_decode_symbol_adapt8(
+                    &mut ts.msac,
+                    &mut color_map_cdf[ctx[m] as usize],
+                    pal_sz - 1,
+                )
             ) as usize;
             pal_idx[(i - j) * stride + j] = order[m][color_idx];
         }
@@ -1431,6 +1552,140 @@ unsafe fn obmc_lowest_px(
     }
 }
 
+/// Sets [`Rav1dBlockInfo::tx`] of the units covered by the transform block `tx`
+/// of a var-tx tree, which is split like in `read_coef_tree`.
+///
+/// # Safety
+///
+/// The units must only be accessed by the calling tile thread, see [`store_block_info`].
+unsafe fn block_info_tx_tree(
+    units: &[UnsafeCell<Rav1dBlockInfo>],
+    stride: usize,
+    [x, y]: [usize; 2],
+    [w4, h4]: [usize; 2],
+    tx: RectTxfmSize,
+    depth: usize,
+    tx_split: [u16; 2],
+    [x_off, y_off]: [usize; 2],
+) {
+    let t_dim = &dav1d_txfm_dimensions[tx as usize];
+    let txw = t_dim.w as usize;
+    let txh = t_dim.h as usize;
+    if depth < 2 && tx_split[depth] & 1 << y_off * 4 + x_off != 0 {
+        let sub = t_dim.sub as RectTxfmSize;
+        let sub_t_dim = &dav1d_txfm_dimensions[sub as usize];
+        let txsw = sub_t_dim.w as usize;
+        let txsh = sub_t_dim.h as usize;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            if dx != 0 && (txw < txh || x + txsw >= w4) || dy != 0 && (txh < txw || y + txsh >= h4)
+            {
+                continue;
+            }
+            block_info_tx_tree(
+                units,
+                stride,
+                [x + dx * txsw, y + dy * txsh],
+                [w4, h4],
+                sub,
+                depth + 1,
+                tx_split,
+                [x_off * 2 + dx, y_off * 2 + dy],
+            );
+        }
+    } else {
+        for row in units[y * stride..]
+            .chunks(stride)
+            .take(cmp::min(txh, h4 - y))
+        {
+            for unit in &row[x..cmp::min(x + txw, w4)] {
+                (*unit.get()).tx = tx as u8;
+            }
+        }
+    }
+}
+
+/// Writes the [`Rav1dBlockInfo`] of the block at `t.bx, t.by`
+/// into [`Rav1dFrameContext::block_info_units`].
+///
+/// The block is inside the tile of `t`, and each tile is decoded by one thread at a time,
+/// so the units written here aren't accessed concurrently.
+unsafe fn store_block_info(
+    f: &Rav1dFrameContext,
+    t: &Rav1dTaskContext,
+    b: &Av1Block,
+    bs: BlockSize,
+    w4: c_int,
+    h4: c_int,
+) {
+    let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
+    let stride = f.bw as usize;
+    let Some(units) = &f.block_info_units else {
+        return;
+    };
+    let units = &units[t.by as usize * stride + t.bx as usize..];
+    let w4 = w4 as usize;
+    let h4 = h4 as usize;
+
+    let mut info = Rav1dBlockInfo {
+        bx: t.bx as u16,
+        by: t.by as u16,
+        bs: bs as u8,
+        skip: b.skip,
+        seg_id: b.seg_id,
+        r#ref: [-1, -1],
+        ..Default::default()
+    };
+    if b.intra != 0 {
+        info.intra = 1;
+        info.mode = b.y_mode();
+        info.pal_sz = b.pal_sz()[0];
+        info.tx = b.tx();
+    } else {
+        if frame_hdr.frame_type.is_inter_or_switch() {
+            info.mode = b.inter_mode();
+            info.comp_type = b.comp_type();
+            info.r#ref = b.r#ref();
+            if b.comp_type() == COMP_INTER_NONE {
+                // `mv[1]` shares its storage with the warp parameters
+                info.motion_mode = b.motion_mode();
+                info.mv[0] = b.mv()[0];
+            } else {
+                info.mv = *b.mv();
+            }
+        } else {
+            info.intrabc = 1;
+            info.mv[0] = b.mv()[0];
+        }
+        info.tx = b.max_ytx();
+    }
+    for row in units.chunks(stride).take(h4) {
+        for unit in &row[..w4] {
+            *unit.get() = info;
+        }
+    }
+
+    if b.intra == 0 && b.skip == 0 {
+        let tx_split = [b.tx_split0() as u16, b.tx_split1()];
+        if tx_split != [0, 0] {
+            let ytx = &dav1d_txfm_dimensions[b.max_ytx() as usize];
+            for y in (0..h4).step_by(ytx.h as usize) {
+                for x in (0..w4).step_by(ytx.w as usize) {
+                    block_info_tx_tree(
+                        units,
+                        stride,
+                        [x, y],
+                        [w4, h4],
+                        b.max_ytx() as RectTxfmSize,
+                        0,
+                        tx_split,
+                        [x / ytx.w as usize, y / ytx.h as usize],
+                    );
+                }
+            }
+        }
+    }
+}
+
 unsafe fn decode_b(
     c: &Rav1dContext,
     t: &mut Rav1dTaskContext,
@@ -1453,6 +1708,7 @@ unsafe fn decode_b(
 
     let ts = &mut *t.ts;
     let f = &mut *t.f;
+    ts.set_trace_pos(t.bx, t.by, f.sb_shift);
     let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
     let mut b_mem = Default::default();
     let b = if t.frame_thread.pass != 0 {
@@ -1634,9 +1890,13 @@ unsafe fn decode_b(
         } else if frame_hdr.segmentation.seg_data.preskip != 0 {
             if frame_hdr.segmentation.temporal != 0 && {
                 let index = (*t.a).seg_pred.0[bx4 as usize] + t.l.seg_pred.0[by4 as usize];
-                seg_pred = rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.seg_pred.0[index as usize],
+                seg_pred = trace_symbol!(
+                    ts,
+                    "seg_id_predicted",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.seg_pred.0[index as usize],
+                    )
                 );
                 seg_pred
             } {
@@ -1654,10 +1914,14 @@ unsafe fn decode_b(
             } else {
                 let (pred_seg_id, seg_ctx) =
                     get_cur_frame_segid(t.by, t.bx, have_top, have_left, f.cur_segmap, f.b4_stride);
-                let diff = rav1d_msac_decode_symbol_adapt8(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.seg_id[seg_ctx as usize],
-                    RAV1D_MAX_SEGMENTS as usize - 1,
+                let diff = trace_symbol!(
+                    ts,
+                    "segment_id",
+                    rav1d_msac_decode_symbol_adapt8(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.seg_id[seg_ctx as usize],
+                        RAV1D_MAX_SEGMENTS as usize - 1,
+                    )
                 );
                 let last_active_seg_id = frame_hdr.segmentation.seg_data.last_active_segid;
                 b.seg_id =
@@ -1689,9 +1953,11 @@ unsafe fn decode_b(
         && cmp::min(bw4, bh4) > 1
     {
         let smctx = (*t.a).skip_mode.0[bx4 as usize] + t.l.skip_mode.0[by4 as usize];
-        b.skip_mode =
+        b.skip_mode = trace_symbol!(
+            ts,
+            "skip_mode",
             rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.skip_mode.0[smctx as usize])
-                as u8;
+        ) as u8;
         if DEBUG_BLOCK_INFO(f, t) {
             println!("Post-skipmode[{}]: r={}", b.skip_mode, ts.msac.rng);
         }
@@ -1704,8 +1970,11 @@ unsafe fn decode_b(
         b.skip = 1;
     } else {
         let sctx = (*t.a).skip[bx4 as usize] + t.l.skip[by4 as usize];
-        b.skip =
-            rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.skip[sctx as usize]) as u8;
+        b.skip = trace_symbol!(
+            ts,
+            "skip",
+            rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.skip[sctx as usize])
+        ) as u8;
         if DEBUG_BLOCK_INFO(f, t) {
             println!("Post-skip[{}]: r={}", b.skip, ts.msac.rng);
         }
@@ -1718,9 +1987,13 @@ unsafe fn decode_b(
     {
         if b.skip == 0 && frame_hdr.segmentation.temporal != 0 && {
             let index = (*t.a).seg_pred.0[bx4 as usize] + t.l.seg_pred.0[by4 as usize];
-            seg_pred = rav1d_msac_decode_bool_adapt(
-                &mut ts.msac,
-                &mut ts.cdf.m.seg_pred.0[index as usize],
+            seg_pred = trace_symbol!(
+                ts,
+                "seg_id_predicted",
+                rav1d_msac_decode_bool_adapt(
+                    &mut ts.msac,
+                    &mut ts.cdf.m.seg_pred.0[index as usize],
+                )
             );
             seg_pred
         } {
@@ -1741,10 +2014,14 @@ unsafe fn decode_b(
             if b.skip != 0 {
                 b.seg_id = pred_seg_id as u8;
             } else {
-                let diff = rav1d_msac_decode_symbol_adapt8(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.seg_id[seg_ctx as usize],
-                    RAV1D_MAX_SEGMENTS as usize - 1,
+                let diff = trace_symbol!(
+                    ts,
+                    "segment_id",
+                    rav1d_msac_decode_symbol_adapt8(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.seg_id[seg_ctx as usize],
+                        RAV1D_MAX_SEGMENTS as usize - 1,
+                    )
                 );
                 let last_active_seg_id = frame_hdr.segmentation.seg_data.last_active_segid;
                 b.seg_id =
@@ -1775,7 +2052,11 @@ unsafe fn decode_b(
             0
         } as isize;
         if *(t.cur_sb_cdef_idx_ptr).offset(idx) == -1 {
-            let v = rav1d_msac_decode_bools(&mut ts.msac, frame_hdr.cdef.n_bits as c_uint) as i8;
+            let v = trace_symbol!(
+                ts,
+                "cdef_idx",
+                rav1d_msac_decode_bools(&mut ts.msac, frame_hdr.cdef.n_bits as c_uint)
+            ) as i8;
             *(t.cur_sb_cdef_idx_ptr).offset(idx) = v;
             if bw4 > 16 {
                 *(t.cur_sb_cdef_idx_ptr).offset(idx + 1) = v;
@@ -1812,15 +2093,30 @@ unsafe fn decode_b(
         let prev_delta_lf = ts.last_delta_lf;
 
         if have_delta_q {
-            let mut delta_q =
-                rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut ts.cdf.m.delta_q.0, 3) as c_int;
+            let mut delta_q = trace_symbol!(
+                ts,
+                "delta_q_abs",
+                rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut ts.cdf.m.delta_q.0, 3)
+            ) as c_int;
             if delta_q == 3 {
-                let n_bits = 1 + rav1d_msac_decode_bools(&mut ts.msac, 3);
-                delta_q =
-                    (rav1d_msac_decode_bools(&mut ts.msac, n_bits) + 1 + (1 << n_bits)) as c_int;
+                let n_bits = 1 + trace_symbol!(
+                    ts,
+                    "delta_q_rem_bits",
+                    rav1d_msac_decode_bools(&mut ts.msac, 3)
+                );
+                delta_q = (trace_symbol!(
+                    ts,
+                    "delta_q_abs_bits",
+                    rav1d_msac_decode_bools(&mut ts.msac, n_bits)
+                ) + 1
+                    + (1 << n_bits)) as c_int;
             }
             if delta_q != 0 {
-                if rav1d_msac_decode_bool_equi(&mut ts.msac) {
+                if trace_symbol!(
+                    ts,
+                    "delta_q_sign_bit",
+                    rav1d_msac_decode_bool_equi(&mut ts.msac)
+                ) {
                     delta_q = -delta_q;
                 }
                 delta_q *= 1 << frame_hdr.delta.q.res_log2;
@@ -1846,19 +2142,34 @@ unsafe fn decode_b(
 
                 for i in 0..n_lfs as usize {
                     let delta_lf_index = i + frame_hdr.delta.lf.multi as usize;
-                    let mut delta_lf = rav1d_msac_decode_symbol_adapt4(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.delta_lf[delta_lf_index],
-                        3,
+                    let mut delta_lf = trace_symbol!(
+                        ts,
+                        "delta_lf_abs",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.delta_lf[delta_lf_index],
+                            3,
+                        )
                     ) as c_int;
                     if delta_lf == 3 {
-                        let n_bits = 1 + rav1d_msac_decode_bools(&mut ts.msac, 3);
-                        delta_lf = (rav1d_msac_decode_bools(&mut ts.msac, n_bits)
-                            + 1
+                        let n_bits = 1 + trace_symbol!(
+                            ts,
+                            "delta_lf_rem_bits",
+                            rav1d_msac_decode_bools(&mut ts.msac, 3)
+                        );
+                        delta_lf = (trace_symbol!(
+                            ts,
+                            "delta_lf_abs_bits",
+                            rav1d_msac_decode_bools(&mut ts.msac, n_bits)
+                        ) + 1
                             + (1 << n_bits)) as c_int;
                     }
                     if delta_lf != 0 {
-                        if rav1d_msac_decode_bool_equi(&mut ts.msac) {
+                        if trace_symbol!(
+                            ts,
+                            "delta_lf_sign_bit",
+                            rav1d_msac_decode_bool_equi(&mut ts.msac)
+                        ) {
                             delta_lf = -delta_lf;
                         }
                         delta_lf *= 1 << frame_hdr.delta.lf.res_log2;
@@ -1896,15 +2207,21 @@ unsafe fn decode_b(
             b.intra = (seg.r#ref == 0) as u8;
         } else {
             let ictx = get_intra_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-            b.intra =
-                (!rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.intra[ictx.into()]))
-                    as u8;
+            b.intra = (!trace_symbol!(
+                ts,
+                "is_inter",
+                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.intra[ictx.into()])
+            )) as u8;
             if DEBUG_BLOCK_INFO(f, t) {
                 println!("Post-intra[{}]: r={}", b.intra, ts.msac.rng);
             }
         }
     } else if frame_hdr.allow_intrabc != 0 {
-        b.intra = (!rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.intrabc.0)) as u8;
+        b.intra = (!trace_symbol!(
+            ts,
+            "use_intrabc",
+            rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.intrabc.0)
+        )) as u8;
         if DEBUG_BLOCK_INFO(f, t) {
             println!("Post-intrabcflag[{}]: r={}", b.intra, ts.msac.rng);
         }
@@ -1921,10 +2238,14 @@ unsafe fn decode_b(
                 [dav1d_intra_mode_context[(*t.a).mode.0[bx4 as usize] as usize] as usize]
                 [dav1d_intra_mode_context[t.l.mode.0[by4 as usize] as usize] as usize]
         };
-        *b.y_mode_mut() = rav1d_msac_decode_symbol_adapt16(
-            &mut ts.msac,
-            ymode_cdf,
-            (N_INTRA_PRED_MODES - 1) as usize,
+        *b.y_mode_mut() = trace_symbol!(
+            ts,
+            "y_mode",
+            rav1d_msac_decode_symbol_adapt16(
+                &mut ts.msac,
+                ymode_cdf,
+                (N_INTRA_PRED_MODES - 1) as usize,
+            )
         ) as u8;
         if DEBUG_BLOCK_INFO(f, t) {
             println!("Post-ymode[{}]: r={}", b.y_mode(), ts.msac.rng);
@@ -1933,7 +2254,11 @@ unsafe fn decode_b(
         // angle delta
         if b_dim[2] + b_dim[3] >= 2 && b.y_mode() >= VERT_PRED && b.y_mode() <= VERT_LEFT_PRED {
             let acdf = &mut ts.cdf.m.angle_delta[b.y_mode() as usize - VERT_PRED as usize];
-            let angle = rav1d_msac_decode_symbol_adapt8(&mut ts.msac, acdf, 6);
+            let angle = trace_symbol!(
+                ts,
+                "angle_delta_y",
+                rav1d_msac_decode_symbol_adapt8(&mut ts.msac, acdf, 6)
+            );
             *b.y_angle_mut() = angle as i8 - 3;
         } else {
             *b.y_angle_mut() = 0;
@@ -1946,10 +2271,14 @@ unsafe fn decode_b(
                 (cfl_allowed_mask & (1 << bs)) != 0
             };
             let uvmode_cdf = &mut ts.cdf.m.uv_mode[cfl_allowed as usize][b.y_mode() as usize];
-            *b.uv_mode_mut() = rav1d_msac_decode_symbol_adapt16(
-                &mut ts.msac,
-                uvmode_cdf,
-                (N_UV_INTRA_PRED_MODES as usize) - 1 - (!cfl_allowed as usize),
+            *b.uv_mode_mut() = trace_symbol!(
+                ts,
+                "uv_mode",
+                rav1d_msac_decode_symbol_adapt16(
+                    &mut ts.msac,
+                    uvmode_cdf,
+                    (N_UV_INTRA_PRED_MODES as usize) - 1 - (!cfl_allowed as usize),
+                )
             ) as u8;
             if DEBUG_BLOCK_INFO(f, t) {
                 println!("Post-uvmode[{}]: r={}", b.uv_mode(), ts.msac.rng);
@@ -1957,17 +2286,24 @@ unsafe fn decode_b(
 
             *b.uv_angle_mut() = 0;
             if b.uv_mode() == CFL_PRED {
-                let sign =
-                    rav1d_msac_decode_symbol_adapt8(&mut ts.msac, &mut ts.cdf.m.cfl_sign.0, 7) + 1;
+                let sign = trace_symbol!(
+                    ts,
+                    "cfl_alpha_signs",
+                    rav1d_msac_decode_symbol_adapt8(&mut ts.msac, &mut ts.cdf.m.cfl_sign.0, 7)
+                ) + 1;
                 let sign_u = sign * 0x56 >> 8;
                 let sign_v = sign - sign_u * 3;
                 assert!(sign_u == sign / 3);
                 if sign_u != 0 {
                     let ctx = (sign_u == 2) as usize * 3 + sign_v as usize;
-                    b.cfl_alpha_mut()[0] = rav1d_msac_decode_symbol_adapt16(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.cfl_alpha[ctx],
-                        15,
+                    b.cfl_alpha_mut()[0] = trace_symbol!(
+                        ts,
+                        "cfl_alpha_u",
+                        rav1d_msac_decode_symbol_adapt16(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.cfl_alpha[ctx],
+                            15,
+                        )
                     ) as i8
                         + 1;
                     if sign_u == 1 {
@@ -1978,10 +2314,14 @@ unsafe fn decode_b(
                 }
                 if sign_v != 0 {
                     let ctx = (sign_v == 2) as usize * 3 + sign_u as usize;
-                    b.cfl_alpha_mut()[1] = rav1d_msac_decode_symbol_adapt16(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.cfl_alpha[ctx],
-                        15,
+                    b.cfl_alpha_mut()[1] = trace_symbol!(
+                        ts,
+                        "cfl_alpha_v",
+                        rav1d_msac_decode_symbol_adapt16(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.cfl_alpha[ctx],
+                            15,
+                        )
                     ) as i8
                         + 1;
                     if sign_v == 1 {
@@ -2003,7 +2343,11 @@ unsafe fn decode_b(
                 && b.uv_mode() <= VERT_LEFT_PRED as u8
             {
                 let acdf = &mut ts.cdf.m.angle_delta[b.uv_mode() as usize - VERT_PRED as usize];
-                let angle = rav1d_msac_decode_symbol_adapt8(&mut ts.msac, acdf, 6) as c_int;
+                let angle = trace_symbol!(
+                    ts,
+                    "angle_delta_uv",
+                    rav1d_msac_decode_symbol_adapt8(&mut ts.msac, acdf, 6)
+                ) as c_int;
                 *b.uv_angle_mut() = (angle - 3) as i8;
             }
         }
@@ -2014,9 +2358,13 @@ unsafe fn decode_b(
             if b.y_mode() == DC_PRED {
                 let pal_ctx = ((*t.a).pal_sz.0[bx4 as usize] > 0) as usize
                     + (t.l.pal_sz.0[by4 as usize] > 0) as usize;
-                let use_y_pal = rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.pal_y[sz_ctx as usize][pal_ctx],
+                let use_y_pal = trace_symbol!(
+                    ts,
+                    "has_palette_y",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.pal_y[sz_ctx as usize][pal_ctx],
+                    )
                 );
                 if DEBUG_BLOCK_INFO(f, t) {
                     println!("Post-y_pal[{}]: r={}", use_y_pal, ts.msac.rng);
@@ -2028,9 +2376,13 @@ unsafe fn decode_b(
 
             if has_chroma && b.uv_mode() == DC_PRED {
                 let pal_ctx = b.pal_sz()[0] > 0;
-                let use_uv_pal = rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.pal_uv[pal_ctx as usize],
+                let use_uv_pal = trace_symbol!(
+                    ts,
+                    "has_palette_uv",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.pal_uv[pal_ctx as usize],
+                    )
                 );
                 if DEBUG_BLOCK_INFO(f, t) {
                     println!("Post-uv_pal[{}]: r={}", use_uv_pal, ts.msac.rng);
@@ -2047,15 +2399,21 @@ unsafe fn decode_b(
             && cmp::max(b_dim[2], b_dim[3]) <= 3
             && seq_hdr.filter_intra != 0
         {
-            let is_filter = rav1d_msac_decode_bool_adapt(
-                &mut ts.msac,
-                &mut ts.cdf.m.use_filter_intra[bs as usize],
+            let is_filter = trace_symbol!(
+                ts,
+                "use_filter_intra",
+                rav1d_msac_decode_bool_adapt(
+                    &mut ts.msac,
+                    &mut ts.cdf.m.use_filter_intra[bs as usize],
+                )
             );
             if is_filter {
                 *b.y_mode_mut() = FILTER_PRED as u8;
-                *b.y_angle_mut() =
+                *b.y_angle_mut() = trace_symbol!(
+                    ts,
+                    "filter_intra_mode",
                     rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut ts.cdf.m.filter_intra.0, 4)
-                        as i8;
+                ) as i8;
             }
             if DEBUG_BLOCK_INFO(f, t) {
                 println!(
@@ -2134,10 +2492,14 @@ unsafe fn decode_b(
             if frame_hdr.txfm_mode == RAV1D_TX_SWITCHABLE && t_dim.max > TX_4X4 as u8 {
                 let tctx = get_tx_ctx(&*t.a, &t.l, &*t_dim, by4, bx4);
                 let tx_cdf = &mut ts.cdf.m.txsz[(t_dim.max - 1) as usize][tctx as usize];
-                let depth = rav1d_msac_decode_symbol_adapt4(
-                    &mut ts.msac,
-                    tx_cdf,
-                    cmp::min(t_dim.max, 2) as usize,
+                let depth = trace_symbol!(
+                    ts,
+                    "tx_depth",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut ts.msac,
+                        tx_cdf,
+                        cmp::min(t_dim.max, 2) as usize,
+                    )
                 ) as c_int;
 
                 for _ in 0..depth {
@@ -2418,8 +2780,11 @@ unsafe fn decode_b(
             && cmp::min(bw4, bh4) > 1
         {
             let ctx = get_comp_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-            let is_comp =
-                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.comp[ctx as usize]);
+            let is_comp = trace_symbol!(
+                ts,
+                "comp_mode",
+                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.comp[ctx as usize])
+            );
             if DEBUG_BLOCK_INFO(f, t) {
                 println!("Post-compflag[{}]: r={}", is_comp, ts.msac.rng);
             }
@@ -2469,65 +2834,103 @@ unsafe fn decode_b(
             }
         } else if is_comp {
             let dir_ctx = get_comp_dir_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-            if rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.comp_dir[dir_ctx as usize])
-            {
+            if trace_symbol!(
+                ts,
+                "comp_ref_type",
+                rav1d_msac_decode_bool_adapt(
+                    &mut ts.msac,
+                    &mut ts.cdf.m.comp_dir[dir_ctx as usize]
+                )
+            ) {
                 // bidir - first reference (fw)
                 let ctx1 = av1_get_fwd_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                if rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.comp_fwd_ref[0][ctx1 as usize],
+                if trace_symbol!(
+                    ts,
+                    "comp_ref",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.comp_fwd_ref[0][ctx1 as usize],
+                    )
                 ) {
                     let ctx2 = av1_get_fwd_ref_2_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                    b.ref_mut()[0] = 2 + rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.comp_fwd_ref[2][ctx2 as usize],
+                    b.ref_mut()[0] = 2 + trace_symbol!(
+                        ts,
+                        "comp_ref_p2",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.comp_fwd_ref[2][ctx2 as usize],
+                        )
                     ) as i8;
                 } else {
                     let ctx2 = av1_get_fwd_ref_1_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                    b.ref_mut()[0] = rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.comp_fwd_ref[1][ctx2 as usize],
+                    b.ref_mut()[0] = trace_symbol!(
+                        ts,
+                        "comp_ref_p1",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.comp_fwd_ref[1][ctx2 as usize],
+                        )
                     ) as i8;
                 }
 
                 // second reference (bw)
                 let ctx3 = av1_get_bwd_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                if rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.comp_bwd_ref[0][ctx3 as usize],
+                if trace_symbol!(
+                    ts,
+                    "comp_bwdref",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.comp_bwd_ref[0][ctx3 as usize],
+                    )
                 ) {
                     b.ref_mut()[1] = 6;
                 } else {
                     let ctx4 = av1_get_bwd_ref_1_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                    b.ref_mut()[1] = 4 + rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.comp_bwd_ref[1][ctx4 as usize],
+                    b.ref_mut()[1] = 4 + trace_symbol!(
+                        ts,
+                        "comp_bwdref_p1",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.comp_bwd_ref[1][ctx4 as usize],
+                        )
                     ) as i8;
                 }
             } else {
                 // unidir
                 let uctx_p = av1_get_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                if rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.comp_uni_ref[0][uctx_p as usize],
+                if trace_symbol!(
+                    ts,
+                    "uni_comp_ref",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.comp_uni_ref[0][uctx_p as usize],
+                    )
                 ) {
                     *b.ref_mut() = [4, 6];
                 } else {
                     let uctx_p1 = av1_get_uni_p1_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
                     *b.ref_mut() = [
                         0,
-                        1 + rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.comp_uni_ref[1][uctx_p1 as usize],
+                        1 + trace_symbol!(
+                            ts,
+                            "uni_comp_ref_p1",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.comp_uni_ref[1][uctx_p1 as usize],
+                            )
                         ) as i8,
                     ];
 
                     if b.r#ref()[1] == 2 {
                         let uctx_p2 =
                             av1_get_fwd_ref_2_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                        b.ref_mut()[1] += rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.comp_uni_ref[2][uctx_p2 as usize],
+                        b.ref_mut()[1] += trace_symbol!(
+                            ts,
+                            "uni_comp_ref_p2",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.comp_uni_ref[2][uctx_p2 as usize],
+                            )
                         ) as i8;
                     }
                 }
@@ -2556,10 +2959,14 @@ unsafe fn decode_b(
                 t.bx,
             );
 
-            *b.inter_mode_mut() = rav1d_msac_decode_symbol_adapt8(
-                &mut ts.msac,
-                &mut ts.cdf.m.comp_inter_mode[ctx as usize],
-                N_COMP_INTER_PRED_MODES as usize - 1,
+            *b.inter_mode_mut() = trace_symbol!(
+                ts,
+                "compound_mode",
+                rav1d_msac_decode_symbol_adapt8(
+                    &mut ts.msac,
+                    &mut ts.cdf.m.comp_inter_mode[ctx as usize],
+                    N_COMP_INTER_PRED_MODES as usize - 1,
+                )
             ) as u8;
             if DEBUG_BLOCK_INFO(f, t) {
                 println!(
@@ -2577,15 +2984,23 @@ unsafe fn decode_b(
                 if n_mvs > 1 {
                     // NEARER, NEAR or NEARISH
                     let drl_ctx_v1 = get_drl_context(&mvstack, 0);
-                    *b.drl_idx_mut() += rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.drl_bit[drl_ctx_v1 as usize],
+                    *b.drl_idx_mut() += trace_symbol!(
+                        ts,
+                        "drl_mode",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.drl_bit[drl_ctx_v1 as usize],
+                        )
                     ) as u8;
                     if b.drl_idx() == NEARER_DRL && n_mvs > 2 {
                         let drl_ctx_v2 = get_drl_context(&mvstack, 1);
-                        *b.drl_idx_mut() += rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                        *b.drl_idx_mut() += trace_symbol!(
+                            ts,
+                            "drl_mode",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                            )
                         ) as u8;
                     }
                     if DEBUG_BLOCK_INFO(f, t) {
@@ -2602,15 +3017,23 @@ unsafe fn decode_b(
                 if n_mvs > 2 {
                     // NEAR or NEARISH
                     let drl_ctx_v2 = get_drl_context(&mvstack, 1);
-                    *b.drl_idx_mut() += rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                    *b.drl_idx_mut() += trace_symbol!(
+                        ts,
+                        "drl_mode",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                        )
                     ) as u8;
                     if b.drl_idx() == NEAR_DRL && n_mvs > 3 {
                         let drl_ctx_v3 = get_drl_context(&mvstack, 2);
-                        *b.drl_idx_mut() += rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.drl_bit[drl_ctx_v3 as usize],
+                        *b.drl_idx_mut() += trace_symbol!(
+                            ts,
+                            "drl_mode",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.drl_bit[drl_ctx_v3 as usize],
+                            )
                         ) as u8;
                     }
                     if DEBUG_BLOCK_INFO(f, t) {
@@ -2671,9 +3094,13 @@ unsafe fn decode_b(
             let mut is_segwedge = false;
             if seq_hdr.masked_compound != 0 {
                 let mask_ctx = get_mask_comp_ctx(&*t.a, &t.l, by4, bx4);
-                is_segwedge = rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.mask_comp[mask_ctx as usize],
+                is_segwedge = trace_symbol!(
+                    ts,
+                    "comp_group_idx",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.mask_comp[mask_ctx as usize],
+                    )
                 );
                 if DEBUG_BLOCK_INFO(f, t) {
                     println!(
@@ -2704,9 +3131,13 @@ unsafe fn decode_b(
                         bx4,
                     );
                     *b.comp_type_mut() = COMP_INTER_WEIGHTED_AVG
-                        + rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.jnt_comp[jnt_ctx as usize],
+                        + trace_symbol!(
+                            ts,
+                            "compound_idx",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.jnt_comp[jnt_ctx as usize],
+                            )
                         ) as u8;
                     if DEBUG_BLOCK_INFO(f, t) {
                         println!(
@@ -2727,19 +3158,30 @@ unsafe fn decode_b(
                 if wedge_allowed_mask & (1 << bs) != 0 {
                     let ctx = dav1d_wedge_ctx_lut[bs as usize] as usize;
                     *b.comp_type_mut() = COMP_INTER_WEDGE
-                        - rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.wedge_comp[ctx])
-                            as u8;
+                        - trace_symbol!(
+                            ts,
+                            "compound_type",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.wedge_comp[ctx]
+                            )
+                        ) as u8;
                     if b.comp_type() == COMP_INTER_WEDGE {
-                        *b.wedge_idx_mut() = rav1d_msac_decode_symbol_adapt16(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.wedge_idx[ctx],
-                            15,
+                        *b.wedge_idx_mut() = trace_symbol!(
+                            ts,
+                            "wedge_index",
+                            rav1d_msac_decode_symbol_adapt16(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.wedge_idx[ctx],
+                                15,
+                            )
                         ) as u8;
                     }
                 } else {
                     *b.comp_type_mut() = COMP_INTER_SEG;
                 }
-                *b.mask_sign_mut() = rav1d_msac_decode_bool_equi(&mut ts.msac) as u8;
+                *b.mask_sign_mut() =
+                    trace_symbol!(ts, "mask_sign", rav1d_msac_decode_bool_equi(&mut ts.msac)) as u8;
                 if DEBUG_BLOCK_INFO(f, t) {
                     println!(
                         "Post-seg/wedge[{},wedge_idx={},sign={}]: r={}",
@@ -2760,40 +3202,66 @@ unsafe fn decode_b(
                 b.ref_mut()[0] = 0;
             } else {
                 let ctx1 = av1_get_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                if rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.r#ref[0][ctx1 as usize])
-                {
-                    let ctx2 = av1_get_bwd_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                    if rav1d_msac_decode_bool_adapt(
+                if trace_symbol!(
+                    ts,
+                    "single_ref_p1",
+                    rav1d_msac_decode_bool_adapt(
                         &mut ts.msac,
-                        &mut ts.cdf.m.r#ref[1][ctx2 as usize],
+                        &mut ts.cdf.m.r#ref[0][ctx1 as usize]
+                    )
+                ) {
+                    let ctx2 = av1_get_bwd_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
+                    if trace_symbol!(
+                        ts,
+                        "single_ref_p2",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.r#ref[1][ctx2 as usize],
+                        )
                     ) {
                         b.ref_mut()[0] = 6;
                     } else {
                         let ctx3 =
                             av1_get_bwd_ref_1_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                        b.ref_mut()[0] = 4 + rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.r#ref[5][ctx3 as usize],
+                        b.ref_mut()[0] = 4 + trace_symbol!(
+                            ts,
+                            "single_ref_p6",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.r#ref[5][ctx3 as usize],
+                            )
                         ) as i8;
                     }
                 } else {
                     let ctx2 = av1_get_fwd_ref_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                    if rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.r#ref[2][ctx2 as usize],
+                    if trace_symbol!(
+                        ts,
+                        "single_ref_p3",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.r#ref[2][ctx2 as usize],
+                        )
                     ) {
                         let ctx3 =
                             av1_get_fwd_ref_2_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                        b.ref_mut()[0] = 2 + rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.r#ref[4][ctx3 as usize],
+                        b.ref_mut()[0] = 2 + trace_symbol!(
+                            ts,
+                            "single_ref_p5",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.r#ref[4][ctx3 as usize],
+                            )
                         ) as i8;
                     } else {
                         let ctx3 =
                             av1_get_fwd_ref_1_ctx(&*t.a, &t.l, by4, bx4, have_top, have_left);
-                        b.ref_mut()[0] = rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.r#ref[3][ctx3 as usize],
+                        b.ref_mut()[0] = trace_symbol!(
+                            ts,
+                            "single_ref_p4",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.r#ref[3][ctx3 as usize],
+                            )
                         ) as i8;
                     }
                 }
@@ -2824,17 +3292,25 @@ unsafe fn decode_b(
             if seg
                 .map(|seg| seg.skip != 0 || seg.globalmv != 0)
                 .unwrap_or(false)
-                || rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.newmv_mode[(ctx & 7) as usize],
+                || trace_symbol!(
+                    ts,
+                    "new_mv",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.newmv_mode[(ctx & 7) as usize],
+                    )
                 )
             {
                 if seg
                     .map(|seg| seg.skip != 0 || seg.globalmv != 0)
                     .unwrap_or(false)
-                    || !rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.globalmv_mode[(ctx >> 3 & 1) as usize],
+                    || !trace_symbol!(
+                        ts,
+                        "zero_mv",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.globalmv_mode[(ctx >> 3 & 1) as usize],
+                        )
                     )
                 {
                     *b.inter_mode_mut() = GLOBALMV;
@@ -2850,9 +3326,13 @@ unsafe fn decode_b(
                         || frame_hdr.gmv[b.r#ref()[0] as usize].r#type == RAV1D_WM_TYPE_TRANSLATION;
                 } else {
                     has_subpel_filter = true;
-                    if rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.refmv_mode[(ctx >> 4 & 15) as usize],
+                    if trace_symbol!(
+                        ts,
+                        "ref_mv",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.refmv_mode[(ctx >> 4 & 15) as usize],
+                        )
                     ) {
                         // NEAREST, NEARER, NEAR or NEARISH
                         *b.inter_mode_mut() = NEARMV;
@@ -2861,17 +3341,25 @@ unsafe fn decode_b(
                             // NEARER, NEAR or NEARISH
                             let drl_ctx_v2 = get_drl_context(&mvstack, 1);
                             *b.drl_idx_mut() = b.drl_idx()
-                                + rav1d_msac_decode_bool_adapt(
-                                    &mut ts.msac,
-                                    &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                                + trace_symbol!(
+                                    ts,
+                                    "drl_mode",
+                                    rav1d_msac_decode_bool_adapt(
+                                        &mut ts.msac,
+                                        &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                                    )
                                 ) as u8;
                             if b.drl_idx() == NEAR_DRL && n_mvs > 3 {
                                 // NEAR or NEARISH
                                 let drl_ctx_v3 = get_drl_context(&mvstack, 2);
                                 *b.drl_idx_mut() = b.drl_idx()
-                                    + rav1d_msac_decode_bool_adapt(
-                                        &mut ts.msac,
-                                        &mut ts.cdf.m.drl_bit[drl_ctx_v3 as usize],
+                                    + trace_symbol!(
+                                        ts,
+                                        "drl_mode",
+                                        rav1d_msac_decode_bool_adapt(
+                                            &mut ts.msac,
+                                            &mut ts.cdf.m.drl_bit[drl_ctx_v3 as usize],
+                                        )
                                     ) as u8;
                             }
                         }
@@ -2905,17 +3393,25 @@ unsafe fn decode_b(
                     // NEARER, NEAR or NEARISH
                     let drl_ctx_v1 = get_drl_context(&mvstack, 0);
                     *b.drl_idx_mut() = b.drl_idx()
-                        + rav1d_msac_decode_bool_adapt(
-                            &mut ts.msac,
-                            &mut ts.cdf.m.drl_bit[drl_ctx_v1 as usize],
+                        + trace_symbol!(
+                            ts,
+                            "drl_mode",
+                            rav1d_msac_decode_bool_adapt(
+                                &mut ts.msac,
+                                &mut ts.cdf.m.drl_bit[drl_ctx_v1 as usize],
+                            )
                         ) as u8;
                     if b.drl_idx() == NEARER_DRL && n_mvs > 2 {
                         // NEAR or NEARISH
                         let drl_ctx_v2 = get_drl_context(&mvstack, 1);
                         *b.drl_idx_mut() = b.drl_idx()
-                            + rav1d_msac_decode_bool_adapt(
-                                &mut ts.msac,
-                                &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                            + trace_symbol!(
+                                ts,
+                                "drl_mode",
+                                rav1d_msac_decode_bool_adapt(
+                                    &mut ts.msac,
+                                    &mut ts.cdf.m.drl_bit[drl_ctx_v2 as usize],
+                                )
                             ) as u8;
                     }
                 }
@@ -2955,27 +3451,43 @@ unsafe fn decode_b(
             let ii_sz_grp = dav1d_ymode_size_context[bs as usize] as c_int;
             if seq_hdr.inter_intra != 0
                 && interintra_allowed_mask & (1 << bs) != 0
-                && rav1d_msac_decode_bool_adapt(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.interintra[ii_sz_grp as usize],
+                && trace_symbol!(
+                    ts,
+                    "interintra",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.interintra[ii_sz_grp as usize],
+                    )
                 )
             {
-                *b.interintra_mode_mut() = rav1d_msac_decode_symbol_adapt4(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.interintra_mode[ii_sz_grp as usize],
-                    N_INTER_INTRA_PRED_MODES as usize - 1,
+                *b.interintra_mode_mut() = trace_symbol!(
+                    ts,
+                    "interintra_mode",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.interintra_mode[ii_sz_grp as usize],
+                        N_INTER_INTRA_PRED_MODES as usize - 1,
+                    )
                 ) as u8;
                 let wedge_ctx = dav1d_wedge_ctx_lut[bs as usize] as c_int;
                 *b.interintra_type_mut() = INTER_INTRA_BLEND
-                    + rav1d_msac_decode_bool_adapt(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.interintra_wedge[wedge_ctx as usize],
+                    + trace_symbol!(
+                        ts,
+                        "wedge_interintra",
+                        rav1d_msac_decode_bool_adapt(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.interintra_wedge[wedge_ctx as usize],
+                        )
                     ) as u8;
                 if b.interintra_type() == INTER_INTRA_WEDGE {
-                    *b.wedge_idx_mut() = rav1d_msac_decode_symbol_adapt16(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.wedge_idx[wedge_ctx as usize],
-                        15,
+                    *b.wedge_idx_mut() = trace_symbol!(
+                        ts,
+                        "wedge_index",
+                        rav1d_msac_decode_symbol_adapt16(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.wedge_idx[wedge_ctx as usize],
+                            15,
+                        )
                     ) as u8;
                 }
             } else {
@@ -3027,14 +3539,21 @@ unsafe fn decode_b(
                     && mask[0] | mask[1] != 0) as c_int;
 
                 *b.motion_mode_mut() = if allow_warp != 0 {
-                    rav1d_msac_decode_symbol_adapt4(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.motion_mode[bs as usize],
-                        2,
+                    trace_symbol!(
+                        ts,
+                        "motion_mode",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.motion_mode[bs as usize],
+                            2,
+                        )
                     ) as u8
                 } else {
-                    rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.obmc[bs as usize])
-                        as u8
+                    trace_symbol!(
+                        ts,
+                        "use_obmc",
+                        rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.obmc[bs as usize])
+                    ) as u8
                 };
                 if b.motion_mode() == MM_WARP as u8 {
                     has_subpel_filter = false;
@@ -3088,10 +3607,14 @@ unsafe fn decode_b(
             if has_subpel_filter {
                 let comp = b.comp_type() != COMP_INTER_NONE;
                 let ctx1 = get_filter_ctx(&*t.a, &t.l, comp, false, b.r#ref()[0], by4, bx4);
-                let filter0 = rav1d_msac_decode_symbol_adapt4(
-                    &mut ts.msac,
-                    &mut ts.cdf.m.filter.0[0][ctx1 as usize],
-                    RAV1D_N_SWITCHABLE_FILTERS as usize - 1,
+                let filter0 = trace_symbol!(
+                    ts,
+                    "interp_filter",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut ts.msac,
+                        &mut ts.cdf.m.filter.0[0][ctx1 as usize],
+                        RAV1D_N_SWITCHABLE_FILTERS as usize - 1,
+                    )
                 ) as Dav1dFilterMode;
                 if seq_hdr.dual_filter != 0 {
                     let ctx2 = get_filter_ctx(&*t.a, &t.l, comp, true, b.r#ref()[0], by4, bx4);
@@ -3101,10 +3624,14 @@ unsafe fn decode_b(
                             filter0, ctx1, ts.msac.rng,
                         );
                     }
-                    let filter1 = rav1d_msac_decode_symbol_adapt4(
-                        &mut ts.msac,
-                        &mut ts.cdf.m.filter.0[1][ctx2 as usize],
-                        RAV1D_N_SWITCHABLE_FILTERS as usize - 1,
+                    let filter1 = trace_symbol!(
+                        ts,
+                        "interp_filter",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut ts.msac,
+                            &mut ts.cdf.m.filter.0[1][ctx2 as usize],
+                            RAV1D_N_SWITCHABLE_FILTERS as usize - 1,
+                        )
                     ) as Dav1dFilterMode;
                     if DEBUG_BLOCK_INFO(f, t) {
                         println!(
@@ -3259,6 +3786,28 @@ unsafe fn decode_b(
         }
     }
 
+    if f.stats.is_some() {
+        let stats = &mut ts.stats;
+        stats.block_size[bs as usize] += 1;
+        if b.intra != 0 {
+            stats.intra += 1;
+            if *b.pal_sz() != [0, 0] {
+                stats.palette += 1;
+            }
+        } else if frame_hdr.frame_type.is_inter_or_switch() {
+            stats.inter += 1;
+        } else {
+            stats.intrabc += 1;
+        }
+        if b.skip != 0 {
+            stats.skip += 1;
+        }
+    }
+
+    if t.frame_thread.pass != 2 && f.block_info_units.is_some() {
+        store_block_info(f, t, b, bs, w4, h4);
+    }
+
     if t.frame_thread.pass == 1 && b.intra == 0 && frame_hdr.frame_type.is_inter_or_switch() {
         let sby = t.by - ts.tiling.row_start >> f.sb_shift;
         let lowest_px = &mut *ts.lowest_pixel.offset(sby as isize);
@@ -3474,6 +4023,7 @@ unsafe fn decode_sb(
 ) -> Result<(), ()> {
     let f = &*t.f;
     let ts = &mut *t.ts;
+    ts.set_trace_pos(t.bx, t.by, f.sb_shift);
     let hsz = 16 >> bl;
     let have_h_split = f.bw > t.bx + hsz;
     let have_v_split = f.bh > t.by + hsz;
@@ -3506,10 +4056,14 @@ unsafe fn decode_sb(
 
     if have_h_split && have_v_split {
         if let Some(pc) = pc {
-            bp = rav1d_msac_decode_symbol_adapt16(
-                &mut ts.msac,
-                pc,
-                dav1d_partition_type_count[bl as usize].into(),
+            bp = trace_symbol!(
+                ts,
+                "partition",
+                rav1d_msac_decode_symbol_adapt16(
+                    &mut ts.msac,
+                    pc,
+                    dav1d_partition_type_count[bl as usize].into(),
+                )
             ) as BlockPartition;
             if f.cur.p.layout == Rav1dPixelLayout::I422
                 && (bp == PARTITION_V
@@ -3659,7 +4213,11 @@ unsafe fn decode_sb(
     } else if have_h_split {
         let is_split;
         if let Some(pc) = pc {
-            is_split = rav1d_msac_decode_bool(&mut ts.msac, gather_top_partition_prob(pc, bl));
+            is_split = trace_symbol!(
+                ts,
+                "split_or_horz",
+                rav1d_msac_decode_bool(&mut ts.msac, gather_top_partition_prob(pc, bl))
+            );
             if DEBUG_BLOCK_INFO(f, t) {
                 println!(
                     "poc={},y={},x={},bl={},ctx={},bp={}: r={}",
@@ -3704,7 +4262,11 @@ unsafe fn decode_sb(
         assert!(have_v_split);
         let is_split;
         if let Some(pc) = pc {
-            is_split = rav1d_msac_decode_bool(&mut ts.msac, gather_left_partition_prob(pc, bl));
+            is_split = trace_symbol!(
+                ts,
+                "split_or_vert",
+                rav1d_msac_decode_bool(&mut ts.msac, gather_left_partition_prob(pc, bl))
+            );
             if f.cur.p.layout == Rav1dPixelLayout::I422 && !is_split {
                 return Err(());
             }
@@ -3750,6 +4312,10 @@ unsafe fn decode_sb(
         }
     }
 
+    if t.frame_thread.pass != 2 && f.stats.is_some() {
+        (*t.ts).stats.partition[bl as usize][bp as usize] += 1;
+    }
+
     if t.frame_thread.pass != 2 && (bp != PARTITION_SPLIT || bl == BL_8X8) {
         CaseSet::<16, false>::many(
             [(&mut *t.a, 0), (&mut t.l, 1)],
@@ -3866,6 +4432,19 @@ unsafe fn setup_tile(
         data.len(),
         frame_hdr.disable_cdf_update != 0,
     );
+    #[cfg(feature = "syntax_trace")]
+    {
+        ts.trace = c.syntax_trace.map(|trace| TileTrace {
+            trace,
+            tile: (tile_row * frame_hdr.tiling.cols as usize + tile_col) as c_int,
+            sb: [col_sb_start, row_sb_start],
+            b: [col_sb_start << sb_shift, row_sb_start << sb_shift],
+        });
+    }
+    if f.stats.is_some() {
+        ts.stats = Default::default();
+        ts.data_len = data.len() as u32;
+    }
 
     ts.tiling.row = tile_row as c_int;
     ts.tiling.col = tile_col as c_int;
@@ -3936,8 +4515,11 @@ unsafe fn read_restoration_info(
     let lr_ref = ts.lr_ref[p];
 
     if frame_type == RAV1D_RESTORATION_SWITCHABLE {
-        let filter =
-            rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut ts.cdf.m.restore_switchable.0, 2);
+        let filter = trace_symbol!(
+            ts,
+            "restoration_type",
+            rav1d_msac_decode_symbol_adapt4(&mut ts.msac, &mut ts.cdf.m.restore_switchable.0, 2)
+        );
         lr.r#type = if filter != 0 {
             if filter == 2 {
                 RAV1D_RESTORATION_SGRPROJ
@@ -3948,14 +4530,19 @@ unsafe fn read_restoration_info(
             RAV1D_RESTORATION_NONE
         };
     } else {
-        let r#type = rav1d_msac_decode_bool_adapt(
-            &mut ts.msac,
-            if frame_type == RAV1D_RESTORATION_WIENER {
-                &mut ts.cdf.m.restore_wiener.0
-            } else {
-                &mut ts.cdf.m.restore_sgrproj.0
-            },
-        );
+        let r#type = if frame_type == RAV1D_RESTORATION_WIENER {
+            trace_symbol!(
+                ts,
+                "use_wiener",
+                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.restore_wiener.0)
+            )
+        } else {
+            trace_symbol!(
+                ts,
+                "use_sgrproj",
+                rav1d_msac_decode_bool_adapt(&mut ts.msac, &mut ts.cdf.m.restore_sgrproj.0)
+            )
+        };
         lr.r#type = if r#type {
             frame_type
         } else {
@@ -3964,8 +4551,11 @@ unsafe fn read_restoration_info(
     }
 
     fn msac_decode_lr_subexp(ts: &mut Rav1dTileState, r#ref: i8, k: u32, adjustment: i8) -> i8 {
-        (rav1d_msac_decode_subexp(&mut ts.msac, (r#ref + adjustment) as c_uint, 8 << k, k)
-            - adjustment as c_int) as i8
+        (trace_symbol!(
+            ts,
+            "lr_coeff",
+            rav1d_msac_decode_subexp(&mut ts.msac, (r#ref + adjustment) as c_uint, 8 << k, k)
+        ) - adjustment as c_int) as i8
     }
 
     if lr.r#type == RAV1D_RESTORATION_WIENER {
@@ -4000,7 +4590,7 @@ unsafe fn read_restoration_info(
             );
         }
     } else if lr.r#type == RAV1D_RESTORATION_SGRPROJ {
-        let idx = rav1d_msac_decode_bools(&mut ts.msac, 4) as u8;
+        let idx = trace_symbol!(ts, "lr_sgr_set", rav1d_msac_decode_bools(&mut ts.msac, 4)) as u8;
         let sgr_params = &dav1d_sgr_params[idx.into()];
         lr.sgr_idx = idx;
         lr.sgr_weights[0] = if sgr_params[0] != 0 {
@@ -4029,6 +4619,20 @@ pub(crate) unsafe fn rav1d_decode_tile_sbrow(
     c: &Rav1dContext,
     t: &mut Rav1dTaskContext,
 ) -> Result<(), ()> {
+    let res = decode_tile_sbrow(c, t);
+    let f = &*t.f;
+    if f.tile_info_units.is_some() {
+        let ts = &*t.ts;
+        if res.is_err() {
+            ts.status.fetch_max(RAV1D_TILE_ERROR, Ordering::Relaxed);
+        } else if t.frame_thread.pass != 1 && t.by + f.sb_step >= ts.tiling.row_end {
+            ts.status.fetch_max(RAV1D_TILE_DECODED, Ordering::Relaxed);
+        }
+    }
+    res
+}
+
+unsafe fn decode_tile_sbrow(c: &Rav1dContext, t: &mut Rav1dTaskContext) -> Result<(), ()> {
     let f = &*t.f;
     let seq_hdr = &***f.seq_hdr.as_ref().unwrap();
     let root_bl = if seq_hdr.sb128 != 0 {
@@ -4116,6 +4720,8 @@ pub(crate) unsafe fn rav1d_decode_tile_sbrow(
         if c.flush.load(Ordering::Acquire) != 0 {
             return Err(());
         }
+        ts.set_trace_pos(t.bx, t.by, f.sb_shift);
+        let bits_left = ts.msac.bits_left();
         let cdef_idx = &mut (*t.lf_mask).cdef_idx;
         if root_bl == BL_128X128 {
             *cdef_idx = [-1; 4];
@@ -4189,6 +4795,12 @@ pub(crate) unsafe fn rav1d_decode_tile_sbrow(
             }
         }
         decode_sb(c, t, root_bl, c.intra_edge.root[root_bl as usize])?;
+        if let Some(sb_bits) = &f.sb_bits_units {
+            let sbw = f.bw + sb_step - 1 >> f.sb_shift;
+            let sb_idx = (t.by >> f.sb_shift) * sbw + (t.bx >> f.sb_shift);
+            sb_bits[sb_idx as usize]
+                .store((bits_left - ts.msac.bits_left()) as u32, Ordering::Relaxed);
+        }
         if t.bx & 16 != 0 || seq_hdr.sb128 != 0 {
             t.a = (t.a).offset(1);
             t.lf_mask = (t.lf_mask).offset(1);
@@ -4277,6 +4889,9 @@ pub(crate) unsafe fn rav1d_decode_frame_init(
         if f.ts.is_null() {
             return Err(ENOMEM);
         }
+        for n in 0..n_ts as usize {
+            addr_of_mut!((*f.ts.add(n)).status).write(AtomicU32::new(RAV1D_TILE_NOT_DECODED));
+        }
         f.n_ts = n_ts;
     }
 
@@ -4703,11 +5318,18 @@ pub(crate) unsafe fn rav1d_decode_frame_init_cdf(
     let mut tile_row = 0;
     let mut tile_col = 0;
     f.task_thread.update_set = false;
-    for tile in &f.tiles {
+    let mut tile_info_units = f.tile_info_units.take();
+    for (tile_group, tile) in f.tiles.iter().enumerate() {
         let start = tile.hdr.start.try_into().unwrap();
         let end: usize = tile.hdr.end.try_into().unwrap();
 
-        let mut data = tile.data.as_ref();
+        let tile_data = tile.data.as_ref();
+        let data_offset = tile
+            .data
+            .data
+            .as_ref()
+            .map_or(Ok(0), CArc::offset_in_owner)?;
+        let mut data = tile_data;
         for (j, (ts, tile_start_off)) in iter::zip(
             slice::from_raw_parts_mut(f.ts, end + 1),
             if uses_2pass {
@@ -4744,6 +5366,15 @@ pub(crate) unsafe fn rav1d_decode_frame_init_cdf(
 
             let (cur_data, rest_data) = data.split_at(tile_sz);
             setup_tile(c, ts, f, cur_data, tile_row, tile_col, tile_start_off);
+            if let Some(tile_info) = &mut tile_info_units {
+                tile_info[j] = Rav1dTileInfo {
+                    offset: data_offset
+                        + cur_data.as_ptr().offset_from(tile_data.as_ptr()) as usize,
+                    size: cur_data.len(),
+                    tile_group: tile_group as c_int,
+                    status: RAV1D_TILE_NOT_DECODED,
+                };
+            }
             tile_col += 1;
 
             if tile_col == cols {
@@ -4756,6 +5387,7 @@ pub(crate) unsafe fn rav1d_decode_frame_init_cdf(
             data = rest_data;
         }
     }
+    f.tile_info_units = tile_info_units;
 
     if c.n_tc > 1 {
         for (n, ctx) in slice::from_raw_parts_mut(f.a, sb128w * rows * (1 + uses_2pass as usize))
@@ -4834,6 +5466,7 @@ unsafe fn rav1d_decode_frame_main(c: &Rav1dContext, f: &mut Rav1dFrameContext) -
 
             // loopfilter + cdef + restoration
             (f.bd_fn.filter_sbrow)(c, f, t, sby);
+            rav1d_thread_picture_row_progress(c, f, sby);
         }
     }
 
@@ -4864,6 +5497,20 @@ pub(crate) unsafe fn rav1d_decode_frame_exit(
     }
     rav1d_picture_unref_internal(&mut f.cur);
     rav1d_thread_picture_unref(&mut f.sr_cur);
+    let _ = f.recon_stages.take();
+    if let Some(cdf) = f.export_cdf.take() {
+        if let (Ok(()), Some(frame_hdr)) = (retval, &f.frame_hdr) {
+            // The same CDFs that are saved in [`Rav1dContext::cdf`].
+            let src = if frame_hdr.refresh_context != 0 {
+                &f.out_cdf
+            } else {
+                &f.in_cdf
+            };
+            let mut out: CdfContext = mem::zeroed();
+            rav1d_cdf_thread_copy(&mut out, src);
+            let _ = cdf.set((&out).into());
+        }
+    }
     rav1d_cdf_thread_unref(&mut f.in_cdf);
     if let Some(frame_hdr) = &f.frame_hdr {
         if frame_hdr.refresh_context != 0 {
@@ -4877,6 +5524,79 @@ pub(crate) unsafe fn rav1d_decode_frame_exit(
         }
     }
 
+    if let Some(stats) = f.stats.take() {
+        if retval.is_ok() {
+            let ts = slice::from_raw_parts(f.ts, f.n_ts as usize);
+            let mut blocks = Rav1dBlockStats::default();
+            for ts in ts {
+                blocks.add(&ts.stats);
+            }
+            let tile_bytes = ts.iter().map(|ts| ts.data_len).collect();
+            let _ = stats.set(Rav1dFrameStats { blocks, tile_bytes }.into_drav1d());
+        }
+    }
+
+    let block_info_units = f.block_info_units.take();
+    if let Some(block_info) = f.block_info.take() {
+        if let (Ok(()), Some(info)) = (retval, block_info_units) {
+            let map = Rav1dBlockInfoMap {
+                w4: f.bw,
+                h4: f.bh,
+                info: Vec::from(info)
+                    .into_iter()
+                    .map(UnsafeCell::into_inner)
+                    .collect(),
+            };
+            let _ = block_info.set(map.into_drav1d());
+        }
+    }
+
+    let sb_bits_units = f.sb_bits_units.take();
+    if let Some(sb_bits) = f.sb_bits.take() {
+        if let (Ok(()), Some(bits)) = (retval, sb_bits_units) {
+            let map = Rav1dSbBitsMap {
+                sb_size: 4 << f.sb_shift,
+                sbw: f.bw + f.sb_step - 1 >> f.sb_shift,
+                sbh: f.sbh,
+                bits: Vec::from(bits)
+                    .into_iter()
+                    .map(AtomicU32::into_inner)
+                    .collect(),
+            };
+            let _ = sb_bits.set(map.into_drav1d());
+        }
+    }
+
+    let tile_info_units = f.tile_info_units.take();
+    if let Some(tile_layout) = f.tile_layout.take() {
+        // Also on errors, where the status of each tile says what went wrong.
+        if let Some(mut tiles) = tile_info_units {
+            if !f.ts.is_null() {
+                let ts = slice::from_raw_parts(f.ts, f.n_ts as usize);
+                for (tile, ts) in iter::zip(&mut *tiles, ts) {
+                    tile.status = ts.status.swap(RAV1D_TILE_NOT_DECODED, Ordering::Relaxed);
+                }
+            }
+            let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
+            let tiling = &frame_hdr.tiling;
+            let sb_size = 4 << f.sb_shift;
+            let layout = Rav1dTileLayout {
+                cols: tiling.cols,
+                rows: tiling.rows,
+                col_start: tiling.col_start_sb[..=tiling.cols as usize]
+                    .iter()
+                    .map(|&sb| cmp::min(sb as c_int * sb_size, frame_hdr.size.width[0]))
+                    .collect(),
+                row_start: tiling.row_start_sb[..=tiling.rows as usize]
+                    .iter()
+                    .map(|&sb| cmp::min(sb as c_int * sb_size, frame_hdr.size.height))
+                    .collect(),
+                tiles,
+            };
+            let _ = tile_layout.set(layout.into_drav1d());
+        }
+    }
+
     rav1d_ref_dec(&mut f.cur_segmap_ref);
     rav1d_ref_dec(&mut f.prev_segmap_ref);
     rav1d_ref_dec(&mut f.mvs_ref);
@@ -5151,6 +5871,16 @@ pub unsafe fn rav1d_submit_frame(c: &mut Rav1dContext) -> Rav1dResult {
         on_error(f, c, out);
         return res;
     }
+    f.stats = c.collect_stats.then(|| Arc::new(OnceLock::new()));
+    f.sr_cur.p.stats = f.stats.clone();
+    f.export_cdf = c.export_cdf.then(|| Arc::new(OnceLock::new()));
+    f.sr_cur.p.cdf = f.export_cdf.clone();
+    f.block_info = c.export_block_info.then(|| Arc::new(OnceLock::new()));
+    f.sr_cur.p.block_info = f.block_info.clone();
+    f.sb_bits = c.export_sb_bits.then(|| Arc::new(OnceLock::new()));
+    f.sr_cur.p.sb_bits = f.sb_bits.clone();
+    f.tile_layout = c.export_tile_info.then(|| Arc::new(OnceLock::new()));
+    f.sr_cur.p.tile_layout = f.tile_layout.clone();
 
     let seq_hdr = &***f.seq_hdr.as_ref().unwrap();
     let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
@@ -5174,6 +5904,15 @@ pub unsafe fn rav1d_submit_frame(c: &mut Rav1dContext) -> Rav1dResult {
         f.resize_start[1] = get_upscale_x0(in_cw, out_cw, f.resize_step[1]);
     }
 
+    f.recon_stages = match rav1d_recon_stages_alloc(c, f) {
+        Ok(recon_stages) => recon_stages,
+        Err(e) => {
+            on_error(f, c, out);
+            return Err(e);
+        }
+    };
+    f.sr_cur.p.recon_stages = f.recon_stages.clone();
+
     // move f->cur into output queue
     if c.n_fc == 1 {
         if frame_hdr.show_frame != 0 || c.output_invisible_frames {
@@ -5194,8 +5933,28 @@ pub unsafe fn rav1d_submit_frame(c: &mut Rav1dContext) -> Rav1dResult {
     f.sb_step = 16 << seq_hdr.sb128;
     f.sbh = f.bh + f.sb_step - 1 >> f.sb_shift;
     f.b4_stride = (f.bw + 31 & !31) as ptrdiff_t;
+    if f.block_info.is_some() {
+        f.block_info_units = Some(
+            iter::repeat_with(Default::default)
+                .take((f.bw * f.bh) as usize)
+                .collect(),
+        );
+    }
+    if f.sb_bits.is_some() {
+        let sbw = f.bw + f.sb_step - 1 >> f.sb_shift;
+        f.sb_bits_units = Some(
+            iter::repeat_with(|| AtomicU32::new(0))
+                .take((sbw * f.sbh) as usize)
+                .collect(),
+        );
+    }
+    if f.tile_layout.is_some() {
+        let n_tiles = frame_hdr.tiling.cols * frame_hdr.tiling.rows;
+        f.tile_info_units = Some(vec![Default::default(); n_tiles as usize].into());
+    }
     f.bitdepth_max = (1 << f.cur.p.bpc) - 1;
     f.task_thread.error = AtomicI32::new(0);
+    f.task_thread.row_progress = AtomicI32::new(0);
     let uses_2pass = (c.n_fc > 1) as c_int;
     let cols = frame_hdr.tiling.cols;
     let rows = frame_hdr.tiling.rows;
@@ -1,5 +1,11 @@
 use crate::include::common::intops::inv_recenter;
 use crate::include::common::intops::ulog2;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::Dav1dSyntaxElement;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::Rav1dSyntaxTrace;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::RAV1D_SYNTAX_HEADER;
 use std::ffi::c_int;
 use std::ffi::c_uint;
 
@@ -10,6 +16,10 @@ pub struct GetBits<'a> {
     error: c_int,
     index: usize,
     data: &'a [u8],
+    /// Where the bits read by [`Self::get_bit`], [`Self::get_bits`]
+    /// and [`Self::get_sbits`] (and everything built on them) are reported to.
+    #[cfg(feature = "syntax_trace")]
+    pub trace: Option<Rav1dSyntaxTrace>,
 }
 
 impl<'a> GetBits<'a> {
@@ -21,14 +31,51 @@ impl<'a> GetBits<'a> {
             error: 0,
             index: 0,
             data,
+            #[cfg(feature = "syntax_trace")]
+            trace: None,
         }
     }
 
+    /// `name` must be NUL-terminated.
+    #[inline(always)]
+    fn trace(&self, name: &'static str, value: i64, n_bits: c_int) {
+        #[cfg(feature = "syntax_trace")]
+        if let Some(trace) = &self.trace {
+            self.emit(trace, name, value, n_bits);
+        }
+        #[cfg(not(feature = "syntax_trace"))]
+        let _ = (name, value, n_bits);
+    }
+
+    #[cfg(feature = "syntax_trace")]
+    #[cold]
+    #[inline(never)]
+    fn emit(&self, trace: &Rav1dSyntaxTrace, name: &'static str, value: i64, n_bits: c_int) {
+        trace.emit(&Dav1dSyntaxElement {
+            source: RAV1D_SYNTAX_HEADER,
+            name: name.as_ptr().cast(),
+            value,
+            n_bits,
+            bit_pos: (self.pos() - n_bits as usize) as i64,
+            tile: -1,
+            sbx: -1,
+            sby: -1,
+            bx: -1,
+            by: -1,
+        });
+    }
+
     pub const fn has_error(&self) -> c_int {
         self.error
     }
 
     pub fn get_bit(&mut self) -> bool {
+        let bit = self.read_bit();
+        self.trace("f\0", bit as i64, 1);
+        bit
+    }
+
+    fn read_bit(&mut self) -> bool {
         if self.bits_left == 0 {
             if self.index >= self.data.len() {
                 self.error = 1;
@@ -78,7 +125,9 @@ impl<'a> GetBits<'a> {
         let state = self.state;
         self.bits_left -= n;
         self.state = state << n;
-        (state as u64 >> 64 - n) as c_uint
+        let bits = (state as u64 >> 64 - n) as c_uint;
+        self.trace("f\0", bits as i64, n);
+        bits
     }
 
     pub fn get_sbits(&mut self, n: c_int) -> c_int {
@@ -90,7 +139,9 @@ impl<'a> GetBits<'a> {
         let state = self.state;
         self.bits_left -= n;
         self.state = state << n;
-        (state as i64 >> 64 - n) as c_int
+        let bits = (state as i64 >> 64 - n) as c_int;
+        self.trace("su\0", bits as i64, n);
+        bits
     }
 
     pub fn get_uleb128(&mut self) -> c_uint {
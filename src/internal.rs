@@ -8,6 +8,8 @@ use crate::include::dav1d::data::Rav1dData;
 use crate::include::dav1d::dav1d::Rav1dDecodeFrameType;
 use crate::include::dav1d::dav1d::Rav1dEventFlags;
 use crate::include::dav1d::dav1d::Rav1dInloopFilterType;
+use crate::include::dav1d::dav1d::Rav1dRowProgress;
+use crate::include::dav1d::dav1d::Rav1dSyntaxTrace;
 use crate::include::dav1d::headers::DRav1d;
 use crate::include::dav1d::headers::Dav1dFrameHeader;
 use crate::include::dav1d::headers::Dav1dITUTT35;
@@ -19,8 +21,16 @@ use crate::include::dav1d::headers::Rav1dMasteringDisplay;
 use crate::include::dav1d::headers::Rav1dSequenceHeader;
 use crate::include::dav1d::headers::Rav1dWarpedMotionParams;
 use crate::include::dav1d::headers::RAV1D_MAX_SEGMENTS;
+use crate::include::dav1d::picture::Rav1dBlockInfo;
+use crate::include::dav1d::picture::Rav1dBlockInfoCell;
+use crate::include::dav1d::picture::Rav1dBlockStats;
+use crate::include::dav1d::picture::Rav1dCdfCell;
+use crate::include::dav1d::picture::Rav1dFrameStatsCell;
 use crate::include::dav1d::picture::Rav1dPicAllocator;
 use crate::include::dav1d::picture::Rav1dPicture;
+use crate::include::dav1d::picture::Rav1dSbBitsCell;
+use crate::include::dav1d::picture::Rav1dTileInfo;
+use crate::include::dav1d::picture::Rav1dTileLayoutCell;
 use crate::src::align::*;
 use crate::src::cdef::Rav1dCdefDSPContext;
 use crate::src::cdf::CdfContext;
@@ -67,13 +77,17 @@ use crate::src::recon::rav1d_recon_b_intra;
 use crate::src::recon::read_coef_blocks_fn;
 use crate::src::recon::recon_b_inter_fn;
 use crate::src::recon::recon_b_intra_fn;
+use crate::src::recon_stages::Rav1dReconStages;
 use crate::src::refmvs::refmvs_frame;
 use crate::src::refmvs::refmvs_temporal_block;
 use crate::src::refmvs::refmvs_tile;
 use crate::src::refmvs::Rav1dRefmvsDSPContext;
+#[cfg(feature = "syntax_trace")]
+use crate::src::syntax_trace::TileTrace;
 use crate::src::thread_data::thread_data;
 use atomig::Atomic;
 use libc::ptrdiff_t;
+use std::cell::UnsafeCell;
 use std::ffi::c_int;
 use std::ffi::c_uint;
 use std::ptr;
@@ -255,12 +269,27 @@ pub struct Rav1dContext {
     pub(crate) inloop_filters: Rav1dInloopFilterType,
     pub(crate) decode_frame_type: Rav1dDecodeFrameType,
     pub(crate) drain: c_int,
+    /// Set by [`rav1d_seek_hint`]; pictures before it are not output.
+    ///
+    /// [`rav1d_seek_hint`]: crate::src::lib::rav1d_seek_hint
+    pub(crate) seek_target: Option<i64>,
     pub(crate) frame_flags: Atomic<PictureFlags>,
     pub(crate) event_flags: Rav1dEventFlags,
     pub(crate) cached_error_props: Mutex<Rav1dDataProps>,
     pub(crate) cached_error: Rav1dResult,
 
     pub(crate) logger: Option<Rav1dLogger>,
+    pub(crate) row_progress: Option<Rav1dRowProgress>,
+    pub(crate) collect_stats: bool,
+    pub(crate) export_cdf: bool,
+    /// See [`Rav1dReconStages`].
+    pub(crate) recon_stages: c_uint,
+    /// See [`Rav1dReconStages`].
+    pub(crate) export_pred_residual: bool,
+    pub(crate) syntax_trace: Option<Rav1dSyntaxTrace>,
+    pub(crate) export_block_info: bool,
+    pub(crate) export_sb_bits: bool,
+    pub(crate) export_tile_info: bool,
 
     pub(crate) picture_pool: *mut Rav1dMemPool,
 }
@@ -442,6 +471,8 @@ pub(crate) struct Rav1dFrameContext_task_thread {
     pub retval: Rav1dResult,
     pub update_set: bool, // whether we need to update CDF reference
     pub error: AtomicI32,
+    // number of post-filtered luma rows last reported to `c.row_progress`
+    pub row_progress: AtomicI32,
     pub task_counter: AtomicI32,
     pub task_head: *mut Rav1dTask,
     pub task_tail: *mut Rav1dTask,
@@ -485,6 +516,37 @@ pub(crate) struct Rav1dFrameContext {
     pub in_cdf: CdfThreadContext,
     pub out_cdf: CdfThreadContext,
     pub tiles: Vec<Rav1dTileGroup>,
+    /// Set if [`Rav1dContext::collect_stats`], and shared with [`Self::sr_cur`].
+    pub stats: Option<Arc<Rav1dFrameStatsCell>>,
+    /// Set if [`Rav1dContext::export_cdf`], and shared with [`Self::sr_cur`].
+    pub export_cdf: Option<Arc<Rav1dCdfCell>>,
+    /// Set if [`Rav1dContext::recon_stages`] or [`Rav1dContext::export_pred_residual`],
+    /// and shared with [`Self::sr_cur`].
+    pub recon_stages: Option<Arc<Rav1dReconStages>>,
+    /// Set if [`Rav1dContext::export_block_info`], and shared with [`Self::sr_cur`].
+    pub block_info: Option<Arc<Rav1dBlockInfoCell>>,
+    /// The [`Rav1dBlockInfo`] of each 4x4 unit, written in `decode_b`
+    /// and moved into [`Self::block_info`] once the frame is decoded.
+    ///
+    /// The tile threads write the units of their tiles concurrently,
+    /// so each unit is an [`UnsafeCell`].
+    pub block_info_units: Option<Box<[UnsafeCell<Rav1dBlockInfo>]>>,
+    /// Set if [`Rav1dContext::export_sb_bits`], and shared with [`Self::sr_cur`].
+    pub sb_bits: Option<Arc<Rav1dSbBitsCell>>,
+    /// The bits of each superblock, written in [`rav1d_decode_tile_sbrow`]
+    /// and moved into [`Self::sb_bits`] once the frame is decoded.
+    ///
+    /// The tile threads write the superblocks of their tiles concurrently,
+    /// so each count is atomic.
+    ///
+    /// [`rav1d_decode_tile_sbrow`]: crate::src::decode::rav1d_decode_tile_sbrow
+    pub sb_bits_units: Option<Box<[AtomicU32]>>,
+    /// Set if [`Rav1dContext::export_tile_info`], and shared with [`Self::sr_cur`].
+    pub tile_layout: Option<Arc<Rav1dTileLayoutCell>>,
+    /// The [`Rav1dTileInfo`] of each tile, written in `rav1d_decode_frame_init`
+    /// and moved into [`Self::tile_layout`] once the frame is decoded,
+    /// with the status from [`Rav1dTileState::status`].
+    pub tile_info_units: Option<Box<[Rav1dTileInfo]>>,
 
     // for scalable references
     pub svc: [[ScalableMotionParams; 2]; 7], /* [2 x,y][7] */
@@ -564,6 +626,17 @@ pub struct Rav1dTileState {
     pub lflvl: *const [[[u8; 2]; 8]; 4],
 
     pub lr_ref: [Av1RestorationUnit; 3],
+
+    // only counted if [`Rav1dFrameContext::stats`] is set
+    pub stats: Rav1dBlockStats,
+    pub data_len: u32,
+    // a [`Rav1dTileStatus`], only set if [`Rav1dFrameContext::tile_info_units`] is set;
+    // raised with `fetch_max` so an error isn't overwritten by the other pass,
+    // and reset once collected in `rav1d_decode_frame_exit`
+    pub status: AtomicU32,
+    /// Set if [`Rav1dContext::syntax_trace`].
+    #[cfg(feature = "syntax_trace")]
+    pub trace: Option<TileTrace>,
 }
 
 pub struct Cf;
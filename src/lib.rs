@@ -9,12 +9,18 @@ use crate::include::dav1d::data::Dav1dData;
 use crate::include::dav1d::data::Rav1dData;
 use crate::include::dav1d::dav1d::Dav1dContext;
 use crate::include::dav1d::dav1d::Dav1dEventFlags;
+use crate::include::dav1d::dav1d::Dav1dRandomAccessPoint;
+use crate::include::dav1d::dav1d::Dav1dReconStage;
+use crate::include::dav1d::dav1d::Dav1dRefSlot;
 use crate::include::dav1d::dav1d::Dav1dSettings;
+use crate::include::dav1d::dav1d::Dav1dStateSnapshot;
 use crate::include::dav1d::dav1d::Rav1dSettings;
 use crate::include::dav1d::dav1d::RAV1D_DECODEFRAMETYPE_ALL;
 use crate::include::dav1d::dav1d::RAV1D_DECODEFRAMETYPE_KEY;
 use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_ALL;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_COUNT;
 use crate::include::dav1d::headers::DRav1d;
+use crate::include::dav1d::headers::Dav1dFrameHeader;
 use crate::include::dav1d::headers::Dav1dSequenceHeader;
 use crate::include::dav1d::headers::Rav1dFilmGrainData;
 use crate::include::dav1d::headers::Rav1dSequenceHeader;
@@ -31,6 +37,7 @@ use crate::src::error::Rav1dError::EAGAIN;
 use crate::src::error::Rav1dError::EINVAL;
 use crate::src::error::Rav1dError::ENOENT;
 use crate::src::error::Rav1dError::ENOMEM;
+use crate::src::error::Rav1dError::ENOPROTOOPT;
 use crate::src::error::Rav1dResult;
 use crate::src::fg_apply;
 use crate::src::internal::CodedBlockInfo;
@@ -51,6 +58,7 @@ use crate::src::mem::rav1d_free_aligned;
 use crate::src::mem::rav1d_freep_aligned;
 use crate::src::mem::rav1d_mem_pool_end;
 use crate::src::mem::rav1d_mem_pool_init;
+use crate::src::obu;
 use crate::src::obu::rav1d_parse_obus;
 use crate::src::picture::dav1d_default_picture_alloc;
 use crate::src::picture::dav1d_default_picture_release;
@@ -64,9 +72,15 @@ use crate::src::picture::rav1d_thread_picture_unref;
 use crate::src::picture::PictureFlags;
 use crate::src::picture::Rav1dThreadPicture;
 use crate::src::r#ref::rav1d_ref_dec;
+use crate::src::recon_stages::rav1d_recon_stages_ref;
 use crate::src::refmvs::rav1d_refmvs_clear;
 use crate::src::refmvs::rav1d_refmvs_dsp_init;
 use crate::src::refmvs::rav1d_refmvs_init;
+use crate::src::snapshot::rav1d_decode_frame_stateless;
+use crate::src::snapshot::rav1d_ref_slot_unref;
+use crate::src::snapshot::rav1d_restore_state;
+use crate::src::snapshot::rav1d_snapshot_ref_slot;
+use crate::src::snapshot::rav1d_snapshot_state;
 use crate::src::thread_task::rav1d_task_delayed_fg;
 use crate::src::thread_task::rav1d_worker_task;
 use crate::src::thread_task::FRAME_ERROR;
@@ -79,6 +93,7 @@ use libc::pthread_attr_setstacksize;
 use libc::pthread_attr_t;
 use libc::pthread_join;
 use libc::pthread_t;
+use std::array;
 use std::cmp;
 use std::ffi::c_char;
 use std::ffi::c_int;
@@ -147,6 +162,15 @@ impl Default for Rav1dSettings {
             output_invisible_frames: false,
             inloop_filters: RAV1D_INLOOPFILTER_ALL,
             decode_frame_type: RAV1D_DECODEFRAMETYPE_ALL,
+            row_progress: None,
+            collect_stats: false,
+            export_cdf: false,
+            recon_stages: 0,
+            export_pred_residual: false,
+            syntax_trace: None,
+            export_block_info: false,
+            export_sb_bits: false,
+            export_tile_info: false,
         }
     }
 }
@@ -244,6 +268,10 @@ pub(crate) unsafe fn rav1d_open(c_out: &mut *mut Rav1dContext, s: &Rav1dSettings
             && s.decode_frame_type <= RAV1D_DECODEFRAMETYPE_KEY,
         EINVAL
     ))?;
+    validate_input!((s.recon_stages >> RAV1D_RECON_STAGE_COUNT == 0, EINVAL))?;
+    if s.syntax_trace.is_some() && !cfg!(feature = "syntax_trace") {
+        return Err(ENOPROTOOPT);
+    }
     let mut thread_attr: pthread_attr_t = std::mem::zeroed();
     if pthread_attr_init(&mut thread_attr) != 0 {
         return Err(ENOMEM);
@@ -270,6 +298,15 @@ pub(crate) unsafe fn rav1d_open(c_out: &mut *mut Rav1dContext, s: &Rav1dSettings
     (*c).output_invisible_frames = s.output_invisible_frames;
     (*c).inloop_filters = s.inloop_filters;
     (*c).decode_frame_type = s.decode_frame_type;
+    (*c).row_progress = s.row_progress.clone();
+    (*c).collect_stats = s.collect_stats;
+    (*c).export_cdf = s.export_cdf;
+    (*c).recon_stages = s.recon_stages;
+    (*c).export_pred_residual = s.export_pred_residual;
+    (*c).syntax_trace = s.syntax_trace.clone();
+    (*c).export_block_info = s.export_block_info;
+    (*c).export_sb_bits = s.export_sb_bits;
+    (*c).export_tile_info = s.export_tile_info;
     (*c).cached_error_props = Default::default();
     if rav1d_mem_pool_init(&mut (*c).segmap_pool).is_err()
         || rav1d_mem_pool_init(&mut (*c).refmvs_pool).is_err()
@@ -277,8 +314,9 @@ pub(crate) unsafe fn rav1d_open(c_out: &mut *mut Rav1dContext, s: &Rav1dSettings
     {
         return error(c, c_out, &mut thread_attr);
     }
-    if (*c).allocator.alloc_picture_callback == dav1d_default_picture_alloc
+    if (*c).allocator.alloc_picture_callback == Some(dav1d_default_picture_alloc)
         && (*c).allocator.release_picture_callback == dav1d_default_picture_release
+        && (*c).allocator.alloc_picture_planes_callback.is_none()
     {
         if !((*c).allocator.cookie).is_null() {
             return error(c, c_out, &mut thread_attr);
@@ -287,7 +325,7 @@ pub(crate) unsafe fn rav1d_open(c_out: &mut *mut Rav1dContext, s: &Rav1dSettings
             return error(c, c_out, &mut thread_attr);
         }
         (*c).allocator.cookie = (*c).picture_pool as *mut c_void;
-    } else if (*c).allocator.alloc_picture_callback == dav1d_default_picture_alloc
+    } else if (*c).allocator.alloc_picture_callback == Some(dav1d_default_picture_alloc)
         || (*c).allocator.release_picture_callback == dav1d_default_picture_release
     {
         return error(c, c_out, &mut thread_attr);
@@ -455,7 +493,7 @@ pub(crate) unsafe fn rav1d_parse_sequence_header(
         };
         if let Some(data) = &mut data {
             while !data.is_empty() {
-                let len = rav1d_parse_obus(&mut *c, data, &mut props, true)?;
+                let len = rav1d_parse_obus(&mut *c, data, &mut props, true, &mut None)?;
                 data.slice_in_place(len..);
             }
         }
@@ -490,6 +528,39 @@ pub unsafe extern "C" fn dav1d_parse_sequence_header(
     .into()
 }
 
+/// Inspect a temporal unit and report whether decoding can start at it,
+/// without decoding anything.
+///
+/// `seq_hdr` is the sequence header to parse the frame header with
+/// if the temporal unit doesn't contain one itself, and may be null.
+/// If neither is available, `ENOENT` is returned.
+///
+/// OBUs outside of `operating_point`, like [`Dav1dSettings::operating_point`],
+/// are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_parse_random_access_point(
+    out: *mut Dav1dRandomAccessPoint,
+    ptr: *const u8,
+    sz: usize,
+    seq_hdr: *const Dav1dSequenceHeader,
+    operating_point: c_int,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!out.is_null(), EINVAL))?;
+        validate_input!((!ptr.is_null() && sz != 0, EINVAL))?;
+        validate_input!((operating_point >= 0 && operating_point <= 31, EINVAL))?;
+        let seq_hdr = seq_hdr.as_ref().cloned().map(Rav1dSequenceHeader::from);
+        let rap = obu::rav1d_parse_random_access_point(
+            slice::from_raw_parts(ptr, sz),
+            seq_hdr.as_ref(),
+            operating_point,
+        )?;
+        out.write(rap.into());
+        Ok(())
+    })()
+    .into()
+}
+
 impl Rav1dFilmGrainData {
     fn has_grain(&self) -> bool {
         self.num_y_points != 0
@@ -530,6 +601,17 @@ unsafe fn output_picture_ready(c: &mut Rav1dContext, drain: bool) -> bool {
     if c.cached_error.is_err() {
         return true;
     }
+    if let Some(target) = c.seek_target {
+        if !c.out.p.data.data[0].is_null() {
+            if c.out.p.m.timestamp < target {
+                // Its event flags are kept in `c.event_flags`
+                // and reported with the next picture that is output.
+                rav1d_thread_picture_unref(&mut c.out);
+            } else {
+                c.seek_target = None;
+            }
+        }
+    }
     if !c.all_layers && c.max_spatial_id {
         if !c.out.p.data.data[0].is_null() && !c.cache.p.data.data[0].is_null() {
             if c.max_spatial_id == (c.cache.p.frame_hdr.as_ref().unwrap().spatial_id != 0)
@@ -635,7 +717,7 @@ unsafe fn gen_picture(c: &mut Rav1dContext) -> Rav1dResult {
     } = mem::take(&mut c.in_0);
     let Some(mut r#in) = r#in else { return Ok(()) };
     while !r#in.is_empty() {
-        let len = rav1d_parse_obus(c, &r#in, &props, false);
+        let len = rav1d_parse_obus(c, &r#in, &props, false, &mut None);
         if let Ok(len) = len {
             r#in.slice_in_place(len..);
         }
@@ -799,6 +881,7 @@ pub(crate) unsafe fn rav1d_flush(c: *mut Rav1dContext) {
         rav1d_thread_picture_unref(&mut (*c).cache);
     }
     (*c).drain = 0 as c_int;
+    (*c).seek_target = None;
     (*c).cached_error = Ok(());
     let mut i = 0;
     while i < 8 {
@@ -884,6 +967,184 @@ pub unsafe extern "C" fn dav1d_flush(c: *mut Dav1dContext) {
     rav1d_flush(c)
 }
 
+pub(crate) unsafe fn rav1d_seek_hint(c: &mut Rav1dContext, target_timestamp: i64) {
+    c.seek_target = Some(target_timestamp);
+}
+
+/// Skip output until the first picture with `m.timestamp >= target_timestamp`.
+///
+/// Meant to be called right after [`dav1d_flush`], before feeding data
+/// from a seek point (e.g. the preceding key frame) in front of the target.
+/// Pictures before the target are not returned by [`dav1d_get_picture`],
+/// and non-reference frames before it are not reconstructed at all.
+/// Frames that are only shown later (e.g. forward key frames shown with
+/// `show_existing_frame`) take the timestamp of the data showing them.
+///
+/// The hint is cleared once a picture at or after the target is output,
+/// or by [`dav1d_flush`].
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_seek_hint(
+    c: *mut Dav1dContext,
+    target_timestamp: i64,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!c.is_null(), EINVAL))?;
+        rav1d_seek_hint(&mut *c, target_timestamp);
+        Ok(())
+    })()
+    .into()
+}
+
+/// Take a snapshot of the reference state of `c`: the reference pictures,
+/// segmentation maps, motion vectors and CDFs, along with the current headers.
+///
+/// Frames still being decoded are waited for first.
+/// Data not yet consumed by [`dav1d_send_data`] and pictures not yet
+/// returned by [`dav1d_get_picture`] are not part of the snapshot.
+/// The snapshot shares its buffers with `c` and must be released with
+/// [`dav1d_state_snapshot_unref`].
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_state_snapshot(
+    c: *mut Dav1dContext,
+    out: *mut *mut Dav1dStateSnapshot,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!c.is_null(), EINVAL))?;
+        validate_input!((!out.is_null(), EINVAL))?;
+        let snapshot = rav1d_snapshot_state(&*c);
+        *out = Arc::into_raw(snapshot).cast_mut();
+        Ok(())
+    })()
+    .into()
+}
+
+/// Flush `c` and restore the reference state from `s`,
+/// so that decoding can resume with the temporal unit that followed it.
+///
+/// `s` may come from a different context, with a different number of threads,
+/// but the same settings otherwise.
+/// If it doesn't fit `c`, because of different settings, a different
+/// sequence header than the last one `c` has seen, or reference pictures
+/// that don't fit that sequence header or the frame size limit of `c`,
+/// `EINVAL` is returned and `c` is left untouched.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_state_restore(
+    c: *mut Dav1dContext,
+    s: *const Dav1dStateSnapshot,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!c.is_null(), EINVAL))?;
+        validate_input!((!s.is_null(), EINVAL))?;
+        rav1d_restore_state(&mut *c, &*s)
+    })()
+    .into()
+}
+
+/// Add a reference to `s` and return it.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_state_snapshot_ref(
+    s: *const Dav1dStateSnapshot,
+) -> *mut Dav1dStateSnapshot {
+    if validate_input!(!s.is_null()).is_err() {
+        return ptr::null_mut();
+    }
+    Arc::increment_strong_count(s);
+    s.cast_mut()
+}
+
+/// Release a reference to `*s` and set `*s` to `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_state_snapshot_unref(s: *mut *mut Dav1dStateSnapshot) {
+    if validate_input!(!s.is_null()).is_err() {
+        return;
+    }
+    if !(*s).is_null() {
+        drop(Arc::from_raw(*s));
+        *s = ptr::null_mut();
+    }
+}
+
+/// Decode the frame at the start of `data` from explicit reference state,
+/// ignoring the state of `c` from previous calls.
+///
+/// `seq_hdr` is the sequence header the frame was coded with, and `refs` are
+/// the 8 reference slots to decode it with, as returned in `out` by
+/// previous calls or by [`dav1d_state_snapshot_get_ref_slot`], or filled in by the caller.
+/// Slots not referenced by the frame may be null.
+///
+/// `c` must be opened with `max_frame_delay` 1.
+/// It is flushed before and after decoding the frame, so pictures not yet
+/// returned by [`dav1d_get_picture`] are dropped.
+///
+/// On success, `frame_hdr` receives the header of the frame, and `out` the state
+/// to save with it, including its reconstructed picture without film grain
+/// applied (see [`dav1d_apply_grain`]), to be released with [`dav1d_ref_slot_unref`].
+/// It is to be stored in each slot in `refresh_frame_flags` of `frame_hdr`,
+/// or, for a `show_existing_frame` of a key frame, in all 8 slots.
+/// For frames that refresh no slots, only `out.pic` is set.
+/// Data following the frame is left in `data`.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_decode_frame_stateless(
+    c: *mut Dav1dContext,
+    seq_hdr: *const Dav1dSequenceHeader,
+    data: *mut Dav1dData,
+    refs: *const *const Dav1dRefSlot,
+    frame_hdr: *mut Dav1dFrameHeader,
+    out: *mut Dav1dRefSlot,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!c.is_null(), EINVAL))?;
+        validate_input!((!seq_hdr.is_null(), EINVAL))?;
+        validate_input!((!data.is_null(), EINVAL))?;
+        validate_input!((!refs.is_null(), EINVAL))?;
+        validate_input!((!frame_hdr.is_null(), EINVAL))?;
+        validate_input!((!out.is_null(), EINVAL))?;
+        let refs = slice::from_raw_parts(refs, 8);
+        let refs = array::from_fn(|i| refs[i].as_ref());
+        let mut data_rust = data.read().into();
+        let result =
+            rav1d_decode_frame_stateless(&mut *c, seq_hdr.read().into(), &mut data_rust, refs);
+        data.write(data_rust.into());
+        let (frame_hdr_rust, out_rust) = result?;
+        frame_hdr.write(frame_hdr_rust.into());
+        out.write(out_rust);
+        Ok(())
+    })()
+    .into()
+}
+
+/// Get reference slot `slot` (0 to 7) of a snapshot, to decode with
+/// [`dav1d_decode_frame_stateless`], in `out`.
+/// It must be released with [`dav1d_ref_slot_unref`].
+///
+/// Returns `ENOENT` if the slot is empty.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_state_snapshot_get_ref_slot(
+    s: *const Dav1dStateSnapshot,
+    slot: c_int,
+    out: *mut Dav1dRefSlot,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!s.is_null(), EINVAL))?;
+        validate_input!((slot >= 0 && slot < 8, EINVAL))?;
+        validate_input!((!out.is_null(), EINVAL))?;
+        out.write(rav1d_snapshot_ref_slot(&*s, slot as usize).ok_or(ENOENT)?);
+        Ok(())
+    })()
+    .into()
+}
+
+/// Release the buffers of a reference slot filled in by the decoder, and clear it.
+///
+/// Slots filled in by the caller must not be passed here.
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_ref_slot_unref(slot: *mut Dav1dRefSlot) {
+    if validate_input!(!slot.is_null()).is_err() {
+        return;
+    }
+    rav1d_ref_slot_unref(&mut *slot);
+}
+
 #[cold]
 pub(crate) unsafe fn rav1d_close(c_out: &mut *mut Rav1dContext) {
     close_internal(c_out, 1 as c_int);
@@ -1072,6 +1333,59 @@ pub unsafe extern "C" fn dav1d_picture_unref(p: *mut Dav1dPicture) {
     p.write(p_rust.into());
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_picture_get_recon_stage(
+    p: *const Dav1dPicture,
+    stage: Dav1dReconStage,
+    out: *mut Dav1dPicture,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!p.is_null(), EINVAL))?;
+        let out = validate_input!(NonNull::new(out).ok_or(EINVAL))?;
+        validate_input!((stage < RAV1D_RECON_STAGE_COUNT, EINVAL))?;
+        let exports = &(*p).exports_ref.as_ref().ok_or(ENOENT)?.as_ref().rav1d;
+        let stages = exports.recon_stages.as_ref().ok_or(ENOENT)?;
+        let pic = rav1d_recon_stages_ref(stages.get(stage)).ok_or(ENOENT)?;
+        out.as_ptr().write(pic.into());
+        Ok(())
+    })()
+    .into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_picture_get_prediction(
+    p: *const Dav1dPicture,
+    out: *mut Dav1dPicture,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!p.is_null(), EINVAL))?;
+        let out = validate_input!(NonNull::new(out).ok_or(EINVAL))?;
+        let exports = &(*p).exports_ref.as_ref().ok_or(ENOENT)?.as_ref().rav1d;
+        let stages = exports.recon_stages.as_ref().ok_or(ENOENT)?;
+        let pic = rav1d_recon_stages_ref(stages.pred()).ok_or(ENOENT)?;
+        out.as_ptr().write(pic.into());
+        Ok(())
+    })()
+    .into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_picture_get_residual(
+    p: *const Dav1dPicture,
+    out: *mut Dav1dPicture,
+) -> Dav1dResult {
+    (|| {
+        validate_input!((!p.is_null(), EINVAL))?;
+        let out = validate_input!(NonNull::new(out).ok_or(EINVAL))?;
+        let exports = &(*p).exports_ref.as_ref().ok_or(ENOENT)?.as_ref().rav1d;
+        let stages = exports.recon_stages.as_ref().ok_or(ENOENT)?;
+        let pic = rav1d_recon_stages_ref(stages.residual()).ok_or(ENOENT)?;
+        out.as_ptr().write(pic.into());
+        Ok(())
+    })()
+    .into()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dav1d_data_create(buf: *mut Dav1dData, sz: usize) -> *mut u8 {
     || -> Rav1dResult<*mut u8> {
@@ -100,6 +100,14 @@ impl MsacContext {
     fn set_allow_update_cdf(&mut self, val: bool) {
         self.allow_update_cdf = val.into()
     }
+
+    /// Approximate number of bits left to decode, including the padding,
+    /// like `od_ec_dec_tell` in libaom, but counting down.
+    pub fn bits_left(&self) -> isize {
+        // Safety: See [`Self::with_buf`].
+        let bytes = unsafe { self.buf_end.offset_from(self.buf_pos) };
+        bytes * 8 + self.cnt as isize + 15
+    }
 }
 
 #[inline]
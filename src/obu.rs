@@ -2,9 +2,16 @@ use crate::include::common::intops::iclip_u8;
 use crate::include::common::intops::ulog2;
 use crate::include::dav1d::common::Rav1dDataProps;
 use crate::include::dav1d::data::Rav1dData;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::Dav1dSyntaxElement;
+use crate::include::dav1d::dav1d::Rav1dRandomAccessPoint;
+use crate::include::dav1d::dav1d::Rav1dRandomAccessType;
 use crate::include::dav1d::dav1d::RAV1D_DECODEFRAMETYPE_INTRA;
 use crate::include::dav1d::dav1d::RAV1D_DECODEFRAMETYPE_REFERENCE;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::RAV1D_SYNTAX_LABEL;
 use crate::include::dav1d::headers::DRav1d;
+use crate::include::dav1d::headers::Dav1dFrameHeader;
 use crate::include::dav1d::headers::Rav1dAdaptiveBoolean;
 use crate::include::dav1d::headers::Rav1dChromaSamplePosition;
 use crate::include::dav1d::headers::Rav1dColorPrimaries;
@@ -78,6 +85,7 @@ use crate::src::cdf::rav1d_cdf_thread_unref;
 use crate::src::decode::rav1d_submit_frame;
 use crate::src::env::get_poc_diff;
 use crate::src::error::Rav1dError::EINVAL;
+use crate::src::error::Rav1dError::ENOENT;
 use crate::src::error::Rav1dError::ERANGE;
 use crate::src::error::Rav1dResult;
 use crate::src::getbits::GetBits;
@@ -102,6 +110,8 @@ use std::array;
 use std::cmp;
 use std::ffi::c_int;
 use std::ffi::c_uint;
+#[cfg(feature = "syntax_trace")]
+use std::ffi::CString;
 use std::fmt;
 use std::mem;
 use std::mem::MaybeUninit;
@@ -136,7 +146,30 @@ impl Debug {
         }
     }
 
-    pub fn log(&self, gb: &GetBits, msg: fmt::Arguments) {
+    /// Reports `label` to the syntax trace, if any,
+    /// naming the header bits read since the previous label.
+    #[cfg(feature = "syntax_trace")]
+    #[cold]
+    fn trace(&self, gb: &GetBits, label: fmt::Arguments) {
+        let Some(trace) = &gb.trace else {
+            return;
+        };
+        let label = CString::new(format!("{}: {label}", self.name)).unwrap();
+        trace.emit(&Dav1dSyntaxElement {
+            source: RAV1D_SYNTAX_LABEL,
+            name: label.as_ptr(),
+            value: (gb.pos() - self.start) as i64,
+            n_bits: 0,
+            bit_pos: gb.pos() as i64,
+            tile: -1,
+            sbx: -1,
+            sby: -1,
+            bx: -1,
+            by: -1,
+        });
+    }
+
+    fn print(&self, gb: &GetBits, msg: fmt::Arguments) {
         let &Self {
             enabled,
             name,
@@ -149,12 +182,34 @@ impl Debug {
         println!("{name}: {msg} [off={offset}]");
     }
 
+    pub fn log(&self, gb: &GetBits, msg: fmt::Arguments) {
+        #[cfg(feature = "syntax_trace")]
+        self.trace(gb, msg);
+        self.print(gb, msg);
+    }
+
     pub fn post(&self, gb: &GetBits, post: &str) {
-        self.log(gb, format_args!("post-{post}"));
+        #[cfg(feature = "syntax_trace")]
+        self.trace(gb, format_args!("{post}"));
+        self.print(gb, format_args!("post-{post}"));
     }
 }
 
-fn parse_seq_hdr(c: &mut Rav1dContext, gb: &mut GetBits) -> Rav1dResult<Rav1dSequenceHeader> {
+/// The `idc` of `operating_point` in `seq_hdr`,
+/// or of the first operating point if there are fewer.
+fn operating_point_idc(seq_hdr: &Rav1dSequenceHeader, operating_point: c_int) -> c_uint {
+    let op_idx = if operating_point < seq_hdr.num_operating_points {
+        operating_point
+    } else {
+        0
+    };
+    seq_hdr.operating_points[op_idx as usize].idc as c_uint
+}
+
+fn parse_seq_hdr(
+    gb: &mut GetBits,
+    strict_std_compliance: bool,
+) -> Rav1dResult<Rav1dSequenceHeader> {
     let debug = Debug::new(false, "SEQHDR", gb);
 
     let profile = gb.get_bits(3) as c_int;
@@ -209,7 +264,7 @@ fn parse_seq_hdr(c: &mut Rav1dContext, gb: &mut GetBits) -> Rav1dResult<Rav1dSeq
         if timing_info_present != 0 {
             num_units_in_tick = gb.get_bits(32) as c_int;
             time_scale = gb.get_bits(32) as c_int;
-            if c.strict_std_compliance && (num_units_in_tick == 0 || time_scale == 0) {
+            if strict_std_compliance && (num_units_in_tick == 0 || time_scale == 0) {
                 return Err(EINVAL);
             }
             equal_picture_interval = gb.get_bit() as c_int;
@@ -228,7 +283,7 @@ fn parse_seq_hdr(c: &mut Rav1dContext, gb: &mut GetBits) -> Rav1dResult<Rav1dSeq
             if decoder_model_info_present != 0 {
                 encoder_decoder_buffer_delay_length = gb.get_bits(5) as c_int + 1;
                 num_units_in_decoding_tick = gb.get_bits(32) as c_int;
-                if c.strict_std_compliance && num_units_in_decoding_tick == 0 {
+                if strict_std_compliance && num_units_in_decoding_tick == 0 {
                     return Err(EINVAL);
                 }
                 buffer_removal_delay_length = gb.get_bits(5) as c_int + 1;
@@ -290,19 +345,6 @@ fn parse_seq_hdr(c: &mut Rav1dContext, gb: &mut GetBits) -> Rav1dResult<Rav1dSeq
         debug.post(gb, "operating-points");
     }
 
-    let op_idx = if c.operating_point < num_operating_points {
-        c.operating_point
-    } else {
-        0
-    };
-    c.operating_point_idc = operating_points[op_idx as usize].idc as c_uint;
-    let spatial_mask = c.operating_point_idc >> 8;
-    c.max_spatial_id = if spatial_mask != 0 {
-        ulog2(spatial_mask) != 0
-    } else {
-        false
-    };
-
     let width_n_bits = gb.get_bits(4) as c_int + 1;
     let height_n_bits = gb.get_bits(4) as c_int + 1;
     let max_width = gb.get_bits(width_n_bits) as c_int + 1;
@@ -493,7 +535,7 @@ fn parse_seq_hdr(c: &mut Rav1dContext, gb: &mut GetBits) -> Rav1dResult<Rav1dSeq
             RAV1D_CHR_UNKNOWN
         };
     }
-    if c.strict_std_compliance && mtrx == RAV1D_MC_IDENTITY && layout != Rav1dPixelLayout::I444 {
+    if strict_std_compliance && mtrx == RAV1D_MC_IDENTITY && layout != Rav1dPixelLayout::I444 {
         return Err(EINVAL);
     }
     let separate_uv_delta_q;
@@ -2112,11 +2154,16 @@ fn check_for_overrun(
     0
 }
 
+/// Parses the OBU at the start of `in` and returns its length.
+///
+/// If that completes a frame, which is then decoded or shown,
+/// its header is moved from [`Rav1dContext::frame_hdr`] to `done`.
 unsafe fn parse_obus(
     c: &mut Rav1dContext,
     r#in: &CArc<[u8]>,
     props: &Rav1dDataProps,
     global: bool,
+    done: &mut Option<Arc<DRav1d<Rav1dFrameHeader, Dav1dFrameHeader>>>,
 ) -> Rav1dResult<usize> {
     unsafe fn skip(c: &mut Rav1dContext, len: usize, init_byte_pos: usize) -> usize {
         // update refs with only the headers in case we skip the frame
@@ -2135,6 +2182,10 @@ unsafe fn parse_obus(
     }
 
     let mut gb = GetBits::new(r#in);
+    #[cfg(feature = "syntax_trace")]
+    {
+        gb.trace = c.syntax_trace;
+    }
 
     // obu header
     gb.get_bit(); // obu_forbidden_bit
@@ -2239,9 +2290,16 @@ unsafe fn parse_obus(
 
     match r#type {
         RAV1D_OBU_SEQ_HDR => {
-            let seq_hdr = parse_seq_hdr(c, &mut gb).inspect_err(|_| {
+            let seq_hdr = parse_seq_hdr(&mut gb, c.strict_std_compliance).inspect_err(|_| {
                 writeln!(c.logger, "Error parsing sequence header");
             })?;
+            c.operating_point_idc = operating_point_idc(&seq_hdr, c.operating_point);
+            let spatial_mask = c.operating_point_idc >> 8;
+            c.max_spatial_id = if spatial_mask != 0 {
+                ulog2(spatial_mask) != 0
+            } else {
+                false
+            };
             if check_for_overrun(c, &mut gb, init_bit_pos, len) != 0 {
                 return Err(EINVAL);
             }
@@ -2610,7 +2668,7 @@ unsafe fn parse_obus(
                     rav1d_ref_dec(&mut c.refs[i as usize].refmvs);
                 }
             }
-            c.frame_hdr = None;
+            *done = c.frame_hdr.take();
         } else if c.n_tiles == frame_hdr.tiling.cols * frame_hdr.tiling.rows {
             match frame_hdr.frame_type {
                 Rav1dFrameType::Inter | Rav1dFrameType::Switch => {
@@ -2631,12 +2689,21 @@ unsafe fn parse_obus(
                 }
                 _ => {}
             }
+            // Nothing depends on a non-reference frame before the seek target,
+            // and it won't be output, so it doesn't need to be reconstructed.
+            if frame_hdr.refresh_frame_flags == 0
+                && c.seek_target.is_some_and(|target| props.timestamp < target)
+            {
+                return Ok(skip(c, len, init_byte_pos));
+            }
             if c.tiles.is_empty() {
                 return Err(EINVAL);
             }
+            // The header is moved to the frame being decoded.
+            let submitted = c.frame_hdr.clone();
             rav1d_submit_frame(&mut *c)?;
             assert!(c.tiles.is_empty());
-            c.frame_hdr = None;
+            *done = submitted;
             c.n_tiles = 0;
         }
     }
@@ -2644,13 +2711,136 @@ unsafe fn parse_obus(
     Ok(len + init_byte_pos)
 }
 
+/// The sequence header state that [`rav1d_parse_random_access_point`] needs.
+#[derive(Clone, Copy)]
+struct RandomAccessSeqHdr {
+    /// The frame type is determined by the first few bits of the frame header,
+    /// which only depend on this.
+    reduced_still_picture_header: bool,
+    operating_point_idc: c_uint,
+}
+
+impl RandomAccessSeqHdr {
+    fn new(seq_hdr: &Rav1dSequenceHeader, operating_point: c_int) -> Self {
+        Self {
+            reduced_still_picture_header: seq_hdr.reduced_still_picture_header != 0,
+            operating_point_idc: operating_point_idc(seq_hdr, operating_point),
+        }
+    }
+}
+
+/// Inspects the OBUs of a temporal unit, up to and including its first
+/// frame header in `operating_point`, to see whether decoding can start at it.
+/// Nothing is decoded.
+///
+/// The sequence header is the last one in the temporal unit, or else `seq_hdr`.
+pub(crate) fn rav1d_parse_random_access_point(
+    mut data: &[u8],
+    seq_hdr: Option<&Rav1dSequenceHeader>,
+    operating_point: c_int,
+) -> Rav1dResult<Rav1dRandomAccessPoint> {
+    let mut seq_hdr = seq_hdr.map(|seq_hdr| RandomAccessSeqHdr::new(seq_hdr, operating_point));
+    let mut seq_hdr_present = false;
+    while !data.is_empty() {
+        let mut gb = GetBits::new(data);
+
+        // obu header
+        gb.get_bit(); // obu_forbidden_bit
+        let r#type = gb.get_bits(4) as Rav1dObuType;
+        let has_extension = gb.get_bit() as c_int;
+        let has_length_field = gb.get_bit() as c_int;
+        gb.get_bit(); // reserved
+        let mut temporal_id = 0;
+        let mut spatial_id = 0;
+        if has_extension != 0 {
+            temporal_id = gb.get_bits(3);
+            spatial_id = gb.get_bits(2);
+            gb.get_bits(3); // reserved
+        }
+
+        // obu length field
+        let len = if has_length_field != 0 {
+            gb.get_uleb128() as usize
+        } else {
+            data.len() - 1 - has_extension as usize
+        };
+        if gb.has_error() != 0 {
+            return Err(EINVAL);
+        }
+
+        let init_byte_pos = gb.pos() >> 3;
+        if len > data.len() - init_byte_pos {
+            return Err(EINVAL);
+        }
+        let next = &data[init_byte_pos + len..];
+
+        // skip obu not belonging to the selected temporal/spatial layer
+        let operating_point_idc = seq_hdr.map_or(0, |seq_hdr| seq_hdr.operating_point_idc);
+        if r#type != RAV1D_OBU_SEQ_HDR && has_extension != 0 && operating_point_idc != 0 {
+            let in_temporal_layer = operating_point_idc >> temporal_id & 1 != 0;
+            let in_spatial_layer = operating_point_idc >> spatial_id + 8 & 1 != 0;
+            if !in_temporal_layer || !in_spatial_layer {
+                data = next;
+                continue;
+            }
+        }
+
+        match r#type {
+            RAV1D_OBU_SEQ_HDR => {
+                let mut gb = GetBits::new(&data[init_byte_pos..init_byte_pos + len]);
+                let hdr = parse_seq_hdr(&mut gb, false)?;
+                seq_hdr = Some(RandomAccessSeqHdr::new(&hdr, operating_point));
+                seq_hdr_present = true;
+            }
+            RAV1D_OBU_FRAME | RAV1D_OBU_FRAME_HDR => {
+                let seq_hdr = seq_hdr.ok_or(ENOENT)?;
+                let r#type = if seq_hdr.reduced_still_picture_header {
+                    // A key frame with `show_frame` set.
+                    Rav1dRandomAccessType::Key
+                } else if len == 0 || gb.get_bit() {
+                    // `show_existing_frame`, or an empty (invalid) header.
+                    Rav1dRandomAccessType::None
+                } else {
+                    let frame_type = Rav1dFrameType::from_repr(gb.get_bits(2) as usize).unwrap();
+                    let show_frame = gb.get_bit();
+                    // Intra-only frames aren't random access points, as the frames
+                    // after them may still reference frames from before them.
+                    match frame_type {
+                        Rav1dFrameType::Key if show_frame => Rav1dRandomAccessType::Key,
+                        // A hidden key frame can only be shown later if `showable_frame` is set,
+                        // which directly follows `show_frame` then.
+                        Rav1dFrameType::Key if gb.get_bit() => Rav1dRandomAccessType::ForwardKey,
+                        _ => Rav1dRandomAccessType::None,
+                    }
+                };
+                if gb.has_error() != 0 {
+                    return Err(EINVAL);
+                }
+                return Ok(Rav1dRandomAccessPoint {
+                    r#type,
+                    seq_hdr_present,
+                });
+            }
+            _ => {}
+        }
+
+        data = next;
+    }
+
+    Ok(Rav1dRandomAccessPoint {
+        r#type: Rav1dRandomAccessType::None,
+        seq_hdr_present,
+    })
+}
+
 pub(crate) unsafe fn rav1d_parse_obus(
     c: &mut Rav1dContext,
     r#in: &CArc<[u8]>,
     props: &Rav1dDataProps,
     global: bool,
+    done: &mut Option<Arc<DRav1d<Rav1dFrameHeader, Dav1dFrameHeader>>>,
 ) -> Rav1dResult<usize> {
-    parse_obus(c, r#in, props, global).inspect_err(|_| {
+    parse_obus(c, r#in, props, global, done).inspect_err(|_| {
         *c.cached_error_props.get_mut().unwrap() = props.clone();
         writeln!(c.logger, "Error parsing OBU data");
     })
@@ -1,6 +1,9 @@
 use crate::include::common::validate::validate_input;
 use crate::include::dav1d::common::Rav1dDataProps;
 use crate::include::dav1d::dav1d::Rav1dEventFlags;
+use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_CDEF;
+use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_DEBLOCK;
+use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_RESTORATION;
 use crate::include::dav1d::headers::DRav1d;
 use crate::include::dav1d::headers::Dav1dFrameHeader;
 use crate::include::dav1d::headers::Dav1dITUTT35;
@@ -12,6 +15,9 @@ use crate::include::dav1d::headers::Rav1dMasteringDisplay;
 use crate::include::dav1d::headers::Rav1dPixelLayout;
 use crate::include::dav1d::headers::Rav1dSequenceHeader;
 use crate::include::dav1d::picture::Dav1dPicture;
+use crate::include::dav1d::picture::Dav1dPictureMemfd;
+use crate::include::dav1d::picture::Dav1dPictureRequirements;
+use crate::include::dav1d::picture::Dav1dPlaneRequirements;
 use crate::include::dav1d::picture::Rav1dPicAllocator;
 use crate::include::dav1d::picture::Rav1dPicture;
 use crate::include::dav1d::picture::Rav1dPictureData;
@@ -19,6 +25,7 @@ use crate::include::dav1d::picture::Rav1dPictureParameters;
 use crate::include::dav1d::picture::RAV1D_PICTURE_ALIGNMENT;
 use crate::src::error::Dav1dResult;
 use crate::src::error::Rav1dError::EGeneric;
+use crate::src::error::Rav1dError::EINVAL;
 use crate::src::error::Rav1dError::ENOMEM;
 use crate::src::error::Rav1dResult;
 use crate::src::internal::Rav1dContext;
@@ -36,8 +43,10 @@ use atomig::Atom;
 use atomig::AtomLogic;
 use bitflags::bitflags;
 use libc::ptrdiff_t;
+use std::cmp;
 use std::ffi::c_int;
 use std::ffi::c_void;
+use std::iter;
 use std::mem;
 use std::ptr;
 use std::ptr::NonNull;
@@ -47,6 +56,39 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use to_method::To as _;
 
+#[cfg(target_os = "linux")]
+use libc::close;
+#[cfg(target_os = "linux")]
+use libc::fcntl;
+#[cfg(target_os = "linux")]
+use libc::ftruncate;
+#[cfg(target_os = "linux")]
+use libc::memfd_create;
+#[cfg(target_os = "linux")]
+use libc::mmap;
+#[cfg(target_os = "linux")]
+use libc::munmap;
+#[cfg(target_os = "linux")]
+use libc::off_t;
+#[cfg(target_os = "linux")]
+use libc::F_ADD_SEALS;
+#[cfg(target_os = "linux")]
+use libc::F_SEAL_GROW;
+#[cfg(target_os = "linux")]
+use libc::F_SEAL_SHRINK;
+#[cfg(target_os = "linux")]
+use libc::MAP_FAILED;
+#[cfg(target_os = "linux")]
+use libc::MAP_SHARED;
+#[cfg(target_os = "linux")]
+use libc::MFD_ALLOW_SEALING;
+#[cfg(target_os = "linux")]
+use libc::MFD_CLOEXEC;
+#[cfg(target_os = "linux")]
+use libc::PROT_READ;
+#[cfg(target_os = "linux")]
+use libc::PROT_WRITE;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Atom, AtomLogic)]
 pub struct PictureFlags(u8);
 
@@ -88,18 +130,15 @@ pub(crate) struct pic_ctx_context {
     pub pic: Rav1dPicture,
 }
 
-pub unsafe extern "C" fn dav1d_default_picture_alloc(
-    p_c: *mut Dav1dPicture,
-    cookie: *mut c_void,
-) -> Dav1dResult {
-    assert!(::core::mem::size_of::<Rav1dMemPoolBuffer>() <= RAV1D_PICTURE_ALIGNMENT);
-    let mut p = p_c.read().to::<Rav1dPicture>();
-    let hbd = (p.p.bpc > 8) as c_int;
-    let aligned_w = p.p.w + 127 & !127;
-    let aligned_h = p.p.h + 127 & !127;
-    let has_chroma = p.p.layout != Rav1dPixelLayout::I400;
-    let ss_ver = (p.p.layout == Rav1dPixelLayout::I420) as c_int;
-    let ss_hor = (p.p.layout != Rav1dPixelLayout::I444) as c_int;
+/// The strides and plane sizes used by the built-in allocators,
+/// which satisfy [`rav1d_picture_requirements`].
+fn default_picture_layout(p: &Rav1dPictureParameters) -> ([ptrdiff_t; 2], [usize; 3]) {
+    let hbd = (p.bpc > 8) as c_int;
+    let aligned_w = p.w + 127 & !127;
+    let aligned_h = p.h + 127 & !127;
+    let has_chroma = p.layout != Rav1dPixelLayout::I400;
+    let ss_ver = (p.layout == Rav1dPixelLayout::I420) as c_int;
+    let ss_hor = (p.layout != Rav1dPixelLayout::I444) as c_int;
     let mut y_stride = (aligned_w << hbd) as ptrdiff_t;
     let mut uv_stride = if has_chroma { y_stride >> ss_hor } else { 0 };
     if y_stride & 1023 == 0 {
@@ -108,10 +147,19 @@ pub unsafe extern "C" fn dav1d_default_picture_alloc(
     if uv_stride & 1023 == 0 && has_chroma {
         uv_stride += RAV1D_PICTURE_ALIGNMENT as isize;
     }
-    p.stride[0] = y_stride;
-    p.stride[1] = uv_stride;
     let y_sz = (y_stride * aligned_h as isize) as usize;
     let uv_sz = (uv_stride * (aligned_h >> ss_ver) as isize) as usize;
+    ([y_stride, uv_stride], [y_sz, uv_sz, uv_sz])
+}
+
+pub unsafe extern "C" fn dav1d_default_picture_alloc(
+    p_c: *mut Dav1dPicture,
+    cookie: *mut c_void,
+) -> Dav1dResult {
+    assert!(::core::mem::size_of::<Rav1dMemPoolBuffer>() <= RAV1D_PICTURE_ALIGNMENT);
+    let mut p = p_c.read().to::<Rav1dPicture>();
+    let (stride, [y_sz, uv_sz, _]) = default_picture_layout(&p.p);
+    p.stride = stride;
     let pic_size = y_sz + 2 * uv_sz;
     let buf = rav1d_mem_pool_pop(
         cookie as *mut Rav1dMemPool,
@@ -144,16 +192,143 @@ pub unsafe extern "C" fn dav1d_default_picture_release(p: *mut Dav1dPicture, coo
     );
 }
 
+/// Allocates each picture in its own `memfd` region,
+/// so that it can be shared with other processes without copying.
+///
+/// [`Dav1dPicture::allocator_data`] points to a [`Dav1dPictureMemfd`]
+/// describing the region, which stays valid until the picture is released
+/// with [`dav1d_memfd_picture_release`].
+/// The `cookie` is unused.
+#[cfg(target_os = "linux")]
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_memfd_picture_alloc(
+    p_c: *mut Dav1dPicture,
+    _cookie: *mut c_void,
+) -> Dav1dResult {
+    let mut p = p_c.read().to::<Rav1dPicture>();
+    let (stride, [y_sz, uv_sz, _]) = default_picture_layout(&p.p);
+    let offset = [0, y_sz, y_sz + uv_sz];
+    let size = y_sz + 2 * uv_sz + RAV1D_PICTURE_ALIGNMENT;
+    let fd = memfd_create(
+        b"dav1d-picture\0".as_ptr().cast(),
+        MFD_CLOEXEC | MFD_ALLOW_SEALING,
+    );
+    if fd < 0 {
+        return Rav1dResult::<()>::Err(ENOMEM).into();
+    }
+    let base = if ftruncate(fd, size as off_t) == 0
+        && fcntl(fd, F_ADD_SEALS, F_SEAL_SHRINK | F_SEAL_GROW) == 0
+    {
+        mmap(
+            ptr::null_mut(),
+            size,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            fd,
+            0,
+        )
+    } else {
+        MAP_FAILED
+    };
+    if base == MAP_FAILED {
+        close(fd);
+        return Rav1dResult::<()>::Err(ENOMEM).into();
+    }
+    let memfd = Box::new(Dav1dPictureMemfd { fd, size, offset });
+    p.stride = stride;
+    p.data = Rav1dPictureData {
+        data: offset.map(|offset| base.cast::<u8>().add(offset).cast()),
+        allocator_data: NonNull::new(Box::into_raw(memfd).cast()),
+    };
+    p_c.write(p.into());
+    Rav1dResult::Ok(()).into()
+}
+
+/// Releases a picture allocated by [`dav1d_memfd_picture_alloc`],
+/// unmapping and closing its `memfd`.
+/// Other processes that received the file descriptor keep their mapping.
+#[cfg(target_os = "linux")]
+#[no_mangle]
+pub unsafe extern "C" fn dav1d_memfd_picture_release(p: *mut Dav1dPicture, _cookie: *mut c_void) {
+    let Some(memfd) = (*p).allocator_data else {
+        return;
+    };
+    let memfd = Box::from_raw(memfd.as_ptr().cast::<Dav1dPictureMemfd>());
+    if let Some(data) = (*p).data[0] {
+        munmap(data.as_ptr(), memfd.size);
+    }
+    close(memfd.fd);
+}
+
 impl Default for Rav1dPicAllocator {
     fn default() -> Self {
         Self {
             cookie: ptr::null_mut(),
-            alloc_picture_callback: dav1d_default_picture_alloc,
+            alloc_picture_callback: Some(dav1d_default_picture_alloc),
             release_picture_callback: dav1d_default_picture_release,
+            alloc_picture_planes_callback: None,
         }
     }
 }
 
+/// The fixed requirements that [`dav1d_default_picture_alloc`] satisfies
+/// and that are documented for [`Dav1dPicAllocator::alloc_picture_callback`],
+/// spelled out per plane:
+/// a pixel width/height multiple of 128 pixels
+/// and [`RAV1D_PICTURE_ALIGNMENT`]-byte alignment and padding.
+///
+/// [`Dav1dPicAllocator::alloc_picture_callback`]: crate::include::dav1d::picture::Dav1dPicAllocator::alloc_picture_callback
+pub(crate) fn rav1d_picture_requirements(p: &Rav1dPictureParameters) -> Dav1dPictureRequirements {
+    let hbd = (p.bpc > 8) as c_int;
+    let aligned_w = p.w + 127 & !127;
+    let aligned_h = p.h + 127 & !127;
+    let ss_ver = (p.layout == Rav1dPixelLayout::I420) as c_int;
+    let ss_hor = (p.layout != Rav1dPixelLayout::I444) as c_int;
+    let plane = |w: c_int, h: c_int| Dav1dPlaneRequirements {
+        width: w << hbd,
+        height: h,
+        alignment: RAV1D_PICTURE_ALIGNMENT as c_int,
+        padding: RAV1D_PICTURE_ALIGNMENT as c_int,
+    };
+    let luma = plane(aligned_w, aligned_h);
+    let chroma = if p.layout == Rav1dPixelLayout::I400 {
+        Default::default()
+    } else {
+        plane(aligned_w >> ss_hor, aligned_h >> ss_ver)
+    };
+    Dav1dPictureRequirements {
+        planes: [luma, chroma, chroma],
+    }
+}
+
+/// Checks that the `data`, `stride` and buffer `size`s filled in by an allocator satisfy `req`.
+fn picture_satisfies_requirements(
+    p: &Rav1dPicture,
+    size: &[usize; 3],
+    req: &Dav1dPictureRequirements,
+) -> bool {
+    if p.stride[1] != 0 && req.planes[1].width == 0 {
+        return false;
+    }
+    iter::zip(iter::zip(p.data.data, size), &req.planes)
+        .enumerate()
+        .all(|(i, ((data, &size), req))| {
+            if req.width == 0 {
+                return true;
+            }
+            let stride = p.stride[(i != 0) as usize].unsigned_abs();
+            let alignment = req.alignment as usize;
+            let min_size = stride
+                .checked_mul(req.height as usize)
+                .and_then(|len| len.checked_add(req.padding as usize));
+            !data.is_null()
+                && data as usize % alignment == 0
+                && stride % alignment == 0
+                && stride >= req.width as usize
+                && min_size.is_some_and(|min_size| size >= min_size)
+        })
+}
+
 unsafe extern "C" fn free_buffer(_data: *const u8, user_data: *mut c_void) {
     let pic_ctx: *mut pic_ctx_context = user_data as *mut pic_ctx_context;
     let pic_ctx = Box::from_raw(pic_ctx);
@@ -182,11 +357,33 @@ impl Rav1dPicAllocator {
             frame_hdr,
             ..Default::default()
         };
+        let req = rav1d_picture_requirements(&pic.p);
+        let mut size = [0; 3];
         let mut pic_c = pic.to::<Dav1dPicture>();
         // Safety: `pic_c` is a valid `Dav1dPicture` with `data`, `stride`, `allocator_data` unset.
-        let result = unsafe { (self.alloc_picture_callback)(&mut pic_c, self.cookie) };
+        let result = unsafe {
+            match (
+                self.alloc_picture_planes_callback,
+                self.alloc_picture_callback,
+            ) {
+                (Some(alloc_picture_planes_callback), _) => {
+                    alloc_picture_planes_callback(&mut pic_c, &req, &mut size, self.cookie)
+                }
+                (None, Some(alloc_picture_callback)) => {
+                    alloc_picture_callback(&mut pic_c, self.cookie)
+                }
+                // Rejected by [`Rav1dPicAllocator::try_with_planes`].
+                (None, None) => Rav1dResult::<()>::Err(EINVAL).into(),
+            }
+        };
         result.try_to::<Rav1dResult>().unwrap()?;
         let mut pic = pic_c.to::<Rav1dPicture>();
+        if self.alloc_picture_planes_callback.is_some()
+            && !picture_satisfies_requirements(&pic, &size, &req)
+        {
+            self.dealloc_picture_data(pic.data.data, pic.data.allocator_data);
+            return Err(EINVAL);
+        }
 
         let pic_ctx = Box::new(pic_ctx_context {
             allocator: self.clone(),
@@ -304,6 +501,59 @@ pub(crate) unsafe fn rav1d_thread_picture_alloc(
     Ok(())
 }
 
+/// The number of luma rows of `f.sr_cur` that are final
+/// once sbrows `0..=sby` of `f` have been post-filtered.
+///
+/// Deblocking, CDEF, super-resolution and loop restoration each stop
+/// 8 luma rows (4 with vertically subsampled chroma) above the bottom
+/// of an sbrow and only finish those rows with the next sbrow,
+/// either because the next sbrow's edges modify them,
+/// or because they need the rows below as input.
+/// Without any of them, an sbrow is final as soon as it's reconstructed.
+fn post_filter_rows(c: &Rav1dContext, f: &Rav1dFrameContext, sby: c_int) -> c_int {
+    let h = f.sr_cur.p.p.h;
+    if sby + 1 >= f.sbh {
+        return h;
+    }
+    let seq_hdr = &***f.seq_hdr.as_ref().unwrap();
+    let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
+    let deblock = c.inloop_filters & RAV1D_INLOOPFILTER_DEBLOCK != 0
+        && (frame_hdr.loopfilter.level_y[0] != 0 || frame_hdr.loopfilter.level_y[1] != 0);
+    let cdef = c.inloop_filters & RAV1D_INLOOPFILTER_CDEF != 0 && seq_hdr.cdef != 0;
+    let superres = frame_hdr.size.width[0] != frame_hdr.size.width[1];
+    let restoration =
+        c.inloop_filters & RAV1D_INLOOPFILTER_RESTORATION != 0 && f.lf.restore_planes != 0;
+    let lag = if deblock || cdef || superres || restoration {
+        8
+    } else {
+        0
+    };
+    cmp::min(h, (sby + 1) * f.sb_step * 4 - lag)
+}
+
+/// Reports to [`Rav1dContext::row_progress`] that sbrows `0..=sby` of `f`
+/// have been fully post-filtered.
+pub(crate) unsafe fn rav1d_thread_picture_row_progress(
+    c: &Rav1dContext,
+    f: &Rav1dFrameContext,
+    sby: c_int,
+) {
+    let Some(row_progress) = &c.row_progress else {
+        return;
+    };
+    let p = &f.sr_cur;
+    // upon flush, this can be free'ed already
+    if p.p.data.data[0].is_null() || !(p.visible || c.output_invisible_frames) {
+        return;
+    }
+    let rows = post_filter_rows(c, f, sby);
+    if f.task_thread.row_progress.fetch_max(rows, Ordering::SeqCst) >= rows {
+        return;
+    }
+    let pic = p.p.dav1d_view();
+    (row_progress.callback)(row_progress.cookie, &pic, rows);
+}
+
 pub(crate) unsafe fn rav1d_picture_alloc_copy(
     c: &Rav1dContext,
     dst: &mut Rav1dPicture,
@@ -325,7 +575,14 @@ pub(crate) unsafe fn rav1d_picture_alloc_copy(
         src.p.bpc,
         src.m.clone(),
         &mut (*pic_ctx).allocator,
-    )
+    )?;
+    dst.stats = src.stats.clone();
+    dst.cdf = src.cdf.clone();
+    dst.recon_stages = src.recon_stages.clone();
+    dst.block_info = src.block_info.clone();
+    dst.sb_bits = src.sb_bits.clone();
+    dst.tile_layout = src.tile_layout.clone();
+    Ok(())
 }
 
 pub(crate) unsafe fn rav1d_picture_ref(dst: &mut Rav1dPicture, src: &Rav1dPicture) {
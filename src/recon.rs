@@ -9,6 +9,11 @@ use crate::include::common::intops::iclip;
 use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_CDEF;
 use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_DEBLOCK;
 use crate::include::dav1d::dav1d::RAV1D_INLOOPFILTER_RESTORATION;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_CDEF;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_COUNT;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_DEBLOCK;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_RESTORATION;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_SUPERRES;
 use crate::include::dav1d::headers::Rav1dPixelLayout;
 use crate::include::dav1d::headers::Rav1dWarpedMotionParams;
 use crate::include::dav1d::headers::RAV1D_WM_TYPE_TRANSLATION;
@@ -89,8 +94,12 @@ use crate::src::msac::rav1d_msac_decode_symbol_adapt4;
 use crate::src::msac::rav1d_msac_decode_symbol_adapt8;
 use crate::src::msac::MsacContext;
 use crate::src::picture::Rav1dThreadPicture;
+use crate::src::recon_stages::rav1d_recon_stages_capture;
+use crate::src::recon_stages::rav1d_recon_stages_pred;
+use crate::src::recon_stages::rav1d_recon_stages_residual;
 use crate::src::refmvs::refmvs_block;
 use crate::src::scan::dav1d_scans;
+use crate::src::syntax_trace::trace_symbol;
 use crate::src::tables::dav1d_block_dimensions;
 use crate::src::tables::dav1d_filter_2d;
 use crate::src::tables::dav1d_filter_mode_to_y_mode;
@@ -469,9 +478,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
         );
     }
     let sctx = get_skip_ctx(t_dim, bs, a, l, chroma, (*f).cur.p.layout) as c_int;
-    let all_skip = rav1d_msac_decode_bool_adapt(
-        &mut (*ts).msac,
-        &mut (*ts).cdf.coef.skip[(*t_dim).ctx as usize][sctx as usize],
+    let all_skip = trace_symbol!(
+        (*ts),
+        "all_zero",
+        rav1d_msac_decode_bool_adapt(
+            &mut (*ts).msac,
+            &mut (*ts).cdf.coef.skip[(*t_dim).ctx as usize][sctx as usize],
+        )
     ) as c_int;
     if dbg != 0 {
         printf(
@@ -513,18 +526,26 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     b.c2rust_unnamed.c2rust_unnamed.y_mode as c_int
                 }) as IntraPredMode;
             if frame_hdr.reduced_txtp_set != 0 || (*t_dim).min as c_int == TX_16X16 as c_int {
-                idx = rav1d_msac_decode_symbol_adapt4(
-                    &mut (*ts).msac,
-                    &mut (*ts).cdf.m.txtp_intra2[(*t_dim).min as usize][y_mode_nofilt as usize],
-                    4 as c_int as usize,
+                idx = trace_symbol!(
+                    (*ts),
+                    "intra_tx_type",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut (*ts).msac,
+                        &mut (*ts).cdf.m.txtp_intra2[(*t_dim).min as usize][y_mode_nofilt as usize],
+                        4 as c_int as usize,
+                    )
                 );
                 *txtp = dav1d_tx_types_per_set[idx.wrapping_add(0 as c_int as c_uint) as usize]
                     as TxfmType;
             } else {
-                idx = rav1d_msac_decode_symbol_adapt8(
-                    &mut (*ts).msac,
-                    &mut (*ts).cdf.m.txtp_intra1[(*t_dim).min as usize][y_mode_nofilt as usize],
-                    6 as c_int as usize,
+                idx = trace_symbol!(
+                    (*ts),
+                    "intra_tx_type",
+                    rav1d_msac_decode_symbol_adapt8(
+                        &mut (*ts).msac,
+                        &mut (*ts).cdf.m.txtp_intra1[(*t_dim).min as usize][y_mode_nofilt as usize],
+                        6 as c_int as usize,
+                    )
                 );
                 *txtp = dav1d_tx_types_per_set[idx.wrapping_add(5 as c_int as c_uint) as usize]
                     as TxfmType;
@@ -542,25 +563,37 @@ unsafe fn decode_coefs<BD: BitDepth>(
             }
         } else {
             if frame_hdr.reduced_txtp_set != 0 || (*t_dim).max as c_int == TX_32X32 as c_int {
-                idx = rav1d_msac_decode_bool_adapt(
-                    &mut (*ts).msac,
-                    &mut (*ts).cdf.m.txtp_inter3[(*t_dim).min as usize],
+                idx = trace_symbol!(
+                    (*ts),
+                    "inter_tx_type",
+                    rav1d_msac_decode_bool_adapt(
+                        &mut (*ts).msac,
+                        &mut (*ts).cdf.m.txtp_inter3[(*t_dim).min as usize],
+                    )
                 ) as c_uint;
                 *txtp =
                     (idx.wrapping_sub(1 as c_int as c_uint) & IDTX as c_int as c_uint) as TxfmType;
             } else if (*t_dim).min as c_int == TX_16X16 as c_int {
-                idx = rav1d_msac_decode_symbol_adapt16(
-                    &mut (*ts).msac,
-                    &mut (*ts).cdf.m.txtp_inter2.0,
-                    11 as c_int as usize,
+                idx = trace_symbol!(
+                    (*ts),
+                    "inter_tx_type",
+                    rav1d_msac_decode_symbol_adapt16(
+                        &mut (*ts).msac,
+                        &mut (*ts).cdf.m.txtp_inter2.0,
+                        11 as c_int as usize,
+                    )
                 );
                 *txtp = dav1d_tx_types_per_set[idx.wrapping_add(12 as c_int as c_uint) as usize]
                     as TxfmType;
             } else {
-                idx = rav1d_msac_decode_symbol_adapt16(
-                    &mut (*ts).msac,
-                    &mut (*ts).cdf.m.txtp_inter1[(*t_dim).min as usize],
-                    15 as c_int as usize,
+                idx = trace_symbol!(
+                    (*ts),
+                    "inter_tx_type",
+                    rav1d_msac_decode_symbol_adapt16(
+                        &mut (*ts).msac,
+                        &mut (*ts).cdf.m.txtp_inter1[(*t_dim).min as usize],
+                        15 as c_int as usize,
+                    )
                 );
                 *txtp = dav1d_tx_types_per_set[idx.wrapping_add(24 as c_int as c_uint) as usize]
                     as TxfmType;
@@ -577,6 +610,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
             }
         }
     }
+    if (*f).stats.is_some() {
+        let stats = &mut (*ts).stats;
+        stats.tx_size[tx as usize] += 1;
+        stats.tx_type[*txtp as usize] += 1;
+    }
     let mut eob_bin = 0;
     let tx2dszctx = cmp::min((*t_dim).lw as c_int, TX_32X32 as c_int)
         + cmp::min((*t_dim).lh as c_int, TX_32X32 as c_int);
@@ -585,45 +623,59 @@ unsafe fn decode_coefs<BD: BitDepth>(
     match tx2dszctx {
         0 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_16[chroma as usize][is_1d as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_16",
                 rav1d_msac_decode_symbol_adapt4(&mut (*ts).msac, eob_bin_cdf, (4 + 0) as usize)
-                    as c_int;
+            ) as c_int;
         }
         1 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_32[chroma as usize][is_1d as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_32",
                 rav1d_msac_decode_symbol_adapt8(&mut (*ts).msac, eob_bin_cdf, (4 + 1) as usize)
-                    as c_int;
+            ) as c_int;
         }
         2 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_64[chroma as usize][is_1d as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_64",
                 rav1d_msac_decode_symbol_adapt8(&mut (*ts).msac, eob_bin_cdf, (4 + 2) as usize)
-                    as c_int;
+            ) as c_int;
         }
         3 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_128[chroma as usize][is_1d as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_128",
                 rav1d_msac_decode_symbol_adapt8(&mut (*ts).msac, eob_bin_cdf, (4 + 3) as usize)
-                    as c_int;
+            ) as c_int;
         }
         4 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_256[chroma as usize][is_1d as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_256",
                 rav1d_msac_decode_symbol_adapt16(&mut (*ts).msac, eob_bin_cdf, (4 + 4) as usize)
-                    as c_int;
+            ) as c_int;
         }
         5 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_512[chroma as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_512",
                 rav1d_msac_decode_symbol_adapt16(&mut (*ts).msac, eob_bin_cdf, (4 + 5) as usize)
-                    as c_int;
+            ) as c_int;
         }
         6 => {
             let eob_bin_cdf = &mut (*ts).cdf.coef.eob_bin_1024[chroma as usize];
-            eob_bin =
+            eob_bin = trace_symbol!(
+                (*ts),
+                "eob_pt_1024",
                 rav1d_msac_decode_symbol_adapt16(&mut (*ts).msac, eob_bin_cdf, (4 + 6) as usize)
-                    as c_int;
+            ) as c_int;
         }
         _ => {}
     }
@@ -641,7 +693,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
     if eob_bin > 1 {
         let eob_hi_bit_cdf = &mut (*ts).cdf.coef.eob_hi_bit[(*t_dim).ctx as usize][chroma as usize]
             [eob_bin as usize];
-        let eob_hi_bit = rav1d_msac_decode_bool_adapt(&mut (*ts).msac, eob_hi_bit_cdf) as c_int;
+        let eob_hi_bit = trace_symbol!(
+            (*ts),
+            "eob_extra",
+            rav1d_msac_decode_bool_adapt(&mut (*ts).msac, eob_hi_bit_cdf)
+        ) as c_int;
         if dbg != 0 {
             printf(
                 b"Post-eob_hi_bit[%d][%d][%d][%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -653,8 +709,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
             );
         }
         eob = (((eob_hi_bit | 2) << eob_bin - 2) as c_uint
-            | rav1d_msac_decode_bools(&mut (*ts).msac, (eob_bin - 2) as c_uint))
-            as c_int;
+            | trace_symbol!(
+                (*ts),
+                "eob_extra_bit",
+                rav1d_msac_decode_bools(&mut (*ts).msac, (eob_bin - 2) as c_uint)
+            )) as c_int;
         if dbg != 0 {
             printf(
                 b"Post-eob[%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -683,10 +742,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
         let sh = cmp::min((*t_dim).h as c_int, 8 as c_int);
         let mut ctx: c_uint =
             (1 as c_int + (eob > sw * sh * 2) as c_int + (eob > sw * sh * 4) as c_int) as c_uint;
-        let eob_tok = rav1d_msac_decode_symbol_adapt4(
-            &mut (*ts).msac,
-            &mut *eob_cdf.offset(ctx as isize),
-            2 as c_int as usize,
+        let eob_tok = trace_symbol!(
+            (*ts),
+            "coeff_base_eob",
+            rav1d_msac_decode_symbol_adapt4(
+                &mut (*ts).msac,
+                &mut *eob_cdf.offset(ctx as isize),
+                2 as c_int as usize,
+            )
         ) as c_int;
         let mut tok = eob_tok + 1;
         let mut level_tok = tok * 0x41 as c_int;
@@ -753,9 +816,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         7 as c_int
                     }) as c_uint;
-                    tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     ) as c_int;
                     level_tok = tok + ((3 as c_int) << 6);
                     if dbg != 0 {
@@ -806,10 +873,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     if TX_CLASS_2D as c_int == TX_CLASS_2D as c_int {
                         y |= x;
                     }
-                    tok = rav1d_msac_decode_symbol_adapt4(
-                        &mut (*ts).msac,
-                        &mut *lo_cdf.offset(ctx as isize),
-                        3 as c_int as usize,
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_base",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut (*ts).msac,
+                            &mut *lo_cdf.offset(ctx as isize),
+                            3 as c_int as usize,
+                        )
                     ) as c_int;
                     if dbg != 0 {
                         printf(
@@ -838,9 +909,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                             } else {
                                 mag.wrapping_add(1 as c_int as c_uint) >> 1
                             });
-                        tok = rav1d_msac_decode_hi_tok(
-                            &mut (*ts).msac,
-                            &mut *hi_cdf.offset(ctx as isize),
+                        tok = trace_symbol!(
+                            (*ts),
+                            "coeff_br",
+                            rav1d_msac_decode_hi_tok(
+                                &mut (*ts).msac,
+                                &mut *hi_cdf.offset(ctx as isize),
+                            )
                         ) as c_int;
                         if dbg != 0 {
                             printf(
@@ -882,10 +957,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                         stride as usize,
                     ) as c_uint
                 };
-                dc_tok = rav1d_msac_decode_symbol_adapt4(
-                    &mut (*ts).msac,
-                    &mut *lo_cdf.offset(ctx as isize),
-                    3 as c_int as usize,
+                dc_tok = trace_symbol!(
+                    (*ts),
+                    "coeff_base",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut (*ts).msac,
+                        &mut *lo_cdf.offset(ctx as isize),
+                        3 as c_int as usize,
+                    )
                 );
                 if dbg != 0 {
                     printf(
@@ -910,9 +989,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         mag.wrapping_add(1 as c_int as c_uint) >> 1
                     };
-                    dc_tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    dc_tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     );
                     if dbg != 0 {
                         printf(
@@ -976,9 +1059,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         7 as c_int
                     }) as c_uint;
-                    tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     ) as c_int;
                     level_tok = tok + ((3 as c_int) << 6);
                     if dbg != 0 {
@@ -1029,10 +1116,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     if TX_CLASS_H as c_int == TX_CLASS_2D as c_int {
                         y |= x;
                     }
-                    tok = rav1d_msac_decode_symbol_adapt4(
-                        &mut (*ts).msac,
-                        &mut *lo_cdf.offset(ctx as isize),
-                        3 as c_int as usize,
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_base",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut (*ts).msac,
+                            &mut *lo_cdf.offset(ctx as isize),
+                            3 as c_int as usize,
+                        )
                     ) as c_int;
                     if dbg != 0 {
                         printf(
@@ -1061,9 +1152,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                             } else {
                                 mag.wrapping_add(1 as c_int as c_uint) >> 1
                             });
-                        tok = rav1d_msac_decode_hi_tok(
-                            &mut (*ts).msac,
-                            &mut *hi_cdf.offset(ctx as isize),
+                        tok = trace_symbol!(
+                            (*ts),
+                            "coeff_br",
+                            rav1d_msac_decode_hi_tok(
+                                &mut (*ts).msac,
+                                &mut *hi_cdf.offset(ctx as isize),
+                            )
                         ) as c_int;
                         if dbg != 0 {
                             printf(
@@ -1105,10 +1200,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                         stride as usize,
                     ) as c_uint
                 };
-                dc_tok = rav1d_msac_decode_symbol_adapt4(
-                    &mut (*ts).msac,
-                    &mut *lo_cdf.offset(ctx as isize),
-                    3 as c_int as usize,
+                dc_tok = trace_symbol!(
+                    (*ts),
+                    "coeff_base",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut (*ts).msac,
+                        &mut *lo_cdf.offset(ctx as isize),
+                        3 as c_int as usize,
+                    )
                 );
                 if dbg != 0 {
                     printf(
@@ -1133,9 +1232,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         mag.wrapping_add(1 as c_int as c_uint) >> 1
                     };
-                    dc_tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    dc_tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     );
                     if dbg != 0 {
                         printf(
@@ -1199,9 +1302,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         7 as c_int
                     }) as c_uint;
-                    tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     ) as c_int;
                     level_tok = tok + ((3 as c_int) << 6);
                     if dbg != 0 {
@@ -1252,10 +1359,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     if TX_CLASS_V as c_int == TX_CLASS_2D as c_int {
                         y |= x;
                     }
-                    tok = rav1d_msac_decode_symbol_adapt4(
-                        &mut (*ts).msac,
-                        &mut *lo_cdf.offset(ctx as isize),
-                        3 as c_int as usize,
+                    tok = trace_symbol!(
+                        (*ts),
+                        "coeff_base",
+                        rav1d_msac_decode_symbol_adapt4(
+                            &mut (*ts).msac,
+                            &mut *lo_cdf.offset(ctx as isize),
+                            3 as c_int as usize,
+                        )
                     ) as c_int;
                     if dbg != 0 {
                         printf(
@@ -1284,9 +1395,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                             } else {
                                 mag.wrapping_add(1 as c_int as c_uint) >> 1
                             });
-                        tok = rav1d_msac_decode_hi_tok(
-                            &mut (*ts).msac,
-                            &mut *hi_cdf.offset(ctx as isize),
+                        tok = trace_symbol!(
+                            (*ts),
+                            "coeff_br",
+                            rav1d_msac_decode_hi_tok(
+                                &mut (*ts).msac,
+                                &mut *hi_cdf.offset(ctx as isize),
+                            )
                         ) as c_int;
                         if dbg != 0 {
                             printf(
@@ -1328,10 +1443,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
                         stride as usize,
                     ) as c_uint
                 };
-                dc_tok = rav1d_msac_decode_symbol_adapt4(
-                    &mut (*ts).msac,
-                    &mut *lo_cdf.offset(ctx as isize),
-                    3 as c_int as usize,
+                dc_tok = trace_symbol!(
+                    (*ts),
+                    "coeff_base",
+                    rav1d_msac_decode_symbol_adapt4(
+                        &mut (*ts).msac,
+                        &mut *lo_cdf.offset(ctx as isize),
+                        3 as c_int as usize,
+                    )
                 );
                 if dbg != 0 {
                     printf(
@@ -1356,9 +1475,13 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     } else {
                         mag.wrapping_add(1 as c_int as c_uint) >> 1
                     };
-                    dc_tok = rav1d_msac_decode_hi_tok(
-                        &mut (*ts).msac,
-                        &mut *hi_cdf.offset(ctx as isize),
+                    dc_tok = trace_symbol!(
+                        (*ts),
+                        "coeff_br",
+                        rav1d_msac_decode_hi_tok(
+                            &mut (*ts).msac,
+                            &mut *hi_cdf.offset(ctx as isize),
+                        )
                     );
                     if dbg != 0 {
                         printf(
@@ -1379,10 +1502,14 @@ unsafe fn decode_coefs<BD: BitDepth>(
             }
         }
     } else {
-        let tok_br = rav1d_msac_decode_symbol_adapt4(
-            &mut (*ts).msac,
-            &mut *eob_cdf.offset(0),
-            2 as c_int as usize,
+        let tok_br = trace_symbol!(
+            (*ts),
+            "coeff_base_eob",
+            rav1d_msac_decode_symbol_adapt4(
+                &mut (*ts).msac,
+                &mut *eob_cdf.offset(0),
+                2 as c_int as usize,
+            )
         ) as c_int;
         dc_tok = (1 + tok_br) as c_uint;
         if dbg != 0 {
@@ -1396,7 +1523,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
             );
         }
         if tok_br == 2 {
-            dc_tok = rav1d_msac_decode_hi_tok(&mut (*ts).msac, &mut *hi_cdf.offset(0));
+            dc_tok = trace_symbol!(
+                (*ts),
+                "coeff_br",
+                rav1d_msac_decode_hi_tok(&mut (*ts).msac, &mut *hi_cdf.offset(0))
+            );
             if dbg != 0 {
                 printf(
                     b"Post-dc_hi_tok[%d][%d][0][%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1431,7 +1562,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
     } else {
         dc_sign_ctx = get_dc_sign_ctx(tx, a, l) as c_int;
         let dc_sign_cdf = &mut (*ts).cdf.coef.dc_sign[chroma as usize][dc_sign_ctx as usize];
-        dc_sign = rav1d_msac_decode_bool_adapt(&mut (*ts).msac, dc_sign_cdf) as c_int;
+        dc_sign = trace_symbol!(
+            (*ts),
+            "dc_sign",
+            rav1d_msac_decode_bool_adapt(&mut (*ts).msac, dc_sign_cdf)
+        ) as c_int;
         if dbg != 0 {
             printf(
                 b"Post-dc_sign[%d][%d][%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1446,7 +1581,8 @@ unsafe fn decode_coefs<BD: BitDepth>(
         if !qm_tbl.is_null() {
             dc_dq = dc_dq * *qm_tbl.offset(0) as c_int + 16 >> 5;
             if dc_tok == 15 as c_uint {
-                dc_tok = (read_golomb(&mut (*ts).msac)).wrapping_add(15 as c_int as c_uint);
+                dc_tok = (trace_symbol!((*ts), "golomb", read_golomb(&mut (*ts).msac)))
+                    .wrapping_add(15 as c_int as c_uint);
                 if dbg != 0 {
                     printf(
                         b"Post-dc_residual[%d->%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1475,7 +1611,8 @@ unsafe fn decode_coefs<BD: BitDepth>(
             }
         } else {
             if dc_tok == 15 as c_uint {
-                dc_tok = (read_golomb(&mut (*ts).msac)).wrapping_add(15 as c_int as c_uint);
+                dc_tok = (trace_symbol!((*ts), "golomb", read_golomb(&mut (*ts).msac)))
+                    .wrapping_add(15 as c_int as c_uint);
                 if dbg != 0 {
                     printf(
                         b"Post-dc_residual[%d->%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1507,7 +1644,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
         1669574575799829731 => {
             let ac_dq: c_uint = *dq_tbl.offset(1) as c_uint;
             loop {
-                let sign = rav1d_msac_decode_bool_equi(&mut (*ts).msac) as c_int;
+                let sign = trace_symbol!(
+                    (*ts),
+                    "sign_bit",
+                    rav1d_msac_decode_bool_equi(&mut (*ts).msac)
+                ) as c_int;
                 if dbg != 0 {
                     printf(
                         b"Post-sign[%d=%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1524,7 +1665,8 @@ unsafe fn decode_coefs<BD: BitDepth>(
                     >> 5;
                 let dq_sat;
                 if rc_tok >= ((15 as c_int) << 11) as c_uint {
-                    tok = (read_golomb(&mut (*ts).msac)).wrapping_add(15 as c_int as c_uint);
+                    tok = (trace_symbol!((*ts), "golomb", read_golomb(&mut (*ts).msac)))
+                        .wrapping_add(15 as c_int as c_uint);
                     if dbg != 0 {
                         printf(
                             b"Post-residual[%d=%d->%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1557,7 +1699,11 @@ unsafe fn decode_coefs<BD: BitDepth>(
         2404388531445638768 => {
             let ac_dq: c_uint = *dq_tbl.offset(1) as c_uint;
             loop {
-                let sign = rav1d_msac_decode_bool_equi(&mut (*ts).msac) as c_int;
+                let sign = trace_symbol!(
+                    (*ts),
+                    "sign_bit",
+                    rav1d_msac_decode_bool_equi(&mut (*ts).msac)
+                ) as c_int;
                 if dbg != 0 {
                     printf(
                         b"Post-sign[%d=%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1570,7 +1716,8 @@ unsafe fn decode_coefs<BD: BitDepth>(
                 let mut tok: c_uint;
                 let mut dq;
                 if rc_tok >= ((15 as c_int) << 11) as c_uint {
-                    tok = (read_golomb(&mut (*ts).msac)).wrapping_add(15 as c_int as c_uint);
+                    tok = (trace_symbol!((*ts), "golomb", read_golomb(&mut (*ts).msac)))
+                        .wrapping_add(15 as c_int as c_uint);
                     if dbg != 0 {
                         printf(
                             b"Post-residual[%d=%d->%d]: r=%d\n\0" as *const u8 as *const c_char,
@@ -1789,6 +1936,7 @@ unsafe fn read_coef_tree<BD: BitDepth>(
                         "dq",
                     );
                 }
+                let [w, h] = [(*t_dim).w as c_int * 4, (*t_dim).h as c_int * 4];
                 ((*dsp).itx.itxfm_add[ytx as usize][txtp as usize])
                     .expect("non-null function pointer")(
                     dst.cast(),
@@ -1797,6 +1945,7 @@ unsafe fn read_coef_tree<BD: BitDepth>(
                     eob,
                     (*f).bitdepth_max,
                 );
+                rav1d_recon_stages_residual::<BD>(&*f, 0, dst, w, h);
                 if DEBUG_BLOCK_INFO(&*f, &*t) && 0 != 0 {
                     hex_dump::<BD>(
                         dst,
@@ -2712,6 +2861,13 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                             );
                         }
                     }
+                    rav1d_recon_stages_pred::<BD>(
+                        &*f,
+                        0,
+                        dst,
+                        (*t_dim).w as c_int * 4,
+                        (*t_dim).h as c_int * 4,
+                    );
                     if b.skip == 0 {
                         let cf: *mut BD::Coef;
                         let eob;
@@ -2778,6 +2934,7 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                                     "dq",
                                 );
                             }
+                            let [w, h] = [(*t_dim).w as c_int * 4, (*t_dim).h as c_int * 4];
                             ((*dsp).itx.itxfm_add[b.c2rust_unnamed.c2rust_unnamed.tx as usize]
                                 [txtp as usize])
                                 .expect("non-null function pointer")(
@@ -2787,6 +2944,7 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                                 eob,
                                 (*f).bitdepth_max,
                             );
+                            rav1d_recon_stages_residual::<BD>(&*f, 0, dst, w, h);
                             if DEBUG_BLOCK_INFO(&*f, t) && 0 != 0 {
                                 hex_dump::<BD>(
                                     dst,
@@ -3122,6 +3280,13 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                                     );
                                 }
                             }
+                            rav1d_recon_stages_pred::<BD>(
+                                &*f,
+                                1 + pl as usize,
+                                dst,
+                                (*uv_t_dim).w as c_int * 4,
+                                (*uv_t_dim).h as c_int * 4,
+                            );
                             if b.skip == 0 {
                                 let mut txtp: TxfmType = DCT_DCT;
                                 let eob;
@@ -3203,6 +3368,9 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                                             "dq",
                                         );
                                     }
+                                    let [w, h] =
+                                        [(*uv_t_dim).w as c_int * 4, (*uv_t_dim).h as c_int * 4];
+                                    let pl = 1 + pl as usize;
                                     ((*dsp).itx.itxfm_add[b.uvtx as usize][txtp as usize])
                                         .expect("non-null function pointer")(
                                         dst.cast(),
@@ -3211,6 +3379,7 @@ pub(crate) unsafe fn rav1d_recon_b_intra<BD: BitDepth>(
                                         eob,
                                         (*f).bitdepth_max,
                                     );
+                                    rav1d_recon_stages_residual::<BD>(&*f, pl, dst, w, h);
                                     if DEBUG_BLOCK_INFO(&*f, t) && 0 != 0 {
                                         hex_dump::<BD>(
                                             dst,
@@ -4241,6 +4410,13 @@ pub(crate) unsafe fn rav1d_recon_b_inter<BD: BitDepth>(
             );
         }
     }
+    rav1d_recon_stages_pred::<BD>(&*f, 0, dst, bw4 * 4, bh4 * 4);
+    if has_chroma != 0 {
+        for pl in 1..3 {
+            let uvdst = ((*f).cur.data.data[pl] as *mut BD::Pixel).offset(uvdstoff as isize);
+            rav1d_recon_stages_pred::<BD>(&*f, pl, uvdst, cbw4 * 4, cbh4 * 4);
+        }
+    }
     let cw4 = w4 + ss_hor >> ss_hor;
     let ch4 = h4 + ss_ver >> ss_ver;
     if b.skip != 0 {
@@ -4414,14 +4590,18 @@ pub(crate) unsafe fn rav1d_recon_b_inter<BD: BitDepth>(
                                         "dq",
                                     );
                                 }
+                                let dst = uvdst.offset((4 * x) as isize);
+                                let [w, h] = [(*uvtx).w as c_int * 4, (*uvtx).h as c_int * 4];
+                                let pl = 1 + pl as usize;
                                 ((*dsp).itx.itxfm_add[b.uvtx as usize][txtp as usize])
                                     .expect("non-null function pointer")(
-                                    uvdst.offset((4 * x) as isize).cast(),
+                                    dst.cast(),
                                     (*f).cur.stride[1],
                                     cf.cast(),
                                     eob,
                                     (*f).bitdepth_max,
                                 );
+                                rav1d_recon_stages_residual::<BD>(&*f, pl, dst, w, h);
                                 if DEBUG_BLOCK_INFO(&*f, t) && 0 != 0 {
                                     hex_dump::<BD>(
                                         &mut *uvdst.offset((4 * x) as isize),
@@ -4461,6 +4641,7 @@ pub(crate) unsafe fn rav1d_filter_sbrow_deblock_cols<BD: BitDepth>(
     _t: &mut Rav1dTaskContext,
     sby: c_int,
 ) {
+    rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_DEBLOCK);
     let frame_hdr = &***f.frame_hdr.as_ref().unwrap();
     if c.inloop_filters as c_uint & RAV1D_INLOOPFILTER_DEBLOCK as c_int as c_uint == 0
         || frame_hdr.loopfilter.level_y[0] == 0 && frame_hdr.loopfilter.level_y[1] == 0
@@ -4497,6 +4678,8 @@ pub(crate) unsafe fn rav1d_filter_sbrow_deblock_rows<BD: BitDepth>(
     _t: &mut Rav1dTaskContext,
     sby: c_int,
 ) {
+    // Without deblocking, this is the first stage of the sbrow.
+    rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_DEBLOCK);
     let y = sby * f.sb_step * 4;
     let ss_ver = (f.cur.p.layout as c_uint == Rav1dPixelLayout::I420 as c_int as c_uint) as c_int;
     let p: [*mut BD::Pixel; 3] = [
@@ -4529,6 +4712,7 @@ pub(crate) unsafe fn rav1d_filter_sbrow_cdef<BD: BitDepth>(
     sby: c_int,
 ) {
     let f: *const Rav1dFrameContext = tc.f;
+    rav1d_recon_stages_capture(&*f, sby, RAV1D_RECON_STAGE_CDEF);
     if c.inloop_filters as c_uint & RAV1D_INLOOPFILTER_CDEF as c_int as c_uint == 0 {
         return;
     }
@@ -4586,6 +4770,7 @@ pub(crate) unsafe fn rav1d_filter_sbrow_resize<BD: BitDepth>(
     _t: &mut Rav1dTaskContext,
     sby: c_int,
 ) {
+    rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_SUPERRES);
     let sbsz = f.sb_step;
     let y = sby * sbsz * 4;
     let ss_ver = (f.cur.p.layout as c_uint == Rav1dPixelLayout::I420 as c_int as c_uint) as c_int;
@@ -4653,6 +4838,7 @@ pub(crate) unsafe fn rav1d_filter_sbrow_lr<BD: BitDepth>(
     _t: &mut Rav1dTaskContext,
     sby: c_int,
 ) {
+    rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_RESTORATION);
     if c.inloop_filters as c_uint & RAV1D_INLOOPFILTER_RESTORATION as c_int as c_uint == 0 {
         return;
     }
@@ -4688,6 +4874,7 @@ pub(crate) unsafe fn rav1d_filter_sbrow<BD: BitDepth>(
     if f.lf.restore_planes != 0 {
         rav1d_filter_sbrow_lr::<BD>(c, f, t, sby);
     }
+    rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_COUNT);
 }
 
 pub(crate) unsafe fn rav1d_backup_ipred_edge<BD: BitDepth>(t: &mut Rav1dTaskContext) {
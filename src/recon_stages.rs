@@ -0,0 +1,299 @@
+use crate::include::common::bitdepth::BitDepth;
+use crate::include::dav1d::dav1d::Rav1dReconStage;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_COUNT;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_PREFILTER;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_SUPERRES;
+use crate::include::dav1d::headers::Rav1dPixelLayout;
+use crate::include::dav1d::picture::Rav1dPicture;
+use crate::src::error::Rav1dResult;
+use crate::src::internal::Rav1dContext;
+use crate::src::internal::Rav1dFrameContext;
+use crate::src::picture::rav1d_picture_alloc_copy;
+use crate::src::picture::rav1d_picture_ref;
+use crate::src::picture::rav1d_picture_unref_internal;
+use std::cmp;
+use std::ffi::c_int;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Copies of a frame as it was after each in-loop filtering stage,
+/// see [`Rav1dContext::recon_stages`],
+/// and its prediction and residual, see [`Rav1dContext::export_pred_residual`].
+///
+/// The pictures are filled in sbrow by sbrow while the frame is decoded,
+/// so they are complete once the frame is.
+pub struct Rav1dReconStages {
+    /// Empty for the stages that aren't captured.
+    pics: [Rav1dPicture; RAV1D_RECON_STAGE_COUNT as usize],
+    /// Empty unless [`Rav1dContext::export_pred_residual`].
+    pred: Rav1dPicture,
+    /// The reconstruction minus [`Self::pred`], offset by half the pixel range.
+    /// Empty unless [`Rav1dContext::export_pred_residual`].
+    residual: Rav1dPicture,
+    /// Bitmask of the stages copied so far, per sbrow.
+    captured: Box<[AtomicU8]>,
+}
+
+impl Drop for Rav1dReconStages {
+    fn drop(&mut self) {
+        for pic in self
+            .pics
+            .iter_mut()
+            .chain([&mut self.pred, &mut self.residual])
+        {
+            // Safety: The pictures were allocated in `rav1d_recon_stages_alloc`.
+            unsafe { rav1d_picture_unref_internal(pic) };
+        }
+    }
+}
+
+fn is_allocated(pic: &Rav1dPicture) -> bool {
+    !pic.data.data[0].is_null()
+}
+
+impl Rav1dReconStages {
+    pub(crate) fn get(&self, stage: Rav1dReconStage) -> Option<&Rav1dPicture> {
+        self.pics
+            .get(stage as usize)
+            .filter(|pic| is_allocated(pic))
+    }
+
+    pub(crate) fn pred(&self) -> Option<&Rav1dPicture> {
+        Some(&self.pred).filter(|pic| is_allocated(pic))
+    }
+
+    pub(crate) fn residual(&self) -> Option<&Rav1dPicture> {
+        Some(&self.residual).filter(|pic| is_allocated(pic))
+    }
+}
+
+/// The picture that `stage` is applied to.
+/// Superres upscales into [`Rav1dFrameContext::sr_cur`],
+/// which is the same picture as [`Rav1dFrameContext::cur`] without superres.
+fn stage_src(f: &Rav1dFrameContext, stage: Rav1dReconStage) -> &Rav1dPicture {
+    if stage >= RAV1D_RECON_STAGE_SUPERRES {
+        &f.sr_cur.p
+    } else {
+        &f.cur
+    }
+}
+
+/// The residual pixel value of a zero residual, i.e. half the pixel range.
+fn residual_zero(pic: &Rav1dPicture) -> u16 {
+    1 << pic.p.bpc - 1
+}
+
+/// Calls `row(pl, y, w)` for each row `y` of each plane `pl` of `pic`
+/// within luma rows `[y_start, y_end)`, where `w` is the width of the plane.
+fn for_each_row(
+    pic: &Rav1dPicture,
+    y_start: c_int,
+    y_end: c_int,
+    mut row: impl FnMut(usize, isize, usize),
+) {
+    let layout = pic.p.layout;
+    let has_chroma = (layout != Rav1dPixelLayout::I400) as usize;
+    for pl in 0..1 + 2 * has_chroma {
+        let ss_ver = (pl != 0 && layout == Rav1dPixelLayout::I420) as c_int;
+        let ss_hor = (pl != 0 && layout != Rav1dPixelLayout::I444) as c_int;
+        let h = pic.p.h + ss_ver >> ss_ver;
+        let w = pic.p.w + ss_hor >> ss_hor;
+        for y in y_start >> ss_ver..cmp::min(y_end >> ss_ver, h) {
+            row(pl, y as isize, w as usize);
+        }
+    }
+}
+
+/// The start of row `y` of plane `pl` of `pic`.
+unsafe fn pixel_row<P>(pic: &Rav1dPicture, pl: usize, y: isize) -> *mut P {
+    (pic.data.data[pl] as *mut u8)
+        .offset(y * pic.stride[(pl != 0) as usize])
+        .cast()
+}
+
+/// Allocates the pictures for the stages in [`Rav1dContext::recon_stages`]
+/// and for [`Rav1dContext::export_pred_residual`],
+/// once [`Rav1dFrameContext::cur`] and [`Rav1dFrameContext::sr_cur`] are.
+pub(crate) unsafe fn rav1d_recon_stages_alloc(
+    c: &Rav1dContext,
+    f: &Rav1dFrameContext,
+) -> Rav1dResult<Option<Arc<Rav1dReconStages>>> {
+    if c.recon_stages == 0 && !c.export_pred_residual {
+        return Ok(None);
+    }
+    let seq_hdr = &***f.seq_hdr.as_ref().unwrap();
+    let sb_size = 64 << seq_hdr.sb128;
+    let sbh = (f.cur.p.h + sb_size - 1) / sb_size;
+    let mut stages = Rav1dReconStages {
+        pics: Default::default(),
+        pred: Default::default(),
+        residual: Default::default(),
+        captured: (0..sbh).map(|_| AtomicU8::new(0)).collect(),
+    };
+    for (stage, pic) in stages.pics.iter_mut().enumerate() {
+        let stage = stage as Rav1dReconStage;
+        if c.recon_stages & (1 << stage) == 0 {
+            continue;
+        }
+        let src = stage_src(f, stage);
+        rav1d_picture_alloc_copy(c, pic, src.p.w, src)?;
+    }
+    if c.export_pred_residual {
+        rav1d_picture_alloc_copy(c, &mut stages.pred, f.cur.p.w, &f.cur)?;
+        rav1d_picture_alloc_copy(c, &mut stages.residual, f.cur.p.w, &f.cur)?;
+        // Only the residuals of coded transform blocks are written.
+        let residual = &stages.residual;
+        let zero = residual_zero(residual);
+        for_each_row(residual, 0, residual.p.h, |pl, y, w| {
+            if residual.p.bpc > 8 {
+                slice::from_raw_parts_mut(pixel_row::<u16>(residual, pl, y), w).fill(zero);
+            } else {
+                slice::from_raw_parts_mut(pixel_row::<u8>(residual, pl, y), w).fill(zero as u8);
+            }
+        });
+    }
+    Ok(Some(Arc::new(stages)))
+}
+
+/// Copies the rows of sbrow `sby` that the filter of `stage` is done with,
+/// from the picture being decoded to the picture of `stage`.
+///
+/// The filters lag 8 luma rows behind the sbrow,
+/// since the rows above the next sbrow are filtered together with it.
+unsafe fn copy_rows(f: &Rav1dFrameContext, stage: Rav1dReconStage, dst: &Rav1dPicture, sby: c_int) {
+    let src = stage_src(f, stage);
+    let sb_h = f.sb_step * 4;
+    let [mut y_start, mut y_end] = [sby * sb_h, (sby + 1) * sb_h];
+    if stage != RAV1D_RECON_STAGE_PREFILTER {
+        y_start -= 8 * (sby != 0) as c_int;
+        y_end -= 8 * (sby + 1 < f.sbh) as c_int;
+    }
+    let hbd = (src.p.bpc > 8) as usize;
+    for_each_row(src, y_start, y_end, |pl, y, w| {
+        ptr::copy_nonoverlapping(
+            pixel_row::<u8>(src, pl, y),
+            pixel_row::<u8>(dst, pl, y),
+            w << hbd,
+        );
+    });
+}
+
+/// Captures sbrow `sby` for all of the stages before `stage`
+/// that weren't captured yet.
+///
+/// Must be called before the filter of `stage` is applied to `sby`,
+/// and after the filters of all of the stages before it were.
+/// Stages that are disabled for the frame leave the picture unchanged,
+/// so it's enough to call this before each enabled stage
+/// and with [`RAV1D_RECON_STAGE_COUNT`] once all are applied.
+pub(crate) unsafe fn rav1d_recon_stages_capture(
+    f: &Rav1dFrameContext,
+    sby: c_int,
+    stage: Rav1dReconStage,
+) {
+    let Some(stages) = &f.recon_stages else {
+        return;
+    };
+    let before = (1 << stage) - 1;
+    let captured = stages.captured[sby as usize].fetch_or(before, Ordering::Relaxed);
+    for (s, dst) in stages.pics.iter().enumerate().take(stage as usize) {
+        if captured & (1 << s) != 0 {
+            continue;
+        }
+        if is_allocated(dst) {
+            copy_rows(f, s as Rav1dReconStage, dst, sby);
+        }
+    }
+}
+
+/// The position and size of the part of the `w`x`h` block at `dst`
+/// in plane `pl` of [`Rav1dFrameContext::cur`] that's within the picture.
+unsafe fn block_in_cur<BD: BitDepth>(
+    f: &Rav1dFrameContext,
+    pl: usize,
+    dst: *const BD::Pixel,
+    w: c_int,
+    h: c_int,
+) -> ([isize; 2], [usize; 2]) {
+    let layout = f.cur.p.layout;
+    let ss_ver = (pl != 0 && layout == Rav1dPixelLayout::I420) as c_int;
+    let ss_hor = (pl != 0 && layout != Rav1dPixelLayout::I444) as c_int;
+    let stride = f.cur.stride[(pl != 0) as usize] / mem::size_of::<BD::Pixel>() as isize;
+    let offset = dst.offset_from(f.cur.data.data[pl].cast());
+    let x = offset.rem_euclid(stride.abs());
+    let y = (offset - x) / stride;
+    let plane_w = (f.cur.p.w + ss_hor >> ss_hor) as isize;
+    let plane_h = (f.cur.p.h + ss_ver >> ss_ver) as isize;
+    let w = cmp::max(cmp::min(w as isize, plane_w - x), 0);
+    let h = cmp::max(cmp::min(h as isize, plane_h - y), 0);
+    ([x, y], [w as usize, h as usize])
+}
+
+/// Saves the prediction of the `w`x`h` block at `dst` in plane `pl`,
+/// once it's predicted and before any residual is added to it.
+///
+/// Intra blocks are predicted transform block by transform block,
+/// from the reconstruction of the ones before,
+/// while inter blocks are predicted whole.
+pub(crate) unsafe fn rav1d_recon_stages_pred<BD: BitDepth>(
+    f: &Rav1dFrameContext,
+    pl: usize,
+    dst: *const BD::Pixel,
+    w: c_int,
+    h: c_int,
+) {
+    let Some(pred) = f.recon_stages.as_ref().and_then(|stages| stages.pred()) else {
+        return;
+    };
+    let ([x, y], [w, h]) = block_in_cur::<BD>(f, pl, dst, w, h);
+    for y in y..y + h as isize {
+        ptr::copy_nonoverlapping(
+            pixel_row::<BD::Pixel>(&f.cur, pl, y).offset(x),
+            pixel_row::<BD::Pixel>(pred, pl, y).offset(x),
+            w,
+        );
+    }
+}
+
+/// Saves the residual of the `w`x`h` transform block at `dst` in plane `pl`,
+/// right after it's added to the prediction saved by [`rav1d_recon_stages_pred`].
+pub(crate) unsafe fn rav1d_recon_stages_residual<BD: BitDepth>(
+    f: &Rav1dFrameContext,
+    pl: usize,
+    dst: *const BD::Pixel,
+    w: c_int,
+    h: c_int,
+) {
+    let Some(stages) = &f.recon_stages else {
+        return;
+    };
+    let (Some(pred), Some(residual)) = (stages.pred(), stages.residual()) else {
+        return;
+    };
+    let bd = BD::from_c(f.bitdepth_max);
+    let zero = residual_zero(residual) as c_int;
+    let ([x, y], [w, h]) = block_in_cur::<BD>(f, pl, dst, w, h);
+    for y in y..y + h as isize {
+        let recon = slice::from_raw_parts(pixel_row::<BD::Pixel>(&f.cur, pl, y).offset(x), w);
+        let pred = slice::from_raw_parts(pixel_row::<BD::Pixel>(pred, pl, y).offset(x), w);
+        let residual =
+            slice::from_raw_parts_mut(pixel_row::<BD::Pixel>(residual, pl, y).offset(x), w);
+        for ((residual, &recon), &pred) in residual.iter_mut().zip(recon).zip(pred) {
+            let recon: c_int = recon.into();
+            let pred: c_int = pred.into();
+            *residual = bd.iclip_pixel(recon - pred + zero);
+        }
+    }
+}
+
+/// Returns a new reference to `pic`, if it was captured.
+pub(crate) unsafe fn rav1d_recon_stages_ref(pic: Option<&Rav1dPicture>) -> Option<Rav1dPicture> {
+    let pic = pic?;
+    let mut out = Default::default();
+    rav1d_picture_ref(&mut out, pic);
+    Some(out)
+}
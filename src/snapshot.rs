@@ -0,0 +1,587 @@
+use crate::include::common::intops::ulog2;
+use crate::include::dav1d::common::Rav1dDataProps;
+use crate::include::dav1d::data::Rav1dData;
+use crate::include::dav1d::dav1d::Dav1dRefSlot;
+use crate::include::dav1d::dav1d::Dav1dSavedMv;
+use crate::include::dav1d::dav1d::Rav1dDecodeFrameType;
+use crate::include::dav1d::dav1d::Rav1dInloopFilterType;
+use crate::include::dav1d::headers::DRav1d;
+use crate::include::dav1d::headers::Dav1dFrameHeader;
+use crate::include::dav1d::headers::Dav1dSequenceHeader;
+use crate::include::dav1d::headers::Rav1dContentLightLevel;
+use crate::include::dav1d::headers::Rav1dFrameHeader;
+use crate::include::dav1d::headers::Rav1dMasteringDisplay;
+use crate::include::dav1d::headers::Rav1dPixelLayout;
+use crate::include::dav1d::headers::Rav1dSequenceHeader;
+use crate::include::dav1d::picture::Rav1dPicture;
+use crate::include::dav1d::picture::Rav1dPictureData;
+use crate::include::dav1d::picture::Rav1dPictureParameters;
+use crate::src::c_arc::CArc;
+use crate::src::cdf::rav1d_cdf_thread_alloc;
+use crate::src::cdf::rav1d_cdf_thread_copy;
+use crate::src::cdf::rav1d_cdf_thread_ref;
+use crate::src::cdf::rav1d_cdf_thread_unref;
+use crate::src::cdf::CdfContext;
+use crate::src::cdf::CdfThreadContext;
+use crate::src::error::Rav1dError::EINVAL;
+use crate::src::error::Rav1dError::ENOMEM;
+use crate::src::error::Rav1dResult;
+use crate::src::internal::Rav1dContext;
+use crate::src::internal::Rav1dContext_refs;
+use crate::src::levels::mv;
+use crate::src::lib::rav1d_flush;
+use crate::src::mem::Rav1dMemPool;
+use crate::src::obu::rav1d_parse_obus;
+use crate::src::picture::rav1d_picture_ref;
+use crate::src::picture::rav1d_picture_unref_internal;
+use crate::src::picture::rav1d_thread_picture_ref;
+use crate::src::picture::rav1d_thread_picture_unref;
+use crate::src::picture::PictureFlags;
+use crate::src::picture::Rav1dThreadPicture;
+use crate::src::r#ref::rav1d_ref_create_using_pool;
+use crate::src::r#ref::rav1d_ref_dec;
+use crate::src::r#ref::rav1d_ref_inc;
+use crate::src::r#ref::Rav1dRef;
+use crate::src::refmvs::refmvs_temporal_block;
+use crate::src::thread_task::FRAME_ERROR;
+use std::array;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::mem;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// The state that decoding the next frame depends on,
+/// taken from a [`Rav1dContext`] between temporal units.
+///
+/// All the pictures, segmentation maps, motion vectors and CDFs
+/// are shared with the context through their refcounts, not copied.
+pub struct Rav1dStateSnapshot {
+    settings: SnapshotSettings,
+    refs: [Rav1dContext_refs; 8],
+    cdf: [CdfThreadContext; 8],
+    seq_hdr: Option<Arc<DRav1d<Rav1dSequenceHeader, Dav1dSequenceHeader>>>,
+    frame_hdr: Option<Arc<DRav1d<Rav1dFrameHeader, Dav1dFrameHeader>>>,
+    content_light: Option<Arc<Rav1dContentLightLevel>>,
+    mastering_display: Option<Arc<Rav1dMasteringDisplay>>,
+}
+
+/// The settings of the context a [`Rav1dStateSnapshot`] was taken from
+/// that change the decoded reference state,
+/// so it can only be restored to contexts with the same ones.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct SnapshotSettings {
+    operating_point: c_int,
+    all_layers: bool,
+    inloop_filters: Rav1dInloopFilterType,
+    decode_frame_type: Rav1dDecodeFrameType,
+}
+
+impl SnapshotSettings {
+    fn new(c: &Rav1dContext) -> Self {
+        Self {
+            operating_point: c.operating_point,
+            all_layers: c.all_layers,
+            inloop_filters: c.inloop_filters,
+            decode_frame_type: c.decode_frame_type,
+        }
+    }
+}
+
+impl Drop for Rav1dStateSnapshot {
+    fn drop(&mut self) {
+        // Safety: Everything was ref'ed in `rav1d_snapshot_state`.
+        unsafe {
+            for r#ref in &mut self.refs {
+                rav1d_thread_picture_unref(&mut r#ref.p);
+                rav1d_ref_dec(&mut r#ref.segmap);
+                rav1d_ref_dec(&mut r#ref.refmvs);
+            }
+            for cdf in &mut self.cdf {
+                rav1d_cdf_thread_unref(cdf);
+            }
+        }
+    }
+}
+
+unsafe fn ref_inc(r#ref: *mut Rav1dRef) -> *mut Rav1dRef {
+    if !r#ref.is_null() {
+        rav1d_ref_inc(r#ref);
+    }
+    r#ref
+}
+
+/// `dst` must be empty, as after [`rav1d_flush`].
+unsafe fn refs_ref(dst: &mut Rav1dContext_refs, src: &Rav1dContext_refs) {
+    rav1d_thread_picture_ref(&mut dst.p, &src.p);
+    dst.segmap = ref_inc(src.segmap);
+    dst.refmvs = ref_inc(src.refmvs);
+    dst.refpoc = src.refpoc;
+}
+
+unsafe fn refs_clone(src: &Rav1dContext_refs) -> Rav1dContext_refs {
+    let mut r#ref = Rav1dContext_refs {
+        p: Default::default(),
+        segmap: ptr::null_mut(),
+        refmvs: ptr::null_mut(),
+        refpoc: [0; 7],
+    };
+    refs_ref(&mut r#ref, src);
+    r#ref
+}
+
+unsafe fn cdf_clone(src: &CdfThreadContext) -> CdfThreadContext {
+    let mut cdf = mem::zeroed();
+    rav1d_cdf_thread_ref(&mut cdf, src as *const _ as *mut _);
+    cdf
+}
+
+/// Waits until no frame is being decoded anymore,
+/// so that all reference frames are complete.
+unsafe fn wait_for_frames(c: &Rav1dContext) {
+    if c.n_fc == 1 {
+        return;
+    }
+    let mut task_thread_lock = c.task_thread.delayed_fg.lock().unwrap();
+    for n in 0..c.n_fc {
+        let f = &*c.fc.offset(n as isize);
+        while !f.tiles.is_empty() {
+            task_thread_lock = f.task_thread.cond.wait(task_thread_lock).unwrap();
+        }
+    }
+}
+
+pub(crate) unsafe fn rav1d_snapshot_state(c: &Rav1dContext) -> Arc<Rav1dStateSnapshot> {
+    wait_for_frames(c);
+    let refs = array::from_fn(|i| refs_clone(&c.refs[i]));
+    let cdf = array::from_fn(|i| cdf_clone(&c.cdf[i]));
+    Arc::new(Rav1dStateSnapshot {
+        settings: SnapshotSettings::new(c),
+        refs,
+        cdf,
+        seq_hdr: c.seq_hdr.clone(),
+        frame_hdr: c.frame_hdr.clone(),
+        content_light: c.content_light.clone(),
+        mastering_display: c.mastering_display.clone(),
+    })
+}
+
+/// Checks that `s` can be restored to `c`:
+/// it must have been taken from a context with the same [`SnapshotSettings`]
+/// and, if `c` has seen a sequence header already, the same sequence,
+/// and all its reference pictures must be complete
+/// and match its sequence header and the frame size limit of `c`.
+fn validate_snapshot(c: &Rav1dContext, s: &Rav1dStateSnapshot) -> Rav1dResult {
+    if s.settings != SnapshotSettings::new(c) {
+        return Err(EINVAL);
+    }
+    if let (Some(c_seq_hdr), Some(seq_hdr)) = (&c.seq_hdr, &s.seq_hdr) {
+        if !seq_hdr.eq_without_operating_parameter_info(c_seq_hdr) {
+            return Err(EINVAL);
+        }
+    }
+    for r#ref in &s.refs {
+        let p = &r#ref.p;
+        if p.p.data.data[0].is_null() {
+            continue;
+        }
+        let seq_hdr = s.seq_hdr.as_ref().ok_or(EINVAL)?;
+        let Rav1dPictureParameters { w, h, layout, bpc } = p.p.p;
+        if bpc != 8 + 2 * seq_hdr.hbd
+            || layout != seq_hdr.layout
+            || w > seq_hdr.max_width
+            || h > seq_hdr.max_height
+            || c.frame_size_limit != 0 && w as i64 * h as i64 > c.frame_size_limit as i64
+        {
+            return Err(EINVAL);
+        }
+        // A frame that failed to decode with frame threading.
+        if let Some(progress) = &p.progress {
+            if progress[1].load(Ordering::SeqCst) == FRAME_ERROR {
+                return Err(EINVAL);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Flushes `c` and replaces its state with the one in `s`.
+///
+/// `c` may be a different context than the one the snapshot was taken from,
+/// and may use a different number of frame threads.
+/// If `s` doesn't pass [`validate_snapshot`], `c` is left untouched.
+pub(crate) unsafe fn rav1d_restore_state(
+    c: &mut Rav1dContext,
+    s: &Rav1dStateSnapshot,
+) -> Rav1dResult {
+    validate_snapshot(c, s)?;
+    rav1d_flush(c);
+    for i in 0..8 {
+        let r#ref = &mut c.refs[i];
+        refs_ref(r#ref, &s.refs[i]);
+        // The progress of reference frames is only tracked, and waited on,
+        // with frame threading, but all snapshot frames are complete.
+        r#ref.p.progress = if c.n_fc > 1 && !r#ref.p.p.data.data[0].is_null() {
+            Some(Arc::new([
+                AtomicU32::new(u32::MAX),
+                AtomicU32::new(u32::MAX),
+            ]))
+        } else {
+            None
+        };
+        rav1d_cdf_thread_ref(&mut c.cdf[i], &s.cdf[i] as *const _ as *mut _);
+    }
+    c.seq_hdr = s.seq_hdr.clone();
+    c.frame_hdr = s.frame_hdr.clone();
+    c.content_light = s.content_light.clone();
+    c.mastering_display = s.mastering_display.clone();
+    set_operating_point(c);
+    Ok(())
+}
+
+/// Same as in `parse_seq_hdr`, but for this context's operating point.
+fn set_operating_point(c: &mut Rav1dContext) {
+    let Some(seq_hdr) = &c.seq_hdr else {
+        return;
+    };
+    let op_idx = if c.operating_point < seq_hdr.num_operating_points {
+        c.operating_point
+    } else {
+        0
+    };
+    c.operating_point_idc = seq_hdr.operating_points[op_idx as usize].idc as c_uint;
+    let spatial_mask = c.operating_point_idc >> 8;
+    c.max_spatial_id = spatial_mask != 0 && ulog2(spatial_mask) != 0;
+    c.frame_flags
+        .fetch_or(PictureFlags::NEW_SEQUENCE, Ordering::Relaxed);
+}
+
+/// The size of a frame in 4x4 luma units before superres upscaling,
+/// rounded up to a multiple of 2 like [`Rav1dFrameContext::bw`] and [`Rav1dFrameContext::bh`].
+///
+/// [`Rav1dFrameContext::bw`]: crate::src::internal::Rav1dFrameContext::bw
+/// [`Rav1dFrameContext::bh`]: crate::src::internal::Rav1dFrameContext::bh
+fn size4(frame_hdr: &Rav1dFrameHeader) -> (usize, usize) {
+    let w4 = (frame_hdr.size.width[0] + 7 >> 3) << 1;
+    let h4 = (frame_hdr.size.height + 7 >> 3) << 1;
+    (w4 as usize, h4 as usize)
+}
+
+/// The stride and number of rows of the segmentation maps
+/// the decoder allocates for a frame of `w4` by `h4` 4x4 units,
+/// padded to 128x128 superblocks.
+/// Saved motion vectors have half of each, for 8x8 units.
+fn segmap_size(w4: usize, h4: usize) -> (usize, usize) {
+    ((w4 + 31) & !31, (h4 + 31 >> 5) * 32)
+}
+
+/// Copies the state saved in reference slot `r#ref` and `cdf` to plain data.
+///
+/// `r#ref` must hold a picture.
+unsafe fn ref_slot(r#ref: &Rav1dContext_refs, cdf: &CdfThreadContext) -> Dav1dRefSlot {
+    let (w4, h4) = size4(r#ref.p.p.frame_hdr.as_ref().unwrap());
+    let (stride, _) = segmap_size(w4, h4);
+
+    let segment_ids = if r#ref.segmap.is_null() {
+        ptr::null_mut()
+    } else {
+        let src = slice::from_raw_parts((*r#ref.segmap).data.cast::<u8>(), stride * h4);
+        let ids = src
+            .chunks_exact(stride)
+            .flat_map(|row| &row[..w4])
+            .copied()
+            .collect::<Box<[_]>>();
+        Box::into_raw(ids).cast()
+    };
+    let mvs = if r#ref.refmvs.is_null() {
+        ptr::null_mut()
+    } else {
+        let src = slice::from_raw_parts(
+            (*r#ref.refmvs).data.cast::<refmvs_temporal_block>(),
+            stride / 2 * h4 / 2,
+        );
+        let mvs = src
+            .chunks_exact(stride / 2)
+            .flat_map(|row| &row[..w4 / 2])
+            .map(|&refmvs_temporal_block { mv, r#ref }| Dav1dSavedMv {
+                y: mv.y,
+                x: mv.x,
+                r#ref,
+            })
+            .collect::<Box<[_]>>();
+        Box::into_raw(mvs).cast()
+    };
+
+    let mut pic = Rav1dPicture::default();
+    rav1d_picture_ref(&mut pic, &r#ref.p.p);
+    let mut cdf_copy: CdfContext = mem::zeroed();
+    rav1d_cdf_thread_copy(&mut cdf_copy, cdf);
+    Dav1dRefSlot {
+        pic: pic.into(),
+        cdf: (&cdf_copy).into(),
+        showable: r#ref.p.showable as c_int,
+        refpoc: r#ref.refpoc,
+        w4: w4 as c_int,
+        h4: h4 as c_int,
+        segment_ids,
+        mvs,
+    }
+}
+
+/// A [`Dav1dRefSlot`] holding nothing, as [`rav1d_ref_slot_unref`] leaves it.
+fn empty_ref_slot() -> Dav1dRefSlot {
+    Dav1dRefSlot {
+        pic: Default::default(),
+        // Safety: All zeros is valid for these arrays of `u16`s.
+        cdf: unsafe { mem::zeroed() },
+        showable: 0,
+        refpoc: [0; 7],
+        w4: 0,
+        h4: 0,
+        segment_ids: ptr::null_mut(),
+        mvs: ptr::null_mut(),
+    }
+}
+
+/// Releases a [`Dav1dRefSlot`] filled in by the decoder.
+pub(crate) unsafe fn rav1d_ref_slot_unref(slot: &mut Dav1dRefSlot) {
+    let slot = mem::replace(slot, empty_ref_slot());
+    let mut pic = Rav1dPicture::from(slot.pic);
+    rav1d_picture_unref_internal(&mut pic);
+    let (w4, h4) = (slot.w4 as usize, slot.h4 as usize);
+    if !slot.segment_ids.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            slot.segment_ids,
+            w4 * h4,
+        )));
+    }
+    if !slot.mvs.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            slot.mvs,
+            w4 / 2 * h4 / 2,
+        )));
+    }
+}
+
+/// Reference slot `i` of `s`, or [`None`] if it's empty.
+pub(crate) unsafe fn rav1d_snapshot_ref_slot(
+    s: &Rav1dStateSnapshot,
+    i: usize,
+) -> Option<Dav1dRefSlot> {
+    if s.refs[i].p.p.data.data[0].is_null() {
+        return None;
+    }
+    Some(ref_slot(&s.refs[i], &s.cdf[i]))
+}
+
+/// Copies `src` (`w` by `h` entries in raster order) into a new buffer from `pool`,
+/// in the layout the decoder uses, with rows `stride` entries apart.
+unsafe fn ref_create_strided<T: Copy, U>(
+    pool: *mut Rav1dMemPool,
+    src: &[T],
+    w: usize,
+    stride: usize,
+    rows: usize,
+    convert: impl Fn(T) -> U,
+) -> Rav1dResult<*mut Rav1dRef> {
+    let r#ref = rav1d_ref_create_using_pool(pool, mem::size_of::<U>() * stride * rows);
+    if r#ref.is_null() {
+        return Err(ENOMEM);
+    }
+    let dst = (*r#ref).data.cast::<U>();
+    for (y, row) in src.chunks_exact(w).enumerate() {
+        for (x, &entry) in row.iter().enumerate() {
+            dst.add(y * stride + x).write_unaligned(convert(entry));
+        }
+    }
+    Ok(r#ref)
+}
+
+/// Fills reference slot `i` of `c` from `slot`,
+/// checking that it fits the sequence header of `c`.
+///
+/// The picture planes are shared with `slot.pic` through its refcount,
+/// or borrowed if it has none.
+/// Either way, nothing is written to them, as the frame is decoded into a new picture.
+unsafe fn install_ref_slot(c: &mut Rav1dContext, i: usize, slot: &Dav1dRefSlot) -> Rav1dResult {
+    let seq_hdr = c.seq_hdr.as_ref().ok_or(EINVAL)?;
+    let pic = &slot.pic;
+    let frame_hdr = Rav1dFrameHeader::from(pic.frame_hdr.ok_or(EINVAL)?.as_ref().clone());
+    let layout = Rav1dPixelLayout::try_from(pic.p.layout).map_err(|_| EINVAL)?;
+    let n_planes = if layout == Rav1dPixelLayout::I400 {
+        1
+    } else {
+        3
+    };
+    if pic.p.w != frame_hdr.size.width[1]
+        || pic.p.h != frame_hdr.size.height
+        || pic.p.bpc != 8 + 2 * seq_hdr.hbd
+        || layout != seq_hdr.layout
+        || pic.data[..n_planes].iter().any(Option::is_none)
+    {
+        return Err(EINVAL);
+    }
+    let (w4, h4) = size4(&frame_hdr);
+    let has_maps = !slot.segment_ids.is_null() || !slot.mvs.is_null();
+    if has_maps && (slot.w4 as usize, slot.h4 as usize) != (w4, h4) {
+        return Err(EINVAL);
+    }
+    let (stride, rows) = segmap_size(w4, h4);
+
+    let mut segmap = ptr::null_mut();
+    if !slot.segment_ids.is_null() {
+        let src = slice::from_raw_parts(slot.segment_ids, w4 * h4);
+        segmap = ref_create_strided(c.segmap_pool, src, w4, stride, rows, |id| id)?;
+    }
+    let mut refmvs = ptr::null_mut();
+    if !slot.mvs.is_null() {
+        let src = slice::from_raw_parts(slot.mvs, w4 / 2 * h4 / 2);
+        let convert = |Dav1dSavedMv { y, x, r#ref }| refmvs_temporal_block {
+            mv: mv { y, x },
+            r#ref,
+        };
+        match ref_create_strided(c.refmvs_pool, src, w4 / 2, stride / 2, rows / 2, convert) {
+            Ok(r#ref) => refmvs = r#ref,
+            Err(e) => {
+                rav1d_ref_dec(&mut segmap);
+                return Err(e);
+            }
+        }
+    }
+    let cdf = ptr::addr_of_mut!(c.cdf[i]);
+    if let Err(e) = rav1d_cdf_thread_alloc(c, cdf, 0) {
+        rav1d_ref_dec(&mut segmap);
+        rav1d_ref_dec(&mut refmvs);
+        return Err(e);
+    }
+    *(*cdf).data.cdf = (&slot.cdf).into();
+
+    if let Some(r#ref) = pic.r#ref {
+        rav1d_ref_inc(r#ref.as_ptr());
+    }
+    c.refs[i] = Rav1dContext_refs {
+        p: Rav1dThreadPicture {
+            p: Rav1dPicture {
+                frame_hdr: Some(Arc::new(DRav1d::from_rav1d(frame_hdr))),
+                data: Rav1dPictureData {
+                    data: pic
+                        .data
+                        .map(|data| data.map_or_else(ptr::null_mut, NonNull::as_ptr)),
+                    allocator_data: pic.allocator_data,
+                },
+                stride: pic.stride,
+                p: Rav1dPictureParameters {
+                    w: pic.p.w,
+                    h: pic.p.h,
+                    layout,
+                    bpc: pic.p.bpc,
+                },
+                r#ref: pic.r#ref,
+                ..Default::default()
+            },
+            visible: false,
+            showable: slot.showable != 0,
+            flags: PictureFlags::empty(),
+            progress: None,
+        },
+        segmap,
+        refmvs,
+        refpoc: slot.refpoc,
+    };
+    Ok(())
+}
+
+/// Decodes the frame at the start of `in` using `seq_hdr` and the reference slots in `refs`,
+/// and returns its header and the state to save with it.
+///
+/// The frame is decoded on `c`, which mustn't use frame threading,
+/// but with none of the state of `c`:
+/// it is flushed before and after, so that only `seq_hdr` and `refs` are used.
+/// `c` only lends its settings, callbacks and threads.
+///
+/// Data after that frame is left in `in`.
+pub(crate) unsafe fn rav1d_decode_frame_stateless(
+    c: &mut Rav1dContext,
+    seq_hdr: Rav1dSequenceHeader,
+    r#in: &mut Rav1dData,
+    refs: [Option<&Dav1dRefSlot>; 8],
+) -> Rav1dResult<(Rav1dFrameHeader, Dav1dRefSlot)> {
+    if c.n_fc != 1 {
+        return Err(EINVAL);
+    }
+    let Some(mut data) = r#in.data.clone() else {
+        return Err(EINVAL);
+    };
+    rav1d_flush(c);
+    let res = decode_frame(c, seq_hdr, &mut data, &r#in.m, refs);
+    rav1d_flush(c);
+    let res = res?;
+    if data.is_empty() {
+        let _ = mem::take(r#in);
+    } else {
+        r#in.data = Some(data);
+    }
+    Ok(res)
+}
+
+/// [`rav1d_decode_frame_stateless`] on the flushed `c`.
+unsafe fn decode_frame(
+    c: &mut Rav1dContext,
+    seq_hdr: Rav1dSequenceHeader,
+    data: &mut CArc<[u8]>,
+    props: &Rav1dDataProps,
+    refs: [Option<&Dav1dRefSlot>; 8],
+) -> Rav1dResult<(Rav1dFrameHeader, Dav1dRefSlot)> {
+    c.seq_hdr = Some(Arc::new(DRav1d::from_rav1d(seq_hdr)));
+    set_operating_point(c);
+    for (i, slot) in refs.into_iter().enumerate() {
+        if let Some(slot) = slot {
+            install_ref_slot(c, i, slot)?;
+        }
+    }
+
+    // Parse OBUs until one completes the frame, which decodes it.
+    let mut frame_hdr = None;
+    while frame_hdr.is_none() {
+        if data.is_empty() {
+            return Err(EINVAL);
+        }
+        let len = rav1d_parse_obus(c, data, props, false, &mut frame_hdr)?;
+        data.slice_in_place(len..);
+    }
+    let frame_hdr = frame_hdr.unwrap();
+
+    // The state to save is in the reference slots the frame refreshed.
+    let slot = if frame_hdr.show_existing_frame != 0 {
+        Some(frame_hdr.existing_frame_idx as usize)
+    } else {
+        (0..8).find(|i| frame_hdr.refresh_frame_flags & (1 << i) != 0)
+    };
+    let out = match slot {
+        Some(i) => {
+            // The frame header is only kept if the frame was skipped,
+            // see [`Rav1dSettings::decode_frame_type`].
+            if c.refs[i].p.p.data.data[0].is_null() {
+                return Err(EINVAL);
+            }
+            ref_slot(&c.refs[i], &c.cdf[i])
+        }
+        // A frame that isn't a reference doesn't save any state.
+        None => {
+            if c.out.p.data.data[0].is_null() {
+                return Err(EINVAL);
+            }
+            let mut pic = Rav1dPicture::default();
+            rav1d_picture_ref(&mut pic, &c.out.p);
+            Dav1dRefSlot {
+                pic: pic.into(),
+                ..empty_ref_slot()
+            }
+        }
+    };
+    Ok((frame_hdr.rav1d.clone(), out))
+}
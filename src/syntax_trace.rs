@@ -0,0 +1,74 @@
+//! Reporting the symbols decoded from tiles to a [`Rav1dSyntaxTrace`].
+//!
+//! The symbols are named where they are decoded, with [`trace_symbol`],
+//! which compiles to nothing without the `syntax_trace` feature.
+
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::Dav1dSyntaxElement;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::Rav1dSyntaxTrace;
+#[cfg(feature = "syntax_trace")]
+use crate::include::dav1d::dav1d::RAV1D_SYNTAX_TILE;
+use crate::src::internal::Rav1dTileState;
+use std::ffi::c_int;
+
+/// Where the symbols decoded from a tile are reported to, see [`Rav1dTileState::trace`].
+#[cfg(feature = "syntax_trace")]
+#[derive(Clone, Copy)]
+pub struct TileTrace {
+    pub trace: Rav1dSyntaxTrace,
+    pub tile: c_int,
+    /// Current superblock and block position.
+    pub sb: [c_int; 2],
+    pub b: [c_int; 2],
+}
+
+#[cfg(feature = "syntax_trace")]
+impl TileTrace {
+    /// `name` must be NUL-terminated.
+    #[cold]
+    #[inline(never)]
+    pub fn symbol(&self, name: &'static str, value: i64) {
+        self.trace.emit(&Dav1dSyntaxElement {
+            source: RAV1D_SYNTAX_TILE,
+            name: name.as_ptr().cast(),
+            value,
+            n_bits: 0,
+            bit_pos: -1,
+            tile: self.tile,
+            sbx: self.sb[0],
+            sby: self.sb[1],
+            bx: self.b[0],
+            by: self.b[1],
+        });
+    }
+}
+
+impl Rav1dTileState {
+    /// Sets the block position symbols are reported at, in 4px units.
+    #[inline(always)]
+    pub fn set_trace_pos(&mut self, bx: c_int, by: c_int, sb_shift: c_int) {
+        #[cfg(feature = "syntax_trace")]
+        if let Some(trace) = &mut self.trace {
+            trace.sb = [bx >> sb_shift, by >> sb_shift];
+            trace.b = [bx, by];
+        }
+        #[cfg(not(feature = "syntax_trace"))]
+        let _ = (bx, by, sb_shift);
+    }
+}
+
+/// Evaluates `$decode`, which decodes a symbol from `$ts.msac`,
+/// and reports its value to the [`Rav1dTileState::trace`] of `$ts` as `$name`.
+macro_rules! trace_symbol {
+    ($ts:expr, $name:literal, $decode:expr) => {{
+        let value = $decode;
+        #[cfg(feature = "syntax_trace")]
+        if let Some(trace) = &$ts.trace {
+            trace.symbol(concat!($name, "\0"), value as i64);
+        }
+        value
+    }};
+}
+
+pub(crate) use trace_symbol;
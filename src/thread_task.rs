@@ -3,6 +3,7 @@ use crate::include::common::bitdepth::BitDepth;
 use crate::include::common::bitdepth::BitDepth16;
 use crate::include::common::bitdepth::BitDepth8;
 use crate::include::common::intops::iclip;
+use crate::include::dav1d::dav1d::RAV1D_RECON_STAGE_COUNT;
 use crate::include::dav1d::headers::Rav1dPixelLayout;
 use crate::include::dav1d::picture::Rav1dPicture;
 use crate::src::cdf::rav1d_cdf_thread_update;
@@ -38,7 +39,9 @@ use crate::src::internal::RAV1D_TASK_TYPE_RECONSTRUCTION_PROGRESS;
 use crate::src::internal::RAV1D_TASK_TYPE_SUPER_RESOLUTION;
 use crate::src::internal::RAV1D_TASK_TYPE_TILE_ENTROPY;
 use crate::src::internal::RAV1D_TASK_TYPE_TILE_RECONSTRUCTION;
+use crate::src::picture::rav1d_thread_picture_row_progress;
 use crate::src::picture::Rav1dThreadPicture;
+use crate::src::recon_stages::rav1d_recon_stages_capture;
 use libc::memset;
 use libc::realloc;
 use std::cmp;
@@ -1321,6 +1324,9 @@ pub unsafe extern "C" fn rav1d_worker_task(data: *mut c_void) -> *mut c_void {
                     }
                     RAV1D_TASK_TYPE_RECONSTRUCTION_PROGRESS => {
                         // dummy to cover for no post-filters
+                        if f.task_thread.error.load(Ordering::SeqCst) == 0 {
+                            rav1d_recon_stages_capture(f, sby, RAV1D_RECON_STAGE_COUNT);
+                        }
                     }
                     RAV1D_TASK_TYPE_ENTROPY_PROGRESS => {
                         // dummy to convert tile progress to frame
@@ -1405,6 +1411,9 @@ pub unsafe extern "C" fn rav1d_worker_task(data: *mut c_void) -> *mut c_void {
                     }
                 }
             }
+            if error_0 == 0 {
+                rav1d_thread_picture_row_progress(c, f, sby);
+            }
             if sby + 1 == sbh {
                 f.task_thread.done[0].store(1, Ordering::SeqCst);
             }
@@ -0,0 +1,215 @@
+//! Helpers shared by the API tests: reading the IVF files of the test data
+//! and driving a decoder through the public API.
+
+#![allow(dead_code)]
+
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::Dav1dContext;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I422;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::dav1d_close;
+use rav1d::src::lib::dav1d_data_create;
+use rav1d::src::lib::dav1d_data_unref;
+use rav1d::src::lib::dav1d_default_settings;
+use rav1d::src::lib::dav1d_get_picture;
+use rav1d::src::lib::dav1d_open;
+use rav1d::src::lib::dav1d_picture_unref;
+use rav1d::src::lib::dav1d_send_data;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::mem::MaybeUninit;
+use std::path::Path;
+use std::path::PathBuf;
+use std::ptr;
+use std::slice;
+
+const EAGAIN: i32 = -libc::EAGAIN;
+
+/// The path of a file in `tests/dav1d-test-data`.
+pub fn test_data(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/dav1d-test-data")
+        .join(path)
+}
+
+pub struct IvfFrame {
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+}
+
+impl IvfFrame {
+    /// A copy of the frame in a [`Dav1dData`], to be released with `dav1d_data_unref`.
+    pub fn to_data(&self) -> Dav1dData {
+        let mut data = Dav1dData::default();
+        // Safety: `data` is valid, and the created buffer is `self.data.len()` bytes long.
+        unsafe {
+            let buf = dav1d_data_create(&mut data, self.data.len());
+            assert!(!buf.is_null());
+            ptr::copy_nonoverlapping(self.data.as_ptr(), buf, self.data.len());
+        }
+        data.m.timestamp = self.timestamp;
+        data
+    }
+}
+
+/// Reads all frames of an IVF file.
+pub fn read_ivf(path: &Path) -> Vec<IvfFrame> {
+    let file = fs::read(path).unwrap();
+    assert_eq!(&file[..4], b"DKIF");
+    let header_len = u16::from_le_bytes(file[6..8].try_into().unwrap()) as usize;
+    let mut frames = Vec::new();
+    let mut rest = &file[header_len..];
+    while rest.len() >= 12 {
+        let size = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        let timestamp = i64::from_le_bytes(rest[4..12].try_into().unwrap());
+        let data = rest[12..12 + size].to_vec();
+        rest = &rest[12 + size..];
+        frames.push(IvfFrame { timestamp, data });
+    }
+    frames
+}
+
+/// The [`Dav1dSettings`] of `dav1d_default_settings`,
+/// with `n_threads` and `max_frame_delay` set.
+pub fn settings(n_threads: i32, max_frame_delay: i32) -> Dav1dSettings {
+    let mut s = MaybeUninit::uninit();
+    // Safety: `dav1d_default_settings` initializes all of `s`.
+    let mut s = unsafe {
+        dav1d_default_settings(s.as_mut_ptr());
+        s.assume_init()
+    };
+    s.n_threads = n_threads;
+    s.max_frame_delay = max_frame_delay;
+    s
+}
+
+/// A decoded picture, reduced to what the tests compare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picture {
+    pub timestamp: i64,
+    pub w: i32,
+    pub h: i32,
+    /// A hash of the pixels of all planes.
+    pub hash: u64,
+}
+
+impl Picture {
+    /// # Safety
+    ///
+    /// `pic` must be a picture returned by `dav1d_get_picture`.
+    pub unsafe fn new(pic: &Dav1dPicture) -> Self {
+        let bytes_per_pixel = (pic.p.bpc as usize + 7) / 8;
+        let layout = pic.p.layout;
+        let has_chroma = layout != DAV1D_PIXEL_LAYOUT_I400;
+        let ss_hor =
+            (layout == DAV1D_PIXEL_LAYOUT_I420 || layout == DAV1D_PIXEL_LAYOUT_I422) as usize;
+        let ss_ver = (layout == DAV1D_PIXEL_LAYOUT_I420) as usize;
+        let (w, h) = (pic.p.w as usize, pic.p.h as usize);
+        let mut hasher = DefaultHasher::new();
+        for pl in 0..if has_chroma { 3 } else { 1 } {
+            let (pw, ph) = if pl == 0 {
+                (w, h)
+            } else {
+                ((w + ss_hor) >> ss_hor, (h + ss_ver) >> ss_ver)
+            };
+            let stride = pic.stride[(pl != 0) as usize];
+            let data = pic.data[pl].unwrap().as_ptr().cast::<u8>();
+            for y in 0..ph {
+                let row = data.offset(y as isize * stride);
+                hasher.write(slice::from_raw_parts(row, pw * bytes_per_pixel));
+            }
+        }
+        Self {
+            timestamp: pic.m.timestamp,
+            w: pic.p.w,
+            h: pic.p.h,
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// A decoder opened with `dav1d_open` and closed when dropped.
+pub struct Decoder(pub *mut Dav1dContext);
+
+impl Decoder {
+    pub fn open(settings: &Dav1dSettings) -> Self {
+        let mut c = ptr::null_mut();
+        // Safety: `settings` is valid and `c` is a valid out pointer.
+        let res = unsafe { dav1d_open(&mut c, settings) };
+        assert_eq!(res.0, 0, "dav1d_open failed");
+        Self(c)
+    }
+
+    /// Sends all of `frame`, collecting the pictures that become ready.
+    pub fn send(&mut self, frame: &IvfFrame, out: &mut Vec<Picture>) {
+        self.send_with(frame, |dec| dec.get_pictures(out));
+    }
+
+    /// Sends all of `frame`, calling `ready` to take the pictures that become ready.
+    pub fn send_with(&mut self, frame: &IvfFrame, mut ready: impl FnMut(&mut Self)) {
+        let mut data = frame.to_data();
+        while data.sz > 0 {
+            // Safety: `self.0` is open and `data` is valid.
+            let res = unsafe { dav1d_send_data(self.0, &mut data) };
+            assert!(
+                res.0 == 0 || res.0 == EAGAIN,
+                "dav1d_send_data failed: {}",
+                res.0
+            );
+            ready(self);
+        }
+        // Safety: `data` is valid.
+        unsafe { dav1d_data_unref(&mut data) };
+    }
+
+    /// Collects the pictures that are ready, or all remaining ones
+    /// when no more data is sent.
+    pub fn get_pictures(&mut self, out: &mut Vec<Picture>) {
+        while let Some(pic) = self.get_picture() {
+            out.push(pic);
+        }
+    }
+
+    /// The next picture that's ready, to be released with `dav1d_picture_unref`.
+    pub fn get_dav1d_picture(&mut self) -> Option<Dav1dPicture> {
+        let mut pic = Dav1dPicture::default();
+        // Safety: `self.0` is open and `pic` is valid.
+        let res = unsafe { dav1d_get_picture(self.0, &mut pic) };
+        if res.0 == EAGAIN {
+            return None;
+        }
+        assert_eq!(res.0, 0, "dav1d_get_picture failed");
+        Some(pic)
+    }
+
+    pub fn get_picture(&mut self) -> Option<Picture> {
+        let mut pic = self.get_dav1d_picture()?;
+        // Safety: `pic` was just returned by `dav1d_get_picture`.
+        let picture = unsafe { Picture::new(&pic) };
+        // Safety: `pic` is owned by us.
+        unsafe { dav1d_picture_unref(&mut pic) };
+        Some(picture)
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        // Safety: `self.0` was opened with `dav1d_open`.
+        unsafe { dav1d_close(&mut self.0) };
+    }
+}
+
+/// Decodes all of `frames` and returns the pictures in output order.
+pub fn decode_all(settings: &Dav1dSettings, frames: &[IvfFrame]) -> Vec<Picture> {
+    let mut dec = Decoder::open(settings);
+    let mut out = Vec::new();
+    for frame in frames {
+        dec.send(frame, &mut out);
+    }
+    dec.get_pictures(&mut out);
+    out
+}
@@ -0,0 +1,134 @@
+//! Tests of `dav1d_picture_get_prediction` and `dav1d_picture_get_residual`:
+//! the residual added to the prediction must give the reconstruction before filtering.
+
+mod common;
+
+use crate::common::read_ivf;
+use crate::common::settings;
+use crate::common::test_data;
+use crate::common::Decoder;
+use rav1d::include::dav1d::dav1d::dav1d_export_recon_stage;
+use rav1d::include::dav1d::dav1d::DAV1D_EXPORT_PRED_RESIDUAL;
+use rav1d::include::dav1d::dav1d::DAV1D_RECON_STAGE_PREFILTER;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::dav1d_picture_get_prediction;
+use rav1d::src::lib::dav1d_picture_get_recon_stage;
+use rav1d::src::lib::dav1d_picture_get_residual;
+use rav1d::src::lib::dav1d_picture_unref;
+
+/// The pixels of all planes of `pic`, as `u16`s.
+///
+/// # Safety
+///
+/// `pic` must be a valid picture.
+unsafe fn pixels(pic: &Dav1dPicture) -> Vec<Vec<u16>> {
+    let layout = pic.p.layout;
+    let n_planes = if layout == DAV1D_PIXEL_LAYOUT_I400 {
+        1
+    } else {
+        3
+    };
+    let ss_hor = (layout != DAV1D_PIXEL_LAYOUT_I444) as i32;
+    let ss_ver = (layout == DAV1D_PIXEL_LAYOUT_I420) as i32;
+    (0..n_planes)
+        .map(|pl| {
+            let [w, h] = if pl == 0 {
+                [pic.p.w, pic.p.h]
+            } else {
+                [(pic.p.w + ss_hor) >> ss_hor, (pic.p.h + ss_ver) >> ss_ver]
+            };
+            let stride = pic.stride[(pl != 0) as usize];
+            let data = pic.data[pl].unwrap().as_ptr().cast::<u8>();
+            let mut plane = Vec::new();
+            for y in 0..h as isize {
+                let row = data.offset(y * stride);
+                for x in 0..w as usize {
+                    plane.push(if pic.p.bpc > 8 {
+                        *row.cast::<u16>().add(x)
+                    } else {
+                        *row.add(x) as u16
+                    });
+                }
+            }
+            plane
+        })
+        .collect()
+}
+
+/// Checks the prediction and residual of `pic`,
+/// returning the number of pixels with a non-zero residual.
+///
+/// # Safety
+///
+/// `pic` must be a picture returned by `dav1d_get_picture`.
+unsafe fn check(pic: &Dav1dPicture) -> usize {
+    let mut recon = Dav1dPicture::default();
+    let mut pred = Dav1dPicture::default();
+    let mut residual = Dav1dPicture::default();
+    assert_eq!(
+        dav1d_picture_get_recon_stage(pic, DAV1D_RECON_STAGE_PREFILTER, &mut recon).0,
+        0
+    );
+    assert_eq!(dav1d_picture_get_prediction(pic, &mut pred).0, 0);
+    assert_eq!(dav1d_picture_get_residual(pic, &mut residual).0, 0);
+    let zero = 1 << (pic.p.bpc - 1);
+    let max = (1 << pic.p.bpc) - 1;
+    let mut coded = 0;
+    for ((recon, pred), residual) in pixels(&recon)
+        .iter()
+        .flatten()
+        .zip(pixels(&pred).iter().flatten())
+        .zip(pixels(&residual).iter().flatten())
+    {
+        let [recon, pred, residual] = [*recon as i32, *pred as i32, *residual as i32];
+        // The residual is clipped to the pixel range.
+        if residual != 0 && residual != max {
+            assert_eq!(pred + residual - zero, recon);
+        }
+        coded += (residual != zero) as usize;
+    }
+    for pic in [&mut recon, &mut pred, &mut residual] {
+        dav1d_picture_unref(pic);
+    }
+    coded
+}
+
+fn check_file(path: &str, n_threads: i32, max_frame_delay: i32) {
+    let mut s = settings(n_threads, max_frame_delay);
+    s.export_flags =
+        DAV1D_EXPORT_PRED_RESIDUAL | dav1d_export_recon_stage(DAV1D_RECON_STAGE_PREFILTER);
+    let mut dec = Decoder::open(&s);
+    let mut coded = 0;
+    let mut ready = |dec: &mut Decoder| {
+        while let Some(mut pic) = dec.get_dav1d_picture() {
+            // Safety: `pic` was just returned by `dav1d_get_picture` and is owned by us.
+            unsafe {
+                coded += check(&pic);
+                dav1d_picture_unref(&mut pic);
+            }
+        }
+    };
+    for frame in &read_ivf(&test_data(path)) {
+        dec.send_with(frame, &mut ready);
+    }
+    ready(&mut dec);
+    assert!(coded > 0);
+}
+
+#[test]
+fn residual_added_to_prediction_single_threaded() {
+    check_file("8-bit/data/00000527.ivf", 1, 1);
+}
+
+#[test]
+fn residual_added_to_prediction_frame_threaded() {
+    check_file("8-bit/data/00000527.ivf", 4, 4);
+}
+
+#[test]
+fn residual_added_to_prediction_10_bit() {
+    check_file("10-bit/data/00000671.ivf", 1, 1);
+}
@@ -0,0 +1,170 @@
+//! Tests of `dav1d_parse_random_access_point` on the temporal units of the test data,
+//! and on frame headers written by hand behind a sequence header of the test data.
+
+mod common;
+
+use crate::common::read_ivf;
+use crate::common::test_data;
+use rav1d::include::dav1d::dav1d::Dav1dRandomAccessPoint;
+use rav1d::include::dav1d::dav1d::Dav1dRandomAccessType;
+use rav1d::include::dav1d::dav1d::DAV1D_RANDOM_ACCESS_FORWARD_KEY;
+use rav1d::include::dav1d::dav1d::DAV1D_RANDOM_ACCESS_KEY;
+use rav1d::include::dav1d::dav1d::DAV1D_RANDOM_ACCESS_NONE;
+use rav1d::include::dav1d::headers::Dav1dSequenceHeader;
+use rav1d::src::lib::dav1d_parse_random_access_point;
+use rav1d::src::lib::dav1d_parse_sequence_header;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+const OBU_SEQ_HDR: u8 = 1;
+const OBU_FRAME_HDR: u8 = 3;
+
+// The first bits of a frame header:
+// `show_existing_frame`, `frame_type` (2 bits), `show_frame` and, if that's 0, `showable_frame`.
+const KEY_SHOWN: u8 = 0b0001_0000;
+const KEY_HIDDEN_SHOWABLE: u8 = 0b0000_1000;
+const KEY_HIDDEN: u8 = 0b0000_0000;
+const INTER_SHOWN: u8 = 0b0011_0000;
+const INTRA_ONLY_SHOWN: u8 = 0b0101_0000;
+const SHOW_EXISTING: u8 = 0b1000_0000;
+
+/// Parses `tu`, or returns the error code.
+fn parse(
+    tu: &[u8],
+    seq_hdr: Option<&Dav1dSequenceHeader>,
+    operating_point: i32,
+) -> Result<(Dav1dRandomAccessType, bool), i32> {
+    let mut rap = Dav1dRandomAccessPoint::default();
+    let seq_hdr = seq_hdr.map_or(ptr::null(), |seq_hdr| seq_hdr as *const _);
+    // Safety: All pointers are valid.
+    let res = unsafe {
+        dav1d_parse_random_access_point(&mut rap, tu.as_ptr(), tu.len(), seq_hdr, operating_point)
+    };
+    if res.0 != 0 {
+        return Err(res.0);
+    }
+    Ok((rap.r#type, rap.seq_hdr_present != 0))
+}
+
+fn parse_seq_hdr(data: &[u8]) -> Dav1dSequenceHeader {
+    let mut seq_hdr = MaybeUninit::uninit();
+    // Safety: All pointers are valid, and `seq_hdr` is filled on success.
+    unsafe {
+        let res = dav1d_parse_sequence_header(seq_hdr.as_mut_ptr(), data.as_ptr(), data.len());
+        assert_eq!(res.0, 0);
+        seq_hdr.assume_init()
+    }
+}
+
+/// An OBU with a length field, and an extension header if `layer` is given
+/// as `(temporal_id, spatial_id)`.
+fn obu(r#type: u8, layer: Option<(u8, u8)>, payload: &[u8]) -> Vec<u8> {
+    let mut obu = vec![r#type << 3 | (layer.is_some() as u8) << 2 | 1 << 1];
+    if let Some((temporal_id, spatial_id)) = layer {
+        obu.push(temporal_id << 5 | spatial_id << 3);
+    }
+    assert!(payload.len() < 0x80);
+    obu.push(payload.len() as u8);
+    obu.extend_from_slice(payload);
+    obu
+}
+
+/// The first sequence header OBU of `data`.
+fn find_seq_hdr_obu(mut data: &[u8]) -> Vec<u8> {
+    loop {
+        let r#type = data[0] >> 3 & 0xf;
+        let has_extension = data[0] >> 2 & 1 != 0;
+        let mut pos = 1 + has_extension as usize;
+        let mut len = 0;
+        let mut shift = 0;
+        loop {
+            let byte = data[pos];
+            pos += 1;
+            len |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        if r#type == OBU_SEQ_HDR {
+            return data[..pos + len].to_vec();
+        }
+        data = &data[pos + len..];
+    }
+}
+
+#[test]
+fn random_access_points_of_stream() {
+    let frames = read_ivf(&test_data("8-bit/data/00000797.ivf"));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    assert_eq!(
+        parse(&frames[0].data, None, 0),
+        Ok((DAV1D_RANDOM_ACCESS_KEY, true))
+    );
+    for frame in &frames[1..] {
+        assert_eq!(
+            parse(&frame.data, None, 0),
+            Err(-libc::ENOENT),
+            "no sequence header to parse the frame header with"
+        );
+        assert_eq!(
+            parse(&frame.data, Some(&seq_hdr), 0),
+            Ok((DAV1D_RANDOM_ACCESS_NONE, false))
+        );
+    }
+}
+
+#[test]
+fn random_access_frame_types() {
+    let frames = read_ivf(&test_data("8-bit/data/00000797.ivf"));
+    let seq_hdr_obu = find_seq_hdr_obu(&frames[0].data);
+    for (frame_hdr, expected) in [
+        (KEY_SHOWN, DAV1D_RANDOM_ACCESS_KEY),
+        (KEY_HIDDEN_SHOWABLE, DAV1D_RANDOM_ACCESS_FORWARD_KEY),
+        (KEY_HIDDEN, DAV1D_RANDOM_ACCESS_NONE),
+        (INTER_SHOWN, DAV1D_RANDOM_ACCESS_NONE),
+        (INTRA_ONLY_SHOWN, DAV1D_RANDOM_ACCESS_NONE),
+        (SHOW_EXISTING, DAV1D_RANDOM_ACCESS_NONE),
+    ] {
+        let mut tu = seq_hdr_obu.clone();
+        tu.extend(obu(OBU_FRAME_HDR, None, &[frame_hdr]));
+        assert_eq!(
+            parse(&tu, None, 0),
+            Ok((expected, true)),
+            "frame header {frame_hdr:#010b}"
+        );
+    }
+}
+
+#[test]
+fn random_access_operating_point() {
+    let frames = read_ivf(&test_data("8-bit/svc/av1-1-b8-22-svc-L2T2.ivf"));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    let ops = &seq_hdr.operating_points[..seq_hdr.num_operating_points as usize];
+    let in_op = |op: usize, (temporal_id, spatial_id): (u8, u8)| {
+        ops[op].idc >> temporal_id & 1 != 0 && ops[op].idc >> (spatial_id + 8) & 1 != 0
+    };
+    let top_layer = (1, 1);
+    let full = (0..ops.len()).find(|&op| in_op(op, top_layer)).unwrap();
+    let base = (0..ops.len()).find(|&op| !in_op(op, top_layer)).unwrap();
+
+    // A key frame in the top layer, followed by an inter frame in the base layer.
+    let mut tu = obu(OBU_FRAME_HDR, Some(top_layer), &[KEY_SHOWN]);
+    tu.extend(obu(OBU_FRAME_HDR, Some((0, 0)), &[INTER_SHOWN]));
+    assert_eq!(
+        parse(&tu, Some(&seq_hdr), full as i32),
+        Ok((DAV1D_RANDOM_ACCESS_KEY, false))
+    );
+    assert_eq!(
+        parse(&tu, Some(&seq_hdr), base as i32),
+        Ok((DAV1D_RANDOM_ACCESS_NONE, false))
+    );
+
+    // The sequence header in the temporal unit selects the operating point as well.
+    let mut tu_with_seq_hdr = find_seq_hdr_obu(&frames[0].data);
+    tu_with_seq_hdr.extend(&tu);
+    assert_eq!(
+        parse(&tu_with_seq_hdr, None, base as i32),
+        Ok((DAV1D_RANDOM_ACCESS_NONE, true))
+    );
+}
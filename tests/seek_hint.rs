@@ -0,0 +1,86 @@
+//! Tests of `dav1d_seek_hint`: decoding from a key frame in front of the target
+//! must output the target picture first, and the same pictures as a full decode from there on.
+
+mod common;
+
+use crate::common::decode_all;
+use crate::common::read_ivf;
+use crate::common::settings;
+use crate::common::test_data;
+use crate::common::Decoder;
+use crate::common::IvfFrame;
+use crate::common::Picture;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::src::lib::dav1d_flush;
+use rav1d::src::lib::dav1d_seek_hint;
+
+/// Has non-reference frames, which are skipped before the target.
+const FILE: &str = "8-bit/data/00000797.ivf";
+
+/// Sends the first `warmup` frames, flushes, and then decodes all of `frames`
+/// again from the start with a seek hint for `target`.
+fn seek(s: &Dav1dSettings, frames: &[IvfFrame], warmup: usize, target: i64) -> Vec<Picture> {
+    let mut dec = Decoder::open(s);
+    let mut out = Vec::new();
+    for frame in &frames[..warmup] {
+        dec.send(frame, &mut out);
+    }
+    out.clear();
+    // Safety: `dec.0` is open.
+    unsafe {
+        dav1d_flush(dec.0);
+        assert_eq!(dav1d_seek_hint(dec.0, target).0, 0);
+    }
+    for frame in frames {
+        dec.send(frame, &mut out);
+    }
+    dec.get_pictures(&mut out);
+    out
+}
+
+fn check_seek(n_threads: i32, max_frame_delay: i32) {
+    let frames = read_ivf(&test_data(FILE));
+    let s = settings(n_threads, max_frame_delay);
+    let all = decode_all(&s, &frames);
+    assert!(all.len() > 8);
+    for target in [1, all.len() / 2, all.len() - 1] {
+        for warmup in [0, frames.len() / 3] {
+            let out = seek(&s, &frames, warmup, all[target].timestamp);
+            assert_eq!(
+                out.first(),
+                Some(&all[target]),
+                "target {target}, warmup {warmup}"
+            );
+            assert_eq!(out, all[target..], "target {target}, warmup {warmup}");
+        }
+    }
+}
+
+#[test]
+fn seek_hint_single_threaded() {
+    check_seek(1, 1);
+}
+
+#[test]
+fn seek_hint_frame_threaded() {
+    check_seek(4, 4);
+}
+
+#[test]
+fn seek_hint_cleared_by_flush() {
+    let frames = read_ivf(&test_data(FILE));
+    let s = settings(1, 1);
+    let all = decode_all(&s, &frames);
+    let mut dec = Decoder::open(&s);
+    // Safety: `dec.0` is open.
+    unsafe {
+        assert_eq!(dav1d_seek_hint(dec.0, i64::MAX).0, 0);
+        dav1d_flush(dec.0);
+    }
+    let mut out = Vec::new();
+    for frame in &frames {
+        dec.send(frame, &mut out);
+    }
+    dec.get_pictures(&mut out);
+    assert_eq!(out, all);
+}
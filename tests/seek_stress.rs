@@ -13,14 +13,29 @@ mod compat {
 mod input {
     mod annexb;
     pub mod input;
+    // Only the parts of the box reader used by `mp4` are used here, as AVIF images aren't.
+    #[allow(dead_code)]
+    mod isobmff;
     mod ivf;
+    mod matroska;
+    mod mp4;
+    mod mpegts;
+    mod rtp;
     mod section5;
 } // mod input
 #[path = "../tools/output"]
 mod output {
+    mod annexb;
+    mod framehash;
+    mod ivf;
     mod md5;
     mod null;
     mod output;
+    // Only the OBU parsing of the bitstream muxers is used here, as `seek_stress` always decodes.
+    #[allow(dead_code)]
+    mod remux;
+    mod section5;
+    mod xxhash;
     mod y4m2;
     mod yuv;
 } // mod output
@@ -71,6 +86,7 @@ use std::ffi::c_int;
 use std::ffi::c_uint;
 use std::ffi::c_ulonglong;
 use std::ffi::c_void;
+use std::ptr;
 
 unsafe fn get_seed() -> c_uint {
     let mut ts: libc::timespec = libc::timespec {
@@ -289,6 +305,11 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         demuxer: 0 as *const c_char,
         muxer: 0 as *const c_char,
         frametimes: 0 as *const c_char,
+        syntaxtrace: 0 as *const c_char,
+        sbheatmap: 0 as *const c_char,
+        compare: 0 as *const c_char,
+        compare_format: 0 as *const c_char,
+        alpha: 0 as *const c_char,
         verify: 0 as *const c_char,
         limit: 0,
         skip: 0,
@@ -297,6 +318,8 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         realtime_fps: 0.,
         realtime_cache: 0,
         neg_stride: 0,
+        overlay: 0,
+        filter_obus: 0,
     };
     let mut lib_settings: Dav1dSettings = Dav1dSettings {
         n_threads: 0,
@@ -315,7 +338,9 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         output_invisible_frames: 0,
         inloop_filters: DAV1D_INLOOPFILTER_NONE,
         decode_frame_type: DAV1D_DECODEFRAMETYPE_ALL,
-        reserved: [0; 16],
+        export_flags: 0,
+        reserved: Default::default(),
+        callbacks: ptr::null(),
     };
     let mut in_0: *mut DemuxerContext = 0 as *mut DemuxerContext;
     let mut c: *mut Dav1dContext = 0 as *mut Dav1dContext;
@@ -343,9 +368,15 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
     let mut pts: u64;
     xor128_srand(get_seed());
     parse(argc, argv, &mut cli_settings, &mut lib_settings);
+    // Only demuxers with a `seek` can be tested; default to IVF.
+    let demuxer = if cli_settings.demuxer.is_null() {
+        b"ivf\0" as *const u8 as *const c_char
+    } else {
+        cli_settings.demuxer
+    };
     if input_open(
         &mut in_0,
-        b"ivf\0" as *const u8 as *const c_char,
+        demuxer,
         cli_settings.inputfile,
         i_fps.as_mut_ptr(),
         &mut total,
@@ -0,0 +1,144 @@
+//! Tests of `dav1d_state_snapshot` and `dav1d_state_restore`: decoding after a restore
+//! must continue like decoding without one, and snapshots that don't fit are rejected.
+
+mod common;
+
+use crate::common::decode_all;
+use crate::common::read_ivf;
+use crate::common::settings;
+use crate::common::test_data;
+use crate::common::Decoder;
+use crate::common::IvfFrame;
+use crate::common::Picture;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::dav1d::Dav1dStateSnapshot;
+use rav1d::include::dav1d::dav1d::DAV1D_INLOOPFILTER_NONE;
+use rav1d::src::lib::dav1d_state_restore;
+use rav1d::src::lib::dav1d_state_snapshot;
+use rav1d::src::lib::dav1d_state_snapshot_unref;
+use std::ptr;
+
+const FILE: &str = "8-bit/data/00000797.ivf";
+const OTHER_FILE: &str = "8-bit/data/00000527.ivf";
+
+/// A snapshot taken with `dav1d_state_snapshot` and released when dropped.
+struct Snapshot(*mut Dav1dStateSnapshot);
+
+impl Snapshot {
+    fn take(dec: &Decoder) -> Self {
+        let mut s = ptr::null_mut();
+        // Safety: `dec.0` is open.
+        let res = unsafe { dav1d_state_snapshot(dec.0, &mut s) };
+        assert_eq!(res.0, 0);
+        Self(s)
+    }
+
+    /// Restores `self` to `dec`, returning the error code.
+    fn restore(&self, dec: &Decoder) -> i32 {
+        // Safety: `dec.0` is open and `self.0` is a valid snapshot.
+        unsafe { dav1d_state_restore(dec.0, self.0) }.0
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        // Safety: `self.0` was taken with `dav1d_state_snapshot`.
+        unsafe { dav1d_state_snapshot_unref(&mut self.0) };
+    }
+}
+
+/// Decodes `frames` up to and including `frames[at]` and takes a snapshot there.
+fn snapshot_at(s: &Dav1dSettings, frames: &[IvfFrame], at: usize) -> (Decoder, Snapshot) {
+    let mut dec = Decoder::open(s);
+    let mut out = Vec::new();
+    for frame in &frames[..=at] {
+        dec.send(frame, &mut out);
+    }
+    let snapshot = Snapshot::take(&dec);
+    (dec, snapshot)
+}
+
+/// Decodes `frames` and returns all pictures.
+fn decode_rest(dec: &mut Decoder, frames: &[IvfFrame]) -> Vec<Picture> {
+    let mut out = Vec::new();
+    for frame in frames {
+        dec.send(frame, &mut out);
+    }
+    dec.get_pictures(&mut out);
+    out
+}
+
+fn check_restore(snapshot_settings: &Dav1dSettings, restore_settings: &Dav1dSettings) {
+    let frames = read_ivf(&test_data(FILE));
+    let all = decode_all(snapshot_settings, &frames);
+    for at in [0, frames.len() / 2, frames.len() - 2] {
+        let expected: Vec<_> = all
+            .iter()
+            .filter(|pic| pic.timestamp > frames[at].timestamp)
+            .cloned()
+            .collect();
+        let (mut dec, snapshot) = snapshot_at(snapshot_settings, &frames, at);
+
+        // Into a new decoder.
+        let mut restored = Decoder::open(restore_settings);
+        assert_eq!(snapshot.restore(&restored), 0);
+        assert_eq!(
+            decode_rest(&mut restored, &frames[at + 1..]),
+            expected,
+            "at {at}"
+        );
+
+        // Back into the same decoder, after it decoded some more.
+        decode_rest(&mut dec, &frames[at + 1..]);
+        assert_eq!(snapshot.restore(&dec), 0);
+        assert_eq!(
+            decode_rest(&mut dec, &frames[at + 1..]),
+            expected,
+            "at {at}"
+        );
+    }
+}
+
+#[test]
+fn restore_single_threaded() {
+    check_restore(&settings(1, 1), &settings(1, 1));
+}
+
+#[test]
+fn restore_into_frame_threaded() {
+    check_restore(&settings(1, 1), &settings(4, 4));
+}
+
+#[test]
+fn restore_from_frame_threaded() {
+    check_restore(&settings(4, 4), &settings(1, 1));
+}
+
+#[test]
+fn restore_rejects_different_settings() {
+    let frames = read_ivf(&test_data(FILE));
+    let (_dec, snapshot) = snapshot_at(&settings(1, 1), &frames, 3);
+    let mut s = settings(1, 1);
+    s.inloop_filters = DAV1D_INLOOPFILTER_NONE;
+    assert_eq!(snapshot.restore(&Decoder::open(&s)), -libc::EINVAL);
+    let mut s = settings(1, 1);
+    s.frame_size_limit = 16 * 16;
+    assert_eq!(snapshot.restore(&Decoder::open(&s)), -libc::EINVAL);
+}
+
+#[test]
+fn restore_rejects_different_sequence() {
+    let frames = read_ivf(&test_data(FILE));
+    let other_frames = read_ivf(&test_data(OTHER_FILE));
+    let s = settings(1, 1);
+    let (_dec, snapshot) = snapshot_at(&s, &frames, 3);
+    let other_all = decode_all(&s, &other_frames);
+
+    let mut other = Decoder::open(&s);
+    let mut out = Vec::new();
+    other.send(&other_frames[0], &mut out);
+    assert_eq!(snapshot.restore(&other), -libc::EINVAL);
+    // The decoder is left untouched.
+    out.extend(decode_rest(&mut other, &other_frames[1..]));
+    assert_eq!(out, other_all);
+}
@@ -0,0 +1,366 @@
+//! Tests of `dav1d_decode_frame_stateless`: decoding every frame from reference slots
+//! kept by the caller, including ones serialized and filled in again,
+//! must output the same pictures as a regular decode.
+
+mod common;
+
+use crate::common::decode_all;
+use crate::common::read_ivf;
+use crate::common::settings;
+use crate::common::test_data;
+use crate::common::Decoder;
+use crate::common::IvfFrame;
+use crate::common::Picture;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::Dav1dRefSlot;
+use rav1d::include::dav1d::dav1d::Dav1dSavedMv;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::dav1d::Dav1dStateSnapshot;
+use rav1d::include::dav1d::headers::Dav1dFrameHeader;
+use rav1d::include::dav1d::headers::Dav1dSequenceHeader;
+use rav1d::include::dav1d::headers::DAV1D_FRAME_TYPE_KEY;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use rav1d::src::lib::dav1d_data_unref;
+use rav1d::src::lib::dav1d_decode_frame_stateless;
+use rav1d::src::lib::dav1d_parse_sequence_header;
+use rav1d::src::lib::dav1d_ref_slot_unref;
+use rav1d::src::lib::dav1d_state_snapshot;
+use rav1d::src::lib::dav1d_state_snapshot_get_ref_slot;
+use rav1d::src::lib::dav1d_state_snapshot_unref;
+use std::array;
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::slice;
+
+/// Has motion field projection, which uses the saved motion vectors.
+const MFMV_FILE: &str = "8-bit/mfmv/av1-1-b8-06-mfmv.ivf";
+/// Has non-reference frames.
+const FILE: &str = "8-bit/data/00000797.ivf";
+
+/// Planes are aligned like the default picture allocator aligns them.
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Aligned([u8; 64]);
+
+/// A reference slot, as returned by the decoder or filled in from a copy.
+enum Slot {
+    Decoder(Dav1dRefSlot),
+    Copy {
+        slot: Dav1dRefSlot,
+        _planes: Vec<Vec<Aligned>>,
+        _frame_hdr: Box<Dav1dFrameHeader>,
+        _segment_ids: Vec<u8>,
+        _mvs: Vec<Dav1dSavedMv>,
+    },
+}
+
+impl Slot {
+    fn slot(&self) -> &Dav1dRefSlot {
+        match self {
+            Self::Decoder(slot) | Self::Copy { slot, .. } => slot,
+        }
+    }
+
+    /// Copies all of `self` to buffers owned by the caller,
+    /// like after serializing and deserializing it.
+    fn to_copy(&self) -> Self {
+        let src = self.slot();
+        let pic = &src.pic;
+        let has_chroma = pic.p.layout != DAV1D_PIXEL_LAYOUT_I400;
+        let ss_ver = (pic.p.layout == DAV1D_PIXEL_LAYOUT_I420) as usize;
+        let ss_hor = (pic.p.layout != DAV1D_PIXEL_LAYOUT_I444) as usize;
+        let bytes_per_pixel = (pic.p.bpc as usize + 7) / 8;
+        let mut planes = Vec::new();
+        let mut data = [None; 3];
+        let n_planes = if has_chroma { 3 } else { 1 };
+        for (pl, (dst, src)) in data.iter_mut().zip(pic.data).enumerate().take(n_planes) {
+            let stride = pic.stride[(pl != 0) as usize] as usize;
+            let (w, h) = if pl == 0 {
+                (pic.p.w as usize, pic.p.h as usize)
+            } else {
+                (
+                    (pic.p.w as usize + ss_hor) >> ss_hor,
+                    (pic.p.h as usize + ss_ver) >> ss_ver,
+                )
+            };
+            assert!(stride >= w * bytes_per_pixel);
+            // Padded like the default picture allocator pads them.
+            let len = stride * ((h + 127) & !127);
+            let mut plane = vec![Aligned([0; 64]); (len + 63) / 64];
+            // Safety: The source plane has `h` rows `stride` bytes apart.
+            unsafe {
+                let src = src.unwrap().as_ptr().cast::<u8>();
+                ptr::copy_nonoverlapping(src, plane.as_mut_ptr().cast(), stride * h);
+            }
+            *dst = NonNull::new(plane.as_mut_ptr().cast::<c_void>());
+            planes.push(plane);
+        }
+        // Safety: `frame_hdr` is set in slots returned by the decoder.
+        let mut frame_hdr = Box::new(unsafe { pic.frame_hdr.unwrap().as_ref().clone() });
+
+        let (w4, h4) = (src.w4 as usize, src.h4 as usize);
+        // Safety: The maps have `w4 * h4` and `w4 / 2 * h4 / 2` entries if set.
+        let mut segment_ids = unsafe { copy_map(src.segment_ids, w4 * h4) };
+        let mut mvs = unsafe { copy_map(src.mvs, w4 / 2 * h4 / 2) };
+
+        let slot = Dav1dRefSlot {
+            pic: Dav1dPicture {
+                frame_hdr: NonNull::new(&mut *frame_hdr),
+                data,
+                stride: pic.stride,
+                p: Dav1dPictureParameters {
+                    w: pic.p.w,
+                    h: pic.p.h,
+                    layout: pic.p.layout,
+                    bpc: pic.p.bpc,
+                },
+                ..Default::default()
+            },
+            cdf: src.cdf,
+            showable: src.showable,
+            refpoc: src.refpoc,
+            w4: src.w4,
+            h4: src.h4,
+            segment_ids: if src.segment_ids.is_null() {
+                ptr::null_mut()
+            } else {
+                segment_ids.as_mut_ptr()
+            },
+            mvs: if src.mvs.is_null() {
+                ptr::null_mut()
+            } else {
+                mvs.as_mut_ptr()
+            },
+        };
+        Self::Copy {
+            slot,
+            _planes: planes,
+            _frame_hdr: frame_hdr,
+            _segment_ids: segment_ids,
+            _mvs: mvs,
+        }
+    }
+}
+
+/// # Safety
+///
+/// `map` must be null or point to `len` entries.
+unsafe fn copy_map<T: Copy>(map: *const T, len: usize) -> Vec<T> {
+    if map.is_null() {
+        return Vec::new();
+    }
+    slice::from_raw_parts(map, len).to_vec()
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Self::Decoder(slot) = self {
+            // Safety: `slot` was returned by the decoder.
+            unsafe { dav1d_ref_slot_unref(slot) };
+        }
+    }
+}
+
+fn parse_seq_hdr(data: &[u8]) -> Dav1dSequenceHeader {
+    let mut seq_hdr = MaybeUninit::uninit();
+    // Safety: All pointers are valid, and `seq_hdr` is filled on success.
+    unsafe {
+        let res = dav1d_parse_sequence_header(seq_hdr.as_mut_ptr(), data.as_ptr(), data.len());
+        assert_eq!(res.0, 0);
+        seq_hdr.assume_init()
+    }
+}
+
+/// The settings for comparing decoded pictures with the reconstructed ones.
+fn settings_without_grain(n_threads: i32, max_frame_delay: i32) -> Dav1dSettings {
+    let mut s = settings(n_threads, max_frame_delay);
+    s.apply_grain = 0;
+    s
+}
+
+/// Decodes the frame at the start of `data` from `slots`,
+/// returning the error code, or its header and state.
+fn decode_frame(
+    dec: &Decoder,
+    seq_hdr: &Dav1dSequenceHeader,
+    data: &mut Dav1dData,
+    slots: &[Option<Rc<Slot>>; 8],
+) -> Result<(Dav1dFrameHeader, Slot), i32> {
+    let refs: [*const Dav1dRefSlot; 8] = array::from_fn(|i| {
+        slots[i]
+            .as_ref()
+            .map_or(ptr::null(), |slot| slot.slot() as *const _)
+    });
+    let mut frame_hdr = MaybeUninit::uninit();
+    let mut out = MaybeUninit::uninit();
+    // Safety: All pointers are valid, and `frame_hdr` and `out` are filled on success.
+    unsafe {
+        let res = dav1d_decode_frame_stateless(
+            dec.0,
+            seq_hdr,
+            data,
+            refs.as_ptr(),
+            frame_hdr.as_mut_ptr(),
+            out.as_mut_ptr(),
+        );
+        if res.0 != 0 {
+            return Err(res.0);
+        }
+        Ok((frame_hdr.assume_init(), Slot::Decoder(out.assume_init())))
+    }
+}
+
+/// Decodes all of `frames` frame by frame from `slots`, updating them,
+/// and returns the pictures that are shown.
+/// If `copy`, each slot is copied to buffers owned by the caller first.
+fn decode_stateless(
+    s: &Dav1dSettings,
+    seq_hdr: &Dav1dSequenceHeader,
+    frames: &[IvfFrame],
+    slots: &mut [Option<Rc<Slot>>; 8],
+    copy: bool,
+) -> Vec<Picture> {
+    let dec = Decoder::open(s);
+    let mut out = Vec::new();
+    for frame in frames {
+        let mut data = frame.to_data();
+        while data.sz > 0 {
+            let (frame_hdr, slot) = decode_frame(&dec, seq_hdr, &mut data, slots).unwrap();
+            // Safety: `slot.pic` is a picture returned by the decoder.
+            let pic = unsafe { Picture::new(&slot.slot().pic) };
+            if frame_hdr.show_frame != 0 || frame_hdr.show_existing_frame != 0 {
+                out.push(pic);
+            }
+            // Safety: `frame_hdr` is set in slots returned by the decoder.
+            let frame_type = unsafe { slot.slot().pic.frame_hdr.unwrap().as_ref().frame_type };
+            let refresh =
+                if frame_hdr.show_existing_frame != 0 && frame_type == DAV1D_FRAME_TYPE_KEY {
+                    0xff
+                } else {
+                    frame_hdr.refresh_frame_flags
+                };
+            let slot = Rc::new(if copy { slot.to_copy() } else { slot });
+            for (i, r#ref) in slots.iter_mut().enumerate() {
+                if refresh & (1 << i) != 0 {
+                    *r#ref = Some(slot.clone());
+                }
+            }
+        }
+        // Safety: `data` is valid.
+        unsafe { dav1d_data_unref(&mut data) };
+    }
+    out
+}
+
+/// Only the pixels are compared, as the metadata of the pictures comes from
+/// the data they were decoded from, which is split differently here.
+fn pixels(pics: &[Picture]) -> Vec<(i32, i32, u64)> {
+    pics.iter().map(|pic| (pic.w, pic.h, pic.hash)).collect()
+}
+
+fn check_stateless(file: &str, n_threads: i32, copy: bool) {
+    let frames = read_ivf(&test_data(file));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    let all = decode_all(&settings_without_grain(1, 1), &frames);
+    let s = settings_without_grain(n_threads, 1);
+    let out = decode_stateless(&s, &seq_hdr, &frames, &mut Default::default(), copy);
+    assert_eq!(pixels(&out), pixels(&all));
+}
+
+#[test]
+fn stateless() {
+    check_stateless(FILE, 1, false);
+    check_stateless(MFMV_FILE, 1, false);
+}
+
+#[test]
+fn stateless_tile_threaded() {
+    check_stateless(MFMV_FILE, 4, false);
+}
+
+#[test]
+fn stateless_from_copies() {
+    check_stateless(FILE, 1, true);
+    check_stateless(MFMV_FILE, 1, true);
+}
+
+/// A snapshot taken with `dav1d_state_snapshot`, released when dropped.
+struct Snapshot(*mut Dav1dStateSnapshot);
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        // Safety: `self.0` was taken with `dav1d_state_snapshot`.
+        unsafe { dav1d_state_snapshot_unref(&mut self.0) };
+    }
+}
+
+#[test]
+fn stateless_from_snapshot() {
+    let frames = read_ivf(&test_data(MFMV_FILE));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    let s = settings_without_grain(1, 1);
+    let all = decode_all(&s, &frames);
+    let at = frames.len() / 2;
+
+    let mut dec = Decoder::open(&s);
+    let mut out = Vec::new();
+    for frame in &frames[..=at] {
+        dec.send(frame, &mut out);
+    }
+    let mut snapshot = Snapshot(ptr::null_mut());
+    // Safety: `dec.0` is open.
+    assert_eq!(unsafe { dav1d_state_snapshot(dec.0, &mut snapshot.0) }.0, 0);
+    let mut slots: [Option<Rc<Slot>>; 8] = Default::default();
+    for (i, slot) in slots.iter_mut().enumerate() {
+        let mut out = MaybeUninit::uninit();
+        // Safety: `snapshot.0` is valid and `out` is filled on success.
+        let res =
+            unsafe { dav1d_state_snapshot_get_ref_slot(snapshot.0, i as i32, out.as_mut_ptr()) };
+        assert_eq!(res.0, 0, "slot {i}");
+        // Safety: `out` was filled.
+        *slot = Some(Rc::new(Slot::Decoder(unsafe { out.assume_init() })));
+    }
+
+    let expected: Vec<_> = all
+        .iter()
+        .filter(|pic| pic.timestamp > frames[at].timestamp)
+        .cloned()
+        .collect();
+    let out = decode_stateless(&s, &seq_hdr, &frames[at + 1..], &mut slots, false);
+    assert_eq!(pixels(&out), pixels(&expected));
+}
+
+#[test]
+fn stateless_rejects_frame_threading() {
+    let frames = read_ivf(&test_data(FILE));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    let dec = Decoder::open(&settings(4, 4));
+    let mut data = frames[0].to_data();
+    let res = decode_frame(&dec, &seq_hdr, &mut data, &Default::default());
+    assert_eq!(res.err(), Some(-libc::EINVAL));
+    // Safety: `data` is valid.
+    unsafe { dav1d_data_unref(&mut data) };
+}
+
+#[test]
+fn stateless_rejects_missing_data() {
+    let frames = read_ivf(&test_data(FILE));
+    let seq_hdr = parse_seq_hdr(&frames[0].data);
+    let dec = Decoder::open(&settings(1, 1));
+    // The temporal unit cut off in the middle of its frame.
+    let cut = IvfFrame {
+        timestamp: 0,
+        data: frames[0].data[..frames[0].data.len() / 2].to_vec(),
+    };
+    let mut data = cut.to_data();
+    assert!(decode_frame(&dec, &seq_hdr, &mut data, &Default::default()).is_err());
+    // Safety: `data` is valid.
+    unsafe { dav1d_data_unref(&mut data) };
+}
@@ -1,13 +1,22 @@
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_void;
+
 // NOTE: temporary code to support Linux and macOS, should be removed eventually
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
         extern "C" {
+            pub static mut stdin: *mut libc::FILE;
+
             pub static mut stdout: *mut libc::FILE;
 
             pub static mut stderr: *mut libc::FILE;
         }
     } else if #[cfg(target_os = "macos")] {
         extern "C" {
+            #[link_name = "__stdinp"]
+            pub static mut stdin: *mut libc::FILE;
+
             #[link_name = "__stdoutp"]
             pub static mut stdout: *mut libc::FILE;
 
@@ -16,3 +25,124 @@ cfg_if::cfg_if! {
         }
     }
 }
+
+/// The callbacks of a read-only `FILE` opened with [`fopen_cookie`].
+pub struct CookieFunctions {
+    /// Reads up to `size` bytes into `buf`, returning the number read, or -1 on error.
+    pub read: unsafe fn(cookie: *mut c_void, buf: *mut c_char, size: usize) -> isize,
+    /// Seeks like `lseek`, returning the new position, or -1 on error.
+    pub seek: unsafe fn(cookie: *mut c_void, offset: i64, whence: c_int) -> i64,
+    pub close: unsafe fn(cookie: *mut c_void) -> c_int,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "linux")] {
+        #[repr(C)]
+        struct cookie_io_functions_t {
+            read: Option<unsafe extern "C" fn(*mut c_void, *mut c_char, usize) -> isize>,
+            write: Option<unsafe extern "C" fn(*mut c_void, *const c_char, usize) -> isize>,
+            seek: Option<unsafe extern "C" fn(*mut c_void, *mut i64, c_int) -> c_int>,
+            close: Option<unsafe extern "C" fn(*mut c_void) -> c_int>,
+        }
+
+        extern "C" {
+            fn fopencookie(
+                cookie: *mut c_void,
+                mode: *const c_char,
+                io_funcs: cookie_io_functions_t,
+            ) -> *mut libc::FILE;
+        }
+
+        /// The cookie passed to `fopencookie`.
+        struct Cookie {
+            cookie: *mut c_void,
+            functions: CookieFunctions,
+        }
+
+        unsafe extern "C" fn cookie_read(cookie: *mut c_void, buf: *mut c_char, size: usize) -> isize {
+            let c = &*(cookie as *const Cookie);
+            (c.functions.read)(c.cookie, buf, size)
+        }
+
+        unsafe extern "C" fn cookie_seek(cookie: *mut c_void, offset: *mut i64, whence: c_int) -> c_int {
+            let c = &*(cookie as *const Cookie);
+            let pos = (c.functions.seek)(c.cookie, *offset, whence);
+            if pos < 0 {
+                return -1;
+            }
+            *offset = pos;
+            0
+        }
+
+        unsafe extern "C" fn cookie_close(cookie: *mut c_void) -> c_int {
+            let c = Box::from_raw(cookie as *mut Cookie);
+            (c.functions.close)(c.cookie)
+        }
+
+        /// Opens a read-only `FILE` whose reads and seeks go through `functions`.
+        pub unsafe fn fopen_cookie(cookie: *mut c_void, functions: CookieFunctions) -> *mut libc::FILE {
+            let c = Box::into_raw(Box::new(Cookie { cookie, functions }));
+            let f = fopencookie(
+                c as *mut c_void,
+                b"rb\0" as *const u8 as *const c_char,
+                cookie_io_functions_t {
+                    read: Some(cookie_read),
+                    write: None,
+                    seek: Some(cookie_seek),
+                    close: Some(cookie_close),
+                },
+            );
+            if f.is_null() {
+                drop(Box::from_raw(c));
+            }
+            f
+        }
+    } else if #[cfg(target_os = "macos")] {
+        extern "C" {
+            fn funopen(
+                cookie: *const c_void,
+                readfn: Option<unsafe extern "C" fn(*mut c_void, *mut c_char, c_int) -> c_int>,
+                writefn: Option<unsafe extern "C" fn(*mut c_void, *const c_char, c_int) -> c_int>,
+                seekfn: Option<unsafe extern "C" fn(*mut c_void, i64, c_int) -> i64>,
+                closefn: Option<unsafe extern "C" fn(*mut c_void) -> c_int>,
+            ) -> *mut libc::FILE;
+        }
+
+        /// The cookie passed to `funopen`.
+        struct Cookie {
+            cookie: *mut c_void,
+            functions: CookieFunctions,
+        }
+
+        unsafe extern "C" fn cookie_read(cookie: *mut c_void, buf: *mut c_char, size: c_int) -> c_int {
+            let c = &*(cookie as *const Cookie);
+            (c.functions.read)(c.cookie, buf, size as usize) as c_int
+        }
+
+        unsafe extern "C" fn cookie_seek(cookie: *mut c_void, offset: i64, whence: c_int) -> i64 {
+            let c = &*(cookie as *const Cookie);
+            (c.functions.seek)(c.cookie, offset, whence)
+        }
+
+        unsafe extern "C" fn cookie_close(cookie: *mut c_void) -> c_int {
+            let c = Box::from_raw(cookie as *mut Cookie);
+            (c.functions.close)(c.cookie)
+        }
+
+        /// Opens a read-only `FILE` whose reads and seeks go through `functions`.
+        pub unsafe fn fopen_cookie(cookie: *mut c_void, functions: CookieFunctions) -> *mut libc::FILE {
+            let c = Box::into_raw(Box::new(Cookie { cookie, functions }));
+            let f = funopen(
+                c as *const c_void,
+                Some(cookie_read),
+                None,
+                Some(cookie_seek),
+                Some(cookie_close),
+            );
+            if f.is_null() {
+                drop(Box::from_raw(c));
+            }
+            f
+        }
+    }
+}
@@ -10,33 +10,57 @@ mod compat {
 } // mod compat
 mod input {
     mod annexb;
+    pub mod avif;
     pub mod input;
+    mod isobmff;
     mod ivf;
+    mod matroska;
+    mod mp4;
+    mod mpegts;
+    mod rtp;
     mod section5;
+    pub mod y4m2_reader;
 } // mod input
 mod output {
+    mod annexb;
+    pub mod compare;
+    mod framehash;
+    mod ivf;
     mod md5;
     mod null;
     pub mod output;
+    pub mod overlay;
+    pub mod remux;
+    pub mod sb_heatmap;
+    mod section5;
+    mod xxhash;
     mod y4m2;
     mod yuv;
 } // mod output
 mod dav1d_cli_parse;
 
+use crate::compat::errno::errno_location;
 use crate::compat::stdio::stderr;
 use crate::dav1d_cli_parse::parse;
 use crate::dav1d_cli_parse::CLISettings;
 use crate::dav1d_cli_parse::REALTIME_CUSTOM;
 use crate::dav1d_cli_parse::REALTIME_DISABLE;
+use crate::input::avif::avif_decode;
+use crate::input::avif::avif_probe;
 use crate::input::input::input_close;
 use crate::input::input::input_open;
 use crate::input::input::input_read;
 use crate::input::input::DemuxerContext;
+use crate::output::compare::compare_open;
 use crate::output::output::output_close;
+use crate::output::output::output_is_bitstream;
 use crate::output::output::output_open;
 use crate::output::output::output_verify;
 use crate::output::output::output_write;
 use crate::output::output::MuxerContext;
+use crate::output::overlay::overlay_draw;
+use crate::output::remux::remux;
+use crate::output::sb_heatmap::sb_heatmap_write;
 use libc::fclose;
 use libc::fflush;
 use libc::fileno;
@@ -57,11 +81,16 @@ use libc::EINVAL;
 use rav1d::include::dav1d::common::Dav1dDataProps;
 use rav1d::include::dav1d::common::Dav1dUserData;
 use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::Dav1dCallbacks;
 use rav1d::include::dav1d::dav1d::Dav1dContext;
 use rav1d::include::dav1d::dav1d::Dav1dLogger;
 use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::dav1d::Dav1dSyntaxElement;
+use rav1d::include::dav1d::dav1d::Dav1dSyntaxTrace;
 use rav1d::include::dav1d::dav1d::DAV1D_DECODEFRAMETYPE_ALL;
 use rav1d::include::dav1d::dav1d::DAV1D_INLOOPFILTER_NONE;
+use rav1d::include::dav1d::dav1d::DAV1D_SYNTAX_HEADER;
+use rav1d::include::dav1d::dav1d::DAV1D_SYNTAX_LABEL;
 use rav1d::include::dav1d::headers::Dav1dColorPrimaries;
 use rav1d::include::dav1d::headers::Dav1dSequenceHeader;
 use rav1d::include::dav1d::headers::Dav1dSequenceHeaderOperatingParameterInfo;
@@ -87,9 +116,11 @@ use rav1d::Dav1dResult;
 use std::ffi::c_char;
 use std::ffi::c_double;
 use std::ffi::c_int;
+use std::ffi::c_longlong;
 use std::ffi::c_uint;
 use std::ffi::c_ulonglong;
 use std::ffi::c_void;
+use std::ptr;
 use std::ptr::NonNull;
 
 unsafe fn get_time_nanos() -> u64 {
@@ -148,6 +179,40 @@ unsafe fn synchronize(
     }
 }
 
+unsafe extern "C" fn write_syntax_element(cookie: *mut c_void, e: *const Dav1dSyntaxElement) {
+    let file = cookie as *mut libc::FILE;
+    let e = &*e;
+    // Each element is written with a single call, as stdio locks the file per call,
+    // so that lines from different threads don't get mixed.
+    match e.source {
+        DAV1D_SYNTAX_HEADER => fprintf(
+            file,
+            b"hdr @%lld %s(%d) = %lld\n\0" as *const u8 as *const c_char,
+            e.bit_pos as c_longlong,
+            e.name,
+            e.n_bits,
+            e.value as c_longlong,
+        ),
+        DAV1D_SYNTAX_LABEL => fprintf(
+            file,
+            b"hdr @%lld -- %s\n\0" as *const u8 as *const c_char,
+            e.bit_pos as c_longlong,
+            e.name,
+        ),
+        _ => fprintf(
+            file,
+            b"tile %d sb %d,%d b %d,%d %s = %lld\n\0" as *const u8 as *const c_char,
+            e.tile,
+            e.sbx,
+            e.sby,
+            e.bx,
+            e.by,
+            e.name,
+            e.value as c_longlong,
+        ),
+    };
+}
+
 unsafe fn print_stats(istty: c_int, n: c_uint, num: c_uint, elapsed: u64, i_fps: c_double) {
     let mut buf: [c_char; 80] = [0; 80];
     let mut b: *mut c_char = buf.as_mut_ptr();
@@ -278,6 +343,11 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         demuxer: 0 as *const c_char,
         muxer: 0 as *const c_char,
         frametimes: 0 as *const c_char,
+        syntaxtrace: 0 as *const c_char,
+        sbheatmap: 0 as *const c_char,
+        compare: 0 as *const c_char,
+        compare_format: 0 as *const c_char,
+        alpha: 0 as *const c_char,
         verify: 0 as *const c_char,
         limit: 0,
         skip: 0,
@@ -286,6 +356,8 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         realtime_fps: 0.,
         realtime_cache: 0,
         neg_stride: 0,
+        overlay: 0,
+        filter_obus: 0,
     };
     let mut lib_settings: Dav1dSettings = Dav1dSettings {
         n_threads: 0,
@@ -304,11 +376,14 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         output_invisible_frames: 0,
         inloop_filters: DAV1D_INLOOPFILTER_NONE,
         decode_frame_type: DAV1D_DECODEFRAMETYPE_ALL,
-        reserved: [0; 16],
+        export_flags: 0,
+        reserved: Default::default(),
+        callbacks: ptr::null(),
     };
     let mut in_0: *mut DemuxerContext = 0 as *mut DemuxerContext;
     let mut out: *mut MuxerContext = 0 as *mut MuxerContext;
     let mut p = Default::default();
+    let mut overlay_buf = Vec::new();
     let mut c: *mut Dav1dContext = 0 as *mut Dav1dContext;
     let mut data: Dav1dData = Dav1dData {
         data: None,
@@ -334,6 +409,10 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
     let mut elapsed: u64 = 0;
     let i_fps: c_double;
     let mut frametimes: *mut libc::FILE = 0 as *mut libc::FILE;
+    let mut syntaxtrace: *mut libc::FILE = 0 as *mut libc::FILE;
+    let mut callbacks = Dav1dCallbacks::default();
+    let mut sbheatmap: *mut libc::FILE = 0 as *mut libc::FILE;
+    let mut compare = None;
     let version: *const c_char = dav1d_version();
     if strcmp(version, b"966d63c1\0" as *const u8 as *const c_char) != 0 {
         fprintf(
@@ -349,6 +428,9 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         lib_settings.allocator.alloc_picture_callback = Some(picture_alloc);
         lib_settings.allocator.release_picture_callback = Some(picture_release);
     }
+    if cli_settings.demuxer.is_null() && avif_probe(cli_settings.inputfile) {
+        return avif_decode(&cli_settings, &mut lib_settings);
+    }
     res = input_open(
         &mut in_0,
         cli_settings.demuxer,
@@ -465,9 +547,55 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
             );
         }
     }
+    if output_is_bitstream(cli_settings.muxer, cli_settings.outputfile) {
+        res = remux(&cli_settings, &lib_settings, in_0, &mut data, &timebase);
+        input_close(in_0);
+        return res;
+    }
     if cli_settings.limit != 0 as c_int as c_uint && cli_settings.limit < total {
         total = cli_settings.limit;
     }
+    if !(cli_settings.syntaxtrace).is_null() {
+        syntaxtrace = fopen(
+            cli_settings.syntaxtrace,
+            b"w\0" as *const u8 as *const c_char,
+        );
+        if syntaxtrace.is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                cli_settings.syntaxtrace,
+                strerror(*errno_location()),
+            );
+            return 1 as c_int;
+        }
+        callbacks.syntax_trace = Dav1dSyntaxTrace {
+            cookie: syntaxtrace as *mut c_void,
+            callback: Some(write_syntax_element),
+        };
+        lib_settings.callbacks = &callbacks;
+    }
+    if !(cli_settings.sbheatmap).is_null() {
+        sbheatmap = fopen(
+            cli_settings.sbheatmap,
+            b"wb\0" as *const u8 as *const c_char,
+        );
+        if sbheatmap.is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                cli_settings.sbheatmap,
+                strerror(*errno_location()),
+            );
+            return 1 as c_int;
+        }
+    }
+    if !(cli_settings.compare).is_null() {
+        compare = compare_open(cli_settings.compare, cli_settings.compare_format);
+        if compare.is_none() {
+            return 1 as c_int;
+        }
+    }
     res = dav1d_open(&mut c, &mut lib_settings).0;
     if res != 0 {
         return 1 as c_int;
@@ -534,6 +662,7 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
                     cli_settings.outputfile,
                     &mut p.p,
                     fps.as_mut_ptr() as *const c_uint,
+                    cli_settings.verify,
                 );
                 if res < 0 {
                     if !frametimes.is_null() {
@@ -542,6 +671,21 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
                     return 1 as c_int;
                 }
             }
+            if !sbheatmap.is_null() {
+                res = sb_heatmap_write(sbheatmap, &p);
+                if res < 0 {
+                    break;
+                }
+            }
+            if let Some(compare) = &mut compare {
+                res = compare.frame(&p);
+                if res < 0 {
+                    break;
+                }
+            }
+            if cli_settings.overlay != 0 {
+                overlay_draw(&mut p, cli_settings.overlay, &mut overlay_buf);
+            }
             res = output_write(out, &mut p);
             if res < 0 {
                 break;
@@ -597,6 +741,7 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
                         cli_settings.outputfile,
                         &mut p.p,
                         fps.as_mut_ptr() as *const c_uint,
+                        cli_settings.verify,
                     );
                     if res < 0 {
                         if !frametimes.is_null() {
@@ -605,6 +750,21 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
                         return 1 as c_int;
                     }
                 }
+                if !sbheatmap.is_null() {
+                    res = sb_heatmap_write(sbheatmap, &p);
+                    if res < 0 {
+                        break;
+                    }
+                }
+                if let Some(compare) = &mut compare {
+                    res = compare.frame(&p);
+                    if res < 0 {
+                        break;
+                    }
+                }
+                if cli_settings.overlay != 0 {
+                    overlay_draw(&mut p, cli_settings.overlay, &mut overlay_buf);
+                }
                 res = output_write(out, &mut p);
                 if res < 0 {
                     break;
@@ -644,7 +804,16 @@ unsafe fn main_0(argc: c_int, argv: *const *mut c_char) -> c_int {
         fprintf(stderr, b"No data decoded\n\0" as *const u8 as *const c_char);
         res = 1 as c_int;
     }
+    if let Some(compare) = compare {
+        res |= compare.close();
+    }
     dav1d_close(&mut c);
+    if !syntaxtrace.is_null() {
+        fclose(syntaxtrace);
+    }
+    if !sbheatmap.is_null() {
+        fclose(sbheatmap);
+    }
     return if res == 0 { 0 as c_int } else { 1 as c_int };
 }
 
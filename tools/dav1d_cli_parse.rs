@@ -17,6 +17,8 @@ use rav1d::include::dav1d::dav1d::DAV1D_DECODEFRAMETYPE_ALL;
 use rav1d::include::dav1d::dav1d::DAV1D_DECODEFRAMETYPE_INTRA;
 use rav1d::include::dav1d::dav1d::DAV1D_DECODEFRAMETYPE_KEY;
 use rav1d::include::dav1d::dav1d::DAV1D_DECODEFRAMETYPE_REFERENCE;
+use rav1d::include::dav1d::dav1d::DAV1D_EXPORT_BLOCK_INFO;
+use rav1d::include::dav1d::dav1d::DAV1D_EXPORT_SB_BITS;
 use rav1d::include::dav1d::dav1d::DAV1D_INLOOPFILTER_ALL;
 use rav1d::include::dav1d::dav1d::DAV1D_INLOOPFILTER_CDEF;
 use rav1d::include::dav1d::dav1d::DAV1D_INLOOPFILTER_DEBLOCK;
@@ -31,6 +33,7 @@ use std::ffi::c_int;
 use std::ffi::c_uint;
 use std::ffi::c_ulong;
 use std::ffi::c_void;
+use std::ffi::CStr;
 use std::process::exit;
 
 extern "C" {
@@ -55,6 +58,14 @@ pub struct CLISettings {
     pub demuxer: *const c_char,
     pub muxer: *const c_char,
     pub frametimes: *const c_char,
+    pub syntaxtrace: *const c_char,
+    pub sbheatmap: *const c_char,
+    /// Reference `.y4m` or raw `.yuv` file to compare the output frames against.
+    pub compare: *const c_char,
+    /// Format of a raw `compare` reference, as `WxH[:layout[:bitdepth]]`.
+    pub compare_format: *const c_char,
+    /// Output file for the alpha plane of an AVIF image.
+    pub alpha: *const c_char,
     pub verify: *const c_char,
     pub limit: c_uint,
     pub skip: c_uint,
@@ -63,6 +74,10 @@ pub struct CLISettings {
     pub realtime_fps: c_double,
     pub realtime_cache: c_uint,
     pub neg_stride: c_int,
+    /// Bitmask of `OVERLAY_*` debug visualizations to draw onto the output frames.
+    pub overlay: c_uint,
+    /// Bitmask of `OBU_FILTER_*` OBUs to drop when remuxing with a bitstream muxer.
+    pub filter_obus: c_uint,
 }
 
 #[repr(C)]
@@ -71,6 +86,13 @@ pub struct EnumParseTable {
     pub val: c_int,
 }
 
+pub const ARG_FILTER_OBUS: arg = 280;
+pub const ARG_ALPHA: arg = 279;
+pub const ARG_COMPARE_FORMAT: arg = 278;
+pub const ARG_COMPARE: arg = 277;
+pub const ARG_SB_HEATMAP: arg = 276;
+pub const ARG_OVERLAY: arg = 275;
+pub const ARG_SYNTAX_TRACE: arg = 274;
 pub const ARG_DECODE_FRAME_TYPE: arg = 273;
 pub const ARG_INLOOP_FILTERS: arg = 272;
 pub const ARG_OUTPUT_INVISIBLE: arg = 271;
@@ -101,7 +123,7 @@ pub type CpuMask = c_uint;
 static short_opts: [c_char; 11] =
     unsafe { *::core::mem::transmute::<&[u8; 11], &[c_char; 11]>(b"i:o:vql:s:\0") };
 
-static mut long_opts: [option; 25] = [
+static mut long_opts: [option; 32] = [
     {
         option {
             name: b"input\0" as *const u8 as *const c_char,
@@ -294,6 +316,62 @@ static mut long_opts: [option; 25] = [
             val: ARG_DECODE_FRAME_TYPE as c_int,
         }
     },
+    {
+        option {
+            name: b"syntaxtrace\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_SYNTAX_TRACE as c_int,
+        }
+    },
+    {
+        option {
+            name: b"overlay\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_OVERLAY as c_int,
+        }
+    },
+    {
+        option {
+            name: b"sbheatmap\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_SB_HEATMAP as c_int,
+        }
+    },
+    {
+        option {
+            name: b"compare\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_COMPARE as c_int,
+        }
+    },
+    {
+        option {
+            name: b"compareformat\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_COMPARE_FORMAT as c_int,
+        }
+    },
+    {
+        option {
+            name: b"alpha\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_ALPHA as c_int,
+        }
+    },
+    {
+        option {
+            name: b"filterobus\0" as *const u8 as *const c_char,
+            has_arg: 1 as c_int,
+            flag: 0 as *const c_int as *mut c_int,
+            val: ARG_FILTER_OBUS as c_int,
+        }
+    },
     {
         option {
             name: 0 as *const c_char,
@@ -318,7 +396,7 @@ unsafe extern "C" fn usage(app: *const c_char, reason: *const c_char, args: ...)
     );
     fprintf(
         stderr,
-        b"Supported options:\n --input/-i $file:     input file\n --output/-o $file:    output file (%%n, %%w or %%h will be filled in for per-frame files)\n --demuxer $name:      force demuxer type ('ivf', 'section5' or 'annexb'; default: detect from content)\n --muxer $name:        force muxer type ('md5', 'yuv', 'yuv4mpeg2' or 'null'; default: detect from extension)\n                       use 'frame' as prefix to write per-frame files; if filename contains %%n, will default to writing per-frame files\n --quiet/-q:           disable status messages\n --frametimes $file:   dump frame times to file\n --limit/-l $num:      stop decoding after $num frames\n --skip/-s $num:       skip decoding of the first $num frames\n --realtime [$fract]:  limit framerate, optional argument to override input framerate\n --realtimecache $num: set the size of the cache in realtime mode (default: 0)\n --version/-v:         print version and exit\n --threads $num:       number of threads (default: 0)\n --framedelay $num:    maximum frame delay, capped at $threads (default: 0);\n                       set to 1 for low-latency decoding\n --filmgrain $num:     enable film grain application (default: 1, except if muxer is md5 or xxh3)\n --oppoint $num:       select an operating point of a scalable AV1 bitstream (0 - 31)\n --alllayers $num:     output all spatial layers of a scalable AV1 bitstream (default: 1)\n --sizelimit $num:     stop decoding if the frame size exceeds the specified limit\n --strict $num:        whether to abort decoding on standard compliance violations\n                       that don't affect bitstream decoding (default: 1)\n --verify $md5:        verify decoded md5. implies --muxer md5, no output\n --cpumask $mask:      restrict permitted CPU instruction sets (0, 'sse2', 'ssse3', 'sse41', 'avx2' or 'avx512icl'; default: -1)\n --negstride:          use negative picture strides\n                       this is mostly meant as a developer option\n --outputinvisible $num: whether to output invisible (alt-ref) frames (default: 0)\n --inloopfilters $str: which in-loop filters to enable (none, (no)deblock, (no)cdef, (no)restoration or all; default: all)\n --decodeframetype $str: which frame types to decode (reference, intra, key or all; default: all)\n\0"
+        b"Supported options:\n --input/-i $file:     input file\n --output/-o $file:    output file (%%n, %%w or %%h will be filled in for per-frame files)\n --demuxer $name:      force demuxer type ('ivf', 'matroska', 'mp4', 'mpegts', 'rtp', 'section5' or 'annexb'; default: detect from content)\n --muxer $name:        force muxer type ('md5', 'xxh3', 'md5frames', 'xxh3frames', 'yuv', 'yuv4mpeg2', 'null',\n                       or 'ivf', 'section5' or 'annexb' to remux the bitstream without decoding it; default: detect from extension)\n                       use 'frame' as prefix to write per-frame files; if filename contains %%n, will default to writing per-frame files\n --quiet/-q:           disable status messages\n --frametimes $file:   dump frame times to file\n --syntaxtrace $file:  dump every decoded syntax element to file (requires the syntax_trace feature)\n --limit/-l $num:      stop decoding after $num frames\n --skip/-s $num:       skip decoding of the first $num frames\n --realtime [$fract]:  limit framerate, optional argument to override input framerate\n --realtimecache $num: set the size of the cache in realtime mode (default: 0)\n --version/-v:         print version and exit\n --threads $num:       number of threads (default: 0)\n --framedelay $num:    maximum frame delay, capped at $threads (default: 0);\n                       set to 1 for low-latency decoding\n --filmgrain $num:     enable film grain application (default: 1, except if muxer is md5 or xxh3)\n --oppoint $num:       select an operating point of a scalable AV1 bitstream (0 - 31)\n --alllayers $num:     output all spatial layers of a scalable AV1 bitstream (default: 1)\n --sizelimit $num:     stop decoding if the frame size exceeds the specified limit\n --strict $num:        whether to abort decoding on standard compliance violations\n                       that don't affect bitstream decoding (default: 1)\n --verify $md5:        verify decoded md5. implies --muxer md5, no output\n                       with --muxer md5frames or xxh3frames, $md5 is a file with the checksums of each frame\n --cpumask $mask:      restrict permitted CPU instruction sets (0, 'sse2', 'ssse3', 'sse41', 'avx2' or 'avx512icl'; default: -1)\n --negstride:          use negative picture strides\n                       this is mostly meant as a developer option\n --outputinvisible $num: whether to output invisible (alt-ref) frames (default: 0)\n --inloopfilters $str: which in-loop filters to enable (none, (no)deblock, (no)cdef, (no)restoration or all; default: all)\n --decodeframetype $str: which frame types to decode (reference, intra, key or all; default: all)\n --overlay $str:       draw debug visualizations onto the output frames, a comma-separated list of\n                       partition, tx, mv, mode or all\n --sbheatmap $file:    write a heatmap of the bits of each superblock of each output frame to file,\n                       as a stream of binary PPM images\n --compare $file:      print the PSNR and SSIM of each output frame and their averages against\n                       a reference .y4m or raw .yuv file\n --compareformat $fmt: format of a raw reference, as WxH[:layout[:bitdepth]] with layout 400, 420,\n                       422 or 444; parts left out default to those of the decoded frames\n --alpha $file:        write the alpha plane of an AVIF image to file, as a monochrome image\n --filterobus $str:    OBUs to drop when remuxing, a comma-separated list of metadata, padding,\n                       redundant (frame headers) or layers (outside of the operating point of --oppoint)\n\0"
             as *const u8 as *const c_char,
     );
     exit(1 as c_int);
@@ -529,6 +607,100 @@ static mut decode_frame_type_tbl: [EnumParseTable; 4] = [
     },
 ];
 
+pub const OVERLAY_PARTITION: c_uint = 1 << 0;
+pub const OVERLAY_TX: c_uint = 1 << 1;
+pub const OVERLAY_MV: c_uint = 1 << 2;
+pub const OVERLAY_MODE: c_uint = 1 << 3;
+
+static mut overlay_tbl: [EnumParseTable; 5] = [
+    EnumParseTable {
+        str_0: b"partition\0" as *const u8 as *const c_char,
+        val: OVERLAY_PARTITION as c_int,
+    },
+    EnumParseTable {
+        str_0: b"tx\0" as *const u8 as *const c_char,
+        val: OVERLAY_TX as c_int,
+    },
+    EnumParseTable {
+        str_0: b"mv\0" as *const u8 as *const c_char,
+        val: OVERLAY_MV as c_int,
+    },
+    EnumParseTable {
+        str_0: b"mode\0" as *const u8 as *const c_char,
+        val: OVERLAY_MODE as c_int,
+    },
+    EnumParseTable {
+        str_0: b"all\0" as *const u8 as *const c_char,
+        val: (OVERLAY_PARTITION | OVERLAY_TX | OVERLAY_MV | OVERLAY_MODE) as c_int,
+    },
+];
+
+/// Parses a comma-separated list of the names in `tbl` into a bitmask.
+unsafe fn parse_flags(
+    optarg_0: *const c_char,
+    tbl: &[EnumParseTable],
+    expected: *const c_char,
+    option: c_int,
+    app: *const c_char,
+) -> c_uint {
+    let mut res = 0;
+    for name in CStr::from_ptr(optarg_0).to_bytes().split(|&c| c == b',') {
+        match tbl
+            .iter()
+            .find(|entry| CStr::from_ptr(entry.str_0).to_bytes() == name)
+        {
+            Some(entry) => res |= entry.val as c_uint,
+            None => error(app, optarg_0, option, expected),
+        }
+    }
+    res
+}
+
+unsafe fn parse_overlay(optarg_0: *const c_char, option: c_int, app: *const c_char) -> c_uint {
+    parse_flags(
+        optarg_0,
+        &overlay_tbl,
+        b"a comma-separated list of partition, tx, mv, mode or all\0" as *const u8 as *const c_char,
+        option,
+        app,
+    )
+}
+
+pub const OBU_FILTER_METADATA: c_uint = 1 << 0;
+pub const OBU_FILTER_PADDING: c_uint = 1 << 1;
+pub const OBU_FILTER_REDUNDANT: c_uint = 1 << 2;
+pub const OBU_FILTER_LAYERS: c_uint = 1 << 3;
+
+static mut obu_filter_tbl: [EnumParseTable; 4] = [
+    EnumParseTable {
+        str_0: b"metadata\0" as *const u8 as *const c_char,
+        val: OBU_FILTER_METADATA as c_int,
+    },
+    EnumParseTable {
+        str_0: b"padding\0" as *const u8 as *const c_char,
+        val: OBU_FILTER_PADDING as c_int,
+    },
+    EnumParseTable {
+        str_0: b"redundant\0" as *const u8 as *const c_char,
+        val: OBU_FILTER_REDUNDANT as c_int,
+    },
+    EnumParseTable {
+        str_0: b"layers\0" as *const u8 as *const c_char,
+        val: OBU_FILTER_LAYERS as c_int,
+    },
+];
+
+unsafe fn parse_obu_filter(optarg_0: *const c_char, option: c_int, app: *const c_char) -> c_uint {
+    parse_flags(
+        optarg_0,
+        &obu_filter_tbl,
+        b"a comma-separated list of metadata, padding, redundant or layers\0" as *const u8
+            as *const c_char,
+        option,
+        app,
+    )
+}
+
 unsafe fn parse_enum(
     optarg_0: *mut c_char,
     tbl: *const EnumParseTable,
@@ -740,6 +912,31 @@ pub unsafe fn parse(
                     *argv.offset(0),
                 ) as Dav1dDecodeFrameType;
             }
+            274 => {
+                (*cli_settings).syntaxtrace = optarg;
+            }
+            275 => {
+                (*cli_settings).overlay =
+                    parse_overlay(optarg, ARG_OVERLAY as c_int, *argv.offset(0));
+                (*lib_settings).export_flags |= DAV1D_EXPORT_BLOCK_INFO;
+            }
+            276 => {
+                (*cli_settings).sbheatmap = optarg;
+                (*lib_settings).export_flags |= DAV1D_EXPORT_SB_BITS;
+            }
+            277 => {
+                (*cli_settings).compare = optarg;
+            }
+            278 => {
+                (*cli_settings).compare_format = optarg;
+            }
+            279 => {
+                (*cli_settings).alpha = optarg;
+            }
+            280 => {
+                (*cli_settings).filter_obus =
+                    parse_obu_filter(optarg, ARG_FILTER_OBUS as c_int, *argv.offset(0));
+            }
             _ => {
                 usage(*argv.offset(0), 0 as *const c_char);
             }
@@ -769,10 +966,18 @@ pub unsafe fn parse(
                 (*cli_settings).muxer,
                 b"xxh3\0" as *const u8 as *const c_char,
             ) != 0
+            && strcmp(
+                (*cli_settings).muxer,
+                b"md5frames\0" as *const u8 as *const c_char,
+            ) != 0
+            && strcmp(
+                (*cli_settings).muxer,
+                b"xxh3frames\0" as *const u8 as *const c_char,
+            ) != 0
         {
             usage(
                 *argv.offset(0),
-                b"Verification (--verify) requires a checksum muxer (md5 or xxh3)\0" as *const u8
+                b"Verification (--verify) requires a checksum muxer (md5, xxh3, md5frames or xxh3frames)\0" as *const u8
                     as *const c_char,
             );
         }
@@ -790,6 +995,14 @@ pub unsafe fn parse(
             || strcmp(
                 (*cli_settings).muxer,
                 b"xxh3\0" as *const u8 as *const c_char,
+            ) == 0
+            || strcmp(
+                (*cli_settings).muxer,
+                b"md5frames\0" as *const u8 as *const c_char,
+            ) == 0
+            || strcmp(
+                (*cli_settings).muxer,
+                b"xxh3frames\0" as *const u8 as *const c_char,
             ) == 0)
     {
         (*lib_settings).apply_grain = 0 as c_int;
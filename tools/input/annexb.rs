@@ -1,7 +1,6 @@
 use crate::compat::errno::errno_location;
 use crate::compat::stdio::stderr;
-use libc::fclose;
-use libc::fopen;
+use crate::input::input::input_is_stream;
 use libc::fprintf;
 use libc::fread;
 use libc::fseeko;
@@ -39,6 +38,7 @@ pub struct Demuxer {
         unsafe extern "C" fn(
             *mut DemuxerPriv,
             *const c_char,
+            *mut libc::FILE,
             *mut c_uint,
             *mut c_uint,
             *mut c_uint,
@@ -227,27 +227,24 @@ unsafe extern "C" fn annexb_probe(data: *const u8) -> c_int {
 unsafe extern "C" fn annexb_open(
     c: *mut AnnexbInputContext,
     file: *const c_char,
+    f: *mut libc::FILE,
     fps: *mut c_uint,
     num_frames: *mut c_uint,
     timebase: *mut c_uint,
 ) -> c_int {
     let mut res;
     let mut len: usize = 0;
-    (*c).f = fopen(file, b"rb\0" as *const u8 as *const c_char);
-    if ((*c).f).is_null() {
-        fprintf(
-            stderr,
-            b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
-            file,
-            strerror(*errno_location()),
-        );
-        return -1;
-    }
+    (*c).f = f;
     *fps.offset(0) = 25 as c_int as c_uint;
     *fps.offset(1) = 1 as c_int as c_uint;
     *timebase.offset(0) = 25 as c_int as c_uint;
     *timebase.offset(1) = 1 as c_int as c_uint;
     *num_frames = 0 as c_int as c_uint;
+    if input_is_stream(file) {
+        // Streams can't be seeked back after counting the frames.
+        *num_frames = u32::MAX;
+        return 0 as c_int;
+    }
     loop {
         res = leb128((*c).f, &mut len);
         if res < 0 {
@@ -301,9 +298,7 @@ unsafe extern "C" fn annexb_read(c: *mut AnnexbInputContext, data: *mut Dav1dDat
     return 0 as c_int;
 }
 
-unsafe extern "C" fn annexb_close(c: *mut AnnexbInputContext) {
-    fclose((*c).f);
-}
+unsafe extern "C" fn annexb_close(_c: *mut AnnexbInputContext) {}
 
 #[no_mangle]
 pub static mut annexb_demuxer: Demuxer = Demuxer {
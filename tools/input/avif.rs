@@ -0,0 +1,1108 @@
+//! Decoding of AVIF images and image sequences.
+//!
+//! Unlike the demuxers, which feed a stream of frames to a single decoder,
+//! an image is made of items that are each decoded on their own:
+//! the primary item, which may be a `grid` of `av01` tiles that are stitched together,
+//! and its alpha auxiliary image. The `clap`, `irot` and `imir` transformations
+//! of the primary item are then applied to both, and they are written with the muxers,
+//! the alpha plane as a monochrome image to the file given with `--alpha`.
+//!
+//! Image sequences (`avis`) are ISOBMFF tracks, of which the color track and its alpha
+//! auxiliary track are decoded side by side, and transformed by the boxes of the sample entry
+//! of the color track.
+
+use crate::dav1d_cli_parse::CLISettings;
+use crate::input::input::input_is_stream;
+use crate::input::input::print_error;
+use crate::input::input::reduce_fraction;
+use crate::input::isobmff::boxes;
+use crate::input::isobmff::find_box;
+use crate::input::isobmff::Reader;
+use crate::input::mp4::auxiliary_of;
+use crate::input::mp4::parse_av01_trak;
+use crate::input::mp4::Av01Track;
+use crate::output::output::output_close;
+use crate::output::output::output_open;
+use crate::output::output::output_verify;
+use crate::output::output::output_write;
+use crate::output::output::MuxerContext;
+use libc::EAGAIN;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::Dav1dContext;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I422;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::src::lib::dav1d_close;
+use rav1d::src::lib::dav1d_data_create;
+use rav1d::src::lib::dav1d_data_unref;
+use rav1d::src::lib::dav1d_get_picture;
+use rav1d::src::lib::dav1d_open;
+use rav1d::src::lib::dav1d_picture_unref;
+use rav1d::src::lib::dav1d_send_data;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::ptr;
+use std::ptr::NonNull;
+
+const ALPHA_URNS: [&[u8]; 2] = [
+    b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha",
+    b"urn:mpeg:hevc:2015:auxid:1",
+];
+
+/// Whether an `auxC` or `auxi` box, which starts with the URN of its auxiliary type,
+/// describes an alpha plane.
+fn is_alpha(aux_type: &[u8]) -> bool {
+    let urn = aux_type.get(4..).unwrap_or_default();
+    let urn = urn.split(|&b| b == 0).next().unwrap();
+    ALPHA_URNS.contains(&urn)
+}
+
+struct Item {
+    id: u32,
+    kind: [u8; 4],
+    /// Whether the extents are offsets into the `idat` box rather than into the file.
+    in_idat: bool,
+    /// `(offset, length)`, where a length of 0 means up to the end of the file.
+    extents: Vec<(u64, u64)>,
+}
+
+/// An item reference of type `kind` from item `from` to items `to`.
+struct ItemRef {
+    kind: [u8; 4],
+    from: u32,
+    to: Vec<u32>,
+}
+
+/// The contents of the `meta` box.
+#[derive(Default)]
+struct Meta<'a> {
+    primary: u32,
+    items: Vec<Item>,
+    refs: Vec<ItemRef>,
+    properties: Vec<([u8; 4], &'a [u8])>,
+    /// The 1-based indices of the properties of each item.
+    associations: Vec<(u32, Vec<u16>)>,
+    idat: &'a [u8],
+}
+
+/// Reads a field of `size` bytes, as used for the offsets and lengths in `iloc`.
+fn read_sized(r: &mut Reader, size: u8) -> Option<u64> {
+    match size {
+        0 => Some(0),
+        4 => r.u32().map(|v| v as u64),
+        8 => r.u64(),
+        _ => None,
+    }
+}
+
+fn parse_iinf(data: &[u8], meta: &mut Meta) -> Option<()> {
+    let mut r = Reader::new(data);
+    let (version, _) = r.full_box()?;
+    if version == 0 {
+        r.u16()?;
+    } else {
+        r.u32()?;
+    }
+    for (_, infe) in boxes(r.rest()).filter(|(kind, _)| kind == b"infe") {
+        let mut r = Reader::new(infe);
+        let (version, _) = r.full_box()?;
+        // Item types only exist since version 2.
+        if version < 2 {
+            continue;
+        }
+        let id = if version == 2 {
+            r.u16()? as u32
+        } else {
+            r.u32()?
+        };
+        r.u16()?;
+        let kind = r.bytes(4)?.try_into().unwrap();
+        match meta.items.iter_mut().find(|item| item.id == id) {
+            Some(item) => item.kind = kind,
+            None => meta.items.push(Item {
+                id,
+                kind,
+                in_idat: false,
+                extents: Vec::new(),
+            }),
+        }
+    }
+    Some(())
+}
+
+fn parse_iloc(data: &[u8], meta: &mut Meta) -> Option<()> {
+    let mut r = Reader::new(data);
+    let (version, _) = r.full_box()?;
+    let sizes = r.u8()?;
+    let (offset_size, length_size) = (sizes >> 4, sizes & 0xf);
+    let sizes = r.u8()?;
+    let base_offset_size = sizes >> 4;
+    let index_size = if version == 1 || version == 2 {
+        sizes & 0xf
+    } else {
+        0
+    };
+    let count = if version < 2 {
+        r.u16()? as u32
+    } else {
+        r.u32()?
+    };
+    for _ in 0..count {
+        let id = if version < 2 {
+            r.u16()? as u32
+        } else {
+            r.u32()?
+        };
+        let construction_method = if version == 1 || version == 2 {
+            r.u16()? & 0xf
+        } else {
+            0
+        };
+        r.u16()?;
+        let base_offset = read_sized(&mut r, base_offset_size)?;
+        let mut extents = Vec::new();
+        for _ in 0..r.u16()? {
+            read_sized(&mut r, index_size)?;
+            let offset = read_sized(&mut r, offset_size)?;
+            let length = read_sized(&mut r, length_size)?;
+            extents.push((base_offset.checked_add(offset)?, length));
+        }
+        let in_idat = construction_method == 1;
+        match meta.items.iter_mut().find(|item| item.id == id) {
+            Some(item) => (item.in_idat, item.extents) = (in_idat, extents),
+            None => meta.items.push(Item {
+                id,
+                kind: [0; 4],
+                in_idat,
+                extents,
+            }),
+        }
+    }
+    Some(())
+}
+
+fn parse_iref(data: &[u8], meta: &mut Meta) -> Option<()> {
+    let mut r = Reader::new(data);
+    let (version, _) = r.full_box()?;
+    for (kind, data) in boxes(r.rest()) {
+        let mut r = Reader::new(data);
+        let id = |r: &mut Reader| {
+            if version == 0 {
+                r.u16().map(|id| id as u32)
+            } else {
+                r.u32()
+            }
+        };
+        let from = id(&mut r)?;
+        let mut to = Vec::new();
+        for _ in 0..r.u16()? {
+            to.push(id(&mut r)?);
+        }
+        meta.refs.push(ItemRef { kind, from, to });
+    }
+    Some(())
+}
+
+fn parse_ipma(data: &[u8], meta: &mut Meta) -> Option<()> {
+    let mut r = Reader::new(data);
+    let (version, flags) = r.full_box()?;
+    for _ in 0..r.u32()? {
+        let id = if version < 1 {
+            r.u16()? as u32
+        } else {
+            r.u32()?
+        };
+        let mut indices = Vec::new();
+        for _ in 0..r.u8()? {
+            // The top bit is the `essential` flag.
+            indices.push(if flags & 1 != 0 {
+                r.u16()? & 0x7fff
+            } else {
+                (r.u8()? & 0x7f) as u16
+            });
+        }
+        meta.associations.push((id, indices));
+    }
+    Some(())
+}
+
+fn parse_meta<'a>(meta_box: &'a [u8]) -> Result<Meta<'a>, String> {
+    let mut r = Reader::new(meta_box);
+    r.full_box().ok_or("invalid meta box")?;
+    let mut meta = Meta::default();
+    for (kind, data) in boxes(r.rest()) {
+        match &kind {
+            b"pitm" => {
+                let mut r = Reader::new(data);
+                let (version, _) = r.full_box().ok_or("invalid pitm box")?;
+                meta.primary = if version == 0 {
+                    r.u16().map(|id| id as u32)
+                } else {
+                    r.u32()
+                }
+                .ok_or("invalid pitm box")?;
+            }
+            b"iinf" => parse_iinf(data, &mut meta).ok_or("invalid iinf box")?,
+            b"iloc" => parse_iloc(data, &mut meta).ok_or("invalid iloc box")?,
+            b"iref" => parse_iref(data, &mut meta).ok_or("invalid iref box")?,
+            b"iprp" => {
+                if let Some(ipco) = find_box(data, b"ipco") {
+                    meta.properties = boxes(ipco).collect();
+                }
+                for (_, ipma) in boxes(data).filter(|(kind, _)| kind == b"ipma") {
+                    parse_ipma(ipma, &mut meta).ok_or("invalid ipma box")?;
+                }
+            }
+            b"idat" => meta.idat = data,
+            _ => {}
+        }
+    }
+    Ok(meta)
+}
+
+impl<'a> Meta<'a> {
+    fn item(&self, id: u32) -> Result<&Item, String> {
+        self.items
+            .iter()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("missing item {id}"))
+    }
+
+    /// The payload of the property of type `kind` associated with item `id`.
+    fn property(&self, id: u32, kind: &[u8; 4]) -> Option<&'a [u8]> {
+        let (_, indices) = self.associations.iter().find(|(item, _)| *item == id)?;
+        indices
+            .iter()
+            .filter_map(|&index| self.properties.get((index as usize).checked_sub(1)?))
+            .find(|(k, _)| k == kind)
+            .map(|&(_, payload)| payload)
+    }
+
+    /// The items referenced by item `from` with a reference of type `kind`.
+    fn references(&self, from: u32, kind: &[u8; 4]) -> Vec<u32> {
+        self.refs
+            .iter()
+            .filter(|r| r.from == from && &r.kind == kind)
+            .flat_map(|r| r.to.iter().copied())
+            .collect()
+    }
+
+    /// The alpha auxiliary image of item `id`.
+    fn alpha(&self, id: u32) -> Option<u32> {
+        self.refs
+            .iter()
+            .filter(|r| &r.kind == b"auxl" && r.to.contains(&id))
+            .map(|r| r.from)
+            .find(|&aux| self.property(aux, b"auxC").map_or(false, is_alpha))
+    }
+
+    fn item_data(&self, file: &'a [u8], id: u32) -> Result<Vec<u8>, String> {
+        let item = self.item(id)?;
+        let src = if item.in_idat { self.idat } else { file };
+        let mut data = Vec::new();
+        for &(offset, length) in &item.extents {
+            let extent = usize::try_from(offset)
+                .ok()
+                .and_then(|offset| src.get(offset..))
+                .and_then(|rest| {
+                    if length == 0 {
+                        Some(rest)
+                    } else {
+                        rest.get(..usize::try_from(length).ok()?)
+                    }
+                })
+                .ok_or_else(|| format!("item {id} is out of bounds"))?;
+            data.extend_from_slice(extent);
+        }
+        Ok(data)
+    }
+}
+
+/// A decoded plane, without padding.
+struct Plane {
+    w: usize,
+    h: usize,
+    /// Bytes per sample.
+    bps: usize,
+    data: Vec<u8>,
+}
+
+impl Plane {
+    /// Builds a `w`x`h` plane whose sample at `(x, y)` is the sample of `self` at `src(x, y)`.
+    fn remap(&self, w: usize, h: usize, src: impl Fn(usize, usize) -> (usize, usize)) -> Self {
+        let bps = self.bps;
+        let mut data = vec![0; w * h * bps];
+        for y in 0..h {
+            for x in 0..w {
+                let (sx, sy) = src(x, y);
+                let s = (sy * self.w + sx) * bps;
+                data[(y * w + x) * bps..][..bps].copy_from_slice(&self.data[s..s + bps]);
+            }
+        }
+        Self { w, h, bps, data }
+    }
+}
+
+/// A decoded image, with the first picture decoded for it,
+/// whose headers are used when writing the image.
+struct Image {
+    pic: Dav1dPicture,
+    planes: Vec<Plane>,
+}
+
+impl Image {
+    /// The subsampling of plane `pl`.
+    fn ss(&self, pl: usize) -> (usize, usize) {
+        let layout = self.pic.p.layout;
+        if pl == 0 {
+            return (0, 0);
+        }
+        (
+            (layout != DAV1D_PIXEL_LAYOUT_I444) as usize,
+            (layout == DAV1D_PIXEL_LAYOUT_I420) as usize,
+        )
+    }
+
+    fn crop(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        for pl in 0..self.planes.len() {
+            let (ss_hor, ss_ver) = self.ss(pl);
+            let (px, py) = (x >> ss_hor, y >> ss_ver);
+            let (pw, ph) = (w + ss_hor >> ss_hor, h + ss_ver >> ss_ver);
+            self.planes[pl] = self.planes[pl].remap(pw, ph, |x, y| (px + x, py + y));
+        }
+        (self.pic.p.w, self.pic.p.h) = (w as c_int, h as c_int);
+    }
+
+    /// Rotates by `angle` times 90 degrees anti-clockwise.
+    fn rotate(&mut self, angle: u8) -> Result<(), String> {
+        if angle % 2 != 0 && self.pic.p.layout == DAV1D_PIXEL_LAYOUT_I422 {
+            return Err("can't rotate a 4:2:2 image by 90 degrees".into());
+        }
+        for plane in &mut self.planes {
+            let (w, h) = (plane.w, plane.h);
+            *plane = match angle {
+                1 => plane.remap(h, w, |x, y| (w - 1 - y, x)),
+                2 => plane.remap(w, h, |x, y| (w - 1 - x, h - 1 - y)),
+                3 => plane.remap(h, w, |x, y| (y, h - 1 - x)),
+                _ => continue,
+            };
+        }
+        if angle % 2 != 0 {
+            let p = &mut self.pic.p;
+            (p.w, p.h) = (p.h, p.w);
+        }
+        Ok(())
+    }
+
+    /// Mirrors about a vertical axis if `axis` is 0, or about a horizontal axis if it is 1.
+    fn mirror(&mut self, axis: u8) {
+        for plane in &mut self.planes {
+            let (w, h) = (plane.w, plane.h);
+            *plane = if axis == 0 {
+                plane.remap(w, h, |x, y| (w - 1 - x, y))
+            } else {
+                plane.remap(w, h, |x, y| (x, h - 1 - y))
+            };
+        }
+    }
+
+    /// Applies the `clap`, `irot` and `imir` properties found by `property`, in that order,
+    /// releasing the picture if they can't be applied.
+    unsafe fn transform<'a>(
+        mut self,
+        property: impl Fn(&[u8; 4]) -> Option<&'a [u8]>,
+    ) -> Result<Self, String> {
+        let mut apply = || {
+            if let Some(clap) = property(b"clap") {
+                let [x, y, w, h] = clean_aperture(clap, self.pic.p.w, self.pic.p.h)?;
+                // Chroma can't be cropped at odd positions of subsampled planes.
+                let (ss_hor, ss_ver) = self.ss(1);
+                if x & ss_hor != 0 || y & ss_ver != 0 {
+                    return Err("clap isn't aligned to the chroma samples".into());
+                }
+                self.crop(x, y, w, h);
+            }
+            if let Some(irot) = property(b"irot") {
+                self.rotate(irot.first().ok_or("invalid irot box")? & 3)?;
+            }
+            if let Some(imir) = property(b"imir") {
+                self.mirror(imir.first().ok_or("invalid imir box")? & 1);
+            }
+            Ok(())
+        };
+        match apply() {
+            Ok(()) => Ok(self),
+            Err(e) => {
+                dav1d_picture_unref(&mut self.pic);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// A signed fraction with a positive denominator.
+#[derive(Clone, Copy)]
+struct Fraction {
+    n: i128,
+    d: i128,
+}
+
+impl Fraction {
+    fn new(n: i128, d: i128) -> Option<Self> {
+        (d > 0).then_some(Self { n, d })
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            n: self.n * other.d + other.n * self.d,
+            d: self.d * other.d,
+        }
+    }
+
+    /// The value, if it's an integer.
+    fn integer(self) -> Option<i128> {
+        (self.n % self.d == 0).then(|| self.n / self.d)
+    }
+}
+
+/// The `[x, y, w, h]` of the clean aperture described by the `clap` box in an image of `fw`x`fh`.
+///
+/// Its size and offsets are fractions, the offsets being of its center from the center of the
+/// image, so that it starts at `offset + (fw - w) / 2`. Apertures that don't fall
+/// on whole samples are rejected rather than rounded, as AVIF requires them to.
+fn clean_aperture(clap: &[u8], fw: c_int, fh: c_int) -> Result<[usize; 4], String> {
+    let mut r = Reader::new(clap);
+    let mut v = [0u32; 8];
+    for v in &mut v {
+        *v = r.u32().ok_or("invalid clap box")?;
+    }
+    let [w_n, w_d, h_n, h_d, x_n, x_d, y_n, y_d] = v;
+    let mut aperture = [0; 4];
+    for (dim, (size_n, size_d, off_n, off_d, full)) in
+        [(w_n, w_d, x_n, x_d, fw), (h_n, h_d, y_n, y_d, fh)]
+            .into_iter()
+            .enumerate()
+    {
+        let fraction = |n, d: u32| Fraction::new(n, d as i128).ok_or("invalid clap box");
+        let size = fraction(size_n as i128, size_d)?
+            .integer()
+            .ok_or("clap isn't a whole number of samples")?;
+        // The offset is signed.
+        let start = fraction(off_n as i32 as i128, off_d)?
+            .add(fraction(full as i128 - size, 2)?)
+            .integer()
+            .ok_or("clap doesn't start on a sample")?;
+        if size < 1 || start < 0 || start + size > full as i128 {
+            return Err("clap is out of the image".into());
+        }
+        (aperture[dim], aperture[dim + 2]) = (start as usize, size as usize);
+    }
+    Ok(aperture)
+}
+
+/// Copies the visible planes of `p`.
+unsafe fn copy_planes(p: &Dav1dPicture) -> Vec<Plane> {
+    let bps = 1 + (p.p.bpc > 8) as usize;
+    let (w, h) = (p.p.w as usize, p.p.h as usize);
+    let mut dims = vec![(w, h)];
+    if p.p.layout != DAV1D_PIXEL_LAYOUT_I400 {
+        let ss_hor = (p.p.layout != DAV1D_PIXEL_LAYOUT_I444) as usize;
+        let ss_ver = (p.p.layout == DAV1D_PIXEL_LAYOUT_I420) as usize;
+        let chroma = (w + ss_hor >> ss_hor, h + ss_ver >> ss_ver);
+        dims.extend([chroma, chroma]);
+    }
+    dims.into_iter()
+        .enumerate()
+        .map(|(pl, (pw, ph))| {
+            let mut ptr = p.data[pl].unwrap().as_ptr() as *const u8;
+            let stride = p.stride[(pl != 0) as usize];
+            let mut data = Vec::with_capacity(pw * ph * bps);
+            for _ in 0..ph {
+                data.extend_from_slice(std::slice::from_raw_parts(ptr, pw * bps));
+                ptr = ptr.wrapping_offset(stride);
+            }
+            Plane {
+                w: pw,
+                h: ph,
+                bps,
+                data,
+            }
+        })
+        .collect()
+}
+
+/// Decodes a single AV1 image.
+unsafe fn decode_av1(c: *mut Dav1dContext, obus: &[u8]) -> Result<Dav1dPicture, String> {
+    let mut data = Dav1dData::default();
+    let ptr = dav1d_data_create(&mut data, obus.len());
+    if ptr.is_null() {
+        return Err("failed to allocate data".into());
+    }
+    ptr::copy_nonoverlapping(obus.as_ptr(), ptr, obus.len());
+    let mut p = Default::default();
+    let mut drain_tries = 0;
+    let res = loop {
+        if data.sz > 0 {
+            let res = dav1d_send_data(c, &mut data).0;
+            if res < 0 && res != -EAGAIN {
+                break res;
+            }
+        }
+        let res = dav1d_get_picture(c, &mut p).0;
+        if res != -EAGAIN {
+            break res;
+        }
+        // Once all the data is sent, the second call drains the decoder.
+        if data.sz == 0 {
+            drain_tries += 1;
+            if drain_tries > 2 {
+                break res;
+            }
+        }
+    };
+    if data.sz > 0 {
+        dav1d_data_unref(&mut data);
+    }
+    if res < 0 {
+        return Err(format!(
+            "failed to decode: {}",
+            CStr::from_ptr(libc::strerror(-res)).to_string_lossy()
+        ));
+    }
+    Ok(p)
+}
+
+/// Decodes item `id`, stitching the tiles of a `grid` together.
+unsafe fn decode_item(
+    c: *mut Dav1dContext,
+    file: &[u8],
+    meta: &Meta,
+    id: u32,
+) -> Result<Image, String> {
+    let item = meta.item(id)?;
+    match &item.kind {
+        b"av01" => {
+            let pic = decode_av1(c, &meta.item_data(file, id)?)?;
+            let planes = copy_planes(&pic);
+            Ok(Image { pic, planes })
+        }
+        b"grid" => {
+            let desc = meta.item_data(file, id)?;
+            let mut r = Reader::new(&desc);
+            let mut parse = || {
+                r.u8()?;
+                let flags = r.u8()?;
+                let rows = r.u8()? as usize + 1;
+                let cols = r.u8()? as usize + 1;
+                let (w, h) = if flags & 1 != 0 {
+                    (r.u32()? as usize, r.u32()? as usize)
+                } else {
+                    (r.u16()? as usize, r.u16()? as usize)
+                };
+                Some((rows, cols, w, h))
+            };
+            let (rows, cols, w, h) = parse().ok_or("invalid grid")?;
+            let tiles = meta.references(id, b"dimg");
+            if tiles.len() != rows * cols {
+                return Err(format!("grid of {rows}x{cols} has {} tiles", tiles.len()));
+            }
+            let mut image: Option<Image> = None;
+            let mut tile_format = None;
+            for (n, &tile) in tiles.iter().enumerate() {
+                let mut pic = decode_av1(c, &meta.item_data(file, tile)?)?;
+                let tile_planes = copy_planes(&pic);
+                let format = (pic.p.w, pic.p.h, pic.p.layout, pic.p.bpc);
+                let image = match &mut image {
+                    Some(image) => {
+                        let matches = Some(format) == tile_format;
+                        dav1d_picture_unref(&mut pic);
+                        if !matches {
+                            dav1d_picture_unref(&mut image.pic);
+                            return Err("grid tiles differ in size or format".into());
+                        }
+                        image
+                    }
+                    None => {
+                        let (tw, th) = (pic.p.w as usize, pic.p.h as usize);
+                        if tw * cols < w || th * rows < h {
+                            dav1d_picture_unref(&mut pic);
+                            return Err("grid tiles don't cover the image".into());
+                        }
+                        tile_format = Some(format);
+                        // The canvas covers the whole grid, and is cropped to the output size.
+                        let planes = tile_planes
+                            .iter()
+                            .map(|plane| Plane {
+                                w: plane.w * cols,
+                                h: plane.h * rows,
+                                bps: plane.bps,
+                                data: vec![0; plane.w * cols * plane.h * rows * plane.bps],
+                            })
+                            .collect();
+                        pic.p.w = (tw * cols) as c_int;
+                        pic.p.h = (th * rows) as c_int;
+                        image.insert(Image { pic, planes })
+                    }
+                };
+                let (row, col) = (n / cols, n % cols);
+                for (plane, tile_plane) in image.planes.iter_mut().zip(&tile_planes) {
+                    let bps = plane.bps;
+                    let row_len = tile_plane.w * bps;
+                    for y in 0..tile_plane.h {
+                        let dst = ((row * tile_plane.h + y) * plane.w + col * tile_plane.w) * bps;
+                        plane.data[dst..dst + row_len]
+                            .copy_from_slice(&tile_plane.data[y * row_len..][..row_len]);
+                    }
+                }
+            }
+            let mut image = image.unwrap();
+            image.crop(0, 0, w, h);
+            Ok(image)
+        }
+        kind => Err(format!(
+            "unsupported item type {}",
+            String::from_utf8_lossy(kind)
+        )),
+    }
+}
+
+/// An open decoder, closed when dropped.
+struct Decoder(*mut Dav1dContext);
+
+impl Decoder {
+    unsafe fn open(lib_settings: &Dav1dSettings) -> Result<Self, String> {
+        let mut c = ptr::null_mut();
+        if dav1d_open(&mut c, lib_settings).0 != 0 {
+            return Err("failed to open the decoder".into());
+        }
+        Ok(Self(c))
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        unsafe { dav1d_close(&mut self.0) };
+    }
+}
+
+/// Decodes item `id` with a new decoder, and applies the transformations of item `transform_id`.
+unsafe fn decode_image(
+    lib_settings: &Dav1dSettings,
+    file: &[u8],
+    meta: &Meta,
+    id: u32,
+    transform_id: u32,
+) -> Result<Image, String> {
+    let c = Decoder::open(lib_settings)?;
+    decode_item(c.0, file, meta, id)?.transform(|kind| meta.property(transform_id, kind))
+}
+
+/// Decodes sample `n` of `track` with `c`, which decoded the samples before it,
+/// and applies the transformations of the sample entry of track `transform`.
+unsafe fn decode_sample(
+    c: &Decoder,
+    file: &[u8],
+    track: &Av01Track,
+    n: usize,
+    transform: &Av01Track,
+) -> Result<Image, String> {
+    let sample = track
+        .samples
+        .get(n)
+        .ok_or_else(|| format!("missing sample {n}"))?;
+    let data = usize::try_from(sample.offset)
+        .ok()
+        .and_then(|offset| file.get(offset..)?.get(..sample.size as usize))
+        .ok_or_else(|| format!("sample {n} is out of bounds"))?;
+    let pic = if n == 0 {
+        decode_av1(c.0, &[&track.config[..], data].concat())?
+    } else {
+        decode_av1(c.0, data)?
+    };
+    let planes = copy_planes(&pic);
+    Image { pic, planes }.transform(|kind| find_box(transform.entry, kind))
+}
+
+/// Where images are written with the muxer `name` (or the one for the extension of `file`),
+/// as monochrome images if `alpha`.
+struct ImageOutput {
+    name: *const c_char,
+    file: *const c_char,
+    verify: *const c_char,
+    alpha: bool,
+    fps: [c_uint; 2],
+    /// Opened with the first image.
+    out: *mut MuxerContext,
+}
+
+impl ImageOutput {
+    fn new(
+        name: *const c_char,
+        file: *const c_char,
+        verify: *const c_char,
+        alpha: bool,
+        fps: [c_uint; 2],
+    ) -> Self {
+        Self {
+            name,
+            file,
+            verify,
+            alpha,
+            fps,
+            out: ptr::null_mut(),
+        }
+    }
+
+    unsafe fn write(&mut self, mut image: Image) -> c_int {
+        let p = &mut image.pic;
+        // The aspect ratio written by the `yuv4mpeg2` muxer comes from the render size.
+        let mut frame_hdr = p.frame_hdr.unwrap().as_ref().clone();
+        frame_hdr.render_width = p.p.w;
+        frame_hdr.render_height = p.p.h;
+        p.frame_hdr = Some(NonNull::from(&mut frame_hdr));
+        if self.alpha {
+            p.p.layout = DAV1D_PIXEL_LAYOUT_I400;
+            image.planes.truncate(1);
+        }
+        p.data = [None; 3];
+        for (pl, plane) in image.planes.iter_mut().enumerate() {
+            p.data[pl] = NonNull::new(plane.data.as_mut_ptr() as *mut c_void);
+            p.stride[(pl != 0) as usize] = (plane.w * plane.bps) as isize;
+        }
+        if self.out.is_null() {
+            let res = output_open(
+                &mut self.out,
+                self.name,
+                self.file,
+                &p.p,
+                self.fps.as_ptr(),
+                self.verify,
+            );
+            if res < 0 {
+                self.out = ptr::null_mut();
+                dav1d_picture_unref(p);
+                return res;
+            }
+        }
+        output_write(self.out, p)
+    }
+
+    /// Closes the muxer, verifying what was written if asked to.
+    unsafe fn close(self) -> c_int {
+        if self.out.is_null() {
+            return 0;
+        }
+        if !self.verify.is_null() {
+            return output_verify(self.out, self.verify);
+        }
+        output_close(self.out);
+        0
+    }
+}
+
+/// Decodes the still image described by `meta`, and writes its color and alpha planes.
+unsafe fn decode_still(
+    cli_settings: &CLISettings,
+    lib_settings: &Dav1dSettings,
+    path: &str,
+    file: &[u8],
+    meta: &Meta,
+) -> c_int {
+    let alpha = meta.alpha(meta.primary);
+    if alpha.is_none() && !cli_settings.alpha.is_null() {
+        print_error(format!("{path} has no alpha plane"));
+    }
+
+    let image = match decode_image(lib_settings, file, meta, meta.primary, meta.primary) {
+        Ok(image) => image,
+        Err(e) => {
+            print_error(format!("Failed to decode {path}: {e}"));
+            return 1;
+        }
+    };
+    let mut out = ImageOutput::new(
+        cli_settings.muxer,
+        cli_settings.outputfile,
+        cli_settings.verify,
+        false,
+        [1, 1],
+    );
+    let mut res = out.write(image);
+    res |= out.close();
+    if let (Some(alpha), false) = (alpha, cli_settings.alpha.is_null()) {
+        match decode_image(lib_settings, file, meta, alpha, meta.primary) {
+            Ok(image) => {
+                let mut out =
+                    ImageOutput::new(ptr::null(), cli_settings.alpha, ptr::null(), true, [1, 1]);
+                res |= out.write(image);
+                res |= out.close();
+            }
+            Err(e) => {
+                print_error(format!("Failed to decode the alpha plane of {path}: {e}"));
+                res = 1;
+            }
+        }
+    }
+    (res != 0) as c_int
+}
+
+/// Decodes the image sequence in the `moov` box, and writes the frames of its color track
+/// and of its alpha track, both transformed as the color track is.
+unsafe fn decode_sequence(
+    cli_settings: &CLISettings,
+    lib_settings: &Dav1dSettings,
+    path: &str,
+    file: &[u8],
+    moov: &[u8],
+) -> c_int {
+    let mut tracks = Vec::new();
+    for (_, trak) in boxes(moov).filter(|(kind, _)| kind == b"trak") {
+        match parse_av01_trak(trak, file.len() as u64) {
+            Ok(Some(track)) => tracks.push((auxiliary_of(trak), track)),
+            Ok(None) => {}
+            Err(e) => {
+                print_error(format!("Failed to parse {path}: {e}"));
+                return 1;
+            }
+        }
+    }
+    let Some((_, color)) = tracks.iter().find(|(aux, _)| aux.is_empty()) else {
+        print_error(format!("Failed to parse {path}: missing av01 track"));
+        return 1;
+    };
+    let alpha = tracks
+        .iter()
+        .find(|(aux, track)| {
+            aux.contains(&color.id) && find_box(track.entry, b"auxi").map_or(false, is_alpha)
+        })
+        .map(|(_, track)| track);
+    if alpha.is_none() && !cli_settings.alpha.is_null() {
+        print_error(format!("{path} has no alpha plane"));
+    }
+    let alpha = alpha.filter(|_| !cli_settings.alpha.is_null());
+
+    let duration = color.samples.iter().map(|s| s.duration as u64).sum();
+    let fps = reduce_fraction(
+        color.samples.len() as u64 * color.timescale as u64,
+        duration,
+    );
+    let mut out = ImageOutput::new(
+        cli_settings.muxer,
+        cli_settings.outputfile,
+        cli_settings.verify,
+        false,
+        fps,
+    );
+    let mut alpha_out = ImageOutput::new(ptr::null(), cli_settings.alpha, ptr::null(), true, fps);
+    let mut n_frames = color.samples.len();
+    if cli_settings.limit != 0 {
+        n_frames = n_frames.min(cli_settings.skip as usize + cli_settings.limit as usize);
+    }
+    let mut res = (|| {
+        let c = Decoder::open(lib_settings)?;
+        let alpha_c = alpha.map(|_| Decoder::open(lib_settings)).transpose()?;
+        for n in 0..n_frames {
+            let image = decode_sample(&c, file, color, n, color)?;
+            let alpha_image = match (&alpha_c, alpha) {
+                (Some(alpha_c), Some(alpha)) => match decode_sample(alpha_c, file, alpha, n, color)
+                {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        dav1d_picture_unref(&mut { image }.pic);
+                        return Err(format!("alpha plane: {e}"));
+                    }
+                },
+                _ => None,
+            };
+            // The skipped frames are still decoded, as the ones after them depend on them.
+            if n < cli_settings.skip as usize {
+                for mut image in [Some(image), alpha_image].into_iter().flatten() {
+                    dav1d_picture_unref(&mut image.pic);
+                }
+                continue;
+            }
+            let mut res = out.write(image);
+            if let Some(image) = alpha_image {
+                res |= alpha_out.write(image);
+            }
+            if res != 0 {
+                return Ok(res);
+            }
+        }
+        Ok(0)
+    })()
+    .unwrap_or_else(|e: String| {
+        print_error(format!("Failed to decode {path}: {e}"));
+        1
+    });
+    res |= out.close();
+    res |= alpha_out.close();
+    (res != 0) as c_int
+}
+
+/// Returns whether `filename` is an AVIF image or image sequence, from the brands of its `ftyp` box.
+pub unsafe fn avif_probe(filename: *const c_char) -> bool {
+    // Reading a stream here would consume its data, and AVIF images aren't streamed anyway.
+    if input_is_stream(filename) {
+        return false;
+    }
+    let path = CStr::from_ptr(filename).to_string_lossy().into_owned();
+    let mut hdr = [0; 64];
+    let Ok(len) = File::open(path).and_then(|mut f| f.read(&mut hdr)) else {
+        return false;
+    };
+    let Some((kind, ftyp)) = Reader::new(&hdr[..len]).next_box().or_else(|| {
+        // The `ftyp` box may not fit in the header, but its brands are at its start.
+        (len >= 8).then(|| (hdr[4..8].try_into().unwrap(), &hdr[8..len]))
+    }) else {
+        return false;
+    };
+    if &kind != b"ftyp" {
+        return false;
+    }
+    // The minor version isn't a brand.
+    ftyp.chunks_exact(4)
+        .enumerate()
+        .any(|(n, brand)| n != 1 && [&b"avif"[..], b"avis", b"mif1"].contains(&brand))
+}
+
+/// Decodes the AVIF file `cli_settings.inputfile`, and writes its color and alpha planes:
+/// the frames of its image sequence if it has one, and its still image otherwise.
+pub unsafe fn avif_decode(cli_settings: &CLISettings, lib_settings: &mut Dav1dSettings) -> c_int {
+    let path = CStr::from_ptr(cli_settings.inputfile)
+        .to_string_lossy()
+        .into_owned();
+    let file = match fs::read(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            print_error(format!("Failed to read {path}: {e}"));
+            return 1;
+        }
+    };
+    if let Some((_, moov)) = boxes(&file).find(|(kind, _)| kind == b"moov") {
+        return decode_sequence(cli_settings, lib_settings, &path, &file, moov);
+    }
+    let meta = match boxes(&file)
+        .find(|(kind, _)| kind == b"meta")
+        .ok_or_else(|| "missing meta box".to_owned())
+        .and_then(|(_, meta)| parse_meta(meta))
+    {
+        Ok(meta) => meta,
+        Err(e) => {
+            print_error(format!("Failed to parse {path}: {e}"));
+            return 1;
+        }
+    };
+    decode_still(cli_settings, lib_settings, &path, &file, &meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean_aperture;
+    use super::parse_meta;
+
+    fn bx(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut b = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        b.extend(kind);
+        b.extend(payload);
+        b
+    }
+
+    /// A full box of version `version`, with no flags.
+    fn full_box(kind: &[u8; 4], version: u8, payload: &[u8]) -> Vec<u8> {
+        bx(kind, &[&[version, 0, 0, 0], payload].concat())
+    }
+
+    fn clap(fields: [i32; 8]) -> Vec<u8> {
+        fields.iter().flat_map(|f| f.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn clean_aperture_is_offset_from_the_center() {
+        let clap = clap([100, 1, 60, 1, 10, 1, -6, 1]);
+        assert_eq!(clean_aperture(&clap, 208, 144), Ok([64, 36, 100, 60]));
+    }
+
+    #[test]
+    fn clean_aperture_is_rational() {
+        // 3 samples centered in 4 start half way through a sample, unless moved by half a sample.
+        assert!(clean_aperture(&clap([3, 1, 4, 1, 0, 1, 0, 1]), 4, 4).is_err());
+        assert_eq!(
+            clean_aperture(&clap([6, 2, 8, 2, 1, 2, 0, 4]), 4, 4),
+            Ok([1, 0, 3, 4])
+        );
+        assert!(clean_aperture(&clap([7, 2, 4, 1, 0, 1, 0, 1]), 4, 4).is_err());
+    }
+
+    #[test]
+    fn clean_aperture_is_inside_the_image() {
+        assert!(clean_aperture(&clap([4, 1, 4, 1, 1, 1, 0, 1]), 4, 4).is_err());
+        assert!(clean_aperture(&clap([0, 1, 4, 1, 0, 1, 0, 1]), 4, 4).is_err());
+        assert!(clean_aperture(&clap([4, 0, 4, 1, 0, 1, 0, 1]), 4, 4).is_err());
+        assert!(clean_aperture(&clap([4, 1, 4, 1, 0, 1, 0, 1])[..16], 4, 4).is_err());
+    }
+
+    #[test]
+    fn meta_items() {
+        let infe = |id: u16| {
+            full_box(
+                b"infe",
+                2,
+                &[&id.to_be_bytes()[..], &[0, 0], b"av01"].concat(),
+            )
+        };
+        let iloc = [
+            // 4 byte offsets and lengths, no base offset, 2 items.
+            &[0x44, 0x00, 0, 2][..],
+            // Item 1 at offset 4 of the file.
+            &[0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 2],
+            // Item 2 at offset 1 of the `idat` box.
+            &[0, 2, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 3],
+        ]
+        .concat();
+        let meta = [
+            &[0; 4][..],
+            &full_box(b"pitm", 0, &[0, 1]),
+            &full_box(b"iinf", 0, &[&[0, 2][..], &infe(1), &infe(2)].concat()),
+            &full_box(b"iloc", 1, &iloc),
+            &full_box(b"iref", 0, &bx(b"auxl", &[0, 2, 0, 1, 0, 1])),
+            &bx(
+                b"iprp",
+                &[
+                    bx(
+                        b"ipco",
+                        &[
+                            bx(b"irot", &[1]),
+                            full_box(b"auxC", 0, b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha\0"),
+                        ]
+                        .concat(),
+                    ),
+                    // Item 1 has property 1, and item 2 property 2, marked as essential.
+                    full_box(b"ipma", 0, &[0, 0, 0, 2, 0, 1, 1, 1, 0, 2, 1, 0x82]),
+                ]
+                .concat(),
+            ),
+            &bx(b"idat", b"_idat"),
+        ]
+        .concat();
+        let meta = parse_meta(&meta).unwrap();
+        assert_eq!(meta.primary, 1);
+        assert_eq!(meta.alpha(1), Some(2));
+        assert_eq!(meta.alpha(2), None);
+        assert_eq!(meta.property(1, b"irot"), Some(&[1][..]));
+        assert_eq!(meta.property(2, b"irot"), None);
+        assert_eq!(meta.item_data(b"fileXYdata", 1).unwrap(), b"XY");
+        assert_eq!(meta.item_data(b"fileXYdata", 2).unwrap(), b"ida");
+        assert!(meta.item_data(b"fileX", 1).is_err());
+        assert!(meta.item_data(b"fileXYdata", 3).is_err());
+    }
+}
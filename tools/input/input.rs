@@ -1,27 +1,42 @@
 use crate::compat::errno::errno_location;
+use crate::compat::stdio::fopen_cookie;
 use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdin;
+use crate::compat::stdio::CookieFunctions;
 use libc::calloc;
 use libc::fclose;
 use libc::fopen;
 use libc::fprintf;
 use libc::fread;
 use libc::free;
+use libc::fseeko;
 use libc::malloc;
 use libc::strcmp;
 use libc::strerror;
+use libc::EINVAL;
 use libc::ENOMEM;
 use libc::ENOPROTOOPT;
+use libc::ESPIPE;
+use libc::SEEK_CUR;
+use libc::SEEK_END;
+use libc::SEEK_SET;
 use rav1d::include::dav1d::data::Dav1dData;
 use std::cmp;
 use std::ffi::c_char;
 use std::ffi::c_int;
 use std::ffi::c_uint;
 use std::ffi::c_void;
+use std::ffi::CString;
 use std::mem;
+use std::ptr;
 
 extern "C" {
     pub type DemuxerPriv;
     static ivf_demuxer: Demuxer;
+    static matroska_demuxer: Demuxer;
+    static mp4_demuxer: Demuxer;
+    static mpegts_demuxer: Demuxer;
+    static rtp_demuxer: Demuxer;
     static annexb_demuxer: Demuxer;
     static section5_demuxer: Demuxer;
 }
@@ -30,6 +45,10 @@ extern "C" {
 pub struct DemuxerContext {
     pub data: *mut DemuxerPriv,
     pub impl_0: *const Demuxer,
+    /// The input, read by the demuxer and closed after it.
+    pub f: *mut libc::FILE,
+    /// Whether the input is a stream, which can't be seeked.
+    pub stream: c_int,
     pub priv_data: [u64; 0],
 }
 
@@ -43,6 +62,7 @@ pub struct Demuxer {
         unsafe extern "C" fn(
             *mut DemuxerPriv,
             *const c_char,
+            *mut libc::FILE,
             *mut c_uint,
             *mut c_uint,
             *mut c_uint,
@@ -53,15 +73,251 @@ pub struct Demuxer {
     pub close: Option<unsafe extern "C" fn(*mut DemuxerPriv) -> ()>,
 }
 
-static mut demuxers: [*const Demuxer; 4] = unsafe {
+static mut demuxers: [*const Demuxer; 8] = unsafe {
     [
         &ivf_demuxer as *const Demuxer,
+        &matroska_demuxer as *const Demuxer,
+        &mp4_demuxer as *const Demuxer,
+        &mpegts_demuxer as *const Demuxer,
+        &rtp_demuxer as *const Demuxer,
         &annexb_demuxer as *const Demuxer,
         &section5_demuxer as *const Demuxer,
         0 as *const Demuxer,
     ]
 };
 
+/// Initializes the private data of a demuxer at the start of its `open`.
+///
+/// [`input_open`] allocates the private data zeroed, which isn't valid
+/// for demuxers that keep owned data like a [`Vec`] in it.
+/// Their `close` drops it again with [`ptr::drop_in_place`].
+pub unsafe fn demuxer_priv_init<T: Default>(c: *mut T) {
+    c.write(T::default());
+}
+
+/// Prints `msg` and a newline to stderr.
+pub fn print_error(msg: String) {
+    let msg = CString::new(msg).unwrap();
+    // Safety: the format takes one string, and `msg` is one.
+    unsafe {
+        fprintf(
+            stderr,
+            b"%s\n\0" as *const u8 as *const c_char,
+            msg.as_ptr(),
+        );
+    }
+}
+
+/// Fills all of `buf` from `f`, returning whether it could.
+pub unsafe fn read_exact(f: *mut libc::FILE, buf: &mut [u8]) -> bool {
+    buf.is_empty() || fread(buf.as_mut_ptr() as *mut c_void, buf.len(), 1, f) == 1
+}
+
+/// Reduces the frame rate or timebase `num / den` to fit in 32 bits,
+/// or returns `[0, 0]` if either is 0, for an unknown frame rate.
+pub fn reduce_fraction(mut num: u64, mut den: u64) -> [c_uint; 2] {
+    if num == 0 || den == 0 {
+        return [0, 0];
+    }
+    let (mut a, mut b) = (num, den);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    num /= a;
+    den /= a;
+    while num | den > u32::MAX as u64 {
+        num >>= 1;
+        den >>= 1;
+    }
+    [num as c_uint, den as c_uint]
+}
+
+/// Reads the leb128 at the start of `data`, and advances `data` past it.
+/// Returns [`None`] if it's truncated, longer than the 8 bytes AV1 allows,
+/// or too large for a `usize`.
+pub fn read_leb128(data: &mut &[u8]) -> Option<usize> {
+    // Accumulated in a `u64`, as 8 bytes overflow a 32-bit `usize`.
+    let mut value = 0u64;
+    for n in 0..8 {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= ((byte & 0x7f) as u64) << (n * 7);
+        if byte & 0x80 == 0 {
+            return usize::try_from(value).ok();
+        }
+    }
+    None
+}
+
+/// Appends `value` to `out` as a leb128.
+pub fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// How much of the data behind the read position of a stream is kept once the demuxer
+/// is open, for demuxers that seek back within the last frame.
+const STREAM_WINDOW: usize = 16 << 20;
+
+const STREAM_READ_SIZE: usize = 64 << 10;
+
+/// A pipe or another non-seekable input, read through a buffer so that the data read
+/// while probing is read again by the demuxer, and seeking is emulated:
+/// forward by reading, and backward within the buffered data.
+struct Stream {
+    f: *mut libc::FILE,
+    /// The data from offset `start` on.
+    buf: Vec<u8>,
+    start: u64,
+    pos: u64,
+    /// Whether all the data is kept, rather than only the last [`STREAM_WINDOW`] bytes read.
+    /// It is while probing and opening the demuxer, which reads the probed data again,
+    /// and may index the whole input and seek back to its start.
+    retain: bool,
+    eof: bool,
+}
+
+impl Stream {
+    unsafe fn open(filename: *const c_char) -> *mut Self {
+        let f = if strcmp(filename, b"-\0" as *const u8 as *const c_char) == 0 {
+            stdin
+        } else {
+            fopen(filename, b"rb\0" as *const u8 as *const c_char)
+        };
+        if f.is_null() {
+            return ptr::null_mut();
+        }
+        Box::into_raw(Box::new(Self {
+            f,
+            buf: Vec::new(),
+            start: 0,
+            pos: 0,
+            retain: true,
+            eof: false,
+        }))
+    }
+
+    /// Buffers the data up to offset `end`, or up to the end of the stream.
+    unsafe fn fill(&mut self, end: u64) {
+        while !self.eof && self.start + (self.buf.len() as u64) < end {
+            let len = self.buf.len();
+            self.buf.resize(len + STREAM_READ_SIZE, 0);
+            let n = fread(
+                self.buf[len..].as_mut_ptr() as *mut c_void,
+                1,
+                STREAM_READ_SIZE,
+                self.f,
+            );
+            self.buf.truncate(len + n);
+            self.eof = n == 0;
+        }
+    }
+
+    unsafe fn read(&mut self, buf: &mut [u8]) -> usize {
+        self.fill(self.pos + buf.len() as u64);
+        let offset = (self.pos - self.start) as usize;
+        let n = cmp::min(buf.len(), self.buf.len().saturating_sub(offset));
+        if n == 0 {
+            return 0;
+        }
+        buf[..n].copy_from_slice(&self.buf[offset..offset + n]);
+        self.pos += n as u64;
+        let behind = (self.pos - self.start) as usize;
+        if !self.retain && behind > 2 * STREAM_WINDOW {
+            self.buf.drain(..behind - STREAM_WINDOW);
+            self.start += (behind - STREAM_WINDOW) as u64;
+        }
+        n
+    }
+
+    unsafe fn seek(&mut self, offset: i64, whence: c_int) -> i64 {
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => self.pos as i64,
+            SEEK_END => {
+                self.fill(u64::MAX);
+                (self.start + self.buf.len() as u64) as i64
+            }
+            _ => {
+                *errno_location() = EINVAL;
+                return -1;
+            }
+        };
+        let pos = base + offset;
+        if pos < self.start as i64 {
+            *errno_location() = ESPIPE;
+            return -1;
+        }
+        self.pos = pos as u64;
+        pos
+    }
+}
+
+unsafe fn stream_read(cookie: *mut c_void, buf: *mut c_char, size: usize) -> isize {
+    let buf = std::slice::from_raw_parts_mut(buf as *mut u8, size);
+    (*(cookie as *mut Stream)).read(buf) as isize
+}
+
+unsafe fn stream_seek(cookie: *mut c_void, offset: i64, whence: c_int) -> i64 {
+    (*(cookie as *mut Stream)).seek(offset, whence)
+}
+
+unsafe fn stream_close(cookie: *mut c_void) -> c_int {
+    let stream = Box::from_raw(cookie as *mut Stream);
+    if stream.f != stdin {
+        fclose(stream.f);
+    }
+    0
+}
+
+/// Returns whether `filename` is `-`, for stdin, or a pipe or another file that can't be seeked.
+pub unsafe fn input_is_stream(filename: *const c_char) -> bool {
+    if strcmp(filename, b"-\0" as *const u8 as *const c_char) == 0 {
+        return true;
+    }
+    let mut st: libc::stat = mem::zeroed();
+    if libc::stat(filename, &mut st) != 0 {
+        return false;
+    }
+    let fmt = st.st_mode & libc::S_IFMT;
+    fmt == libc::S_IFIFO || fmt == libc::S_IFSOCK || fmt == libc::S_IFCHR
+}
+
+/// Opens `filename` for reading, where `-` is stdin.
+///
+/// Streams (see [`input_is_stream`]) are read through a [`Stream`], which is also returned,
+/// to bound the data it keeps once the demuxer is open.
+unsafe fn input_fopen(filename: *const c_char) -> (*mut libc::FILE, *mut Stream) {
+    if !input_is_stream(filename) {
+        let f = fopen(filename, b"rb\0" as *const u8 as *const c_char);
+        return (f, ptr::null_mut());
+    }
+    let stream = Stream::open(filename);
+    if stream.is_null() {
+        return (ptr::null_mut(), ptr::null_mut());
+    }
+    let f = fopen_cookie(
+        stream as *mut c_void,
+        CookieFunctions {
+            read: stream_read,
+            seek: stream_seek,
+            close: stream_close,
+        },
+    );
+    if f.is_null() {
+        stream_close(stream as *mut c_void);
+        return (ptr::null_mut(), ptr::null_mut());
+    }
+    (f, stream)
+}
+
 pub unsafe fn input_open(
     c_out: *mut *mut DemuxerContext,
     name: *const c_char,
@@ -92,7 +348,22 @@ pub unsafe fn input_open(
             );
             return -ENOPROTOOPT;
         }
-    } else {
+    }
+    let (f, stream) = input_fopen(filename);
+    if f.is_null() {
+        fprintf(
+            stderr,
+            b"Failed to open input file %s: %s\n\0" as *const u8 as *const c_char,
+            filename,
+            strerror(*errno_location()),
+        );
+        return if *errno_location() != 0 {
+            -*errno_location()
+        } else {
+            -(5 as c_int)
+        };
+    }
+    if name.is_null() {
         let mut probe_sz = 0;
         i = 0 as c_int;
         while !(demuxers[i as usize]).is_null() {
@@ -101,30 +372,19 @@ pub unsafe fn input_open(
         }
         let probe_data: *mut u8 = malloc(probe_sz as usize) as *mut u8;
         if probe_data.is_null() {
+            fclose(f);
             fprintf(
                 stderr,
                 b"Failed to allocate memory\n\0" as *const u8 as *const c_char,
             );
             return -ENOMEM;
         }
-        let f: *mut libc::FILE = fopen(filename, b"rb\0" as *const u8 as *const c_char);
-        if f.is_null() {
-            fprintf(
-                stderr,
-                b"Failed to open input file %s: %s\n\0" as *const u8 as *const c_char,
-                filename,
-                strerror(*errno_location()),
-            );
-            return if *errno_location() != 0 {
-                -*errno_location()
-            } else {
-                -(5 as c_int)
-            };
-        }
-        res = (fread(probe_data as *mut c_void, 1, probe_sz as usize, f) != 0) as c_int;
-        fclose(f);
+        // The demuxer reads the probed data again, which streams keep until it is open.
+        res = (fread(probe_data as *mut c_void, 1, probe_sz as usize, f) != 0
+            && fseeko(f, 0, SEEK_SET) == 0) as c_int;
         if res == 0 {
             free(probe_data as *mut c_void);
+            fclose(f);
             fprintf(
                 stderr,
                 b"Failed to read probe data\n\0" as *const u8 as *const c_char,
@@ -147,6 +407,7 @@ pub unsafe fn input_open(
         }
         free(probe_data as *mut c_void);
         if (demuxers[i as usize]).is_null() {
+            fclose(f);
             fprintf(
                 stderr,
                 b"Failed to probe demuxer for file %s\n\0" as *const u8 as *const c_char,
@@ -160,6 +421,7 @@ pub unsafe fn input_open(
         mem::size_of::<DemuxerContext>() + (*impl_0).priv_data_size as usize,
     ) as *mut DemuxerContext;
     if c.is_null() {
+        fclose(f);
         fprintf(
             stderr,
             b"Failed to allocate memory\n\0" as *const u8 as *const c_char,
@@ -168,17 +430,25 @@ pub unsafe fn input_open(
     }
     (*c).impl_0 = impl_0;
     (*c).data = ((*c).priv_data).as_mut_ptr() as *mut DemuxerPriv;
+    (*c).f = f;
+    (*c).stream = !stream.is_null() as c_int;
     res = ((*impl_0).open).expect("non-null function pointer")(
         (*c).data,
         filename,
+        f,
         fps,
         num_frames,
         timebase,
     );
     if res < 0 {
+        fclose(f);
         free(c as *mut c_void);
         return res;
     }
+    // From now on, demuxers only read on, or seek back within the last frame.
+    if !stream.is_null() {
+        (*stream).retain = false;
+    }
     *c_out = c;
     return 0 as c_int;
 }
@@ -191,6 +461,14 @@ pub unsafe fn input_read(ctx: *mut DemuxerContext, data: *mut Dav1dData) -> c_in
 // but are still marked as unused since `[[bin]]` are only supposed to be one file in `cargo`.
 #[allow(dead_code)]
 pub unsafe fn input_seek(ctx: *mut DemuxerContext, pts: u64) -> c_int {
+    if (*ctx).stream != 0 {
+        fprintf(
+            stderr,
+            b"Seeking is not supported when reading from a stream\n\0" as *const u8
+                as *const c_char,
+        );
+        return -1;
+    }
     return if ((*(*ctx).impl_0).seek).is_some() {
         ((*(*ctx).impl_0).seek).expect("non-null function pointer")((*ctx).data, pts)
     } else {
@@ -200,5 +478,81 @@ pub unsafe fn input_seek(ctx: *mut DemuxerContext, pts: u64) -> c_int {
 
 pub unsafe fn input_close(ctx: *mut DemuxerContext) {
     ((*(*ctx).impl_0).close).expect("non-null function pointer")((*ctx).data);
+    fclose((*ctx).f);
     free(ctx as *mut c_void);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::read_leb128;
+    use super::reduce_fraction;
+    use super::stream_close;
+    use super::write_leb128;
+    use super::Stream;
+    use super::STREAM_WINDOW;
+    use libc::SEEK_CUR;
+    use libc::SEEK_SET;
+    use std::ffi::c_void;
+
+    #[test]
+    fn leb128_round_trips() {
+        for value in [0, 1, 0x7f, 0x80, 0x3fff, 0x4000, u32::MAX as usize] {
+            let mut buf = Vec::new();
+            write_leb128(&mut buf, value);
+            buf.push(0xff);
+            let mut data = &buf[..];
+            assert_eq!(read_leb128(&mut data), Some(value));
+            assert_eq!(data, [0xff]);
+        }
+    }
+
+    #[test]
+    fn leb128_is_at_most_8_bytes() {
+        let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        let expected = usize::try_from((1u64 << 56) - 1).ok();
+        assert_eq!(read_leb128(&mut &max[..]), expected);
+        assert_eq!(read_leb128(&mut &[0x80; 8][..]), None);
+        assert_eq!(read_leb128(&mut &[0x80, 0x80][..]), None);
+    }
+
+    #[test]
+    fn fractions_are_reduced() {
+        assert_eq!(reduce_fraction(30000, 1000), [30, 1]);
+        assert_eq!(reduce_fraction(0, 1), [0, 0]);
+        assert_eq!(reduce_fraction(1, 0), [0, 0]);
+    }
+
+    #[test]
+    fn streams_keep_a_window_once_not_retaining() {
+        let data = vec![0u8; 3 * STREAM_WINDOW];
+        let mut buf = vec![0; STREAM_WINDOW];
+        unsafe {
+            let f = libc::tmpfile();
+            libc::fwrite(data.as_ptr() as *const c_void, data.len(), 1, f);
+            libc::rewind(f);
+            let stream = Box::into_raw(Box::new(Stream {
+                f,
+                buf: Vec::new(),
+                start: 0,
+                pos: 0,
+                retain: true,
+                eof: false,
+            }));
+            let s = &mut *stream;
+            for _ in 0..3 {
+                assert_eq!(s.read(&mut buf), STREAM_WINDOW);
+            }
+            assert_eq!(s.seek(0, SEEK_SET), 0);
+
+            s.retain = false;
+            for _ in 0..3 {
+                assert_eq!(s.read(&mut buf), STREAM_WINDOW);
+            }
+            assert_eq!(s.read(&mut buf), 0);
+            assert_eq!(s.seek(0, SEEK_SET), -1);
+            let window = STREAM_WINDOW as i64;
+            assert_eq!(s.seek(-window, SEEK_CUR), 2 * window);
+            stream_close(stream as *mut c_void);
+        }
+    }
+}
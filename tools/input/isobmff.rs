@@ -0,0 +1,76 @@
+//! Reading the boxes of ISOBMFF files, shared by the `mp4` demuxer and the AVIF decoder.
+
+/// Big-endian reader over the payload of a box.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(..n)?;
+        self.data = &self.data[n..];
+        Some(bytes)
+    }
+
+    /// The bytes left to read.
+    pub fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    /// The version and flags of a full box.
+    pub fn full_box(&mut self) -> Option<(u8, u32)> {
+        let v = self.u32()?;
+        Some(((v >> 24) as u8, v & 0xffffff))
+    }
+
+    /// Splits the next child box off, returning its type and payload.
+    pub fn next_box(&mut self) -> Option<([u8; 4], &'a [u8])> {
+        let size = self.u32()? as u64;
+        let kind = self.bytes(4)?.try_into().unwrap();
+        let header = if size == 1 { 16 } else { 8 };
+        let size = match size {
+            0 => self.data.len() as u64,
+            1 => self.u64()?.checked_sub(header)?,
+            _ => size.checked_sub(header)?,
+        };
+        Some((kind, self.bytes(usize::try_from(size).ok()?)?))
+    }
+}
+
+/// Iterates over the child boxes in the payload of a box, stopping at the first corrupt one.
+pub fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut r = Reader::new(data);
+    std::iter::from_fn(move || r.next_box())
+}
+
+pub fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data)
+        .find(|(k, _)| k == kind)
+        .map(|(_, payload)| payload)
+}
+
+/// Follows a path of box types from `data`.
+pub fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter()
+        .try_fold(data, |data, kind| find_box(data, kind))
+}
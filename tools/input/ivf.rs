@@ -1,7 +1,6 @@
 use crate::compat::errno::errno_location;
 use crate::compat::stdio::stderr;
-use libc::fclose;
-use libc::fopen;
+use crate::input::input::input_is_stream;
 use libc::fprintf;
 use libc::fread;
 use libc::fseeko;
@@ -38,6 +37,7 @@ pub struct Demuxer {
         unsafe extern "C" fn(
             *mut DemuxerPriv,
             *const c_char,
+            *mut libc::FILE,
             *mut c_uint,
             *mut c_uint,
             *mut c_uint,
@@ -72,64 +72,50 @@ unsafe fn rl64(p: *const u8) -> i64 {
 unsafe extern "C" fn ivf_open(
     c: *mut IvfInputContext,
     file: *const c_char,
+    f: *mut libc::FILE,
     fps: *mut c_uint,
     num_frames: *mut c_uint,
     timebase: *mut c_uint,
 ) -> c_int {
     let mut hdr: [u8; 32] = [0; 32];
-    (*c).f = fopen(file, b"rb\0" as *const u8 as *const c_char);
-    if ((*c).f).is_null() {
+    (*c).f = f;
+    if fread(hdr.as_mut_ptr() as *mut c_void, 32, 1, (*c).f) != 1 {
         fprintf(
             stderr,
-            b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
-            file,
+            b"Failed to read stream header: %s\n\0" as *const u8 as *const c_char,
             strerror(*errno_location()),
         );
         return -1;
-    } else {
-        if fread(hdr.as_mut_ptr() as *mut c_void, 32, 1, (*c).f) != 1 {
-            fprintf(
-                stderr,
-                b"Failed to read stream header: %s\n\0" as *const u8 as *const c_char,
-                strerror(*errno_location()),
-            );
-            fclose((*c).f);
-            return -1;
-        } else {
-            let dkif = b"DKIF";
-            if hdr[..dkif.len()] != dkif[..] {
-                fprintf(
-                    stderr,
-                    b"%s is not an IVF file [tag=%.4s|0x%02x%02x%02x%02x]\n\0" as *const u8
-                        as *const c_char,
-                    file,
-                    hdr.as_mut_ptr(),
-                    hdr[0] as c_int,
-                    hdr[1] as c_int,
-                    hdr[2] as c_int,
-                    hdr[3] as c_int,
-                );
-                fclose((*c).f);
-                return -1;
-            } else {
-                let av01 = b"AV01";
-                if hdr[8..][..av01.len()] != av01[..] {
-                    fprintf(
-                        stderr,
-                        b"%s is not an AV1 file [tag=%.4s|0x%02x%02x%02x%02x]\n\0" as *const u8
-                            as *const c_char,
-                        file,
-                        &mut *hdr.as_mut_ptr().offset(8) as *mut u8,
-                        hdr[8] as c_int,
-                        hdr[9] as c_int,
-                        hdr[10] as c_int,
-                        hdr[11] as c_int,
-                    );
-                    fclose((*c).f);
-                    return -1;
-                }
-            }
-        }
+    }
+    let dkif = b"DKIF";
+    if hdr[..dkif.len()] != dkif[..] {
+        fprintf(
+            stderr,
+            b"%s is not an IVF file [tag=%.4s|0x%02x%02x%02x%02x]\n\0" as *const u8
+                as *const c_char,
+            file,
+            hdr.as_mut_ptr(),
+            hdr[0] as c_int,
+            hdr[1] as c_int,
+            hdr[2] as c_int,
+            hdr[3] as c_int,
+        );
+        return -1;
+    }
+    let av01 = b"AV01";
+    if hdr[8..][..av01.len()] != av01[..] {
+        fprintf(
+            stderr,
+            b"%s is not an AV1 file [tag=%.4s|0x%02x%02x%02x%02x]\n\0" as *const u8
+                as *const c_char,
+            file,
+            &mut *hdr.as_mut_ptr().offset(8) as *mut u8,
+            hdr[8] as c_int,
+            hdr[9] as c_int,
+            hdr[10] as c_int,
+            hdr[11] as c_int,
+        );
+        return -1;
     }
     *timebase.offset(0) = rl32(&mut *hdr.as_mut_ptr().offset(16));
     *timebase.offset(1) = rl32(&mut *hdr.as_mut_ptr().offset(20));
@@ -137,7 +123,9 @@ unsafe extern "C" fn ivf_open(
     let mut data: [u8; 8] = [0; 8];
     (*c).broken = 0 as c_int;
     *num_frames = 0 as c_int as c_uint;
-    while !(fread(data.as_mut_ptr() as *mut c_void, 4, 1, (*c).f) != 1) {
+    // Streams can't be seeked back after counting the frames, so their count is unknown.
+    let stream = input_is_stream(file);
+    while !stream && !(fread(data.as_mut_ptr() as *mut c_void, 4, 1, (*c).f) != 1) {
         let sz: usize = rl32(data.as_mut_ptr()) as usize;
         if fread(data.as_mut_ptr() as *mut c_void, 8, 1, (*c).f) != 1 {
             break;
@@ -179,6 +167,9 @@ unsafe extern "C" fn ivf_open(
         *fresh0 = 0 as c_int as c_uint;
         *fps.offset(0) = *fresh0;
     }
+    if stream {
+        *num_frames = u32::MAX;
+    }
     (*c).timebase = *timebase.offset(0) as c_double / *timebase.offset(1) as c_double;
     (*c).step = duration.wrapping_div(*num_frames) as u64;
     fseeko((*c).f, 32, 0);
@@ -296,9 +287,7 @@ unsafe extern "C" fn ivf_seek(c: *mut IvfInputContext, pts: u64) -> c_int {
     }
 }
 
-unsafe extern "C" fn ivf_close(c: *mut IvfInputContext) {
-    fclose((*c).f);
-}
+unsafe extern "C" fn ivf_close(_c: *mut IvfInputContext) {}
 
 #[no_mangle]
 pub static mut ivf_demuxer: Demuxer = Demuxer {
@@ -0,0 +1,819 @@
+//! Demuxer for the `V_AV1` track of Matroska and WebM files.
+//!
+//! Only the EBML elements needed to find the AV1 blocks are parsed:
+//! the stream headers (`Info`, `Tracks`) and the `Cues` are read into memory,
+//! and the clusters are then scanned element by element,
+//! descending into `Segment`, `Cluster` and `BlockGroup` and skipping everything else.
+//! This works because element IDs are unique across the levels of a Matroska file,
+//! and also supports clusters of unknown size, as written by live muxers.
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::input::input::demuxer_priv_init;
+use crate::input::input::print_error;
+use crate::input::input::read_exact;
+use crate::input::input::reduce_fraction;
+use libc::fprintf;
+use libc::fseeko;
+use libc::ftello;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::src::lib::dav1d_data_create;
+use rav1d::src::lib::dav1d_data_unref;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ptr;
+
+const EBML_ID: u32 = 0x1a45dfa3;
+const DOC_TYPE_ID: u32 = 0x4282;
+const SEGMENT_ID: u32 = 0x18538067;
+const INFO_ID: u32 = 0x1549a966;
+const TIMESTAMP_SCALE_ID: u32 = 0x2ad7b1;
+const TRACKS_ID: u32 = 0x1654ae6b;
+const TRACK_ENTRY_ID: u32 = 0xae;
+const TRACK_NUMBER_ID: u32 = 0xd7;
+const CODEC_ID_ID: u32 = 0x86;
+const CODEC_PRIVATE_ID: u32 = 0x63a2;
+const DEFAULT_DURATION_ID: u32 = 0x23e383;
+const CLUSTER_ID: u32 = 0x1f43b675;
+const TIMESTAMP_ID: u32 = 0xe7;
+const SIMPLE_BLOCK_ID: u32 = 0xa3;
+const BLOCK_GROUP_ID: u32 = 0xa0;
+const BLOCK_ID: u32 = 0xa1;
+const CUES_ID: u32 = 0x1c53bb6b;
+const CUE_POINT_ID: u32 = 0xbb;
+const CUE_TIME_ID: u32 = 0xb3;
+const CUE_TRACK_POSITIONS_ID: u32 = 0xb7;
+const CUE_TRACK_ID: u32 = 0xf7;
+const CUE_CLUSTER_POSITION_ID: u32 = 0xf1;
+
+/// Elements read into memory are small; anything bigger is a corrupt size.
+const MAX_PAYLOAD_SIZE: u64 = 1 << 28;
+
+#[derive(Clone, Copy)]
+pub struct CuePoint {
+    pub time: u64,
+    pub track: u64,
+    /// Offset of the cluster from the start of the segment data.
+    pub cluster_pos: u64,
+}
+
+#[repr(C)]
+pub struct DemuxerPriv {
+    pub f: *mut libc::FILE,
+    pub track: u64,
+    pub timestamp_scale: u64,
+    pub segment_start: libc::off_t,
+    pub first_cluster: libc::off_t,
+    pub cluster_ts: u64,
+    /// The `configOBUs` of the `av1C` in `CodecPrivate`,
+    /// prepended to the first block after opening or seeking.
+    pub config: Vec<u8>,
+    pub config_pending: bool,
+    pub cues: Vec<CuePoint>,
+}
+
+impl Default for DemuxerPriv {
+    fn default() -> Self {
+        Self {
+            f: ptr::null_mut(),
+            track: Default::default(),
+            timestamp_scale: Default::default(),
+            segment_start: Default::default(),
+            first_cluster: Default::default(),
+            cluster_ts: Default::default(),
+            config: Default::default(),
+            config_pending: Default::default(),
+            cues: Default::default(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct Demuxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub probe_sz: c_int,
+    pub probe: Option<unsafe extern "C" fn(*const u8) -> c_int>,
+    pub open: Option<
+        unsafe extern "C" fn(
+            *mut DemuxerPriv,
+            *const c_char,
+            *mut libc::FILE,
+            *mut c_uint,
+            *mut c_uint,
+            *mut c_uint,
+        ) -> c_int,
+    >,
+    pub read: Option<unsafe extern "C" fn(*mut DemuxerPriv, *mut Dav1dData) -> c_int>,
+    pub seek: Option<unsafe extern "C" fn(*mut DemuxerPriv, u64) -> c_int>,
+    pub close: Option<unsafe extern "C" fn(*mut DemuxerPriv) -> ()>,
+}
+
+pub type MatroskaInputContext = DemuxerPriv;
+
+/// A `SimpleBlock` or `Block` of the AV1 track.
+struct BlockHeader {
+    /// Offset of the block element.
+    off: libc::off_t,
+    ts: u64,
+    /// Size of the frame data following the block header.
+    size: u64,
+    /// Only known for a `SimpleBlock`; a `Block` is never considered a keyframe.
+    key: bool,
+}
+
+const PROBE_SZ: usize = 64;
+
+unsafe extern "C" fn matroska_probe(data: *const u8) -> c_int {
+    let data = &*(data as *const [u8; PROBE_SZ]);
+    let is_ebml = data[..4] == EBML_ID.to_be_bytes();
+    let doc_type = |name: &[u8]| data.windows(name.len()).any(|w| w == name);
+    (is_ebml && (doc_type(b"matroska") || doc_type(b"webm"))) as c_int
+}
+
+/// Parses an EBML variable-length integer, keeping its length marker for element IDs.
+/// Also returns whether all value bits are set, which means an unknown size.
+fn parse_vint(data: &mut &[u8], keep_marker: bool) -> Option<(u64, bool)> {
+    let first = *data.first()?;
+    if first == 0 {
+        return None;
+    }
+    let len = first.leading_zeros() as usize + 1;
+    let bytes = data.get(..len)?;
+    *data = &data[len..];
+    let marker = 1u64 << (7 * len);
+    let mut val = bytes.iter().fold(0u64, |val, &b| val << 8 | b as u64);
+    let unknown = val == (marker << 1) - 1;
+    if !keep_marker {
+        val ^= marker;
+    }
+    Some((val, unknown))
+}
+
+fn parse_uint(data: &[u8]) -> u64 {
+    data.iter().fold(0, |val, &b| val << 8 | b as u64)
+}
+
+/// Splits the next child element off the payload of a master element read into memory.
+fn parse_element<'a>(data: &mut &'a [u8]) -> Option<(u32, &'a [u8])> {
+    let (id, _) = parse_vint(data, true)?;
+    let (size, _) = parse_vint(data, false)?;
+    let payload = data.get(..usize::try_from(size).ok()?)?;
+    *data = &data[payload.len()..];
+    Some((id as u32, payload))
+}
+
+/// Iterates over the child elements of a master element, stopping at the first corrupt one.
+fn children(mut data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    std::iter::from_fn(move || parse_element(&mut data))
+}
+
+/// Reads a variable-length integer from `f`, returning it with its length in bytes.
+unsafe fn read_vint(f: *mut libc::FILE, keep_marker: bool) -> Option<(u64, bool, usize)> {
+    let mut buf = [0; 8];
+    if !read_exact(f, &mut buf[..1]) || buf[0] == 0 {
+        return None;
+    }
+    let len = buf[0].leading_zeros() as usize + 1;
+    if !read_exact(f, &mut buf[1..len]) {
+        return None;
+    }
+    let (val, unknown) = parse_vint(&mut &buf[..len], keep_marker)?;
+    Some((val, unknown, len))
+}
+
+/// Reads an element ID and size, with [`None`] as the size of elements of unknown size.
+unsafe fn read_element_header(f: *mut libc::FILE) -> Option<(u32, Option<u64>)> {
+    let (id, _, _) = read_vint(f, true)?;
+    let (size, unknown, _) = read_vint(f, false)?;
+    Some((id as u32, (!unknown).then_some(size)))
+}
+
+unsafe fn read_payload(f: *mut libc::FILE, size: Option<u64>) -> Result<Vec<u8>, String> {
+    let size = size
+        .filter(|&size| size <= MAX_PAYLOAD_SIZE)
+        .ok_or("invalid element size")?;
+    let mut payload = vec![0; size as usize];
+    if !read_exact(f, &mut payload) {
+        return Err("unexpected end of file".into());
+    }
+    Ok(payload)
+}
+
+unsafe fn skip(f: *mut libc::FILE, size: Option<u64>) -> Result<(), String> {
+    let size = size.ok_or("unsupported element of unknown size")?;
+    if fseeko(f, size as libc::off_t, 1) != 0 {
+        return Err("failed to skip element".into());
+    }
+    Ok(())
+}
+
+fn parse_info(c: &mut MatroskaInputContext, info: &[u8]) {
+    for (id, payload) in children(info) {
+        if id == TIMESTAMP_SCALE_ID {
+            c.timestamp_scale = parse_uint(payload);
+        }
+    }
+}
+
+/// Selects the first `V_AV1` track, returning its default duration in nanoseconds if it has one.
+fn parse_tracks(c: &mut MatroskaInputContext, tracks: &[u8]) -> Result<u64, String> {
+    for (_, entry) in children(tracks).filter(|&(id, _)| id == TRACK_ENTRY_ID) {
+        let mut number = 0;
+        let mut codec = &[][..];
+        let mut codec_private = &[][..];
+        let mut default_duration = 0;
+        for (id, payload) in children(entry) {
+            match id {
+                TRACK_NUMBER_ID => number = parse_uint(payload),
+                CODEC_ID_ID => codec = payload,
+                CODEC_PRIVATE_ID => codec_private = payload,
+                DEFAULT_DURATION_ID => default_duration = parse_uint(payload),
+                _ => {}
+            }
+        }
+        if codec != b"V_AV1" || number == 0 {
+            continue;
+        }
+        // The `av1C` box starts with a marker bit and a version of 1,
+        // followed by 3 bytes of sequence header fields and the `configOBUs`.
+        if !codec_private.is_empty() {
+            if codec_private.len() < 4 || codec_private[0] != 0x81 {
+                return Err("invalid av1C in CodecPrivate".into());
+            }
+            c.config = codec_private[4..].to_vec();
+        }
+        c.track = number;
+        return Ok(default_duration);
+    }
+    Err("no V_AV1 track found".into())
+}
+
+fn parse_cues(c: &mut MatroskaInputContext, cues: &[u8]) {
+    c.cues.clear();
+    for (_, point) in children(cues).filter(|&(id, _)| id == CUE_POINT_ID) {
+        let mut time = 0;
+        let mut positions = Vec::new();
+        for (id, payload) in children(point) {
+            match id {
+                CUE_TIME_ID => time = parse_uint(payload),
+                CUE_TRACK_POSITIONS_ID => {
+                    let (mut track, mut cluster_pos) = (0, None);
+                    for (id, payload) in children(payload) {
+                        match id {
+                            CUE_TRACK_ID => track = parse_uint(payload),
+                            CUE_CLUSTER_POSITION_ID => cluster_pos = Some(parse_uint(payload)),
+                            _ => {}
+                        }
+                    }
+                    positions.extend(cluster_pos.map(|cluster_pos| (track, cluster_pos)));
+                }
+                _ => {}
+            }
+        }
+        c.cues
+            .extend(positions.into_iter().map(|(track, cluster_pos)| CuePoint {
+                time,
+                track,
+                cluster_pos,
+            }));
+    }
+}
+
+/// Advances to the next block of the AV1 track,
+/// leaving the file positioned at the start of its frame data.
+///
+/// Returns [`None`] at the end of the file.
+unsafe fn next_block(c: &mut MatroskaInputContext) -> Result<Option<BlockHeader>, String> {
+    loop {
+        let off = ftello(c.f);
+        let Some((id, size)) = read_element_header(c.f) else {
+            return Ok(None);
+        };
+        match id {
+            // Descend into these, whether their size is known or not.
+            SEGMENT_ID | CLUSTER_ID | BLOCK_GROUP_ID => {}
+            TIMESTAMP_ID => c.cluster_ts = parse_uint(&read_payload(c.f, size)?),
+            CUES_ID => parse_cues(c, &read_payload(c.f, size)?),
+            SIMPLE_BLOCK_ID | BLOCK_ID => {
+                let size = size.ok_or("block of unknown size")?;
+                let (track, _, track_len) = read_vint(c.f, false).ok_or("invalid block header")?;
+                let mut hdr = [0; 3];
+                if !read_exact(c.f, &mut hdr) || size < (track_len + 3) as u64 {
+                    return Err("invalid block header".into());
+                }
+                let size = size - (track_len + 3) as u64;
+                if track != c.track {
+                    skip(c.f, Some(size))?;
+                    continue;
+                }
+                if hdr[2] & 0x06 != 0 {
+                    return Err("laced blocks are not allowed for AV1".into());
+                }
+                let rel_ts = i16::from_be_bytes([hdr[0], hdr[1]]) as i64;
+                let ts = (c.cluster_ts as i64 + rel_ts).max(0) as u64;
+                let key = id == SIMPLE_BLOCK_ID && hdr[2] & 0x80 != 0;
+                return Ok(Some(BlockHeader { off, ts, size, key }));
+            }
+            _ => skip(c.f, size)?,
+        }
+    }
+}
+
+/// Parses the stream headers, and counts the frames of the AV1 track.
+unsafe fn matroska_parse_headers(
+    c: &mut MatroskaInputContext,
+    fps: *mut c_uint,
+    num_frames: *mut c_uint,
+    timebase: *mut c_uint,
+) -> Result<(), String> {
+    let (id, size) = read_element_header(c.f).ok_or("not a Matroska file")?;
+    if id != EBML_ID {
+        return Err("not a Matroska file".into());
+    }
+    let doc_type = children(&read_payload(c.f, size)?)
+        .find(|&(id, _)| id == DOC_TYPE_ID)
+        .map(|(_, doc_type)| doc_type.to_vec());
+    // Strings may be padded with zeros.
+    match doc_type
+        .as_deref()
+        .map(|d| d.split(|&b| b == 0).next().unwrap())
+    {
+        Some(b"matroska") | Some(b"webm") => {}
+        _ => return Err("unsupported EBML document type".into()),
+    }
+    let (id, _) = read_element_header(c.f).ok_or("missing segment")?;
+    if id != SEGMENT_ID {
+        return Err("missing segment".into());
+    }
+    c.segment_start = ftello(c.f);
+
+    let mut default_duration = 0;
+    c.first_cluster = -1;
+    while c.first_cluster < 0 {
+        let off = ftello(c.f);
+        let Some((id, size)) = read_element_header(c.f) else {
+            c.first_cluster = off;
+            break;
+        };
+        match id {
+            CLUSTER_ID => c.first_cluster = off,
+            INFO_ID => parse_info(c, &read_payload(c.f, size)?),
+            TRACKS_ID => default_duration = parse_tracks(c, &read_payload(c.f, size)?)?,
+            CUES_ID => parse_cues(c, &read_payload(c.f, size)?),
+            _ => skip(c.f, size)?,
+        }
+    }
+    if c.track == 0 {
+        return Err("no V_AV1 track found".into());
+    }
+    if c.timestamp_scale == 0 {
+        return Err("invalid TimestampScale".into());
+    }
+
+    // Like the IVF demuxer, count the frames to estimate the frame rate.
+    // This also finds `Cues` written after the clusters.
+    fseeko(c.f, c.first_cluster, 0);
+    let mut n = 0u64;
+    let mut first_ts = 0;
+    let mut last_ts = 0;
+    while let Some(block) = next_block(c)? {
+        if n == 0 {
+            first_ts = block.ts;
+        }
+        last_ts = block.ts;
+        n += 1;
+        skip(c.f, Some(block.size))?;
+    }
+    *num_frames = n as c_uint;
+
+    let [tb_num, tb_den] = reduce_fraction(1000000000, c.timestamp_scale);
+    *timebase.offset(0) = tb_num;
+    *timebase.offset(1) = tb_den;
+    let [fps_num, fps_den] = if default_duration != 0 {
+        reduce_fraction(1000000000, default_duration)
+    } else if n > 1 && last_ts > first_ts {
+        reduce_fraction(
+            (n - 1).saturating_mul(1000000000),
+            (last_ts - first_ts).saturating_mul(c.timestamp_scale),
+        )
+    } else {
+        [0, 0]
+    };
+    *fps.offset(0) = fps_num;
+    *fps.offset(1) = fps_den;
+
+    fseeko(c.f, c.first_cluster, 0);
+    c.cluster_ts = 0;
+    c.config_pending = true;
+    Ok(())
+}
+
+unsafe extern "C" fn matroska_open(
+    c: *mut MatroskaInputContext,
+    file: *const c_char,
+    f: *mut libc::FILE,
+    fps: *mut c_uint,
+    num_frames: *mut c_uint,
+    timebase: *mut c_uint,
+) -> c_int {
+    demuxer_priv_init(c);
+    (*c).f = f;
+    (*c).timestamp_scale = 1000000;
+    if let Err(e) = matroska_parse_headers(&mut *c, fps, num_frames, timebase) {
+        print_error(format!(
+            "Failed to parse {}: {e}",
+            std::ffi::CStr::from_ptr(file).to_string_lossy()
+        ));
+        matroska_close(c);
+        return -1;
+    }
+    0
+}
+
+unsafe extern "C" fn matroska_read(c: *mut MatroskaInputContext, buf: *mut Dav1dData) -> c_int {
+    let block = match next_block(&mut *c) {
+        Ok(Some(block)) => block,
+        Ok(None) => return -1,
+        Err(e) => {
+            print_error(format!("Failed to read block: {e}"));
+            return -1;
+        }
+    };
+    let config_len = if (*c).config_pending {
+        (*c).config.len()
+    } else {
+        0
+    };
+    let ptr = dav1d_data_create(buf, config_len + block.size as usize);
+    if ptr.is_null() {
+        return -1;
+    }
+    ptr::copy_nonoverlapping((*c).config.as_ptr(), ptr, config_len);
+    let frame = std::slice::from_raw_parts_mut(ptr.add(config_len), block.size as usize);
+    if !read_exact((*c).f, frame) {
+        fprintf(
+            stderr,
+            b"Failed to read frame data: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        dav1d_data_unref(buf);
+        return -1;
+    }
+    (*c).config_pending = false;
+    (*buf).m.offset = block.off;
+    (*buf).m.timestamp = block.ts as i64;
+    0
+}
+
+/// Seeks to the last keyframe at or before `pts`, or to the first one after it if there is none.
+/// The search starts from the cluster of the last cue point before `pts`,
+/// or from the first cluster if there are no `Cues`.
+unsafe extern "C" fn matroska_seek(c: *mut MatroskaInputContext, pts: u64) -> c_int {
+    let c = &mut *c;
+    let ts = (pts as f64 / c.timestamp_scale as f64).round() as u64;
+    let cluster = c
+        .cues
+        .iter()
+        .filter(|cue| cue.track == c.track && cue.time <= ts)
+        .max_by_key(|cue| cue.time)
+        .map_or(c.first_cluster, |cue| {
+            c.segment_start + cue.cluster_pos as libc::off_t
+        });
+    if fseeko(c.f, cluster, 0) != 0 {
+        fprintf(
+            stderr,
+            b"Failed to seek: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    c.cluster_ts = 0;
+    let mut key = None;
+    loop {
+        let block = match next_block(c) {
+            Ok(Some(block)) => block,
+            Ok(None) => break,
+            Err(e) => {
+                print_error(format!("Failed to seek: {e}"));
+                return -1;
+            }
+        };
+        if block.ts > ts && key.is_some() {
+            break;
+        }
+        if block.key {
+            key = Some((block.off, c.cluster_ts));
+            if block.ts >= ts {
+                break;
+            }
+        }
+        if skip(c.f, Some(block.size)).is_err() {
+            return -1;
+        }
+    }
+    let Some((off, cluster_ts)) = key else {
+        return -1;
+    };
+    if fseeko(c.f, off, 0) != 0 {
+        return -1;
+    }
+    c.cluster_ts = cluster_ts;
+    c.config_pending = true;
+    0
+}
+
+unsafe extern "C" fn matroska_close(c: *mut MatroskaInputContext) {
+    ptr::drop_in_place(c);
+}
+
+#[no_mangle]
+pub static mut matroska_demuxer: Demuxer = Demuxer {
+    priv_data_size: ::core::mem::size_of::<MatroskaInputContext>() as c_ulong as c_int,
+    name: b"matroska\0" as *const u8 as *const c_char,
+    probe_sz: PROBE_SZ as c_int,
+    probe: Some(matroska_probe),
+    open: Some(matroska_open),
+    read: Some(matroska_read),
+    seek: Some(matroska_seek),
+    close: Some(matroska_close),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+
+    /// An element with a size of 1 byte, or 8 bytes if it doesn't fit.
+    fn el(id: u32, payload: &[u8]) -> Vec<u8> {
+        let id = id.to_be_bytes();
+        let mut e = id[id.iter().position(|&b| b != 0).unwrap()..].to_vec();
+        if payload.len() < 0x7f {
+            e.push(0x80 | payload.len() as u8);
+        } else {
+            e.push(0x01);
+            e.extend(&(payload.len() as u64).to_be_bytes()[1..]);
+        }
+        e.extend(payload);
+        e
+    }
+
+    /// An element of unknown size, followed by its children.
+    fn unknown_size_el(id: u32, children: &[u8]) -> Vec<u8> {
+        let mut e = el(id, &[]);
+        *e.last_mut().unwrap() = 0xff;
+        e.extend(children);
+        e
+    }
+
+    fn block(id: u32, track: u8, rel_ts: i16, flags: u8, frame: &[u8]) -> Vec<u8> {
+        let mut payload = vec![0x80 | track];
+        payload.extend(rel_ts.to_be_bytes());
+        payload.push(flags);
+        payload.extend(frame);
+        el(id, &payload)
+    }
+
+    const CONFIG: [u8; 3] = [0x0a, 1, 0xaa];
+
+    /// A WebM file with a VP9 track 1 and an AV1 track 2,
+    /// a cluster of unknown size and one with `Cues`, with blocks at 0, 40, 80 and 120 ms.
+    fn webm(flags: u8) -> Vec<u8> {
+        let tracks = [
+            el(
+                TRACK_ENTRY_ID,
+                &[el(TRACK_NUMBER_ID, &[1]), el(CODEC_ID_ID, b"V_VP9")].concat(),
+            ),
+            el(
+                TRACK_ENTRY_ID,
+                &[
+                    el(TRACK_NUMBER_ID, &[2]),
+                    el(CODEC_ID_ID, b"V_AV1"),
+                    el(CODEC_PRIVATE_ID, &[&[0x81, 0, 0, 0][..], &CONFIG].concat()),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let headers = [
+            el(INFO_ID, &el(TIMESTAMP_SCALE_ID, &[0x0f, 0x42, 0x40])),
+            el(TRACKS_ID, &tracks),
+        ]
+        .concat();
+        let first = unknown_size_el(
+            CLUSTER_ID,
+            &[
+                el(TIMESTAMP_ID, &[0]),
+                block(SIMPLE_BLOCK_ID, 2, 0, 0x80 | flags, &[0x32, 1, 0]),
+                block(SIMPLE_BLOCK_ID, 1, 0, 0x80, &[0xff; 4]),
+                el(BLOCK_GROUP_ID, &block(BLOCK_ID, 2, 40, 0, &[0x32, 1, 40])),
+            ]
+            .concat(),
+        );
+        let second = el(
+            CLUSTER_ID,
+            &[
+                el(TIMESTAMP_ID, &[80]),
+                block(SIMPLE_BLOCK_ID, 2, 0, 0x80, &[0x32, 1, 80]),
+                block(SIMPLE_BLOCK_ID, 2, 40, 0, &[0x32, 1, 120]),
+            ]
+            .concat(),
+        );
+        let second_pos = (headers.len() + first.len()) as u8;
+        let cues = el(
+            CUES_ID,
+            &el(
+                CUE_POINT_ID,
+                &[
+                    el(CUE_TIME_ID, &[80]),
+                    el(
+                        CUE_TRACK_POSITIONS_ID,
+                        &[
+                            el(CUE_TRACK_ID, &[2]),
+                            el(CUE_CLUSTER_POSITION_ID, &[second_pos]),
+                        ]
+                        .concat(),
+                    ),
+                ]
+                .concat(),
+            ),
+        );
+        [
+            el(EBML_ID, &el(DOC_TYPE_ID, b"webm")),
+            unknown_size_el(SEGMENT_ID, &[headers, first, second, cues].concat()),
+        ]
+        .concat()
+    }
+
+    /// Opens `file` as `c`, returning the frame rate, frame count and timebase.
+    unsafe fn open(
+        c: *mut MatroskaInputContext,
+        file: &[u8],
+    ) -> Option<(*mut libc::FILE, [c_uint; 2], c_uint, [c_uint; 2])> {
+        let f = libc::tmpfile();
+        libc::fwrite(file.as_ptr() as *const _, file.len(), 1, f);
+        libc::rewind(f);
+        let (mut fps, mut num_frames, mut timebase) = ([0; 2], 0, [0; 2]);
+        let res = matroska_open(
+            c,
+            b"test.webm\0".as_ptr().cast(),
+            f,
+            fps.as_mut_ptr(),
+            &mut num_frames,
+            timebase.as_mut_ptr(),
+        );
+        if res < 0 {
+            libc::fclose(f);
+            return None;
+        }
+        Some((f, fps, num_frames, timebase))
+    }
+
+    /// Reads the next frame, returning its timestamp and data.
+    unsafe fn read(c: *mut MatroskaInputContext) -> Option<(i64, Vec<u8>)> {
+        let mut data: Dav1dData = Default::default();
+        if matroska_read(c, &mut data) < 0 {
+            return None;
+        }
+        let frame = std::slice::from_raw_parts(data.data.unwrap().as_ptr(), data.sz).to_vec();
+        let ts = data.m.timestamp;
+        dav1d_data_unref(&mut data);
+        Some((ts, frame))
+    }
+
+    #[test]
+    fn vints() {
+        let mut data = &[0x81, 0x40, 0x02, 0x1a, 0x45, 0xdf, 0xa3, 0xff, 0x01][..];
+        assert_eq!(parse_vint(&mut data, false), Some((1, false)));
+        assert_eq!(parse_vint(&mut data, false), Some((2, false)));
+        assert_eq!(parse_vint(&mut data, true), Some((EBML_ID as u64, false)));
+        assert_eq!(parse_vint(&mut data, false), Some((0x7f, true)));
+        // Truncated.
+        assert_eq!(parse_vint(&mut data, false), None);
+        assert_eq!(parse_vint(&mut &[0][..], false), None);
+    }
+
+    #[test]
+    fn children_stop_at_corrupt_elements() {
+        let data = [
+            el(TIMESTAMP_ID, &[1, 2]),
+            el(CUE_TIME_ID, &[3]),
+            vec![0xe7, 0x85, 0],
+        ]
+        .concat();
+        let children = children(&data).collect::<Vec<_>>();
+        assert_eq!(
+            children,
+            [(TIMESTAMP_ID, &[1, 2][..]), (CUE_TIME_ID, &[3][..])]
+        );
+        assert_eq!(parse_uint(&[1, 2]), 0x102);
+    }
+
+    #[test]
+    fn tracks() {
+        let mut c = MatroskaInputContext::default();
+        let entry = |codec_private: &[u8]| {
+            el(
+                TRACK_ENTRY_ID,
+                &[
+                    el(TRACK_NUMBER_ID, &[3]),
+                    el(CODEC_ID_ID, b"V_AV1"),
+                    el(DEFAULT_DURATION_ID, &[0x02, 0x62, 0x5a]),
+                    el(CODEC_PRIVATE_ID, codec_private),
+                ]
+                .concat(),
+            )
+        };
+        assert_eq!(
+            parse_tracks(&mut c, &entry(&[0x81, 0, 0, 0, 1])),
+            Ok(0x2625a)
+        );
+        assert_eq!((c.track, &c.config[..]), (3, &[1][..]));
+        assert!(parse_tracks(&mut c, &entry(&[0x80, 0, 0, 0])).is_err());
+        assert!(parse_tracks(&mut c, &[]).is_err());
+    }
+
+    #[test]
+    fn cues() {
+        let mut c = MatroskaInputContext::default();
+        let point = el(
+            CUE_POINT_ID,
+            &[
+                el(CUE_TIME_ID, &[1, 0]),
+                el(
+                    CUE_TRACK_POSITIONS_ID,
+                    &[el(CUE_TRACK_ID, &[1]), el(CUE_CLUSTER_POSITION_ID, &[9])].concat(),
+                ),
+                el(
+                    CUE_TRACK_POSITIONS_ID,
+                    &[el(CUE_TRACK_ID, &[2]), el(CUE_CLUSTER_POSITION_ID, &[7])].concat(),
+                ),
+                // Without a position.
+                el(CUE_TRACK_POSITIONS_ID, &el(CUE_TRACK_ID, &[3])),
+            ]
+            .concat(),
+        );
+        parse_cues(&mut c, &point);
+        let cues = c
+            .cues
+            .iter()
+            .map(|cue| (cue.time, cue.track, cue.cluster_pos))
+            .collect::<Vec<_>>();
+        assert_eq!(cues, [(256, 1, 9), (256, 2, 7)]);
+    }
+
+    #[test]
+    fn probe() {
+        let mut hdr = [0; PROBE_SZ];
+        let ebml = el(EBML_ID, &el(DOC_TYPE_ID, b"matroska"));
+        hdr[..ebml.len()].copy_from_slice(&ebml);
+        // Safety: `hdr` is `PROBE_SZ` bytes long.
+        assert_ne!(unsafe { matroska_probe(hdr.as_ptr()) }, 0);
+        hdr[0] = 0;
+        assert_eq!(unsafe { matroska_probe(hdr.as_ptr()) }, 0);
+    }
+
+    #[test]
+    fn blocks() {
+        let mut c = MaybeUninit::<MatroskaInputContext>::uninit();
+        let c = c.as_mut_ptr();
+        unsafe {
+            let (f, fps, num_frames, timebase) = open(c, &webm(0)).unwrap();
+            assert_eq!((fps, num_frames, timebase), ([25, 1], 4, [1000, 1]));
+            assert_eq!(read(c), Some((0, [&CONFIG[..], &[0x32, 1, 0]].concat())));
+            assert_eq!(read(c), Some((40, vec![0x32, 1, 40])));
+            assert_eq!(read(c), Some((80, vec![0x32, 1, 80])));
+            assert_eq!(read(c), Some((120, vec![0x32, 1, 120])));
+            assert_eq!(read(c), None);
+            matroska_close(c);
+            libc::fclose(f);
+        }
+    }
+
+    #[test]
+    fn seeks_to_keyframes() {
+        let mut c = MaybeUninit::<MatroskaInputContext>::uninit();
+        let c = c.as_mut_ptr();
+        unsafe {
+            let (f, ..) = open(c, &webm(0)).unwrap();
+            // Through the cue point of the second cluster.
+            assert_eq!(matroska_seek(c, 120_000_000), 0);
+            assert_eq!(read(c), Some((80, [&CONFIG[..], &[0x32, 1, 80]].concat())));
+            assert_eq!(read(c), Some((120, vec![0x32, 1, 120])));
+            // From the first cluster, as the `Block` isn't a keyframe.
+            assert_eq!(matroska_seek(c, 60_000_000), 0);
+            assert_eq!(read(c).unwrap().0, 0);
+            matroska_close(c);
+            libc::fclose(f);
+        }
+    }
+
+    #[test]
+    fn rejects_laced_blocks() {
+        let mut c = MaybeUninit::<MatroskaInputContext>::uninit();
+        assert!(unsafe { open(c.as_mut_ptr(), &webm(0x02)) }.is_none());
+    }
+}
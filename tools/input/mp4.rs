@@ -0,0 +1,825 @@
+//! Demuxer for the `av01` track of ISOBMFF (MP4) files, both plain and fragmented.
+//!
+//! The `moov` box and every `moof` box are read into memory while scanning the file on open,
+//! to build a table of the offset, size, timestamp, duration and sync flag of every sample.
+//! Samples are then read from that table, which also makes seeking to a sync sample simple.
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::input::input::demuxer_priv_init;
+use crate::input::input::print_error;
+use crate::input::input::read_exact;
+use crate::input::input::reduce_fraction;
+use crate::input::isobmff::boxes;
+use crate::input::isobmff::find_box;
+use crate::input::isobmff::find_path;
+use crate::input::isobmff::Reader;
+use libc::fprintf;
+use libc::fseeko;
+use libc::ftello;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::src::lib::dav1d_data_create;
+use rav1d::src::lib::dav1d_data_unref;
+use std::cmp;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::CStr;
+use std::ptr;
+
+/// Boxes read into memory are small; anything bigger is a corrupt size.
+const MAX_BOX_SIZE: u64 = 1 << 28;
+
+/// `sample_is_non_sync_sample` in the sample flags of fragments.
+const SAMPLE_IS_NON_SYNC: u32 = 0x10000;
+
+#[derive(Clone, Copy)]
+pub struct Sample {
+    pub offset: u64,
+    pub size: u32,
+    /// Presentation time in units of the media timescale.
+    pub ts: i64,
+    pub duration: u32,
+    pub sync: bool,
+}
+
+#[repr(C)]
+pub struct DemuxerPriv {
+    pub f: *mut libc::FILE,
+    pub timescale: u32,
+    pub samples: Vec<Sample>,
+    pub cur: usize,
+    /// The `configOBUs` of the `av1C` box,
+    /// prepended to the first sample after opening or seeking.
+    pub config: Vec<u8>,
+    pub config_pending: bool,
+}
+
+impl Default for DemuxerPriv {
+    fn default() -> Self {
+        Self {
+            f: ptr::null_mut(),
+            timescale: Default::default(),
+            samples: Default::default(),
+            cur: Default::default(),
+            config: Default::default(),
+            config_pending: Default::default(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct Demuxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub probe_sz: c_int,
+    pub probe: Option<unsafe extern "C" fn(*const u8) -> c_int>,
+    pub open: Option<
+        unsafe extern "C" fn(
+            *mut DemuxerPriv,
+            *const c_char,
+            *mut libc::FILE,
+            *mut c_uint,
+            *mut c_uint,
+            *mut c_uint,
+        ) -> c_int,
+    >,
+    pub read: Option<unsafe extern "C" fn(*mut DemuxerPriv, *mut Dav1dData) -> c_int>,
+    pub seek: Option<unsafe extern "C" fn(*mut DemuxerPriv, u64) -> c_int>,
+    pub close: Option<unsafe extern "C" fn(*mut DemuxerPriv) -> ()>,
+}
+
+pub type Mp4InputContext = DemuxerPriv;
+
+/// Box types that a file can start with, as not every file starts with an `ftyp` box:
+/// fragmented segments start with `styp`, `sidx` or `moof`,
+/// and some files start with `moov`, `mdat` or padding.
+const TOP_LEVEL_BOXES: [&[u8; 4]; 9] = [
+    b"ftyp", b"styp", b"moov", b"moof", b"sidx", b"mdat", b"free", b"skip", b"wide",
+];
+
+unsafe extern "C" fn mp4_probe(data: *const u8) -> c_int {
+    let hdr = &*(data as *const [u8; 8]);
+    let size = u32::from_be_bytes(hdr[..4].try_into().unwrap());
+    let kind = &hdr[4..];
+    ((size == 0 || size == 1 || size >= 8) && TOP_LEVEL_BOXES.iter().any(|&k| k == kind)) as c_int
+}
+
+/// Sample defaults of a track from its `trex` box, overridden by `tfhd`.
+#[derive(Clone, Copy, Default)]
+struct SampleDefaults {
+    duration: u32,
+    size: u32,
+    flags: u32,
+}
+
+/// The `av01` track found in the `moov` box.
+#[derive(Default)]
+struct Track {
+    id: u32,
+    defaults: SampleDefaults,
+    /// Decode time of the next fragment without a `tfdt`.
+    next_decode_time: u64,
+}
+
+/// Finds the `av01` sample entry in an `stsd` box,
+/// returning its child boxes and the `configOBUs` of its `av1C` box.
+fn parse_sample_entry(stsd: &[u8]) -> Result<Option<(&[u8], Vec<u8>)>, String> {
+    let mut r = Reader::new(stsd);
+    r.full_box().ok_or("invalid stsd box")?;
+    let count = r.u32().ok_or("invalid stsd box")?;
+    for _ in 0..count {
+        let (kind, entry) = r.next_box().ok_or("invalid stsd box")?;
+        if kind != *b"av01" {
+            continue;
+        }
+        // The fields of a `VisualSampleEntry` precede its child boxes.
+        let children = entry.get(78..).ok_or("invalid av01 sample entry")?;
+        let mut r = Reader::new(find_box(children, b"av1C").ok_or("missing av1C box")?);
+        // `av1C` starts with a marker bit and a version of 1,
+        // followed by 3 bytes of sequence header fields and the `configOBUs`.
+        if r.u8() != Some(0x81) || r.bytes(3).is_none() {
+            return Err("invalid av1C box".into());
+        }
+        return Ok(Some((children, r.rest().to_vec())));
+    }
+    Ok(None)
+}
+
+/// Builds the samples of a non-fragmented track from its sample table,
+/// in a file of `file_size` bytes.
+fn parse_sample_table(stbl: &[u8], file_size: u64, samples: &mut Vec<Sample>) -> Option<()> {
+    let mut sizes = Vec::new();
+    if let Some(stsz) = find_box(stbl, b"stsz") {
+        let mut r = Reader::new(stsz);
+        r.full_box()?;
+        let size = r.u32()?;
+        let count = r.u32()?;
+        // Every sample takes up at least a byte of the file,
+        // and without a constant size, 4 bytes of the box.
+        if size == 0 && count as usize > r.rest().len() / 4
+            || count as u64 * cmp::max(size, 1) as u64 > file_size
+        {
+            return None;
+        }
+        sizes.reserve(count as usize);
+        for _ in 0..count {
+            sizes.push(if size != 0 { size } else { r.u32()? });
+        }
+    }
+
+    let mut chunk_offsets = Vec::new();
+    if let Some(stco) = find_box(stbl, b"stco") {
+        let mut r = Reader::new(stco);
+        r.full_box()?;
+        for _ in 0..r.u32()? {
+            chunk_offsets.push(r.u32()? as u64);
+        }
+    } else if let Some(co64) = find_box(stbl, b"co64") {
+        let mut r = Reader::new(co64);
+        r.full_box()?;
+        for _ in 0..r.u32()? {
+            chunk_offsets.push(r.u64()?);
+        }
+    }
+
+    // Entries of `(first_chunk, samples_per_chunk)`, with 1-based chunk numbers.
+    let mut stsc = Vec::new();
+    if let Some(data) = find_box(stbl, b"stsc") {
+        let mut r = Reader::new(data);
+        r.full_box()?;
+        for _ in 0..r.u32()? {
+            let first_chunk = r.u32()?;
+            let samples_per_chunk = r.u32()?;
+            r.u32()?;
+            stsc.push((first_chunk, samples_per_chunk));
+        }
+    }
+
+    let mut offsets = Vec::with_capacity(sizes.len());
+    for (chunk, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        let chunk = chunk as u32 + 1;
+        let Some(&(_, samples_per_chunk)) = stsc.iter().rev().find(|&&(first, _)| first <= chunk)
+        else {
+            break;
+        };
+        let mut offset = chunk_offset;
+        for _ in 0..samples_per_chunk {
+            let Some(&size) = sizes.get(offsets.len()) else {
+                break;
+            };
+            offsets.push(offset);
+            offset = offset.checked_add(size as u64)?;
+        }
+    }
+
+    let mut durations = Vec::with_capacity(sizes.len());
+    if let Some(stts) = find_box(stbl, b"stts") {
+        let mut r = Reader::new(stts);
+        r.full_box()?;
+        for _ in 0..r.u32()? {
+            let count = r.u32()?;
+            let delta = r.u32()?;
+            durations.extend(
+                (0..count)
+                    .map(|_| delta)
+                    .take(sizes.len() - durations.len()),
+            );
+        }
+    }
+
+    // Composition offsets, which are signed in version 1.
+    let mut ctts = Vec::new();
+    if let Some(data) = find_box(stbl, b"ctts") {
+        let mut r = Reader::new(data);
+        let (version, _) = r.full_box()?;
+        for _ in 0..r.u32()? {
+            let count = r.u32()?;
+            let offset = r.u32()?;
+            let offset = if version == 0 {
+                offset as i64
+            } else {
+                offset as i32 as i64
+            };
+            ctts.extend((0..count).map(|_| offset).take(sizes.len() - ctts.len()));
+        }
+    }
+
+    // Without an `stss`, every sample is a sync sample.
+    let mut sync = None;
+    if let Some(stss) = find_box(stbl, b"stss") {
+        let mut r = Reader::new(stss);
+        r.full_box()?;
+        let mut sync_samples = vec![false; sizes.len()];
+        for _ in 0..r.u32()? {
+            if let Some(s) = (r.u32()? as usize)
+                .checked_sub(1)
+                .and_then(|n| sync_samples.get_mut(n))
+            {
+                *s = true;
+            }
+        }
+        sync = Some(sync_samples);
+    }
+
+    let mut decode_time = 0i64;
+    for (n, (&offset, &size)) in offsets.iter().zip(&sizes).enumerate() {
+        let duration = durations.get(n).copied().unwrap_or(0);
+        samples.push(Sample {
+            offset,
+            size,
+            ts: decode_time.checked_add(ctts.get(n).copied().unwrap_or(0))?,
+            duration,
+            sync: sync.as_ref().map_or(true, |sync| sync[n]),
+        });
+        decode_time = decode_time.checked_add(duration as i64)?;
+    }
+    Some(())
+}
+
+/// Appends the samples of the `av01` track in a `moof` box starting at file offset `moof_offset`,
+/// in a file of `file_size` bytes.
+fn parse_moof(
+    moof: &[u8],
+    moof_offset: u64,
+    file_size: u64,
+    track: &mut Track,
+    samples: &mut Vec<Sample>,
+) -> Option<()> {
+    for (_, traf) in boxes(moof).filter(|(kind, _)| kind == b"traf") {
+        let mut r = Reader::new(find_box(traf, b"tfhd")?);
+        let (_, flags) = r.full_box()?;
+        if r.u32()? != track.id {
+            continue;
+        }
+        let mut defaults = track.defaults;
+        // Without an explicit base data offset, offsets are relative to the `moof`.
+        let base = if flags & 0x1 != 0 {
+            r.u64()?
+        } else {
+            moof_offset
+        };
+        if flags & 0x2 != 0 {
+            r.u32()?;
+        }
+        if flags & 0x8 != 0 {
+            defaults.duration = r.u32()?;
+        }
+        if flags & 0x10 != 0 {
+            defaults.size = r.u32()?;
+        }
+        if flags & 0x20 != 0 {
+            defaults.flags = r.u32()?;
+        }
+
+        let mut decode_time = track.next_decode_time;
+        if let Some(tfdt) = find_box(traf, b"tfdt") {
+            let mut r = Reader::new(tfdt);
+            let (version, _) = r.full_box()?;
+            decode_time = if version == 1 {
+                r.u64()?
+            } else {
+                r.u32()? as u64
+            };
+        }
+
+        let mut offset = base;
+        for (_, trun) in boxes(traf).filter(|(kind, _)| kind == b"trun") {
+            let mut r = Reader::new(trun);
+            let (version, flags) = r.full_box()?;
+            let count = r.u32()?;
+            if flags & 0x1 != 0 {
+                offset = base.checked_add_signed(r.u32()? as i32 as i64)?;
+            }
+            let first_sample_flags = if flags & 0x4 != 0 {
+                Some(r.u32()?)
+            } else {
+                None
+            };
+            // Every sample takes up at least a byte of the file,
+            // and 4 bytes of the box for each of the fields that aren't defaulted.
+            let sample_len = 4 * (flags & 0xf00).count_ones() as usize;
+            if sample_len != 0 && count as usize > r.rest().len() / sample_len
+                || (samples.len() as u64 + count as u64) > file_size
+            {
+                return None;
+            }
+            samples.reserve(count as usize);
+            for n in 0..count {
+                let duration = if flags & 0x100 != 0 {
+                    r.u32()?
+                } else {
+                    defaults.duration
+                };
+                let size = if flags & 0x200 != 0 {
+                    r.u32()?
+                } else {
+                    defaults.size
+                };
+                let mut sample_flags = if flags & 0x400 != 0 {
+                    r.u32()?
+                } else {
+                    defaults.flags
+                };
+                if n == 0 {
+                    sample_flags = first_sample_flags.unwrap_or(sample_flags);
+                }
+                let composition_offset = if flags & 0x800 != 0 {
+                    let offset = r.u32()?;
+                    if version == 0 {
+                        offset as i64
+                    } else {
+                        offset as i32 as i64
+                    }
+                } else {
+                    0
+                };
+                samples.push(Sample {
+                    offset,
+                    size,
+                    ts: i64::try_from(decode_time)
+                        .ok()?
+                        .checked_add(composition_offset)?,
+                    duration,
+                    sync: sample_flags & SAMPLE_IS_NON_SYNC == 0,
+                });
+                offset = offset.checked_add(size as u64)?;
+                decode_time = decode_time.checked_add(duration as u64)?;
+            }
+        }
+        track.next_decode_time = decode_time;
+    }
+    Some(())
+}
+
+/// A track with an `av01` sample entry.
+pub struct Av01Track<'a> {
+    pub id: u32,
+    pub timescale: u32,
+    /// The child boxes of the `av01` sample entry.
+    pub entry: &'a [u8],
+    /// The `configOBUs` of the `av1C` box.
+    pub config: Vec<u8>,
+    /// The samples of the sample table, which has none if the track is fragmented.
+    pub samples: Vec<Sample>,
+}
+
+/// Parses a `trak` box in a file of `file_size` bytes, if it's an `av01` track.
+pub fn parse_av01_trak(trak: &[u8], file_size: u64) -> Result<Option<Av01Track>, String> {
+    let Some(stbl) = find_path(trak, &[b"mdia", b"minf", b"stbl"]) else {
+        return Ok(None);
+    };
+    let Some((entry, config)) = parse_sample_entry(find_box(stbl, b"stsd").unwrap_or_default())?
+    else {
+        return Ok(None);
+    };
+    let mut r = Reader::new(find_box(trak, b"tkhd").ok_or("missing tkhd box")?);
+    let (version, _) = r.full_box().ok_or("invalid tkhd box")?;
+    r.bytes(if version == 1 { 16 } else { 8 })
+        .ok_or("invalid tkhd box")?;
+    let id = r.u32().ok_or("invalid tkhd box")?;
+
+    let mut r = Reader::new(find_path(trak, &[b"mdia", b"mdhd"]).ok_or("missing mdhd box")?);
+    let (version, _) = r.full_box().ok_or("invalid mdhd box")?;
+    r.bytes(if version == 1 { 16 } else { 8 })
+        .ok_or("invalid mdhd box")?;
+    let timescale = r.u32().ok_or("invalid mdhd box")?;
+
+    let mut samples = Vec::new();
+    parse_sample_table(stbl, file_size, &mut samples).ok_or("invalid sample table")?;
+    Ok(Some(Av01Track {
+        id,
+        timescale,
+        entry,
+        config,
+        samples,
+    }))
+}
+
+/// The IDs of the tracks that the track in `trak` is an auxiliary track of, like an alpha plane.
+pub fn auxiliary_of(trak: &[u8]) -> Vec<u32> {
+    let mut r = Reader::new(
+        find_box(trak, b"tref")
+            .and_then(|tref| find_box(tref, b"auxl"))
+            .unwrap_or_default(),
+    );
+    std::iter::from_fn(|| r.u32()).collect()
+}
+
+/// Finds the `av01` track in the `moov` box, and the samples of its sample table if it has one.
+fn parse_moov(
+    c: &mut Mp4InputContext,
+    moov: &[u8],
+    file_size: u64,
+    track: &mut Track,
+) -> Result<(), String> {
+    for (_, trak) in boxes(moov).filter(|(kind, _)| kind == b"trak") {
+        // Skip auxiliary tracks, like the alpha track of an AVIF image sequence.
+        if !auxiliary_of(trak).is_empty() {
+            continue;
+        }
+        let Some(av01) = parse_av01_trak(trak, file_size)? else {
+            continue;
+        };
+        track.id = av01.id;
+        c.timescale = av01.timescale;
+        c.samples = av01.samples;
+
+        for (_, trex) in find_box(moov, b"mvex")
+            .into_iter()
+            .flat_map(boxes)
+            .filter(|(kind, _)| kind == b"trex")
+        {
+            let mut r = Reader::new(trex);
+            let mut parse = || {
+                r.full_box()?;
+                let id = r.u32()?;
+                r.u32()?;
+                Some((id, r.u32()?, r.u32()?, r.u32()?))
+            };
+            if let Some((id, duration, size, flags)) = parse() {
+                if id == track.id {
+                    track.defaults = SampleDefaults {
+                        duration,
+                        size,
+                        flags,
+                    };
+                }
+            }
+        }
+        track.next_decode_time = c.samples.iter().map(|s| s.duration as u64).sum();
+        c.config = av01.config;
+        return Ok(());
+    }
+    Err("no av01 track found".into())
+}
+
+/// Scans the top-level boxes, reading `moov` and `moof` boxes into memory and skipping the others.
+unsafe fn mp4_parse(c: &mut Mp4InputContext) -> Result<(), String> {
+    if fseeko(c.f, 0, libc::SEEK_END) != 0 {
+        return Err("failed to seek to the end".into());
+    }
+    let file_size = ftello(c.f) as u64;
+    if fseeko(c.f, 0, libc::SEEK_SET) != 0 {
+        return Err("failed to seek to the start".into());
+    }
+    let mut track = None;
+    loop {
+        let offset = ftello(c.f) as u64;
+        let mut hdr = [0; 8];
+        if !read_exact(c.f, &mut hdr) {
+            break;
+        }
+        let mut r = Reader::new(&hdr);
+        let size = r.u32().unwrap() as u64;
+        let kind = r.bytes(4).unwrap();
+        let size = match size {
+            0 => None,
+            1 => {
+                let mut largesize = [0; 8];
+                if !read_exact(c.f, &mut largesize) {
+                    return Err("truncated box header".into());
+                }
+                Some(
+                    u64::from_be_bytes(largesize)
+                        .checked_sub(16)
+                        .ok_or("invalid box size")?,
+                )
+            }
+            _ => Some(size.checked_sub(8).ok_or("invalid box size")?),
+        };
+        if kind == b"moov" || kind == b"moof" {
+            let size = size
+                .filter(|&size| size <= MAX_BOX_SIZE)
+                .ok_or("invalid box size")?;
+            let mut payload = vec![0; size as usize];
+            if !read_exact(c.f, &mut payload) {
+                return Err("truncated box".into());
+            }
+            if kind == b"moov" {
+                let mut moov_track = Track::default();
+                parse_moov(c, &payload, file_size, &mut moov_track)?;
+                track = Some(moov_track);
+            } else if let Some(track) = &mut track {
+                parse_moof(&payload, offset, file_size, track, &mut c.samples)
+                    .ok_or("invalid moof box")?;
+            }
+        } else if let Some(size) = size {
+            if fseeko(c.f, size as libc::off_t, 1) != 0 {
+                return Err("failed to skip box".into());
+            }
+        } else {
+            // The box extends to the end of the file.
+            break;
+        }
+    }
+    if track.is_none() {
+        return Err("no moov box found".into());
+    }
+    if c.timescale == 0 {
+        return Err("invalid timescale".into());
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn mp4_open(
+    c: *mut Mp4InputContext,
+    file: *const c_char,
+    f: *mut libc::FILE,
+    fps: *mut c_uint,
+    num_frames: *mut c_uint,
+    timebase: *mut c_uint,
+) -> c_int {
+    demuxer_priv_init(c);
+    (*c).f = f;
+    if let Err(e) = mp4_parse(&mut *c) {
+        print_error(format!(
+            "Failed to parse {}: {e}",
+            CStr::from_ptr(file).to_string_lossy()
+        ));
+        mp4_close(c);
+        return -1;
+    }
+    let samples = &(*c).samples;
+    *num_frames = samples.len() as c_uint;
+    *timebase.offset(0) = (*c).timescale;
+    *timebase.offset(1) = 1;
+    let duration = samples.iter().map(|s| s.duration as u64).sum::<u64>();
+    let [fps_num, fps_den] = reduce_fraction(
+        (samples.len() as u64).wrapping_mul((*c).timescale as u64),
+        duration,
+    );
+    *fps.offset(0) = fps_num;
+    *fps.offset(1) = fps_den;
+    (*c).cur = 0;
+    (*c).config_pending = true;
+    0
+}
+
+unsafe extern "C" fn mp4_read(c: *mut Mp4InputContext, buf: *mut Dav1dData) -> c_int {
+    let Some(&sample) = (*c).samples.get((*c).cur) else {
+        return -1;
+    };
+    let config_len = if (*c).config_pending {
+        (*c).config.len()
+    } else {
+        0
+    };
+    if fseeko((*c).f, sample.offset as libc::off_t, 0) != 0 {
+        fprintf(
+            stderr,
+            b"Failed to seek to sample: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    let ptr = dav1d_data_create(buf, config_len + sample.size as usize);
+    if ptr.is_null() {
+        return -1;
+    }
+    ptr::copy_nonoverlapping((*c).config.as_ptr(), ptr, config_len);
+    let frame = std::slice::from_raw_parts_mut(ptr.add(config_len), sample.size as usize);
+    if !read_exact((*c).f, frame) {
+        fprintf(
+            stderr,
+            b"Failed to read frame data: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        dav1d_data_unref(buf);
+        return -1;
+    }
+    (*c).cur += 1;
+    (*c).config_pending = false;
+    (*buf).m.offset = sample.offset as libc::off_t;
+    (*buf).m.timestamp = sample.ts;
+    (*buf).m.duration = sample.duration as i64;
+    0
+}
+
+/// Seeks to the last sync sample at or before `pts`, or to the first one if there is none.
+unsafe extern "C" fn mp4_seek(c: *mut Mp4InputContext, pts: u64) -> c_int {
+    let c = &mut *c;
+    let ts = (pts as f64 * c.timescale as f64 / 1000000000.0f64).round() as i64;
+    let sync = c.samples.iter().enumerate().filter(|(_, s)| s.sync);
+    let first = sync.clone().next().map(|(n, _)| n);
+    let Some(n) = sync
+        .filter(|(_, s)| s.ts <= ts)
+        .last()
+        .map(|(n, _)| n)
+        .or(first)
+    else {
+        return -1;
+    };
+    c.cur = n;
+    c.config_pending = true;
+    0
+}
+
+unsafe extern "C" fn mp4_close(c: *mut Mp4InputContext) {
+    ptr::drop_in_place(c);
+}
+
+#[no_mangle]
+pub static mut mp4_demuxer: Demuxer = Demuxer {
+    priv_data_size: ::core::mem::size_of::<Mp4InputContext>() as c_ulong as c_int,
+    name: b"mp4\0" as *const u8 as *const c_char,
+    probe_sz: 8,
+    probe: Some(mp4_probe),
+    open: Some(mp4_open),
+    read: Some(mp4_read),
+    seek: Some(mp4_seek),
+    close: Some(mp4_close),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::mp4_probe;
+    use super::parse_moof;
+    use super::parse_sample_table;
+    use super::Sample;
+    use super::Track;
+
+    fn bx(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut b = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        b.extend(kind);
+        b.extend(payload);
+        b
+    }
+
+    /// A full box, with `fields` as big endian `u32`s.
+    fn full_box(kind: &[u8; 4], version: u8, flags: u32, fields: &[u32]) -> Vec<u8> {
+        let mut payload = ((version as u32) << 24 | flags).to_be_bytes().to_vec();
+        payload.extend(fields.iter().flat_map(|f| f.to_be_bytes()));
+        bx(kind, &payload)
+    }
+
+    fn probe(hdr: &[u8; 8]) -> bool {
+        // Safety: `hdr` is 8 bytes long.
+        unsafe { mp4_probe(hdr.as_ptr()) != 0 }
+    }
+
+    #[test]
+    fn probes_top_level_boxes() {
+        for kind in [b"ftyp", b"styp", b"moov", b"moof", b"free"] {
+            assert!(probe(&bx(kind, &[]).try_into().unwrap()));
+        }
+        assert!(!probe(b"DKIF\0\0\x20\0"));
+        assert!(!probe(b"\0\0\0\x04ftyp"));
+    }
+
+    #[test]
+    fn sample_table() {
+        let stbl = [
+            full_box(b"stsz", 0, 0, &[0, 3, 10, 20, 30]),
+            full_box(b"stco", 0, 0, &[1, 100]),
+            full_box(b"stsc", 0, 0, &[1, 1, 3, 1]),
+            full_box(b"stts", 0, 0, &[1, 3, 512]),
+            full_box(b"ctts", 0, 0, &[1, 3, 1024]),
+            full_box(b"stss", 0, 0, &[1, 1]),
+        ]
+        .concat();
+        let mut samples = Vec::new();
+        parse_sample_table(&stbl, 1000, &mut samples).unwrap();
+        let samples = samples
+            .iter()
+            .map(|s| (s.offset, s.size, s.ts, s.duration, s.sync))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            samples,
+            [
+                (100, 10, 1024, 512, true),
+                (110, 20, 1536, 512, false),
+                (130, 30, 2048, 512, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn sample_table_rejects_overflowing_offsets() {
+        let co64 = bx(
+            b"co64",
+            &[
+                &[0; 4],
+                &1u32.to_be_bytes()[..],
+                &(u64::MAX - 5).to_be_bytes(),
+            ]
+            .concat(),
+        );
+        let stbl = [
+            full_box(b"stsz", 0, 0, &[10, 2]),
+            co64,
+            full_box(b"stsc", 0, 0, &[1, 1, 2, 1]),
+        ]
+        .concat();
+        assert!(parse_sample_table(&stbl, 1000, &mut Vec::new()).is_none());
+    }
+
+    #[test]
+    fn sample_table_rejects_counts_beyond_the_file() {
+        // A constant size, so the count isn't bounded by the box.
+        let stbl = full_box(b"stsz", 0, 0, &[10, 1000]);
+        assert!(parse_sample_table(&stbl, 1000, &mut Vec::new()).is_none());
+        // Sizes missing from the box.
+        let stbl = full_box(b"stsz", 0, 0, &[0, 100, 10]);
+        assert!(parse_sample_table(&stbl, 1000, &mut Vec::new()).is_none());
+    }
+
+    /// A `moof` of track 1 with a `trun` of `count` samples of `(duration, size)`.
+    fn moof(count: u32, samples: &[(u32, u32)]) -> Vec<u8> {
+        let mut trun = vec![8];
+        trun.extend(
+            samples
+                .iter()
+                .flat_map(|&(duration, size)| [duration, size]),
+        );
+        bx(
+            b"traf",
+            &[
+                full_box(b"tfhd", 0, 0, &[1]),
+                full_box(b"tfdt", 0, 0, &[3000]),
+                full_box(
+                    b"trun",
+                    0,
+                    0x1 | 0x100 | 0x200,
+                    &[&[count][..], &trun].concat(),
+                ),
+            ]
+            .concat(),
+        )
+    }
+
+    #[test]
+    fn fragment() {
+        let mut track = Track {
+            id: 1,
+            ..Default::default()
+        };
+        let mut samples = Vec::<Sample>::new();
+        parse_moof(
+            &moof(2, &[(100, 5), (200, 7)]),
+            50,
+            1000,
+            &mut track,
+            &mut samples,
+        )
+        .unwrap();
+        let samples = samples
+            .iter()
+            .map(|s| (s.offset, s.size, s.ts, s.duration))
+            .collect::<Vec<_>>();
+        assert_eq!(samples, [(58, 5, 3000, 100), (63, 7, 3100, 200)]);
+        assert_eq!(track.next_decode_time, 3300);
+    }
+
+    #[test]
+    fn fragment_rejects_counts_beyond_the_box() {
+        let mut track = Track {
+            id: 1,
+            ..Default::default()
+        };
+        let moof = moof(1 << 20, &[(100, 5)]);
+        assert!(parse_moof(&moof, 50, 1 << 30, &mut track, &mut Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,571 @@
+//! Demuxer for AV1 in MPEG-2 Transport Streams, as specified by
+//! "Carriage of AV1 in MPEG-2 TS" from the AOM.
+//!
+//! The AV1 stream is the first elementary stream of the first program in the PAT
+//! with stream type 0x06 and an `AV01` registration descriptor in the PMT.
+//! The PAT and PMT sections are expected to fit in a single TS packet.
+//!
+//! The start of every PES packet of that stream is indexed when opening the file,
+//! with its PTS and the `random_access_indicator` of its adaptation field,
+//! which marks the points that can be seeked to.
+//! Each PES packet holds a temporal unit whose OBUs are each preceded by a start code
+//! and escaped with emulation prevention bytes. Those are removed, and size fields are added
+//! to the OBUs that don't have one, to give the low overhead bitstream format
+//! of Section 5 that the decoder reads.
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::input::input::demuxer_priv_init;
+use crate::input::input::print_error;
+use crate::input::input::read_leb128;
+use crate::input::input::reduce_fraction;
+use crate::input::input::write_leb128;
+use libc::fprintf;
+use libc::fread;
+use libc::fseeko;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::src::lib::dav1d_data_create;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::ptr;
+
+const TS_PACKET_SIZE: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+
+const PAT_PID: u16 = 0;
+/// The PID of null packets, also used here for PIDs that aren't known yet.
+const NULL_PID: u16 = 0x1fff;
+
+/// `stream_type` of PES packets with private data, which AV1 is carried in.
+const STREAM_TYPE_PRIVATE_PES: u8 = 0x06;
+const REGISTRATION_DESCRIPTOR: u8 = 0x05;
+
+/// PTS are 33-bit timestamps in units of a 90 kHz clock.
+const PTS_CLOCK: u32 = 90000;
+const PTS_WRAP: i64 = 1 << 33;
+
+#[derive(Clone, Copy)]
+pub struct Pes {
+    /// Offset of the TS packet where the PES packet starts.
+    pub offset: u64,
+    /// The PTS, unwrapped to be monotonic across the 33-bit wraparound, or [`i64::MIN`] if unset.
+    pub pts: i64,
+    pub random_access: bool,
+}
+
+#[repr(C)]
+pub struct DemuxerPriv {
+    pub f: *mut libc::FILE,
+    pub pid: u16,
+    pub pes: Vec<Pes>,
+    pub cur: usize,
+}
+
+impl Default for DemuxerPriv {
+    fn default() -> Self {
+        Self {
+            f: ptr::null_mut(),
+            pid: Default::default(),
+            pes: Default::default(),
+            cur: Default::default(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct Demuxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub probe_sz: c_int,
+    pub probe: Option<unsafe extern "C" fn(*const u8) -> c_int>,
+    pub open: Option<
+        unsafe extern "C" fn(
+            *mut DemuxerPriv,
+            *const c_char,
+            *mut libc::FILE,
+            *mut c_uint,
+            *mut c_uint,
+            *mut c_uint,
+        ) -> c_int,
+    >,
+    pub read: Option<unsafe extern "C" fn(*mut DemuxerPriv, *mut Dav1dData) -> c_int>,
+    pub seek: Option<unsafe extern "C" fn(*mut DemuxerPriv, u64) -> c_int>,
+    pub close: Option<unsafe extern "C" fn(*mut DemuxerPriv) -> ()>,
+}
+
+pub type MpegTsInputContext = DemuxerPriv;
+
+/// The number of consecutive packets whose sync byte is checked when probing.
+const PROBE_PACKETS: usize = 3;
+
+unsafe extern "C" fn mpegts_probe(data: *const u8) -> c_int {
+    (0..PROBE_PACKETS).all(|n| *data.add(n * TS_PACKET_SIZE) == TS_SYNC_BYTE) as c_int
+}
+
+/// The parts of a TS packet header that matter here.
+struct Packet<'a> {
+    pid: u16,
+    /// `payload_unit_start_indicator`: a PES packet or a PSI section starts in this packet.
+    start: bool,
+    random_access: bool,
+    payload: &'a [u8],
+}
+
+fn parse_packet(pkt: &[u8; TS_PACKET_SIZE]) -> Option<Packet> {
+    if pkt[0] != TS_SYNC_BYTE {
+        return None;
+    }
+    let pid = u16::from_be_bytes([pkt[1] & 0x1f, pkt[2]]);
+    let start = pkt[1] & 0x40 != 0;
+    let adaptation_field_control = pkt[3] >> 4 & 3;
+    let mut payload = &pkt[4..];
+    let mut random_access = false;
+    if adaptation_field_control & 2 != 0 {
+        let len = payload[0] as usize;
+        random_access = len > 0 && payload[1] & 0x40 != 0;
+        payload = payload.get(1 + len..)?;
+    }
+    if adaptation_field_control & 1 == 0 {
+        payload = &[];
+    }
+    Some(Packet {
+        pid,
+        start,
+        random_access,
+        payload,
+    })
+}
+
+/// Returns the payload of the PSI section with `table_id` starting in `payload`,
+/// after its 8-byte header and without its CRC.
+fn psi_section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    if *section.first()? != table_id {
+        return None;
+    }
+    let len = (u16::from_be_bytes([*section.get(1)?, *section.get(2)?]) & 0xfff) as usize;
+    section.get(8..(3 + len).checked_sub(4)?)
+}
+
+/// The PID of the PMT of the first program in the PAT.
+fn parse_pat(payload: &[u8]) -> Option<u16> {
+    psi_section(payload, 0x00)?
+        .chunks_exact(4)
+        .find(|program| program[0..2] != [0, 0])
+        .map(|program| u16::from_be_bytes([program[2] & 0x1f, program[3]]))
+}
+
+/// The PID of the AV1 stream in the PMT.
+fn parse_pmt(payload: &[u8]) -> Option<u16> {
+    let section = psi_section(payload, 0x02)?;
+    let program_info_len =
+        (u16::from_be_bytes([*section.get(2)?, *section.get(3)?]) & 0xfff) as usize;
+    let mut streams = section.get(4 + program_info_len..)?;
+    while streams.len() >= 5 {
+        let stream_type = streams[0];
+        let pid = u16::from_be_bytes([streams[1] & 0x1f, streams[2]]);
+        let es_info_len = (u16::from_be_bytes([streams[3], streams[4]]) & 0xfff) as usize;
+        let mut descriptors = streams.get(5..5 + es_info_len)?;
+        while descriptors.len() >= 2 {
+            let (tag, len) = (descriptors[0], descriptors[1] as usize);
+            let descriptor = descriptors.get(2..2 + len)?;
+            if stream_type == STREAM_TYPE_PRIVATE_PES
+                && tag == REGISTRATION_DESCRIPTOR
+                && descriptor.starts_with(b"AV01")
+            {
+                return Some(pid);
+            }
+            descriptors = &descriptors[2 + len..];
+        }
+        streams = &streams[5 + es_info_len..];
+    }
+    None
+}
+
+/// Parses the header of a PES packet, returning its PTS and the offset of its payload.
+fn parse_pes_header(payload: &[u8]) -> Option<(Option<i64>, usize)> {
+    if !payload.starts_with(&[0, 0, 1]) {
+        return None;
+    }
+    let pts_dts_flags = *payload.get(7)? >> 6;
+    let header_len = 9 + *payload.get(8)? as usize;
+    let pts = if pts_dts_flags & 2 != 0 {
+        let p = payload.get(9..14)?;
+        Some(
+            ((p[0] as i64 >> 1 & 7) << 30)
+                | ((p[1] as i64) << 22)
+                | ((p[2] as i64 >> 1) << 15)
+                | ((p[3] as i64) << 7)
+                | (p[4] as i64 >> 1),
+        )
+    } else {
+        None
+    };
+    Some((pts, header_len))
+}
+
+unsafe fn read_packet(f: *mut libc::FILE, pkt: &mut [u8; TS_PACKET_SIZE]) -> bool {
+    fread(pkt.as_mut_ptr() as *mut c_void, TS_PACKET_SIZE, 1, f) == 1
+}
+
+/// Scans all packets, finding the AV1 stream and indexing its PES packets.
+unsafe fn mpegts_parse(c: &mut MpegTsInputContext) -> Result<(), String> {
+    let mut pmt_pid = NULL_PID;
+    c.pid = NULL_PID;
+    let mut pkt = [0; TS_PACKET_SIZE];
+    let mut offset = 0;
+    let mut last_pts: Option<i64> = None;
+    while read_packet(c.f, &mut pkt) {
+        let packet = parse_packet(&pkt).ok_or("lost sync")?;
+        if packet.start {
+            if packet.pid == PAT_PID && pmt_pid == NULL_PID {
+                pmt_pid = parse_pat(packet.payload).unwrap_or(NULL_PID);
+            } else if packet.pid == pmt_pid && c.pid == NULL_PID {
+                c.pid = parse_pmt(packet.payload).ok_or("no AV1 stream in the PMT")?;
+            } else if packet.pid == c.pid {
+                let (pts, _) = parse_pes_header(packet.payload).ok_or("invalid PES header")?;
+                let pts = match (pts, last_pts) {
+                    (Some(pts), Some(last)) => {
+                        // Pick the wrap of `pts` closest to the last one.
+                        let pts = pts + (last - pts + PTS_WRAP / 2).div_euclid(PTS_WRAP) * PTS_WRAP;
+                        last_pts = Some(pts);
+                        pts
+                    }
+                    (Some(pts), None) => {
+                        last_pts = Some(pts);
+                        pts
+                    }
+                    (None, _) => i64::MIN,
+                };
+                c.pes.push(Pes {
+                    offset,
+                    pts,
+                    random_access: packet.random_access,
+                });
+            }
+        }
+        offset += TS_PACKET_SIZE as u64;
+    }
+    if pmt_pid == NULL_PID {
+        return Err("no PAT found".into());
+    }
+    if c.pid == NULL_PID {
+        return Err("no PMT found".into());
+    }
+    Ok(())
+}
+
+/// Converts the OBUs of a PES payload, each preceded by a start code and escaped with
+/// emulation prevention bytes, to the low overhead bitstream format.
+fn unescape_obus(data: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    let mut starts = data
+        .windows(3)
+        .enumerate()
+        .filter(|(_, w)| *w == [0, 0, 1])
+        .map(|(n, _)| n);
+    let Some(first) = starts.next() else {
+        return Err("missing OBU start code".into());
+    };
+    if data[..first].iter().any(|&b| b != 0) {
+        return Err("missing OBU start code".into());
+    }
+    let mut bounds = vec![first];
+    bounds.extend(starts);
+    bounds.push(data.len());
+    let mut obu = Vec::new();
+    for bound in bounds.windows(2) {
+        obu.clear();
+        let mut zeros = 0;
+        for &b in &data[bound[0] + 3..bound[1]] {
+            if zeros >= 2 && b == 3 {
+                zeros = 0;
+                continue;
+            }
+            zeros = if b == 0 { zeros + 1 } else { 0 };
+            obu.push(b);
+        }
+        let Some(&header) = obu.first() else {
+            continue;
+        };
+        let header_len = 1 + (header >> 2 & 1) as usize;
+        let has_size_field = header & 0x02 != 0;
+        if has_size_field {
+            // Zero bytes may follow the OBU before the next start code.
+            let mut rest = obu.get(header_len..).ok_or("truncated OBU header")?;
+            let size = read_leb128(&mut rest).ok_or("invalid OBU size")?;
+            let len = obu.len() - rest.len();
+            let obu = obu
+                .get(..len.checked_add(size).ok_or("invalid OBU size")?)
+                .ok_or("truncated OBU")?;
+            out.extend_from_slice(obu);
+            continue;
+        }
+        // Without a size field, trailing zero bytes can't be told apart from padding,
+        // and OBUs end with a nonzero byte of trailing bits anyway.
+        let len = obu.iter().rposition(|&b| b != 0).map_or(0, |n| n + 1);
+        obu.truncate(len.max(header_len));
+        let header_bytes = obu.get(..header_len).ok_or("truncated OBU header")?;
+        out.push(header | 0x02);
+        out.extend_from_slice(&header_bytes[1..]);
+        write_leb128(out, obu.len() - header_len);
+        out.extend_from_slice(&obu[header_len..]);
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn mpegts_open(
+    c: *mut MpegTsInputContext,
+    file: *const c_char,
+    f: *mut libc::FILE,
+    fps: *mut c_uint,
+    num_frames: *mut c_uint,
+    timebase: *mut c_uint,
+) -> c_int {
+    demuxer_priv_init(c);
+    (*c).f = f;
+    if let Err(e) = mpegts_parse(&mut *c) {
+        print_error(format!(
+            "Failed to parse {}: {e}",
+            CStr::from_ptr(file).to_string_lossy()
+        ));
+        mpegts_close(c);
+        return -1;
+    }
+    let pes = &(*c).pes;
+    *num_frames = pes.len() as c_uint;
+    *timebase.offset(0) = PTS_CLOCK;
+    *timebase.offset(1) = 1;
+    // The frame rate is the average over the span of the timestamps.
+    let pts = pes.iter().map(|p| p.pts).filter(|&pts| pts != i64::MIN);
+    let span = pts.clone().max().unwrap_or(0) - pts.clone().min().unwrap_or(0);
+    let [fps_num, fps_den] = reduce_fraction(
+        (pts.count() as u64).saturating_sub(1) * PTS_CLOCK as u64,
+        span as u64,
+    );
+    *fps.offset(0) = fps_num;
+    *fps.offset(1) = fps_den;
+    (*c).cur = 0;
+    0
+}
+
+unsafe extern "C" fn mpegts_read(c: *mut MpegTsInputContext, buf: *mut Dav1dData) -> c_int {
+    let c = &mut *c;
+    let Some(&pes) = c.pes.get(c.cur) else {
+        return -1;
+    };
+    let end = c.pes.get(c.cur + 1).map_or(u64::MAX, |next| next.offset);
+    if fseeko(c.f, pes.offset as libc::off_t, 0) != 0 {
+        fprintf(
+            stderr,
+            b"Failed to seek to PES packet: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    // Gather the payload of the PES packet, which ends where the next one starts.
+    let mut data = Vec::new();
+    let mut pkt = [0; TS_PACKET_SIZE];
+    let mut offset = pes.offset;
+    while offset < end && read_packet(c.f, &mut pkt) {
+        let Some(packet) = parse_packet(&pkt) else {
+            print_error("Lost sync in the middle of a PES packet".into());
+            return -1;
+        };
+        if packet.pid == c.pid {
+            let payload = if offset == pes.offset {
+                let (_, header_len) = parse_pes_header(packet.payload).unwrap();
+                packet.payload.get(header_len..).unwrap_or_default()
+            } else {
+                packet.payload
+            };
+            data.extend_from_slice(payload);
+        }
+        offset += TS_PACKET_SIZE as u64;
+    }
+    let mut obus = Vec::with_capacity(data.len() + 16);
+    if let Err(e) = unescape_obus(&data, &mut obus) {
+        print_error(format!("Invalid PES packet at offset {}: {e}", pes.offset));
+        return -1;
+    }
+    let ptr = dav1d_data_create(buf, obus.len());
+    if ptr.is_null() {
+        return -1;
+    }
+    ptr::copy_nonoverlapping(obus.as_ptr(), ptr, obus.len());
+    c.cur += 1;
+    (*buf).m.offset = pes.offset as libc::off_t;
+    if pes.pts != i64::MIN {
+        (*buf).m.timestamp = pes.pts;
+        if let Some(next) = c.pes.get(c.cur).filter(|next| next.pts > pes.pts) {
+            (*buf).m.duration = next.pts - pes.pts;
+        }
+    }
+    0
+}
+
+/// Seeks to the last random access point at or before `pts`, or to the first one if there is none.
+/// Without any random access points, it seeks to the start.
+unsafe extern "C" fn mpegts_seek(c: *mut MpegTsInputContext, pts: u64) -> c_int {
+    let c = &mut *c;
+    if c.pes.is_empty() {
+        return -1;
+    }
+    let ts = (pts as f64 * PTS_CLOCK as f64 / 1000000000.0f64).round() as i64;
+    let random_access = c.pes.iter().enumerate().filter(|(_, p)| p.random_access);
+    let first = random_access.clone().next().map(|(n, _)| n);
+    c.cur = random_access
+        .filter(|(_, p)| p.pts != i64::MIN && p.pts <= ts)
+        .last()
+        .map(|(n, _)| n)
+        .or(first)
+        .unwrap_or(0);
+    0
+}
+
+unsafe extern "C" fn mpegts_close(c: *mut MpegTsInputContext) {
+    ptr::drop_in_place(c);
+}
+
+#[no_mangle]
+pub static mut mpegts_demuxer: Demuxer = Demuxer {
+    priv_data_size: ::core::mem::size_of::<MpegTsInputContext>() as c_ulong as c_int,
+    name: b"mpegts\0" as *const u8 as *const c_char,
+    probe_sz: (PROBE_PACKETS * TS_PACKET_SIZE) as c_int,
+    probe: Some(mpegts_probe),
+    open: Some(mpegts_open),
+    read: Some(mpegts_read),
+    seek: Some(mpegts_seek),
+    close: Some(mpegts_close),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::parse_packet;
+    use super::parse_pat;
+    use super::parse_pes_header;
+    use super::parse_pmt;
+    use super::unescape_obus;
+    use super::TS_PACKET_SIZE;
+
+    /// A PSI section with `table_id` and `payload` after its 8-byte header,
+    /// preceded by a pointer field and followed by a (wrong) CRC.
+    fn section(table_id: u8, payload: &[u8]) -> Vec<u8> {
+        let len = (5 + payload.len() + 4) as u16;
+        let mut s = vec![
+            0,
+            table_id,
+            0xb0 | (len >> 8) as u8,
+            len as u8,
+            0,
+            1,
+            0xc1,
+            0,
+            0,
+        ];
+        s.extend(payload);
+        s.extend([0; 4]);
+        s
+    }
+
+    #[test]
+    fn packet_with_adaptation_field() {
+        let mut pkt = [0xff; TS_PACKET_SIZE];
+        pkt[..6].copy_from_slice(&[0x47, 0x41, 0x00, 0x30, 1, 0x40]);
+        let packet = parse_packet(&pkt).unwrap();
+        assert_eq!(packet.pid, 0x100);
+        assert!(packet.start);
+        assert!(packet.random_access);
+        assert_eq!(packet.payload.len(), TS_PACKET_SIZE - 6);
+
+        // No payload, and an adaptation field without flags.
+        pkt[..5].copy_from_slice(&[0x47, 0x01, 0x00, 0x20, 0]);
+        let packet = parse_packet(&pkt).unwrap();
+        assert!(!packet.start && !packet.random_access && packet.payload.is_empty());
+
+        pkt[0] = 0;
+        assert!(parse_packet(&pkt).is_none());
+    }
+
+    #[test]
+    fn program_tables() {
+        // The network PID of program 0 comes first, and is skipped.
+        let pat = section(0x00, &[0, 0, 0xe0, 0x10, 0, 1, 0xf0, 0x20]);
+        assert_eq!(parse_pat(&pat), Some(0x1020));
+        assert_eq!(parse_pat(&pat[..pat.len() - 5]), None);
+
+        let pmt = section(
+            0x02,
+            &[
+                &[0xe1, 0x00, 0xf0, 0x00][..],
+                // Private data registered as something else.
+                &[0x06, 0xe1, 0x00, 0xf0, 0x06, 0x05, 0x04],
+                b"Opus",
+                // AV1.
+                &[0x06, 0xe1, 0x01, 0xf0, 0x06, 0x05, 0x04],
+                b"AV01",
+            ]
+            .concat(),
+        );
+        assert_eq!(parse_pmt(&pmt), Some(0x101));
+        assert_eq!(parse_pat(&pmt), None);
+    }
+
+    #[test]
+    fn pes_header() {
+        let pts = 0x1_2345_6789i64;
+        let header = [
+            0,
+            0,
+            1,
+            0xe0,
+            0,
+            0,
+            0x80,
+            0x80,
+            5,
+            0x21 | (pts >> 29 & 0xe) as u8,
+            (pts >> 22) as u8,
+            0x01 | (pts >> 14 & 0xfe) as u8,
+            (pts >> 7) as u8,
+            0x01 | (pts << 1 & 0xfe) as u8,
+        ];
+        assert_eq!(parse_pes_header(&header), Some((Some(pts), 14)));
+        assert_eq!(parse_pes_header(&header[1..]), None);
+    }
+
+    #[test]
+    fn obus_are_unescaped_and_sized() {
+        let mut out = Vec::new();
+        unescape_obus(
+            &[
+                // A temporal delimiter with a size field, followed by padding zeros.
+                &[0, 0, 1, 0x12, 0, 0, 0][..],
+                // A sequence header without a size field, with an emulation prevention byte.
+                &[0, 0, 1, 0x08, 0, 0, 3, 1, 0x80, 0, 0],
+            ]
+            .concat(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, [0x12, 0, 0x0a, 4, 0, 0, 1, 0x80]);
+    }
+
+    #[test]
+    fn obus_with_invalid_sizes_are_rejected() {
+        let mut out = Vec::new();
+        assert!(unescape_obus(&[0x12, 0], &mut out).is_err());
+        assert!(unescape_obus(&[0, 0, 1, 0x12, 2, 0], &mut out).is_err());
+        // A size field longer than 8 bytes.
+        assert!(unescape_obus(
+            &[0, 0, 1, 0x12, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0],
+            &mut out
+        )
+        .is_err());
+    }
+}
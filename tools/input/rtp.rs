@@ -0,0 +1,697 @@
+//! Depacketizer for AV1 RTP streams (the "RTP Payload Format For AV1" from the AOM),
+//! read from `rtpdump` captures (as written by rtptools) or `pcap` captures of UDP traffic.
+//!
+//! The whole capture is read when opening the file. The AV1 stream is the RTP stream
+//! (SSRC) carrying the most payload data. Its packets are sorted by sequence number,
+//! and their OBU elements, possibly fragmented across packets, are reassembled
+//! into temporal units, which end at a packet with the marker bit or a new RTP timestamp.
+//!
+//! Gaps in the sequence numbers are reported as packet loss. A temporal unit
+//! with missing packets is dropped, as are the following ones until a new coded
+//! video sequence starts (a packet with the `N` bit of the aggregation header),
+//! since they can't be decoded without their references.
+//! Such starts are also the points that can be seeked to.
+
+use crate::input::input::demuxer_priv_init;
+use crate::input::input::print_error;
+use crate::input::input::read_exact;
+use crate::input::input::read_leb128;
+use crate::input::input::reduce_fraction;
+use crate::input::input::write_leb128;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::src::lib::dav1d_data_create;
+use std::cmp;
+use std::collections::HashMap;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::CStr;
+use std::ptr;
+
+const RTPDUMP_MAGIC: &[u8] = b"#!rtpplay1.0 ";
+/// The header of an `rtpdump` file following the first line:
+/// start time, source address and port.
+const RTPDUMP_HEADER_SIZE: usize = 16;
+
+/// Magic numbers of `pcap` files with microsecond and nanosecond timestamps.
+const PCAP_MAGIC: [u32; 2] = [0xa1b2c3d4, 0xa1b23c4d];
+const PCAP_HEADER_SIZE: usize = 24;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IPPROTO_UDP: u8 = 17;
+
+/// The RTP clock rate of AV1.
+const RTP_CLOCK: u32 = 90000;
+
+/// A reassembled temporal unit.
+pub struct TemporalUnit {
+    pub data: Vec<u8>,
+    /// File offset of the record holding its first packet.
+    pub offset: u64,
+    /// The RTP timestamp, unwrapped to be monotonic across the 32-bit wraparound.
+    pub ts: i64,
+    /// Whether it starts a coded video sequence.
+    pub key: bool,
+}
+
+#[derive(Default)]
+#[repr(C)]
+pub struct DemuxerPriv {
+    pub tus: Vec<TemporalUnit>,
+    pub cur: usize,
+}
+
+#[repr(C)]
+pub struct Demuxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub probe_sz: c_int,
+    pub probe: Option<unsafe extern "C" fn(*const u8) -> c_int>,
+    pub open: Option<
+        unsafe extern "C" fn(
+            *mut DemuxerPriv,
+            *const c_char,
+            *mut libc::FILE,
+            *mut c_uint,
+            *mut c_uint,
+            *mut c_uint,
+        ) -> c_int,
+    >,
+    pub read: Option<unsafe extern "C" fn(*mut DemuxerPriv, *mut Dav1dData) -> c_int>,
+    pub seek: Option<unsafe extern "C" fn(*mut DemuxerPriv, u64) -> c_int>,
+    pub close: Option<unsafe extern "C" fn(*mut DemuxerPriv) -> ()>,
+}
+
+pub type RtpInputContext = DemuxerPriv;
+
+fn pcap_magic(hdr: &[u8]) -> Option<bool> {
+    let magic = hdr.get(..4)?.try_into().unwrap();
+    if PCAP_MAGIC.contains(&u32::from_le_bytes(magic)) {
+        Some(true)
+    } else if PCAP_MAGIC.contains(&u32::from_be_bytes(magic)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+unsafe extern "C" fn rtp_probe(data: *const u8) -> c_int {
+    let data = std::slice::from_raw_parts(data, RTPDUMP_MAGIC.len());
+    (data == RTPDUMP_MAGIC || pcap_magic(data).is_some()) as c_int
+}
+
+/// An RTP packet.
+struct Packet {
+    ssrc: u32,
+    seq: u16,
+    ts: u32,
+    marker: bool,
+    payload: Vec<u8>,
+    /// File offset of the record holding it.
+    offset: u64,
+}
+
+fn parse_rtp(data: &[u8], offset: u64) -> Option<Packet> {
+    let hdr = data.get(..12)?;
+    if hdr[0] >> 6 != 2 {
+        return None;
+    }
+    // RTCP packets, multiplexed on the same port, have payload types 72 to 76 here.
+    if (72..=76).contains(&(hdr[1] & 0x7f)) {
+        return None;
+    }
+    let mut start = 12 + 4 * (hdr[0] & 0xf) as usize;
+    if hdr[0] & 0x10 != 0 {
+        let ext = data.get(start..start + 4)?;
+        start += 4 + 4 * u16::from_be_bytes([ext[2], ext[3]]) as usize;
+    }
+    let mut end = data.len();
+    if hdr[0] & 0x20 != 0 {
+        end = end.checked_sub(*data.last()? as usize)?;
+    }
+    Some(Packet {
+        ssrc: u32::from_be_bytes(hdr[8..12].try_into().unwrap()),
+        seq: u16::from_be_bytes([hdr[2], hdr[3]]),
+        ts: u32::from_be_bytes(hdr[4..8].try_into().unwrap()),
+        marker: hdr[1] & 0x80 != 0,
+        payload: data.get(start..end)?.to_vec(),
+        offset,
+    })
+}
+
+/// Returns the UDP payload of an IP packet, skipping fragments and other protocols.
+fn ip_payload(ip: &[u8]) -> Option<&[u8]> {
+    let udp = match *ip.first()? >> 4 {
+        4 => {
+            let ihl = 4 * (ip[0] & 0xf) as usize;
+            let total_len = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            // More fragments, or a fragment offset.
+            if *ip.get(9)? != IPPROTO_UDP || fragment & 0x3fff != 0 {
+                return None;
+            }
+            ip.get(ihl..total_len)?
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            if *ip.get(6)? != IPPROTO_UDP {
+                return None;
+            }
+            ip.get(40..40 + payload_len)?
+        }
+        _ => return None,
+    };
+    let udp_len = u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]) as usize;
+    udp.get(8..udp_len)
+}
+
+/// Returns the IP packet in a `pcap` record of link type `linktype`.
+fn link_payload(frame: &[u8], linktype: u32) -> Option<&[u8]> {
+    match linktype {
+        LINKTYPE_NULL => frame.get(4..),
+        LINKTYPE_RAW => Some(frame),
+        LINKTYPE_ETHERNET => {
+            let mut start = 14;
+            let mut ethertype = u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]);
+            while ethertype == ETHERTYPE_VLAN {
+                ethertype = u16::from_be_bytes([*frame.get(start + 2)?, *frame.get(start + 3)?]);
+                start += 4;
+            }
+            [ETHERTYPE_IPV4, ETHERTYPE_IPV6]
+                .contains(&ethertype)
+                .then(|| frame.get(start..))?
+        }
+        LINKTYPE_LINUX_SLL => frame.get(16..),
+        LINKTYPE_LINUX_SLL2 => frame.get(20..),
+        _ => None,
+    }
+}
+
+/// Reads the RTP packets of an `rtpdump` file, after its first line.
+unsafe fn read_rtpdump(f: *mut libc::FILE, mut offset: u64) -> Result<Vec<Packet>, String> {
+    let mut hdr = [0; RTPDUMP_HEADER_SIZE];
+    if !read_exact(f, &mut hdr) {
+        return Err("truncated rtpdump header".into());
+    }
+    offset += RTPDUMP_HEADER_SIZE as u64;
+    let mut packets = Vec::new();
+    let mut record = [0; 8];
+    while read_exact(f, &mut record) {
+        let len = u16::from_be_bytes([record[0], record[1]]) as usize;
+        // The length of the RTP packet, which may be shorter than the data of the record
+        // if it was padded, or longer if only the start of the packet was captured.
+        let rtp_len = u16::from_be_bytes([record[2], record[3]]) as usize;
+        let mut data = vec![
+            0;
+            len.checked_sub(record.len())
+                .ok_or("invalid rtpdump record")?
+        ];
+        if !read_exact(f, &mut data) {
+            return Err("truncated rtpdump record".into());
+        }
+        // RTCP packets have a length of 0. Truncated packets are left out,
+        // to be reported as lost.
+        if let Some(rtp) = data.get(..rtp_len).filter(|_| rtp_len != 0) {
+            packets.extend(parse_rtp(rtp, offset));
+        }
+        offset += len as u64;
+    }
+    Ok(packets)
+}
+
+/// Reads the RTP packets of a `pcap` file, whose first bytes, `start`, were already read.
+unsafe fn read_pcap(f: *mut libc::FILE, start: &[u8]) -> Result<Vec<Packet>, String> {
+    let mut hdr = [0; PCAP_HEADER_SIZE];
+    hdr[..start.len()].copy_from_slice(start);
+    if !read_exact(f, &mut hdr[start.len()..]) {
+        return Err("truncated pcap header".into());
+    }
+    let le = pcap_magic(&hdr).unwrap();
+    let u32_at = |b: &[u8], n: usize| {
+        let b = b[n..n + 4].try_into().unwrap();
+        if le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    };
+    let linktype = u32_at(&hdr, 20) & 0xffff;
+    let mut offset = PCAP_HEADER_SIZE as u64;
+    let mut packets = Vec::new();
+    let mut record = [0; 16];
+    while read_exact(f, &mut record) {
+        let len = u32_at(&record, 8) as usize;
+        if len > 1 << 24 {
+            return Err("invalid pcap record".into());
+        }
+        let mut frame = vec![0; len];
+        if !read_exact(f, &mut frame) {
+            return Err("truncated pcap record".into());
+        }
+        if let Some(udp) = link_payload(&frame, linktype).and_then(ip_payload) {
+            packets.extend(parse_rtp(udp, offset));
+        }
+        offset += (record.len() + len) as u64;
+    }
+    Ok(packets)
+}
+
+/// Appends an OBU element to `out`, adding a size field to the OBU if it has none.
+fn write_obu(obu: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    let header = *obu.first().ok_or("empty OBU element")?;
+    if header & 0x02 != 0 {
+        out.extend_from_slice(obu);
+        return Ok(());
+    }
+    let header_len = 1 + (header >> 2 & 1) as usize;
+    let header_bytes = obu.get(..header_len).ok_or("truncated OBU header")?;
+    out.push(header | 0x02);
+    out.extend_from_slice(&header_bytes[1..]);
+    write_leb128(out, obu.len() - header_len);
+    out.extend_from_slice(&obu[header_len..]);
+    Ok(())
+}
+
+/// Splits the payload of a packet into its aggregation header and OBU elements.
+fn parse_payload(payload: &[u8]) -> Option<(u8, Vec<&[u8]>)> {
+    let (&aggregation, mut data) = payload.split_first()?;
+    let count = (aggregation >> 4 & 3) as usize;
+    let mut elements = Vec::new();
+    while !data.is_empty() {
+        // With a count, the last element has no length field.
+        let len = if count != 0 && elements.len() == count - 1 {
+            data.len()
+        } else {
+            read_leb128(&mut data)?
+        };
+        elements.push(data.get(..len)?);
+        data = &data[len..];
+    }
+    Some((aggregation, elements))
+}
+
+/// Packet loss and its consequences.
+#[derive(Default)]
+struct Loss {
+    packets: u64,
+    dropped: u64,
+}
+
+/// Ends the temporal unit being reassembled, keeping it unless it is `broken`
+/// or follows one that was dropped without starting a coded video sequence.
+fn finish(
+    tu: &mut Option<TemporalUnit>,
+    tus: &mut Vec<TemporalUnit>,
+    broken: &mut bool,
+    waiting: &mut bool,
+    loss: &mut Loss,
+) {
+    let Some(unit) = tu.take() else {
+        return;
+    };
+    if *broken || (*waiting && !unit.key) {
+        *waiting = true;
+        loss.dropped += 1;
+    } else {
+        *waiting = false;
+        tus.push(unit);
+    }
+    *broken = false;
+}
+
+/// Reassembles the temporal units of `packets`, sorted by their extended sequence numbers.
+unsafe fn depacketize(packets: &[(i64, Packet)], loss: &mut Loss) -> Vec<TemporalUnit> {
+    let mut tus = Vec::new();
+    let mut tu: Option<TemporalUnit> = None;
+    // Whether the current temporal unit is missing data.
+    let mut broken = false;
+    // Whether to drop temporal units until the start of a coded video sequence.
+    let mut waiting = false;
+    let mut fragment: Option<Vec<u8>> = None;
+    let mut last_ts = None;
+    for (n, (seq, packet)) in packets.iter().enumerate() {
+        if n != 0 && *seq != packets[n - 1].0 + 1 {
+            let missing = seq - packets[n - 1].0 - 1;
+            loss.packets += missing as u64;
+            print_error(format!(
+                "Packet loss: {missing} packet(s) missing after sequence number {}",
+                packets[n - 1].1.seq,
+            ));
+            broken = true;
+            fragment = None;
+        }
+        // Unwrap the timestamp to the value closest to the last one.
+        let ts = match last_ts {
+            Some(last) => last + (packet.ts.wrapping_sub(last as u32) as i32) as i64,
+            None => packet.ts as i64,
+        };
+        last_ts = Some(ts);
+        if tu.as_ref().map_or(false, |tu| tu.ts != ts) {
+            // The marker bit of the last temporal unit was lost.
+            broken |= fragment.take().is_some();
+            finish(&mut tu, &mut tus, &mut broken, &mut waiting, loss);
+        }
+        let Some((aggregation, elements)) = parse_payload(&packet.payload) else {
+            broken = true;
+            fragment = None;
+            continue;
+        };
+        let continues = aggregation & 0x80 != 0;
+        let fragmented = aggregation & 0x40 != 0;
+        let unit = tu.get_or_insert_with(|| TemporalUnit {
+            data: Vec::new(),
+            offset: packet.offset,
+            ts,
+            key: aggregation & 0x08 != 0,
+        });
+        let last = elements.len().wrapping_sub(1);
+        for (i, element) in elements.into_iter().enumerate() {
+            let obu = if i == 0 && continues {
+                match fragment.take() {
+                    Some(mut obu) => {
+                        obu.extend_from_slice(element);
+                        obu
+                    }
+                    None => {
+                        // The start of the OBU was lost.
+                        broken = true;
+                        continue;
+                    }
+                }
+            } else {
+                if fragment.take().is_some() {
+                    broken = true;
+                }
+                element.to_vec()
+            };
+            if i == last && fragmented {
+                fragment = Some(obu);
+            } else if write_obu(&obu, &mut unit.data).is_err() {
+                broken = true;
+            }
+        }
+        if packet.marker {
+            broken |= fragment.take().is_some();
+            finish(&mut tu, &mut tus, &mut broken, &mut waiting, loss);
+        }
+    }
+    broken |= fragment.is_some();
+    finish(&mut tu, &mut tus, &mut broken, &mut waiting, loss);
+    tus
+}
+
+unsafe fn rtp_parse(c: &mut RtpInputContext, f: *mut libc::FILE) -> Result<(), String> {
+    let mut magic = [0; RTPDUMP_MAGIC.len()];
+    if !read_exact(f, &mut magic) {
+        return Err("truncated header".into());
+    }
+    let mut packets = if magic == RTPDUMP_MAGIC {
+        // Skip the rest of the first line, with the address the capture was made from.
+        let mut offset = magic.len() as u64;
+        let mut byte = [0];
+        loop {
+            if !read_exact(f, &mut byte) {
+                return Err("truncated rtpdump header".into());
+            }
+            offset += 1;
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        read_rtpdump(f, offset)?
+    } else {
+        read_pcap(f, &magic)?
+    };
+
+    let mut payload_sizes = HashMap::<u32, usize>::new();
+    for packet in &packets {
+        *payload_sizes.entry(packet.ssrc).or_default() += packet.payload.len();
+    }
+    let Some((&ssrc, _)) = payload_sizes
+        .iter()
+        .max_by_key(|&(&ssrc, &size)| (size, ssrc))
+    else {
+        return Err("no RTP packets found".into());
+    };
+    packets.retain(|packet| packet.ssrc == ssrc);
+
+    // Unwrap the sequence numbers in capture order, and sort them to undo reordering.
+    let mut last = None::<i64>;
+    let mut packets = packets
+        .into_iter()
+        .map(|packet| {
+            let seq = match last {
+                Some(last) => last + (packet.seq.wrapping_sub(last as u16) as i16) as i64,
+                None => packet.seq as i64,
+            };
+            last = Some(last.map_or(seq, |last| cmp::max(last, seq)));
+            (seq, packet)
+        })
+        .collect::<Vec<_>>();
+    packets.sort_by_key(|&(seq, _)| seq);
+    packets.dedup_by_key(|&mut (seq, _)| seq);
+
+    let mut loss = Loss::default();
+    c.tus = depacketize(&packets, &mut loss);
+    if loss.packets != 0 || loss.dropped != 0 {
+        let total = packets.last().unwrap().0 - packets[0].0 + 1;
+        print_error(format!(
+            "Lost {} of {total} packets, dropped {} temporal unit(s)",
+            loss.packets, loss.dropped,
+        ));
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn rtp_open(
+    c: *mut RtpInputContext,
+    file: *const c_char,
+    f: *mut libc::FILE,
+    fps: *mut c_uint,
+    num_frames: *mut c_uint,
+    timebase: *mut c_uint,
+) -> c_int {
+    demuxer_priv_init(c);
+    if let Err(e) = rtp_parse(&mut *c, f) {
+        print_error(format!(
+            "Failed to parse {}: {e}",
+            CStr::from_ptr(file).to_string_lossy()
+        ));
+        rtp_close(c);
+        return -1;
+    }
+    let tus = &(*c).tus;
+    *num_frames = tus.len() as c_uint;
+    *timebase.offset(0) = RTP_CLOCK;
+    *timebase.offset(1) = 1;
+    // The frame rate is the average over the span of the timestamps.
+    let span = match (tus.first(), tus.last()) {
+        (Some(first), Some(last)) => last.ts - first.ts,
+        _ => 0,
+    };
+    let [fps_num, fps_den] = reduce_fraction(
+        (tus.len() as u64).saturating_sub(1) * RTP_CLOCK as u64,
+        span as u64,
+    );
+    *fps.offset(0) = fps_num;
+    *fps.offset(1) = fps_den;
+    (*c).cur = 0;
+    0
+}
+
+unsafe extern "C" fn rtp_read(c: *mut RtpInputContext, buf: *mut Dav1dData) -> c_int {
+    let c = &mut *c;
+    let Some(tu) = c.tus.get(c.cur) else {
+        return -1;
+    };
+    let ptr = dav1d_data_create(buf, tu.data.len());
+    if ptr.is_null() {
+        return -1;
+    }
+    ptr::copy_nonoverlapping(tu.data.as_ptr(), ptr, tu.data.len());
+    (*buf).m.offset = tu.offset as libc::off_t;
+    (*buf).m.timestamp = tu.ts;
+    if let Some(next) = c.tus.get(c.cur + 1) {
+        (*buf).m.duration = next.ts - tu.ts;
+    }
+    c.cur += 1;
+    0
+}
+
+/// Seeks to the last start of a coded video sequence at or before `pts`,
+/// or to the first one if there is none.
+unsafe extern "C" fn rtp_seek(c: *mut RtpInputContext, pts: u64) -> c_int {
+    let c = &mut *c;
+    let ts = (pts as f64 * RTP_CLOCK as f64 / 1000000000.0f64).round() as i64;
+    let key = c.tus.iter().enumerate().filter(|(_, tu)| tu.key);
+    let first = key.clone().next().map(|(n, _)| n);
+    let Some(n) = key
+        .filter(|(_, tu)| tu.ts <= ts)
+        .last()
+        .map(|(n, _)| n)
+        .or(first)
+    else {
+        return -1;
+    };
+    c.cur = n;
+    0
+}
+
+unsafe extern "C" fn rtp_close(c: *mut RtpInputContext) {
+    ptr::drop_in_place(c);
+}
+
+#[no_mangle]
+pub static mut rtp_demuxer: Demuxer = Demuxer {
+    priv_data_size: ::core::mem::size_of::<RtpInputContext>() as c_ulong as c_int,
+    name: b"rtp\0" as *const u8 as *const c_char,
+    probe_sz: RTPDUMP_MAGIC.len() as c_int,
+    probe: Some(rtp_probe),
+    open: Some(rtp_open),
+    read: Some(rtp_read),
+    seek: Some(rtp_seek),
+    close: Some(rtp_close),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::depacketize;
+    use super::ip_payload;
+    use super::link_payload;
+    use super::parse_payload;
+    use super::parse_rtp;
+    use super::read_rtpdump;
+    use super::write_obu;
+    use super::Loss;
+    use super::Packet;
+    use super::LINKTYPE_ETHERNET;
+    use super::RTPDUMP_HEADER_SIZE;
+
+    /// An RTP packet with the AV1 `payload`.
+    fn rtp(seq: u16, ts: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        let mut p = vec![0x80, (marker as u8) << 7 | 96];
+        p.extend(seq.to_be_bytes());
+        p.extend(ts.to_be_bytes());
+        p.extend(0x1234u32.to_be_bytes());
+        p.extend(payload);
+        p
+    }
+
+    fn packet(seq: u16, ts: u32, marker: bool, payload: &[u8]) -> (i64, Packet) {
+        let packet = parse_rtp(&rtp(seq, ts, marker, payload), 0).unwrap();
+        (seq as i64, packet)
+    }
+
+    #[test]
+    fn rtp_header() {
+        // A CSRC, a header extension of one word and 2 bytes of padding.
+        let mut p = vec![0xb1, 0xe0, 0, 7, 0, 0, 0, 9, 0, 0, 0, 5];
+        p.extend([0; 4]);
+        p.extend([0xbe, 0xde, 0, 1, 0, 0, 0, 0]);
+        p.extend([1, 2, 3, 0, 2]);
+        let packet = parse_rtp(&p, 42).unwrap();
+        assert_eq!((packet.ssrc, packet.seq, packet.ts), (5, 7, 9));
+        assert!(packet.marker);
+        assert_eq!(packet.payload, [1, 2, 3]);
+        assert_eq!(packet.offset, 42);
+
+        // RTCP.
+        p[1] = 0xc8;
+        assert!(parse_rtp(&p, 0).is_none());
+        assert!(parse_rtp(&p[..11], 0).is_none());
+    }
+
+    #[test]
+    fn udp_over_ethernet() {
+        let udp = [&[0, 1, 0, 2, 0, 11, 0, 0][..], b"rtp"].concat();
+        let mut ip = vec![0x45, 0, 0, 20 + 11, 0, 0, 0x40, 0, 64, 17];
+        ip.resize(20, 0);
+        ip.extend(&udp);
+        // Trailing bytes of the link layer aren't part of the IP packet.
+        let frame = [&[0; 12][..], &[0x08, 0x00], &ip, &[0; 4]].concat();
+        let ip = link_payload(&frame, LINKTYPE_ETHERNET).unwrap();
+        assert_eq!(ip_payload(ip), Some(&b"rtp"[..]));
+
+        // A fragment.
+        let mut fragment = ip.to_vec();
+        fragment[6] = 0x20;
+        assert_eq!(ip_payload(&fragment), None);
+    }
+
+    #[test]
+    fn rtpdump_records() {
+        let mut file = vec![0; RTPDUMP_HEADER_SIZE];
+        let mut record = |data: &[u8], rtp_len: usize| {
+            file.extend((8 + data.len() as u16).to_be_bytes());
+            file.extend((rtp_len as u16).to_be_bytes());
+            file.extend([0; 4]);
+            file.extend(data);
+        };
+        let first = rtp(1, 0, true, &[0x10, 0x12, 0]);
+        // Padded after the packet.
+        record(&[&first[..], &[0xff; 3]].concat(), first.len());
+        // RTCP.
+        record(&rtp(2, 0, true, &[]), 0);
+        // Only the header was captured.
+        record(&rtp(3, 0, true, &[]), first.len());
+        let packets = unsafe {
+            let f = libc::tmpfile();
+            libc::fwrite(file.as_ptr() as *const _, file.len(), 1, f);
+            libc::rewind(f);
+            let packets = read_rtpdump(f, 0).unwrap();
+            libc::fclose(f);
+            packets
+        };
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].seq, 1);
+        assert_eq!(packets[0].payload, [0x10, 0x12, 0]);
+        assert_eq!(packets[0].offset, RTPDUMP_HEADER_SIZE as u64);
+    }
+
+    #[test]
+    fn obu_elements() {
+        // Two elements, the last one without a length field.
+        let (aggregation, elements) = parse_payload(&[0x20, 2, 0x30, 1, 0x32, 0]).unwrap();
+        assert_eq!(aggregation, 0x20);
+        assert_eq!(elements, [&[0x30, 1][..], &[0x32, 0]]);
+        // Elements that all have a length field, one of which is too long.
+        assert_eq!(parse_payload(&[0x00, 1, 0x30, 2, 0x32]), None);
+
+        let mut out = Vec::new();
+        write_obu(&[0x30, 1, 2], &mut out).unwrap();
+        write_obu(&[0x36, 0x08, 1, 5], &mut out).unwrap();
+        assert_eq!(out, [0x32, 2, 1, 2, 0x36, 0x08, 1, 5]);
+        assert!(write_obu(&[], &mut out).is_err());
+    }
+
+    #[test]
+    fn fragments_are_reassembled() {
+        let packets = [
+            // A new coded video sequence, with an OBU continued in the next packet.
+            packet(1, 0, false, &[0x58, 0x30, 1]),
+            packet(2, 0, true, &[0x90, 2]),
+            // Sequence number 3 is lost, breaking this temporal unit.
+            packet(4, 1, true, &[0x90, 3]),
+            // Which can't be decoded without a new coded video sequence.
+            packet(5, 2, true, &[0x10, 0x30, 4]),
+            packet(6, 3, true, &[0x18, 0x30, 5]),
+        ];
+        let mut loss = Loss::default();
+        let tus = unsafe { depacketize(&packets, &mut loss) };
+        let tus = tus
+            .iter()
+            .map(|tu| (tu.ts, &tu.data[..]))
+            .collect::<Vec<_>>();
+        assert_eq!(tus, [(0, &[0x32, 2, 1, 2][..]), (3, &[0x32, 1, 5])]);
+        assert_eq!((loss.packets, loss.dropped), (1, 2));
+    }
+}
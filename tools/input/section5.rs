@@ -1,8 +1,7 @@
 use crate::compat::errno::errno_location;
 use crate::compat::stdio::stderr;
-use libc::fclose;
+use crate::input::input::input_is_stream;
 use libc::feof;
-use libc::fopen;
 use libc::fprintf;
 use libc::fread;
 use libc::fseeko;
@@ -37,6 +36,7 @@ pub struct Demuxer {
         unsafe extern "C" fn(
             *mut DemuxerPriv,
             *const c_char,
+            *mut libc::FILE,
             *mut c_uint,
             *mut c_uint,
             *mut c_uint,
@@ -184,25 +184,22 @@ unsafe extern "C" fn section5_probe(data: *const u8) -> c_int {
 unsafe extern "C" fn section5_open(
     c: *mut Section5InputContext,
     file: *const c_char,
+    f: *mut libc::FILE,
     fps: *mut c_uint,
     num_frames: *mut c_uint,
     timebase: *mut c_uint,
 ) -> c_int {
-    (*c).f = fopen(file, b"rb\0" as *const u8 as *const c_char);
-    if ((*c).f).is_null() {
-        fprintf(
-            stderr,
-            b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
-            file,
-            strerror(*errno_location()),
-        );
-        return -1;
-    }
+    (*c).f = f;
     *fps.offset(0) = 25 as c_int as c_uint;
     *fps.offset(1) = 1 as c_int as c_uint;
     *timebase.offset(0) = 25 as c_int as c_uint;
     *timebase.offset(1) = 1 as c_int as c_uint;
     *num_frames = 0 as c_int as c_uint;
+    if input_is_stream(file) {
+        // Streams can't be seeked back after counting the frames.
+        *num_frames = u32::MAX;
+        return 0 as c_int;
+    }
     loop {
         let mut byte: [u8; 2] = [0; 2];
         if fread(
@@ -313,9 +310,7 @@ unsafe extern "C" fn section5_read(c: *mut Section5InputContext, data: *mut Dav1
     return 0 as c_int;
 }
 
-unsafe extern "C" fn section5_close(c: *mut Section5InputContext) {
-    fclose((*c).f);
-}
+unsafe extern "C" fn section5_close(_c: *mut Section5InputContext) {}
 
 #[no_mangle]
 pub static mut section5_demuxer: Demuxer = Demuxer {
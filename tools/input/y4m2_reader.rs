@@ -0,0 +1,189 @@
+//! Reader for the YUV4MPEG2 files written by the `yuv4mpeg2` muxer, and for raw planar YUV files,
+//! used as the reference of `--compare`.
+
+use rav1d::include::dav1d::headers::Dav1dPixelLayout;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I422;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use std::ffi::c_int;
+use std::io;
+use std::io::BufRead;
+use std::io::ErrorKind;
+use std::io::Read;
+
+const Y4M_MAGIC: &[u8] = b"YUV4MPEG2 ";
+
+/// The dimensions and sample format of the frames of a reference file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FrameFormat {
+    pub w: c_int,
+    pub h: c_int,
+    pub layout: Dav1dPixelLayout,
+    pub bpc: c_int,
+}
+
+/// The number of planes of a [`FrameFormat`] and the dimensions of its chroma planes.
+pub struct Chroma {
+    pub n_planes: usize,
+    /// 0 without chroma planes.
+    pub w: c_int,
+    pub h: c_int,
+}
+
+impl FrameFormat {
+    pub fn chroma(&self) -> Chroma {
+        if self.layout == DAV1D_PIXEL_LAYOUT_I400 {
+            return Chroma {
+                n_planes: 1,
+                w: 0,
+                h: 0,
+            };
+        }
+        let ss_ver = (self.layout == DAV1D_PIXEL_LAYOUT_I420) as c_int;
+        let ss_hor = (self.layout != DAV1D_PIXEL_LAYOUT_I444) as c_int;
+        Chroma {
+            n_planes: 3,
+            w: self.w + ss_hor >> ss_hor,
+            h: self.h + ss_ver >> ss_ver,
+        }
+    }
+
+    /// The dimensions of each plane.
+    pub fn planes(&self) -> Vec<(c_int, c_int)> {
+        let chroma = self.chroma();
+        let mut planes = vec![(self.w, self.h)];
+        planes.extend((1..chroma.n_planes).map(|_| (chroma.w, chroma.h)));
+        planes
+    }
+}
+
+/// Parses a `C` tag of a YUV4MPEG2 header into a layout and a bit depth.
+fn parse_colorspace(tag: &str) -> Option<(Dav1dPixelLayout, c_int)> {
+    let (layout, rest) = if let Some(rest) = tag.strip_prefix("mono") {
+        (DAV1D_PIXEL_LAYOUT_I400, rest)
+    } else if let Some(rest) = tag.strip_prefix("420") {
+        // `420jpeg`, `420mpeg2` and `420paldv` only differ in chroma siting.
+        let rest = match rest {
+            "jpeg" | "mpeg2" | "paldv" => "",
+            rest => rest,
+        };
+        (DAV1D_PIXEL_LAYOUT_I420, rest)
+    } else if let Some(rest) = tag.strip_prefix("422") {
+        (DAV1D_PIXEL_LAYOUT_I422, rest)
+    } else if let Some(rest) = tag.strip_prefix("444") {
+        (DAV1D_PIXEL_LAYOUT_I444, rest)
+    } else {
+        return None;
+    };
+    let bpc = match rest.strip_prefix('p').unwrap_or(rest) {
+        "" => 8,
+        "10" => 10,
+        "12" => 12,
+        _ => return None,
+    };
+    Some((layout, bpc))
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Reads a line, without its trailing newline. Returns [`None`] at the end of the file.
+fn read_line(r: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if r.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(invalid("truncated YUV4MPEG2 header".into()));
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Returns whether the file read by `r` starts with a YUV4MPEG2 header.
+pub fn is_y4m(r: &mut impl BufRead) -> io::Result<bool> {
+    Ok(r.fill_buf()?.starts_with(Y4M_MAGIC))
+}
+
+/// Parses the YUV4MPEG2 stream header.
+///
+/// A missing `C` tag means 8-bit 4:2:0, as in other YUV4MPEG2 readers.
+pub fn read_y4m_header(r: &mut impl BufRead) -> io::Result<FrameFormat> {
+    let line = read_line(r)?.unwrap_or_default();
+    let Some(tags) = line.strip_prefix("YUV4MPEG2 ") else {
+        return Err(invalid("not a YUV4MPEG2 file".into()));
+    };
+    let mut w = None;
+    let mut h = None;
+    let mut layout = DAV1D_PIXEL_LAYOUT_I420;
+    let mut bpc = 8;
+    for tag in tags.split(' ').filter(|tag| !tag.is_empty()) {
+        let (key, value) = tag.split_at(1);
+        match key {
+            "W" => w = value.parse().ok(),
+            "H" => h = value.parse().ok(),
+            "C" => {
+                (layout, bpc) = parse_colorspace(value)
+                    .ok_or_else(|| invalid(format!("unsupported YUV4MPEG2 colorspace {value}")))?;
+            }
+            // Frame rate, interlacing, aspect ratio and extensions don't matter here.
+            _ => {}
+        }
+    }
+    match (w, h) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => Ok(FrameFormat { w, h, layout, bpc }),
+        _ => Err(invalid("invalid YUV4MPEG2 frame dimensions".into())),
+    }
+}
+
+/// Reads the `FRAME` header preceding each frame of a YUV4MPEG2 stream.
+/// Returns `false` at the end of the file.
+pub fn read_y4m_frame_header(r: &mut impl BufRead) -> io::Result<bool> {
+    match read_line(r)? {
+        None => Ok(false),
+        Some(line) if line.split(' ').next() == Some("FRAME") => Ok(true),
+        Some(_) => Err(invalid("missing YUV4MPEG2 FRAME header".into())),
+    }
+}
+
+/// Reads the planes of a frame of format `fmt` into `planes`, one sample per element.
+/// Samples of more than 8 bits are little-endian 16-bit words, as written by the muxers.
+///
+/// Returns `false` if the file ends before the frame starts.
+pub fn read_planes(
+    r: &mut impl Read,
+    fmt: &FrameFormat,
+    planes: &mut [Vec<u16>; 3],
+) -> io::Result<bool> {
+    let hbd = (fmt.bpc > 8) as usize;
+    let mut buf = Vec::new();
+    for (pl, (pw, ph)) in fmt.planes().into_iter().enumerate() {
+        buf.resize(((pw * ph) as usize) << hbd, 0);
+        let mut n = 0;
+        while n < buf.len() {
+            match r.read(&mut buf[n..]) {
+                Ok(0) => break,
+                Ok(len) => n += len,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if n == 0 && pl == 0 {
+            return Ok(false);
+        }
+        if n < buf.len() {
+            return Err(invalid("truncated frame".into()));
+        }
+        planes[pl].clear();
+        if hbd != 0 {
+            planes[pl].extend(
+                buf.chunks_exact(2)
+                    .map(|s| u16::from_le_bytes([s[0], s[1]])),
+            );
+        } else {
+            planes[pl].extend(buf.iter().map(|&s| s as u16));
+        }
+    }
+    Ok(true)
+}
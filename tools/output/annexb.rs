@@ -0,0 +1,136 @@
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdout;
+use crate::input::input::write_leb128;
+use crate::output::remux::parse_obus;
+use libc::fclose;
+use libc::fopen;
+use libc::fprintf;
+use libc::fwrite;
+use libc::strcmp;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::headers::DAV1D_OBU_FRAME;
+use rav1d::include::dav1d::headers::DAV1D_OBU_FRAME_HDR;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+
+#[repr(C)]
+pub struct MuxerPriv {
+    pub f: *mut libc::FILE,
+}
+
+#[repr(C)]
+pub struct Muxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub extension: *const c_char,
+    pub write_header: Option<
+        unsafe extern "C" fn(
+            *mut MuxerPriv,
+            *const c_char,
+            *const Dav1dPictureParameters,
+            *const c_uint,
+        ) -> c_int,
+    >,
+    pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
+    pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
+    pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+}
+
+pub type AnnexbOutputContext = MuxerPriv;
+
+unsafe extern "C" fn annexb_open(
+    c: *mut AnnexbOutputContext,
+    file: *const c_char,
+    _p: *const Dav1dPictureParameters,
+    _fps: *const c_uint,
+) -> c_int {
+    if strcmp(file, b"-\0" as *const u8 as *const c_char) == 0 {
+        (*c).f = stdout;
+    } else {
+        (*c).f = fopen(file, b"wb\0" as *const u8 as *const c_char);
+        if ((*c).f).is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                file,
+                strerror(*errno_location()),
+            );
+            return -1;
+        }
+    }
+    return 0 as c_int;
+}
+
+/// Splits the temporal unit into frame units, each starting with a frame header
+/// (but for the temporal delimiter and the OBUs before the first one),
+/// and writes them with `obu_length` fields instead of OBU size fields.
+unsafe extern "C" fn annexb_write(c: *mut AnnexbOutputContext, data: *const Dav1dData) -> c_int {
+    let buf = std::slice::from_raw_parts((*data).data.unwrap().as_ptr(), (*data).sz);
+    let Some(obus) = parse_obus(buf) else {
+        return -1;
+    };
+    let mut frame_units = Vec::new();
+    let mut frame_unit = Vec::new();
+    let mut has_frame = false;
+    let mut obu_buf = Vec::new();
+    for obu in &obus {
+        let is_frame = matches!(obu.obu_type(), DAV1D_OBU_FRAME_HDR | DAV1D_OBU_FRAME);
+        if is_frame && has_frame {
+            write_leb128(&mut frame_units, frame_unit.len());
+            frame_units.append(&mut frame_unit);
+        }
+        has_frame |= is_frame;
+        obu_buf.clear();
+        obu.write(&mut obu_buf, false);
+        write_leb128(&mut frame_unit, obu_buf.len());
+        frame_unit.extend_from_slice(&obu_buf);
+    }
+    write_leb128(&mut frame_units, frame_unit.len());
+    frame_units.append(&mut frame_unit);
+    let mut temporal_unit = Vec::new();
+    write_leb128(&mut temporal_unit, frame_units.len());
+    temporal_unit.append(&mut frame_units);
+    if fwrite(
+        temporal_unit.as_ptr() as *const c_void,
+        temporal_unit.len(),
+        1,
+        (*c).f,
+    ) != 1
+    {
+        fprintf(
+            stderr,
+            b"Failed to write frame data: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    return 0 as c_int;
+}
+
+unsafe extern "C" fn annexb_close(c: *mut AnnexbOutputContext) {
+    if (*c).f != stdout {
+        fclose((*c).f);
+    }
+}
+
+#[no_mangle]
+pub static mut annexb_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<AnnexbOutputContext>() as c_ulong as c_int,
+    name: b"annexb\0" as *const u8 as *const c_char,
+    extension: b"obu\0" as *const u8 as *const c_char,
+    write_header: Some(annexb_open),
+    write_picture: None,
+    write_data: Some(annexb_write),
+    write_trailer: Some(annexb_close),
+    verify: None,
+    verify_open: None,
+};
@@ -0,0 +1,402 @@
+//! Objective quality of the output frames against a reference `.y4m` or raw `.yuv` file:
+//! the PSNR and SSIM of each plane of each frame, and their averages over all frames.
+//!
+//! The average PSNR is computed from the mean squared error over all frames,
+//! not as the mean of the PSNR of each frame, so that identical frames don't make it infinite.
+
+use crate::input::input::print_error;
+use crate::input::y4m2_reader::is_y4m;
+use crate::input::y4m2_reader::read_planes;
+use crate::input::y4m2_reader::read_y4m_frame_header;
+use crate::input::y4m2_reader::read_y4m_header;
+use crate::input::y4m2_reader::FrameFormat;
+use rav1d::include::dav1d::headers::Dav1dPixelLayout;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I422;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::CStr;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufReader;
+use std::slice;
+
+const PLANE_NAMES: [&str; 3] = ["Y", "U", "V"];
+
+/// The format of a raw reference given with `--compareformat`.
+/// Parts that are left out are taken from the first decoded frame.
+#[derive(Default)]
+struct RawFormat {
+    size: Option<(c_int, c_int)>,
+    layout: Option<Dav1dPixelLayout>,
+    bpc: Option<c_int>,
+}
+
+impl RawFormat {
+    /// Parses `WxH[:layout[:bitdepth]]`, where every part may be empty.
+    fn parse(s: &str) -> Option<Self> {
+        let mut fmt = Self::default();
+        let mut parts = s.split(':');
+        if let Some(size) = parts.next().filter(|size| !size.is_empty()) {
+            let (w, h) = size.split_once('x')?;
+            let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+            if w <= 0 || h <= 0 {
+                return None;
+            }
+            fmt.size = Some((w, h));
+        }
+        if let Some(layout) = parts.next().filter(|layout| !layout.is_empty()) {
+            fmt.layout = Some(match layout {
+                "400" => DAV1D_PIXEL_LAYOUT_I400,
+                "420" => DAV1D_PIXEL_LAYOUT_I420,
+                "422" => DAV1D_PIXEL_LAYOUT_I422,
+                "444" => DAV1D_PIXEL_LAYOUT_I444,
+                _ => return None,
+            });
+        }
+        if let Some(bpc) = parts.next().filter(|bpc| !bpc.is_empty()) {
+            fmt.bpc = Some(match bpc {
+                "8" => 8,
+                "10" => 10,
+                "12" => 12,
+                _ => return None,
+            });
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(fmt)
+    }
+
+    fn resolve(&self, decoded: &FrameFormat) -> FrameFormat {
+        let (w, h) = self.size.unwrap_or((decoded.w, decoded.h));
+        FrameFormat {
+            w,
+            h,
+            layout: self.layout.unwrap_or(decoded.layout),
+            bpc: self.bpc.unwrap_or(decoded.bpc),
+        }
+    }
+}
+
+#[derive(Default)]
+struct PlaneStats {
+    sse: u64,
+    samples: u64,
+    ssim: f64,
+}
+
+pub struct Compare {
+    path: String,
+    file: BufReader<File>,
+    y4m: bool,
+    raw_format: RawFormat,
+    /// Known from the header of a `.y4m` reference, or from the first decoded frame otherwise.
+    format: Option<FrameFormat>,
+    reference: [Vec<u16>; 3],
+    decoded: [Vec<u16>; 3],
+    stats: [PlaneStats; 3],
+    n_frames: usize,
+}
+
+fn format_name(fmt: &FrameFormat) -> String {
+    let layout = match fmt.layout {
+        DAV1D_PIXEL_LAYOUT_I400 => "400",
+        DAV1D_PIXEL_LAYOUT_I420 => "420",
+        DAV1D_PIXEL_LAYOUT_I422 => "422",
+        _ => "444",
+    };
+    format!("{}x{} {} {}-bit", fmt.w, fmt.h, layout, fmt.bpc)
+}
+
+fn psnr(sse: u64, samples: u64, max: f64) -> f64 {
+    if sse == 0 {
+        return f64::INFINITY;
+    }
+    10. * (max * max * samples as f64 / sse as f64).log10()
+}
+
+/// The mean SSIM of 8x8 windows every 4 samples,
+/// or of a single window covering the plane if it is smaller than that.
+fn ssim(a: &[u16], b: &[u16], w: usize, h: usize, max: f64) -> f64 {
+    let win = w.min(h).min(8);
+    let step = if win == 8 { 4 } else { win };
+    let n = (win * win) as f64;
+    let c1 = (0.01 * max) * (0.01 * max);
+    let c2 = (0.03 * max) * (0.03 * max);
+    let mut sum = 0.;
+    let mut windows = 0;
+    for y in (0..=h - win).step_by(step) {
+        for x in (0..=w - win).step_by(step) {
+            let (mut s1, mut s2, mut s11, mut s22, mut s12) = (0u64, 0u64, 0u64, 0u64, 0u64);
+            for row in y..y + win {
+                let off = row * w + x;
+                for (&a, &b) in a[off..off + win].iter().zip(&b[off..off + win]) {
+                    let (a, b) = (a as u64, b as u64);
+                    s1 += a;
+                    s2 += b;
+                    s11 += a * a;
+                    s22 += b * b;
+                    s12 += a * b;
+                }
+            }
+            let (mu1, mu2) = (s1 as f64 / n, s2 as f64 / n);
+            let var1 = s11 as f64 / n - mu1 * mu1;
+            let var2 = s22 as f64 / n - mu2 * mu2;
+            let cov = s12 as f64 / n - mu1 * mu2;
+            sum += (2. * mu1 * mu2 + c1) * (2. * cov + c2)
+                / ((mu1 * mu1 + mu2 * mu2 + c1) * (var1 + var2 + c2));
+            windows += 1;
+        }
+    }
+    sum / windows as f64
+}
+
+/// Opens the reference `file` for `--compare`.
+/// `format` is the `--compareformat` of a raw reference, or null.
+///
+/// Prints the error and returns [`None`] on failure.
+pub unsafe fn compare_open(file: *const c_char, format: *const c_char) -> Option<Compare> {
+    let path = CStr::from_ptr(file).to_string_lossy().into_owned();
+    let raw_format = if format.is_null() {
+        RawFormat::default()
+    } else {
+        let s = CStr::from_ptr(format).to_string_lossy();
+        let Some(raw_format) = RawFormat::parse(&s) else {
+            print_error(format!("Invalid reference format '{s}'"));
+            return None;
+        };
+        raw_format
+    };
+    let mut file = match File::open(&path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) => {
+            print_error(format!("Failed to open {path}: {e}"));
+            return None;
+        }
+    };
+    let y4m = match is_y4m(&mut file) {
+        Ok(y4m) => y4m,
+        Err(e) => {
+            print_error(format!("Failed to read {path}: {e}"));
+            return None;
+        }
+    };
+    let mut format = None;
+    if y4m {
+        match read_y4m_header(&mut file) {
+            Ok(fmt) => format = Some(fmt),
+            Err(e) => {
+                print_error(format!("Failed to read {path}: {e}"));
+                return None;
+            }
+        }
+    }
+    Some(Compare {
+        path,
+        file,
+        y4m,
+        raw_format,
+        format,
+        reference: Default::default(),
+        decoded: Default::default(),
+        stats: Default::default(),
+        n_frames: 0,
+    })
+}
+
+impl Compare {
+    /// Compares `p` against the next frame of the reference and prints its PSNR and SSIM.
+    ///
+    /// Returns a negative value if the reference can't be read or doesn't match the format of `p`.
+    pub unsafe fn frame(&mut self, p: &Dav1dPicture) -> c_int {
+        let decoded = FrameFormat {
+            w: p.p.w,
+            h: p.p.h,
+            layout: p.p.layout,
+            bpc: p.p.bpc,
+        };
+        let fmt = *self
+            .format
+            .get_or_insert_with(|| self.raw_format.resolve(&decoded));
+        let n = self.n_frames;
+        if fmt != decoded {
+            print_error(format!(
+                "Frame {n}: decoded {} doesn't match the reference {}",
+                format_name(&decoded),
+                format_name(&fmt),
+            ));
+            return -1;
+        }
+        let read = if self.y4m {
+            read_y4m_frame_header(&mut self.file).and_then(|more| {
+                Ok(more && read_planes(&mut self.file, &fmt, &mut self.reference)?)
+            })
+        } else {
+            read_planes(&mut self.file, &fmt, &mut self.reference)
+        };
+        match read {
+            Ok(true) => {}
+            Ok(false) => {
+                print_error(format!("Frame {n}: {} has only {n} frames", self.path));
+                return -1;
+            }
+            Err(e) => {
+                print_error(format!("Frame {n}: failed to read {}: {e}", self.path));
+                return -1;
+            }
+        }
+
+        let hbd = (fmt.bpc > 8) as usize;
+        let max = ((1 << fmt.bpc) - 1) as f64;
+        let mut line = format!("Frame {n}: PSNR");
+        let mut ssim_line = String::from(", SSIM");
+        for (pl, (pw, ph)) in fmt.planes().into_iter().enumerate() {
+            let (pw, ph) = (pw as usize, ph as usize);
+            let mut ptr = p.data[pl].unwrap().as_ptr() as *const u8;
+            let stride = p.stride[(pl != 0) as usize];
+            let decoded = &mut self.decoded[pl];
+            decoded.clear();
+            for _ in 0..ph {
+                if hbd != 0 {
+                    decoded.extend_from_slice(slice::from_raw_parts(ptr as *const u16, pw));
+                } else {
+                    decoded.extend(slice::from_raw_parts(ptr, pw).iter().map(|&s| s as u16));
+                }
+                ptr = ptr.wrapping_offset(stride);
+            }
+            let reference = &self.reference[pl];
+            let sse = reference
+                .iter()
+                .zip(decoded.iter())
+                .map(|(&a, &b)| (a as i64 - b as i64).pow(2) as u64)
+                .sum();
+            let ssim = ssim(reference, decoded, pw, ph, max);
+            let stats = &mut self.stats[pl];
+            stats.sse += sse;
+            stats.samples += (pw * ph) as u64;
+            stats.ssim += ssim;
+            let psnr = psnr(sse, (pw * ph) as u64, max);
+            write!(line, " {} {psnr:.3}", PLANE_NAMES[pl]).unwrap();
+            write!(ssim_line, " {} {ssim:.5}", PLANE_NAMES[pl]).unwrap();
+        }
+        print_error(line + &ssim_line);
+        self.n_frames += 1;
+        0
+    }
+
+    /// Prints the averages over all compared frames.
+    ///
+    /// Returns nonzero if nothing was compared or the reference has more frames than were decoded.
+    pub unsafe fn close(mut self) -> c_int {
+        let Some(fmt) = self.format.filter(|_| self.n_frames != 0) else {
+            print_error(format!("No frames compared against {}", self.path));
+            return 1;
+        };
+        let max = ((1 << fmt.bpc) - 1) as f64;
+        let n = self.n_frames;
+        let mut line = format!("Average of {n} frames: PSNR");
+        let mut ssim_line = String::from(", SSIM");
+        for (pl, stats) in self.stats.iter().take(fmt.chroma().n_planes).enumerate() {
+            let psnr = psnr(stats.sse, stats.samples, max);
+            let ssim = stats.ssim / n as f64;
+            write!(line, " {} {psnr:.3}", PLANE_NAMES[pl]).unwrap();
+            write!(ssim_line, " {} {ssim:.5}", PLANE_NAMES[pl]).unwrap();
+        }
+        print_error(line + &ssim_line);
+        let more = if self.y4m {
+            read_y4m_frame_header(&mut self.file)
+        } else {
+            read_planes(&mut self.file, &fmt, &mut self.reference)
+        };
+        if let Ok(true) = more {
+            print_error(format!(
+                "{} has more than the {n} decoded frames",
+                self.path
+            ));
+            return 1;
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::psnr;
+    use super::ssim;
+    use super::RawFormat;
+    use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn psnr_of_the_mean_squared_error() {
+        assert_eq!(psnr(0, 100, 255.), f64::INFINITY);
+        // An error of the full range in every sample.
+        assert_close(psnr(255 * 255 * 4, 4, 255.), 0.);
+        // An error of 1 in every sample.
+        assert_close(psnr(64, 64, 1023.), 20. * 1023f64.log10());
+        assert_close(psnr(1, 10, 1.), 10.);
+    }
+
+    #[test]
+    fn ssim_of_identical_planes_is_1() {
+        let plane = (0..16 * 12)
+            .map(|i| (i * 37 % 256) as u16)
+            .collect::<Vec<_>>();
+        assert_close(ssim(&plane, &plane, 16, 12, 255.), 1.);
+        let flat = vec![128; 16 * 12];
+        assert_close(ssim(&flat, &flat, 16, 12, 255.), 1.);
+    }
+
+    #[test]
+    fn ssim_of_8x8_windows_every_4_samples() {
+        let max = 255.;
+        let (c1, c2) = ((0.01 * max) * (0.01 * max), (0.03 * max) * (0.03 * max));
+        // Black against a plane with its right half white:
+        // windows at x = 0, 4 and 8 that see none, half and all of the white.
+        let black = vec![0; 16 * 8];
+        let half = (0..16 * 8)
+            .map(|i| if i % 16 >= 8 { 255 } else { 0 })
+            .collect::<Vec<_>>();
+        let (mu, var) = (max / 2., max * max / 4.);
+        let windows = [
+            1.,
+            c1 * c2 / ((mu * mu + c1) * (var + c2)),
+            c1 / (max * max + c1),
+        ];
+        assert_close(
+            ssim(&black, &half, 16, 8, max),
+            windows.iter().sum::<f64>() / 3.,
+        );
+    }
+
+    #[test]
+    fn ssim_of_planes_smaller_than_a_window() {
+        let max = 1023.;
+        let c1 = (0.01 * max) * (0.01 * max);
+        // A single 2x2 window, leaving out the last column.
+        let a = [0, 0, 7, 0, 0, 7];
+        let b = [1023, 1023, 0, 1023, 1023, 0];
+        assert_close(ssim(&a, &b, 3, 2, max), c1 / (max * max + c1));
+    }
+
+    #[test]
+    fn raw_formats() {
+        let fmt = RawFormat::parse("352x288:444:10").unwrap();
+        assert_eq!(fmt.size, Some((352, 288)));
+        assert_eq!(fmt.layout, Some(DAV1D_PIXEL_LAYOUT_I444));
+        assert_eq!(fmt.bpc, Some(10));
+        let fmt = RawFormat::parse("::12").unwrap();
+        assert_eq!((fmt.size, fmt.layout, fmt.bpc), (None, None, Some(12)));
+        assert!(RawFormat::parse("").is_some());
+        for s in ["352", "0x288", "352x288:411", "352x288:420:9", "1x1:420:8:"] {
+            assert!(RawFormat::parse(s).is_none(), "{s}");
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Muxers writing a checksum of each plane of each output frame, one frame per line,
+//! so that a mismatch can be narrowed down to the first frame and plane that differ.
+//!
+//! The format is like this:
+//!
+//! ```text
+//! #format: frame checksums
+//! #hash: MD5
+//! #frame, timestamp, dimensions, Y, U, V
+//! 0, 0, 352x288, <Y checksum>, <U checksum>, <V checksum>
+//! ```
+//!
+//! `--verify` takes the name of such a file instead of a checksum.
+//! Only the dimensions and the checksums are compared, as timestamps depend on the container.
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdout;
+use crate::input::input::print_error;
+use crate::output::md5::md5_finish;
+use crate::output::md5::md5_init;
+use crate::output::md5::md5_update;
+use crate::output::md5::MD5Context;
+use crate::output::xxhash::Xxh3State;
+use libc::fclose;
+use libc::fopen;
+use libc::fprintf;
+use libc::fwrite;
+use libc::strcmp;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use rav1d::src::lib::dav1d_picture_unref;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+use std::ffi::CStr;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Lines;
+use std::mem;
+use std::ptr;
+use std::ptr::NonNull;
+
+const PLANE_NAMES: [&str; 3] = ["Y", "U", "V"];
+
+#[derive(Clone, Copy)]
+pub enum FrameHash {
+    Md5,
+    Xxh3,
+}
+
+impl FrameHash {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Xxh3 => "XXH3",
+        }
+    }
+
+    /// Hashes `h` rows of `len` bytes, `stride` bytes apart.
+    unsafe fn hash_plane(self, mut ptr: *const u8, stride: isize, len: usize, h: c_int) -> String {
+        let mut hex = String::new();
+        match self {
+            Self::Md5 => {
+                let mut md5: MD5Context = mem::zeroed();
+                md5_init(&mut md5);
+                for _ in 0..h {
+                    md5_update(&mut md5, ptr, len as c_uint);
+                    ptr = ptr.wrapping_offset(stride);
+                }
+                md5_finish(&mut md5);
+                for byte in md5.abcd.iter().flat_map(|abcd| abcd.to_le_bytes()) {
+                    write!(hex, "{byte:02x}").unwrap();
+                }
+            }
+            Self::Xxh3 => {
+                let mut state = Xxh3State::new();
+                for _ in 0..h {
+                    state.update(std::slice::from_raw_parts(ptr, len));
+                    ptr = ptr.wrapping_offset(stride);
+                }
+                for byte in state.canonical() {
+                    write!(hex, "{byte:02x}").unwrap();
+                }
+            }
+        }
+        hex
+    }
+
+    fn header(self) -> String {
+        format!(
+            "#format: frame checksums\n#hash: {}\n#frame, timestamp, dimensions, Y, U, V\n",
+            self.name()
+        )
+    }
+}
+
+/// The frame checksum file given to `--verify`, read as the frames are written.
+pub struct Reference {
+    path: String,
+    lines: Lines<BufReader<File>>,
+    /// The number of frames read so far.
+    n_frames: usize,
+    /// Set once a frame differs, or the file couldn't be read.
+    res: c_int,
+}
+
+impl Reference {
+    /// The next line with the checksums of a frame, if any.
+    fn next_frame(&mut self, hash: FrameHash) -> Option<String> {
+        while self.res == 0 {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => {
+                    print_error(format!("Failed to read {}: {e}", self.path));
+                    self.res = -1;
+                    break;
+                }
+            };
+            if let Some(name) = line.strip_prefix("#hash: ") {
+                if name != hash.name() {
+                    print_error(format!(
+                        "{} contains {name} checksums, not {}",
+                        self.path,
+                        hash.name()
+                    ));
+                    self.res = -1;
+                }
+            } else if !line.starts_with('#') && !line.trim().is_empty() {
+                self.n_frames += 1;
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    /// Compares the checksums of frame `n` against the next ones of the file.
+    fn check(&mut self, hash: FrameHash, n: usize, line: &str) {
+        // Running out of frames is reported by `framehash_verify`.
+        let Some(expected) = self.next_frame(hash) else {
+            return;
+        };
+        let fields = line.split(", ").collect::<Vec<_>>();
+        let expected_fields = expected.split(", ").map(str::trim).collect::<Vec<_>>();
+        if expected_fields.get(2) != fields.get(2) {
+            print_error(format!(
+                "Frame {n}: dimensions {} differ from the expected {}",
+                fields[2],
+                expected_fields.get(2).unwrap_or(&"(none)"),
+            ));
+            self.res = 1;
+            return;
+        }
+        for pl in 0..3 {
+            let (hex, expected_hex) = (fields.get(3 + pl), expected_fields.get(3 + pl));
+            if hex != expected_hex {
+                print_error(format!(
+                    "Frame {n}: plane {} differs ({} instead of the expected {})",
+                    PLANE_NAMES[pl],
+                    hex.unwrap_or(&"(none)"),
+                    expected_hex.unwrap_or(&"(none)"),
+                ));
+                self.res = 1;
+                return;
+            }
+        }
+    }
+}
+
+#[repr(C)]
+pub struct MuxerPriv {
+    pub f: *mut libc::FILE,
+    pub hash: FrameHash,
+    pub n_frames: usize,
+    /// Set by `--verify`, in which case nothing is written to `f`.
+    pub reference: Option<Reference>,
+}
+
+#[repr(C)]
+pub struct Muxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub extension: *const c_char,
+    pub write_header: Option<
+        unsafe extern "C" fn(
+            *mut MuxerPriv,
+            *const c_char,
+            *const Dav1dPictureParameters,
+            *const c_uint,
+        ) -> c_int,
+    >,
+    pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
+    pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
+    pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+}
+
+pub type FrameHashContext = MuxerPriv;
+
+unsafe fn write_str(fh: *mut FrameHashContext, s: &str) -> c_int {
+    if fwrite(s.as_ptr() as *const c_void, s.len(), 1, (*fh).f) != 1 {
+        fprintf(
+            stderr,
+            b"Failed to write frame checksums: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    0
+}
+
+unsafe fn framehash_open(fh: *mut FrameHashContext, file: *const c_char, hash: FrameHash) -> c_int {
+    if strcmp(file, b"-\0" as *const u8 as *const c_char) == 0 {
+        (*fh).f = stdout;
+    } else {
+        (*fh).f = fopen(file, b"wb\0" as *const u8 as *const c_char);
+        if (*fh).f.is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                file,
+                strerror(*errno_location()),
+            );
+            return -1;
+        }
+    }
+    (*fh).hash = hash;
+    (*fh).n_frames = 0;
+    // The private data isn't initialized.
+    ptr::write(&mut (*fh).reference, None);
+    0
+}
+
+unsafe extern "C" fn md5frames_open(
+    fh: *mut FrameHashContext,
+    file: *const c_char,
+    _p: *const Dav1dPictureParameters,
+    _fps: *const c_uint,
+) -> c_int {
+    framehash_open(fh, file, FrameHash::Md5)
+}
+
+unsafe extern "C" fn xxh3frames_open(
+    fh: *mut FrameHashContext,
+    file: *const c_char,
+    _p: *const Dav1dPictureParameters,
+    _fps: *const c_uint,
+) -> c_int {
+    framehash_open(fh, file, FrameHash::Xxh3)
+}
+
+unsafe extern "C" fn framehash_write(fh: *mut FrameHashContext, p: *mut Dav1dPicture) -> c_int {
+    let hbd = ((*p).p.bpc > 8) as c_int;
+    let w = (*p).p.w;
+    let h = (*p).p.h;
+    let n = (*fh).n_frames;
+    let mut line = format!("{n}, {}, {w}x{h}", (*p).m.timestamp);
+    let mut planes = vec![(0, w, h)];
+    if (*p).p.layout != DAV1D_PIXEL_LAYOUT_I400 {
+        let ss_ver = ((*p).p.layout == DAV1D_PIXEL_LAYOUT_I420) as c_int;
+        let ss_hor = ((*p).p.layout != DAV1D_PIXEL_LAYOUT_I444) as c_int;
+        let cw = w + ss_hor >> ss_hor;
+        let ch = h + ss_ver >> ss_ver;
+        planes.extend([(1, cw, ch), (2, cw, ch)]);
+    }
+    for (pl, pw, ph) in planes {
+        let ptr = (*p).data[pl].map_or_else(ptr::null_mut, NonNull::as_ptr) as *const u8;
+        let stride = (*p).stride[(pl != 0) as usize];
+        let hex = (*fh).hash.hash_plane(ptr, stride, (pw << hbd) as usize, ph);
+        write!(line, ", {hex}").unwrap();
+    }
+    dav1d_picture_unref(p);
+    (*fh).n_frames += 1;
+    let hash = (*fh).hash;
+    if let Some(reference) = &mut (*fh).reference {
+        reference.check(hash, n, &line);
+        return 0;
+    }
+    if n == 0 {
+        let res = write_str(fh, &hash.header());
+        if res < 0 {
+            return res;
+        }
+    }
+    line += "\n";
+    write_str(fh, &line)
+}
+
+unsafe extern "C" fn framehash_close(fh: *mut FrameHashContext) {
+    if (*fh).n_frames == 0 {
+        write_str(fh, &(*fh).hash.header());
+    }
+    if (*fh).f != stdout {
+        fclose((*fh).f);
+    }
+}
+
+/// Opens the frame checksum file named `ref_file`,
+/// to compare each frame against it as it's written.
+unsafe extern "C" fn framehash_verify_open(
+    fh: *mut FrameHashContext,
+    ref_file: *const c_char,
+) -> c_int {
+    let path = CStr::from_ptr(ref_file).to_string_lossy().into_owned();
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            print_error(format!("Failed to open {path}: {e}"));
+            if (*fh).f != stdout {
+                fclose((*fh).f);
+            }
+            return -1;
+        }
+    };
+    (*fh).reference = Some(Reference {
+        path,
+        lines: BufReader::new(file).lines(),
+        n_frames: 0,
+        res: 0,
+    });
+    0
+}
+
+/// Prints the first frame and plane that differed from the frame checksum file,
+/// or whether it has a different number of frames.
+unsafe extern "C" fn framehash_verify(
+    fh: *mut FrameHashContext,
+    _ref_file: *const c_char,
+) -> c_int {
+    if (*fh).f != stdout {
+        fclose((*fh).f);
+    }
+    let Some(mut reference) = (*fh).reference.take() else {
+        return -1;
+    };
+    while reference.next_frame((*fh).hash).is_some() {}
+    if reference.res == 0 && reference.n_frames != (*fh).n_frames {
+        print_error(format!(
+            "Decoded {} frames, but {} were expected",
+            (*fh).n_frames,
+            reference.n_frames,
+        ));
+        reference.res = 1;
+    }
+    reference.res
+}
+
+#[no_mangle]
+pub static mut md5frames_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<FrameHashContext>() as c_ulong as c_int,
+    name: b"md5frames\0" as *const u8 as *const c_char,
+    extension: b"md5frames\0" as *const u8 as *const c_char,
+    write_header: Some(md5frames_open),
+    write_picture: Some(framehash_write),
+    write_data: None,
+    write_trailer: Some(framehash_close),
+    verify: Some(framehash_verify),
+    verify_open: Some(framehash_verify_open),
+};
+
+#[no_mangle]
+pub static mut xxh3frames_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<FrameHashContext>() as c_ulong as c_int,
+    name: b"xxh3frames\0" as *const u8 as *const c_char,
+    extension: b"xxh3frames\0" as *const u8 as *const c_char,
+    write_header: Some(xxh3frames_open),
+    write_picture: Some(framehash_write),
+    write_data: None,
+    write_trailer: Some(framehash_close),
+    verify: Some(framehash_verify),
+    verify_open: Some(framehash_verify_open),
+};
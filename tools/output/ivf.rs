@@ -0,0 +1,132 @@
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdout;
+use libc::fclose;
+use libc::fopen;
+use libc::fprintf;
+use libc::fseeko;
+use libc::fwrite;
+use libc::strcmp;
+use libc::strerror;
+use libc::SEEK_SET;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+
+#[repr(C)]
+pub struct MuxerPriv {
+    pub f: *mut libc::FILE,
+    pub frames: u32,
+}
+
+#[repr(C)]
+pub struct Muxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub extension: *const c_char,
+    pub write_header: Option<
+        unsafe extern "C" fn(
+            *mut MuxerPriv,
+            *const c_char,
+            *const Dav1dPictureParameters,
+            *const c_uint,
+        ) -> c_int,
+    >,
+    pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
+    pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
+    pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+}
+
+pub type IvfOutputContext = MuxerPriv;
+
+unsafe fn write_all(f: *mut libc::FILE, buf: &[u8]) -> c_int {
+    if fwrite(buf.as_ptr() as *const c_void, buf.len(), 1, f) != 1 {
+        fprintf(
+            stderr,
+            b"Failed to write frame data: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    0
+}
+
+/// The rate and scale fields of the header are the timebase (`fps`) of the frame timestamps,
+/// and its frame count is filled in by [`ivf_close`] when the output is seekable.
+unsafe extern "C" fn ivf_open(
+    c: *mut IvfOutputContext,
+    file: *const c_char,
+    p: *const Dav1dPictureParameters,
+    fps: *const c_uint,
+) -> c_int {
+    if strcmp(file, b"-\0" as *const u8 as *const c_char) == 0 {
+        (*c).f = stdout;
+    } else {
+        (*c).f = fopen(file, b"wb\0" as *const u8 as *const c_char);
+        if ((*c).f).is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                file,
+                strerror(*errno_location()),
+            );
+            return -1;
+        }
+    }
+    (*c).frames = 0;
+    let mut hdr = [0; 32];
+    hdr[..4].copy_from_slice(b"DKIF");
+    hdr[6..8].copy_from_slice(&32u16.to_le_bytes());
+    hdr[8..12].copy_from_slice(b"AV01");
+    hdr[12..14].copy_from_slice(&((*p).w as u16).to_le_bytes());
+    hdr[14..16].copy_from_slice(&((*p).h as u16).to_le_bytes());
+    hdr[16..20].copy_from_slice(&(*fps.offset(0)).to_le_bytes());
+    hdr[20..24].copy_from_slice(&(*fps.offset(1)).to_le_bytes());
+    return write_all((*c).f, &hdr);
+}
+
+unsafe extern "C" fn ivf_write(c: *mut IvfOutputContext, data: *const Dav1dData) -> c_int {
+    let mut hdr = [0; 12];
+    hdr[..4].copy_from_slice(&((*data).sz as u32).to_le_bytes());
+    hdr[4..].copy_from_slice(&((*data).m.timestamp as u64).to_le_bytes());
+    let buf = std::slice::from_raw_parts((*data).data.unwrap().as_ptr(), (*data).sz);
+    if write_all((*c).f, &hdr) < 0 || write_all((*c).f, buf) < 0 {
+        return -1;
+    }
+    (*c).frames += 1;
+    return 0 as c_int;
+}
+
+unsafe extern "C" fn ivf_close(c: *mut IvfOutputContext) {
+    if fseeko((*c).f, 24, SEEK_SET) == 0 {
+        fwrite(
+            (*c).frames.to_le_bytes().as_ptr() as *const c_void,
+            4,
+            1,
+            (*c).f,
+        );
+    }
+    if (*c).f != stdout {
+        fclose((*c).f);
+    }
+}
+
+#[no_mangle]
+pub static mut ivf_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<IvfOutputContext>() as c_ulong as c_int,
+    name: b"ivf\0" as *const u8 as *const c_char,
+    extension: b"ivf\0" as *const u8 as *const c_char,
+    write_header: Some(ivf_open),
+    write_picture: None,
+    write_data: Some(ivf_write),
+    write_trailer: Some(ivf_close),
+    verify: None,
+    verify_open: None,
+};
@@ -9,6 +9,7 @@ use libc::strcmp;
 use libc::strerror;
 use libc::strlen;
 use libc::strtoul;
+use rav1d::include::dav1d::data::Dav1dData;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
@@ -52,8 +53,10 @@ pub struct Muxer {
         ) -> c_int,
     >,
     pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
     pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
     pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
 }
 
 pub type MD5Context = MuxerPriv;
@@ -89,12 +92,16 @@ unsafe extern "C" fn md5_open(
             return -1;
         }
     }
+    md5_init(md5);
+    return 0 as c_int;
+}
+
+pub(super) unsafe fn md5_init(md5: *mut MD5Context) {
     (*md5).abcd[0] = 0x67452301 as c_int as u32;
     (*md5).abcd[1] = 0xefcdab89 as c_uint;
     (*md5).abcd[2] = 0x98badcfe as c_uint;
     (*md5).abcd[3] = 0x10325476 as c_int as u32;
     (*md5).len = 0 as c_int as u64;
-    return 0 as c_int;
 }
 
 #[inline]
@@ -497,7 +504,7 @@ unsafe fn md5_body(md5: *mut MD5Context, data: *const u32) {
     (*md5).abcd[3] = ((*md5).abcd[3] as c_uint).wrapping_add(d) as u32 as u32;
 }
 
-unsafe fn md5_update(md5: *mut MD5Context, mut data: *const u8, mut len: c_uint) {
+pub(super) unsafe fn md5_update(md5: *mut MD5Context, mut data: *const u8, mut len: c_uint) {
     if len == 0 {
         return;
     }
@@ -573,7 +580,7 @@ unsafe extern "C" fn md5_write(md5: *mut MD5Context, p: *mut Dav1dPicture) -> c_
     return 0 as c_int;
 }
 
-unsafe fn md5_finish(md5: *mut MD5Context) {
+pub(super) unsafe fn md5_finish(md5: *mut MD5Context) {
     static bit: [u8; 2] = [0x80, 0];
     let len: u64 = (*md5).len << 3;
     md5_update(md5, &*bit.as_ptr().offset(0), 1 as c_int as c_uint);
@@ -632,6 +639,8 @@ pub static mut md5_muxer: Muxer = Muxer {
     extension: b"md5\0" as *const u8 as *const c_char,
     write_header: Some(md5_open),
     write_picture: Some(md5_write),
+    write_data: None,
     write_trailer: Some(md5_close),
     verify: Some(md5_verify),
+    verify_open: None,
 };
@@ -1,3 +1,4 @@
+use rav1d::include::dav1d::data::Dav1dData;
 use rav1d::include::dav1d::picture::Dav1dPicture;
 use rav1d::include::dav1d::picture::Dav1dPictureParameters;
 use rav1d::src::lib::dav1d_picture_unref;
@@ -23,8 +24,10 @@ pub struct Muxer {
         ) -> c_int,
     >,
     pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
     pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
     pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
 }
 
 pub type NullOutputContext = MuxerPriv;
@@ -41,6 +44,8 @@ pub static mut null_muxer: Muxer = Muxer {
     extension: b"null\0" as *const u8 as *const c_char,
     write_header: None,
     write_picture: Some(null_write),
+    write_data: None,
     write_trailer: None,
     verify: None,
+    verify_open: None,
 };
@@ -10,6 +10,7 @@ use libc::strlen;
 use libc::strncmp;
 use libc::ENOMEM;
 use libc::ENOPROTOOPT;
+use rav1d::include::dav1d::data::Dav1dData;
 use rav1d::include::dav1d::picture::Dav1dPicture;
 use rav1d::include::dav1d::picture::Dav1dPictureParameters;
 use std::cmp;
@@ -25,8 +26,14 @@ extern "C" {
     pub type MuxerPriv;
     static null_muxer: Muxer;
     static md5_muxer: Muxer;
+    static xxh3_muxer: Muxer;
+    static md5frames_muxer: Muxer;
+    static xxh3frames_muxer: Muxer;
     static yuv_muxer: Muxer;
     static y4m2_muxer: Muxer;
+    static ivf_muxer: Muxer;
+    static section5_muxer: Muxer;
+    static annexb_muxer: Muxer;
 }
 
 #[repr(C)]
@@ -54,16 +61,26 @@ pub struct Muxer {
         ) -> c_int,
     >,
     pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
     pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
     pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    /// Called after `write_header` with the `--verify` argument,
+    /// by muxers that compare each picture as it's written rather than at the end.
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
 }
 
-static mut muxers: [*const Muxer; 5] = unsafe {
+static mut muxers: [*const Muxer; 11] = unsafe {
     [
         &null_muxer as *const Muxer,
         &md5_muxer as *const Muxer,
+        &xxh3_muxer as *const Muxer,
+        &md5frames_muxer as *const Muxer,
+        &xxh3frames_muxer as *const Muxer,
         &yuv_muxer as *const Muxer,
         &y4m2_muxer as *const Muxer,
+        &ivf_muxer as *const Muxer,
+        &section5_muxer as *const Muxer,
+        &annexb_muxer as *const Muxer,
         0 as *const Muxer,
     ]
 };
@@ -92,6 +109,38 @@ unsafe fn find_extension(f: *const c_char) -> *const c_char {
     };
 }
 
+/// Whether the muxer selected by `name` or by the extension of `filename`
+/// writes the compressed bitstream (through [`output_write_data`]) rather than pictures.
+// Only used by `dav1d.rs`, as `seek_stress` always decodes.
+#[allow(dead_code)]
+pub unsafe fn output_is_bitstream(name: *const c_char, filename: *const c_char) -> bool {
+    let key = if !name.is_null() {
+        &*name
+            .offset(5 * (strncmp(name, b"frame\0" as *const u8 as *const c_char, 5) == 0) as isize)
+            as *const c_char
+    } else if filename.is_null() {
+        return false;
+    } else {
+        find_extension(filename)
+    };
+    if key.is_null() {
+        return false;
+    }
+    let mut i = 0;
+    while !(muxers[i]).is_null() {
+        let field = if !name.is_null() {
+            (*muxers[i]).name
+        } else {
+            (*muxers[i]).extension
+        };
+        if strcmp(field, key) == 0 {
+            return (*muxers[i]).write_data.is_some();
+        }
+        i += 1;
+    }
+    false
+}
+
 // TODO(kkysen) These are used in `dav1d.rs` and `seek_stress.rs`
 // but are still marked as unused since `[[bin]]` are only supposed to be one file in `cargo`.
 #[allow(dead_code)]
@@ -101,6 +150,7 @@ pub unsafe fn output_open(
     filename: *const c_char,
     p: *const Dav1dPictureParameters,
     fps: *const c_uint,
+    verify: *const c_char,
 ) -> c_int {
     let mut impl_0: *const Muxer = 0 as *const Muxer;
     let c: *mut MuxerContext;
@@ -205,6 +255,13 @@ pub unsafe fn output_open(
         free(c as *mut c_void);
         return res;
     }
+    if !verify.is_null() && ((*impl_0).verify_open).is_some() && {
+        res = ((*impl_0).verify_open).expect("non-null function pointer")((*c).data, verify);
+        res < 0
+    } {
+        free(c as *mut c_void);
+        return res;
+    }
     *c_out = c;
     return 0 as c_int;
 }
@@ -382,6 +439,44 @@ pub unsafe fn output_write(ctx: *mut MuxerContext, p: *mut Dav1dPicture) -> c_in
     return 0 as c_int;
 }
 
+/// Writes a temporal unit to a bitstream muxer.
+/// `p` describes the coded frames, and is only used for per-frame file names and headers.
+// Only used by `dav1d.rs`, as `seek_stress` always decodes.
+#[allow(dead_code)]
+pub unsafe fn output_write_data(
+    ctx: *mut MuxerContext,
+    p: *const Dav1dPictureParameters,
+    data: *const Dav1dData,
+) -> c_int {
+    let mut res;
+    if (*ctx).one_file_per_frame != 0 && ((*(*ctx).impl_0).write_header).is_some() {
+        let mut filename: [c_char; 1024] = [0; 1024];
+        assemble_filename(
+            ctx,
+            filename.as_mut_ptr(),
+            ::core::mem::size_of::<[c_char; 1024]>() as c_ulong as c_int,
+            p,
+        );
+        res = ((*(*ctx).impl_0).write_header).expect("non-null function pointer")(
+            (*ctx).data,
+            filename.as_mut_ptr(),
+            p,
+            ((*ctx).fps).as_mut_ptr() as *const c_uint,
+        );
+        if res < 0 {
+            return res;
+        }
+    }
+    res = ((*(*ctx).impl_0).write_data).expect("non-null function pointer")((*ctx).data, data);
+    if res < 0 {
+        return res;
+    }
+    if (*ctx).one_file_per_frame != 0 && ((*(*ctx).impl_0).write_trailer).is_some() {
+        ((*(*ctx).impl_0).write_trailer).expect("non-null function pointer")((*ctx).data);
+    }
+    return 0 as c_int;
+}
+
 // TODO(kkysen) These are used in `dav1d.rs` and `seek_stress.rs`
 // but are still marked as unused since `[[bin]]` are only supposed to be one file in `cargo`.
 #[allow(dead_code)]
@@ -404,3 +499,130 @@ pub unsafe fn output_verify(ctx: *mut MuxerContext, md5_str: *const c_char) -> c
     free(ctx as *mut c_void);
     return res;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::output_close;
+    use super::output_open;
+    use super::output_write_data;
+    use super::MuxerContext;
+    use rav1d::include::dav1d::data::Dav1dData;
+    use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+    use std::ffi::CString;
+    use std::fs;
+    use std::process;
+    use std::ptr;
+    use std::ptr::NonNull;
+
+    /// A temporal delimiter, a sequence header and two frames, all with size fields.
+    const TU: [u8; 12] = [0x12, 0, 0x0a, 2, 0xa1, 0xa2, 0x32, 1, 0xc1, 0x32, 1, 0xd1];
+
+    /// Muxes `tus` into a temporary file with the muxer `name` and returns its contents.
+    fn mux(name: &str, tus: &[(&[u8], i64)]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("dav1d-mux-{}.{name}", process::id()));
+        let filename = CString::new(path.to_str().unwrap()).unwrap();
+        let name = CString::new(name).unwrap();
+        let p = Dav1dPictureParameters {
+            w: 352,
+            h: 288,
+            ..Default::default()
+        };
+        let fps = [30, 1];
+        unsafe {
+            let mut out: *mut MuxerContext = ptr::null_mut();
+            let res = output_open(
+                &mut out,
+                name.as_ptr(),
+                filename.as_ptr(),
+                &p,
+                fps.as_ptr(),
+                ptr::null(),
+            );
+            assert_eq!(res, 0);
+            for &(tu, timestamp) in tus {
+                let mut buf = tu.to_vec();
+                let mut data: Dav1dData = Default::default();
+                data.data = NonNull::new(buf.as_mut_ptr());
+                data.sz = buf.len();
+                data.m.timestamp = timestamp;
+                assert_eq!(output_write_data(out, &p, &data), 0);
+            }
+            output_close(out);
+        }
+        let muxed = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        muxed
+    }
+
+    #[test]
+    fn section5() {
+        assert_eq!(
+            mux("section5", &[(&TU, 0), (&TU[..3], 1)]),
+            [&TU[..], &TU[..3]].concat()
+        );
+    }
+
+    #[test]
+    fn annexb() {
+        // The size fields move out of the OBU headers, and each frame starts a frame unit.
+        let tu = [
+            14, 9, 1, 0x10, 3, 0x08, 0xa1, 0xa2, 2, 0x30, 0xc1, 3, 2, 0x30, 0xd1,
+        ];
+        assert_eq!(mux("annexb", &[(&TU, 0)]), tu);
+        // OBUs without size fields are taken as is.
+        assert_eq!(mux("annexb", &[(&[0x10], 0)]), [3, 2, 1, 0x10]);
+    }
+
+    #[test]
+    fn annexb_rejects_truncated_obus() {
+        let path = std::env::temp_dir().join(format!("dav1d-mux-{}.obu", process::id()));
+        let filename = CString::new(path.to_str().unwrap()).unwrap();
+        let p = Default::default();
+        let fps = [30, 1];
+        let mut buf = TU[..5].to_vec();
+        unsafe {
+            let mut out: *mut MuxerContext = ptr::null_mut();
+            let name = b"annexb\0".as_ptr().cast();
+            assert_eq!(
+                output_open(
+                    &mut out,
+                    name,
+                    filename.as_ptr(),
+                    &p,
+                    fps.as_ptr(),
+                    ptr::null()
+                ),
+                0
+            );
+            let mut data: Dav1dData = Default::default();
+            data.data = NonNull::new(buf.as_mut_ptr());
+            data.sz = buf.len();
+            assert!(output_write_data(out, &p, &data) < 0);
+            output_close(out);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ivf() {
+        let muxed = mux("ivf", &[(&TU, 0), (&TU[..2], 512)]);
+        let mut ivf = b"DKIF".to_vec();
+        ivf.extend(0u16.to_le_bytes());
+        ivf.extend(32u16.to_le_bytes());
+        ivf.extend(b"AV01");
+        ivf.extend(352u16.to_le_bytes());
+        ivf.extend(288u16.to_le_bytes());
+        ivf.extend(30u32.to_le_bytes());
+        ivf.extend(1u32.to_le_bytes());
+        // The frame count is filled in on close.
+        ivf.extend(2u32.to_le_bytes());
+        ivf.extend([0; 4]);
+        ivf.extend(12u32.to_le_bytes());
+        ivf.extend(0u64.to_le_bytes());
+        ivf.extend(TU);
+        ivf.extend(2u32.to_le_bytes());
+        ivf.extend(512u64.to_le_bytes());
+        ivf.extend(&TU[..2]);
+        assert_eq!(muxed, ivf);
+    }
+}
@@ -0,0 +1,322 @@
+//! Debug visualizations of the coding decisions of each frame (like ffmpeg's `codecview`),
+//! drawn onto a copy of the output picture from its [`Dav1dBlockInfoMap`].
+//!
+//! [`Dav1dBlockInfoMap`]: rav1d::include::dav1d::picture::Dav1dBlockInfoMap
+
+use crate::dav1d_cli_parse::OVERLAY_MODE;
+use crate::dav1d_cli_parse::OVERLAY_MV;
+use crate::dav1d_cli_parse::OVERLAY_PARTITION;
+use crate::dav1d_cli_parse::OVERLAY_TX;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dBlockInfo;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use std::cmp;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+
+/// Width and height of each `RectTxfmSize`, in 4x4 units.
+static tx_dims: [[c_int; 2]; 19] = [
+    [1, 1],
+    [2, 2],
+    [4, 4],
+    [8, 8],
+    [16, 16],
+    [1, 2],
+    [2, 1],
+    [2, 4],
+    [4, 2],
+    [4, 8],
+    [8, 4],
+    [8, 16],
+    [16, 8],
+    [1, 4],
+    [4, 1],
+    [2, 8],
+    [8, 2],
+    [4, 16],
+    [16, 4],
+];
+
+/// `NEWMV` of `InterPredMode`.
+const NEWMV: u8 = 3;
+
+/// Bitmask of the `CompInterPredMode`s with a `NEWMV` component.
+const COMP_NEWMV_MASK: u32 = 1 << 2 | 1 << 3 | 1 << 4 | 1 << 5 | 1 << 7;
+
+/// Chroma offsets from grey for the mode colors, in 8-bit units.
+const TINT_INTRA: [c_int; 2] = [-40, 80];
+const TINT_PALETTE_INTRABC: [c_int; 2] = [60, 60];
+const TINT_NEWMV: [c_int; 2] = [-60, -60];
+const TINT_COMPOUND: [c_int; 2] = [80, -40];
+
+struct Canvas {
+    data: [*mut u8; 3],
+    stride: [usize; 2],
+    w: c_int,
+    h: c_int,
+    ss_hor: c_int,
+    ss_ver: c_int,
+    has_chroma: bool,
+    hbd: bool,
+    bitdepth_max: c_int,
+}
+
+impl Canvas {
+    unsafe fn px(&self, pl: usize, x: c_int, y: c_int) -> *mut u8 {
+        let row = self.data[pl].add(y as usize * self.stride[(pl != 0) as usize]);
+        row.add((x as usize) << self.hbd as usize)
+    }
+
+    unsafe fn get(&self, pl: usize, x: c_int, y: c_int) -> c_int {
+        let px = self.px(pl, x, y);
+        if self.hbd {
+            (px as *const u16).read_unaligned() as c_int
+        } else {
+            *px as c_int
+        }
+    }
+
+    unsafe fn set(&self, pl: usize, x: c_int, y: c_int, v: c_int) {
+        let px = self.px(pl, x, y);
+        if self.hbd {
+            (px as *mut u16).write_unaligned(v as u16);
+        } else {
+            *px = v as u8;
+        }
+    }
+
+    unsafe fn luma(&self, x: c_int, y: c_int, v: c_int) {
+        if x >= 0 && x < self.w && y >= 0 && y < self.h {
+            self.set(0, x, y, v);
+        }
+    }
+
+    unsafe fn line(&self, [mut x0, mut y0]: [c_int; 2], [x1, y1]: [c_int; 2], v: c_int) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.luma(x0, y0, v);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += step_x;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += step_y;
+            }
+        }
+    }
+
+    unsafe fn arrow(&self, start: [c_int; 2], end: [c_int; 2], v: c_int) {
+        self.line(start, end, v);
+        let dx = (end[0] - start[0]) as f64;
+        let dy = (end[1] - start[1]) as f64;
+        let len = (dx * dx + dy * dy).sqrt();
+        if len < 4. {
+            return;
+        }
+        // the head is the backwards direction rotated by +/-45 degrees
+        let k = 3. * std::f64::consts::FRAC_1_SQRT_2 / len;
+        let heads = [
+            [(-dx + dy) * k, (-dx - dy) * k],
+            [(-dx - dy) * k, (dx - dy) * k],
+        ];
+        for [hx, hy] in heads {
+            let head = [end[0] + hx.round() as c_int, end[1] + hy.round() as c_int];
+            self.line(end, head, v);
+        }
+    }
+
+    /// Blends the chroma of the luma rectangle `[x0, x1) x [y0, y1)` with `tint`.
+    unsafe fn tint(&self, [x0, y0]: [c_int; 2], [x1, y1]: [c_int; 2], tint: [c_int; 2]) {
+        if !self.has_chroma {
+            return;
+        }
+        let cw = self.w + self.ss_hor >> self.ss_hor;
+        let ch = self.h + self.ss_ver >> self.ss_ver;
+        let mid = self.bitdepth_max + 1 >> 1;
+        let scale = (self.bitdepth_max + 1) / 256;
+        for pl in 1..3 {
+            let target = cmp::max(0, cmp::min(self.bitdepth_max, mid + tint[pl - 1] * scale));
+            for y in y0 >> self.ss_ver..cmp::min(y1 + self.ss_ver >> self.ss_ver, ch) {
+                for x in x0 >> self.ss_hor..cmp::min(x1 + self.ss_hor >> self.ss_hor, cw) {
+                    let v = self.get(pl, x, y);
+                    self.set(pl, x, y, v + target + 1 >> 1);
+                }
+            }
+        }
+    }
+}
+
+fn mode_tint(b: &Dav1dBlockInfo) -> Option<[c_int; 2]> {
+    if b.pal_sz != 0 || b.intrabc != 0 {
+        Some(TINT_PALETTE_INTRABC)
+    } else if b.intra != 0 {
+        Some(TINT_INTRA)
+    } else if b.r#ref[1] >= 0 {
+        if COMP_NEWMV_MASK & 1 << b.mode != 0 {
+            Some(TINT_NEWMV)
+        } else {
+            Some(TINT_COMPOUND)
+        }
+    } else if b.mode == NEWMV {
+        Some(TINT_NEWMV)
+    } else {
+        None
+    }
+}
+
+/// Replaces the planes of `p` by copies in `buf` with the `OVERLAY_*` visualizations in `overlay`
+/// drawn onto them, if the picture has its [`Dav1dBlockInfoMap`].
+///
+/// `p` keeps its reference to the original planes, so it is released as usual,
+/// but `buf` must outlive any use of the new planes.
+///
+/// [`Dav1dBlockInfoMap`]: rav1d::include::dav1d::picture::Dav1dBlockInfoMap
+pub unsafe fn overlay_draw(p: &mut Dav1dPicture, overlay: c_uint, buf: &mut Vec<u8>) {
+    let (Some(map), Some(frame_hdr)) = (p.exports.and_then(|e| e.as_ref().block_info), p.frame_hdr)
+    else {
+        return;
+    };
+    let map = map.as_ref();
+    let units = slice::from_raw_parts(map.info, (map.w4 * map.h4) as usize);
+    let w4 = map.w4;
+
+    let hbd = p.p.bpc > 8;
+    let has_chroma = p.p.layout != DAV1D_PIXEL_LAYOUT_I400;
+    let ss_ver = (p.p.layout == DAV1D_PIXEL_LAYOUT_I420) as c_int;
+    let ss_hor = (p.p.layout != DAV1D_PIXEL_LAYOUT_I444) as c_int;
+    let w = p.p.w;
+    let h = p.p.h;
+    let cw = w + ss_hor >> ss_hor;
+    let ch = h + ss_ver >> ss_ver;
+    let stride = [(w as usize) << hbd as usize, (cw as usize) << hbd as usize];
+    let plane_sz = [stride[0] * h as usize, stride[1] * ch as usize];
+    buf.clear();
+    buf.resize(plane_sz[0] + 2 * plane_sz[1] * has_chroma as usize, 0);
+    let base = buf.as_mut_ptr();
+    let data = [
+        base,
+        base.wrapping_add(plane_sz[0]),
+        base.wrapping_add(plane_sz[0] + plane_sz[1]),
+    ];
+    let n_planes = if has_chroma { 3 } else { 1 };
+    for pl in 0..n_planes {
+        let src = p.data[pl].unwrap().as_ptr() as *const u8;
+        let src_stride = p.stride[(pl != 0) as usize];
+        let pl_stride = stride[(pl != 0) as usize];
+        let pl_h = if pl != 0 { ch } else { h };
+        for y in 0..pl_h as isize {
+            ptr::copy_nonoverlapping(
+                src.offset(y * src_stride),
+                data[pl].offset(y * pl_stride as isize),
+                pl_stride,
+            );
+        }
+        p.data[pl] = NonNull::new(data[pl].cast());
+    }
+    p.stride = [stride[0] as isize, stride[1] as isize];
+    let canvas = Canvas {
+        data,
+        stride,
+        w,
+        h,
+        ss_hor,
+        ss_ver,
+        has_chroma,
+        hbd,
+        bitdepth_max: (1 << p.p.bpc) - 1,
+    };
+
+    // The block info is from before superres upscaling.
+    let coded_w = frame_hdr.as_ref().width[0];
+    let scale_x = |x: c_int| (x as i64 * w as i64 / coded_w as i64) as c_int;
+    let unit_x = |x4: c_int| scale_x(x4 * 4);
+    let h4 = cmp::min(map.h4, h + 3 >> 2);
+    let w4_vis = cmp::min(w4, coded_w + 3 >> 2);
+    let unit = |x4: c_int, y4: c_int| &units[(y4 * w4 + x4) as usize];
+    let same_block = |a: &Dav1dBlockInfo, b: &Dav1dBlockInfo| a.bx == b.bx && a.by == b.by;
+
+    if overlay & OVERLAY_MODE != 0 {
+        for y4 in 0..h4 {
+            for x4 in 0..w4_vis {
+                let b = unit(x4, y4);
+                if let Some(tint) = mode_tint(b) {
+                    canvas.tint([unit_x(x4), y4 * 4], [unit_x(x4 + 1), y4 * 4 + 4], tint);
+                }
+            }
+        }
+    }
+
+    for (flag, v) in [(OVERLAY_TX, 0), (OVERLAY_PARTITION, canvas.bitdepth_max)] {
+        if overlay & flag == 0 {
+            continue;
+        }
+        for y4 in 0..h4 {
+            for x4 in 0..w4_vis {
+                let b = unit(x4, y4);
+                let (left, top) = if flag == OVERLAY_PARTITION {
+                    (
+                        x4 > 0 && !same_block(b, unit(x4 - 1, y4)),
+                        y4 > 0 && !same_block(b, unit(x4, y4 - 1)),
+                    )
+                } else {
+                    let [txw, txh] = tx_dims[b.tx as usize];
+                    (
+                        x4 > 0 && (x4 - b.bx as c_int) % txw == 0,
+                        y4 > 0 && (y4 - b.by as c_int) % txh == 0,
+                    )
+                };
+                let [x0, x1] = [unit_x(x4), unit_x(x4 + 1)];
+                if left {
+                    canvas.line([x0, y4 * 4], [x0, y4 * 4 + 3], v);
+                }
+                if top && x1 > x0 {
+                    canvas.line([x0, y4 * 4], [x1 - 1, y4 * 4], v);
+                }
+            }
+        }
+    }
+
+    if overlay & OVERLAY_MV != 0 {
+        for y4 in 0..h4 {
+            for x4 in 0..w4_vis {
+                let b = unit(x4, y4);
+                if b.bx as c_int != x4 || b.by as c_int != y4 || b.intra != 0 {
+                    continue;
+                }
+                // find the size of the block, which may be cut off at the frame edges
+                let bw4 = (x4..w4).take_while(|&x| same_block(b, unit(x, y4))).count() as c_int;
+                let bh4 = (y4..map.h4)
+                    .take_while(|&y| same_block(b, unit(x4, y)))
+                    .count() as c_int;
+                let center = [unit_x(x4) + unit_x(x4 + bw4) >> 1, y4 * 4 + bh4 * 2];
+                for (i, v) in [canvas.bitdepth_max, 0].into_iter().enumerate() {
+                    if b.intrabc == 0 && b.r#ref[i] < 0 || b.intrabc != 0 && i != 0 {
+                        continue;
+                    }
+                    let mv = b.mv[i];
+                    let end = [
+                        center[0] + scale_x(mv.x as c_int) / 8,
+                        center[1] + mv.y as c_int / 8,
+                    ];
+                    if end != center {
+                        canvas.arrow(center, end, v);
+                    }
+                }
+            }
+        }
+    }
+}
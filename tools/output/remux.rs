@@ -0,0 +1,290 @@
+//! Remuxing of the compressed stream with the bitstream muxers (`ivf`, `section5` and `annexb`).
+//!
+//! Instead of being decoded, the packets of the demuxer are split into OBUs
+//! and regrouped into temporal units, each starting with a temporal delimiter
+//! and made of OBUs that all have a size field, which the muxers then frame in their own way.
+//! The OBUs selected with `--filterobus` are dropped along the way,
+//! and so are the temporal units that are left empty.
+
+use crate::compat::stdio::stderr;
+use crate::dav1d_cli_parse::CLISettings;
+use crate::dav1d_cli_parse::OBU_FILTER_LAYERS;
+use crate::dav1d_cli_parse::OBU_FILTER_METADATA;
+use crate::dav1d_cli_parse::OBU_FILTER_PADDING;
+use crate::dav1d_cli_parse::OBU_FILTER_REDUNDANT;
+use crate::input::input::input_read;
+use crate::input::input::read_leb128;
+use crate::input::input::write_leb128;
+use crate::input::input::DemuxerContext;
+use crate::output::output::output_close;
+use crate::output::output::output_open;
+use crate::output::output::output_write_data;
+use crate::output::output::MuxerContext;
+use libc::fprintf;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::dav1d::Dav1dSettings;
+use rav1d::include::dav1d::headers::Dav1dObuType;
+use rav1d::include::dav1d::headers::Dav1dSequenceHeader;
+use rav1d::include::dav1d::headers::DAV1D_OBU_METADATA;
+use rav1d::include::dav1d::headers::DAV1D_OBU_PADDING;
+use rav1d::include::dav1d::headers::DAV1D_OBU_REDUNDANT_FRAME_HDR;
+use rav1d::include::dav1d::headers::DAV1D_OBU_SEQ_HDR;
+use rav1d::include::dav1d::headers::DAV1D_OBU_TD;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use rav1d::src::lib::dav1d_data_create;
+use rav1d::src::lib::dav1d_data_unref;
+use rav1d::src::lib::dav1d_parse_sequence_header;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::mem;
+use std::ptr;
+use std::slice;
+
+/// A temporal delimiter OBU, with an (empty) size field.
+const TEMPORAL_DELIMITER: [u8; 2] = [(DAV1D_OBU_TD as u8) << 3 | 0x02, 0];
+
+/// An OBU, split into its header (with the extension byte, if any) and its payload.
+pub struct Obu<'a> {
+    pub header: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+impl Obu<'_> {
+    pub fn obu_type(&self) -> Dav1dObuType {
+        (self.header[0] >> 3 & 0xf) as Dav1dObuType
+    }
+
+    /// The temporal and spatial ids of the extension header, if any.
+    fn layer(&self) -> Option<(u8, u8)> {
+        self.header.get(1).map(|&ext| (ext >> 5, ext >> 3 & 3))
+    }
+
+    /// Appends the OBU to `out`, with or without a size field.
+    pub fn write(&self, out: &mut Vec<u8>, sized: bool) {
+        out.push(self.header[0] & !0x02 | (sized as u8) << 1);
+        out.extend_from_slice(&self.header[1..]);
+        if sized {
+            write_leb128(out, self.payload.len());
+        }
+        out.extend_from_slice(self.payload);
+    }
+}
+
+/// Splits `buf` into its OBUs, an OBU without a size field extending to the end of `buf`.
+/// Returns [`None`] if the OBUs are truncated.
+pub fn parse_obus(mut buf: &[u8]) -> Option<Vec<Obu>> {
+    let mut obus = Vec::new();
+    while let Some(&byte) = buf.first() {
+        let header_len = 1 + (byte >> 2 & 1) as usize;
+        let header = buf.get(..header_len)?;
+        let mut rest = &buf[header_len..];
+        let size = if byte & 0x02 != 0 {
+            read_leb128(&mut rest)?
+        } else {
+            rest.len()
+        };
+        obus.push(Obu {
+            header,
+            payload: rest.get(..size)?,
+        });
+        buf = &rest[size..];
+    }
+    Some(obus)
+}
+
+struct Remuxer<'a> {
+    cli_settings: &'a CLISettings,
+    operating_point: usize,
+    /// The `operating_point_idc` of the selected operating point.
+    idc: c_int,
+    /// The maximum frame size and format of the current sequence header.
+    p: Option<Dav1dPictureParameters>,
+    timebase: [c_uint; 2],
+    out: *mut MuxerContext,
+    /// The temporal unit being assembled, starting with [`TEMPORAL_DELIMITER`].
+    tu: Vec<u8>,
+    timestamp: i64,
+    written: c_uint,
+}
+
+impl Remuxer<'_> {
+    unsafe fn parse_sequence_header(&mut self, obu: &Obu) -> c_int {
+        let mut buf = Vec::new();
+        obu.write(&mut buf, true);
+        let mut seq: Dav1dSequenceHeader = mem::zeroed();
+        if dav1d_parse_sequence_header(&mut seq, buf.as_ptr(), buf.len()).0 != 0 {
+            fprintf(
+                stderr,
+                b"Failed to parse sequence header\n\0" as *const u8 as *const c_char,
+            );
+            return -1;
+        }
+        self.idc = seq.operating_points[self.operating_point].idc;
+        self.p = Some(Dav1dPictureParameters {
+            w: seq.max_width,
+            h: seq.max_height,
+            layout: seq.layout,
+            bpc: 8 + 2 * seq.hbd,
+        });
+        0
+    }
+
+    fn keep(&self, obu: &Obu) -> bool {
+        let filter = self.cli_settings.filter_obus;
+        let dropped = match obu.obu_type() {
+            DAV1D_OBU_METADATA => OBU_FILTER_METADATA,
+            DAV1D_OBU_PADDING => OBU_FILTER_PADDING,
+            DAV1D_OBU_REDUNDANT_FRAME_HDR => OBU_FILTER_REDUNDANT,
+            _ => 0,
+        };
+        if filter & dropped != 0 {
+            return false;
+        }
+        match obu.layer() {
+            Some((temporal_id, spatial_id)) if filter & OBU_FILTER_LAYERS != 0 && self.idc != 0 => {
+                self.idc >> temporal_id & 1 != 0 && self.idc >> (spatial_id + 8) & 1 != 0
+            }
+            _ => true,
+        }
+    }
+
+    /// Writes the temporal unit, unless nothing but its temporal delimiter is left,
+    /// and starts the next one at `timestamp`.
+    unsafe fn flush(&mut self, timestamp: i64) -> c_int {
+        let mut res = 0;
+        if self.tu.len() > TEMPORAL_DELIMITER.len() {
+            res = self.write();
+        }
+        self.tu.truncate(TEMPORAL_DELIMITER.len());
+        self.timestamp = timestamp;
+        res
+    }
+
+    unsafe fn write(&mut self) -> c_int {
+        let Some(p) = &self.p else {
+            fprintf(
+                stderr,
+                b"Missing sequence header before the first temporal unit\n\0" as *const u8
+                    as *const c_char,
+            );
+            return -1;
+        };
+        if self.out.is_null() {
+            let res = output_open(
+                &mut self.out,
+                self.cli_settings.muxer,
+                self.cli_settings.outputfile,
+                p,
+                self.timebase.as_ptr(),
+                ptr::null(),
+            );
+            if res < 0 {
+                return res;
+            }
+        }
+        let mut data = Dav1dData::default();
+        let ptr = dav1d_data_create(&mut data, self.tu.len());
+        if ptr.is_null() {
+            return -1;
+        }
+        ptr::copy_nonoverlapping(self.tu.as_ptr(), ptr, self.tu.len());
+        // Demuxers without timestamps (`section5` and `annexb`) have a timebase of one frame.
+        data.m.timestamp = if self.timestamp == i64::MIN {
+            self.written as i64
+        } else {
+            self.timestamp
+        };
+        let res = output_write_data(self.out, p, &data);
+        dav1d_data_unref(&mut data);
+        self.written += 1;
+        res
+    }
+}
+
+/// Remuxes the packets of `in_0`, starting with `data`, with the bitstream muxer
+/// selected by `--muxer` or the extension of the output file.
+///
+/// Packets that start with a temporal delimiter are assumed to be delimited by them
+/// (like those of the `annexb` demuxer, which returns single OBUs),
+/// and others to each be a temporal unit without one, as stored in Matroska and ISOBMFF.
+pub unsafe fn remux(
+    cli_settings: &CLISettings,
+    lib_settings: &Dav1dSettings,
+    in_0: *mut DemuxerContext,
+    data: &mut Dav1dData,
+    timebase: &[c_uint; 2],
+) -> c_int {
+    let mut remuxer = Remuxer {
+        cli_settings,
+        operating_point: lib_settings.operating_point as usize,
+        idc: 0,
+        p: None,
+        timebase: *timebase,
+        out: ptr::null_mut(),
+        tu: TEMPORAL_DELIMITER.to_vec(),
+        timestamp: i64::MIN,
+        written: 0,
+    };
+    let mut delimited = None;
+    let mut res = 0;
+    loop {
+        let buf = slice::from_raw_parts(data.data.unwrap().as_ptr(), data.sz);
+        let timestamp = data.m.timestamp;
+        let Some(obus) = parse_obus(buf) else {
+            fprintf(
+                stderr,
+                b"Invalid OBU data in packet\n\0" as *const u8 as *const c_char,
+            );
+            res = -1;
+            break;
+        };
+        let delimited =
+            *delimited.get_or_insert_with(|| obus.first().map(Obu::obu_type) == Some(DAV1D_OBU_TD));
+        if !delimited {
+            res = remuxer.flush(timestamp);
+        }
+        for obu in &obus {
+            if res < 0 {
+                break;
+            }
+            match obu.obu_type() {
+                DAV1D_OBU_TD => {
+                    if delimited {
+                        res = remuxer.flush(timestamp);
+                    }
+                    continue;
+                }
+                DAV1D_OBU_SEQ_HDR => res = remuxer.parse_sequence_header(obu),
+                _ => {}
+            }
+            if remuxer.keep(obu) {
+                obu.write(&mut remuxer.tu, true);
+            }
+        }
+        dav1d_data_unref(data);
+        if res < 0
+            || cli_settings.limit != 0 && remuxer.written >= cli_settings.limit
+            || input_read(in_0, data) != 0
+        {
+            break;
+        }
+    }
+    if data.sz > 0 {
+        dav1d_data_unref(data);
+    }
+    if res == 0 && (cli_settings.limit == 0 || remuxer.written < cli_settings.limit) {
+        res = remuxer.flush(i64::MIN);
+    }
+    if !remuxer.out.is_null() {
+        output_close(remuxer.out);
+    }
+    if cli_settings.quiet == 0 {
+        fprintf(
+            stderr,
+            b"Wrote %u temporal units\n\0" as *const u8 as *const c_char,
+            remuxer.written,
+        );
+    }
+    (res < 0) as c_int
+}
@@ -0,0 +1,56 @@
+//! Heatmaps of the bits each superblock took to code, from the [`Dav1dSbBitsMap`] of each picture,
+//! written as a stream of binary PPM images, one per output picture.
+//!
+//! [`Dav1dSbBitsMap`]: rav1d::include::dav1d::picture::Dav1dSbBitsMap
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use libc::fprintf;
+use libc::fwrite;
+use libc::strerror;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use std::cmp;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_void;
+use std::slice;
+
+/// Maps `bits / max` to black, red, yellow and white.
+fn heat(bits: u32, max: u32) -> [u8; 3] {
+    let t = (bits as u64 * 3 * 255 / max as u64) as u32;
+    [t, t.saturating_sub(255), t.saturating_sub(2 * 255)].map(|c| cmp::min(c, 255) as u8)
+}
+
+/// Writes the heatmap of `p` at the size of the frame before superres upscaling,
+/// scaled to the most expensive superblock of the frame.
+///
+/// Pictures without a [`Dav1dSbBitsMap`] are skipped.
+///
+/// [`Dav1dSbBitsMap`]: rav1d::include::dav1d::picture::Dav1dSbBitsMap
+pub unsafe fn sb_heatmap_write(f: *mut libc::FILE, p: &Dav1dPicture) -> c_int {
+    let (Some(map), Some(frame_hdr)) = (p.exports.and_then(|e| e.as_ref().sb_bits), p.frame_hdr)
+    else {
+        return 0;
+    };
+    let map = map.as_ref();
+    let bits = slice::from_raw_parts(map.bits, (map.sbw * map.sbh) as usize);
+    let max = cmp::max(1, bits.iter().copied().max().unwrap_or(0));
+    let w = frame_hdr.as_ref().width[0] as usize;
+    let h = frame_hdr.as_ref().height as usize;
+    let sb_size = map.sb_size as usize;
+
+    let mut img = format!("P6\n{w} {h}\n255\n").into_bytes();
+    for y in 0..h {
+        let row = &bits[y / sb_size * map.sbw as usize..][..map.sbw as usize];
+        img.extend((0..w).flat_map(|x| heat(row[x / sb_size], max)));
+    }
+    if fwrite(img.as_ptr() as *const c_void, img.len(), 1, f) != 1 {
+        fprintf(
+            stderr,
+            b"Failed to write heatmap: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    0
+}
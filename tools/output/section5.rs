@@ -0,0 +1,108 @@
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdout;
+use libc::fclose;
+use libc::fopen;
+use libc::fprintf;
+use libc::fwrite;
+use libc::strcmp;
+use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+
+#[repr(C)]
+pub struct MuxerPriv {
+    pub f: *mut libc::FILE,
+}
+
+#[repr(C)]
+pub struct Muxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub extension: *const c_char,
+    pub write_header: Option<
+        unsafe extern "C" fn(
+            *mut MuxerPriv,
+            *const c_char,
+            *const Dav1dPictureParameters,
+            *const c_uint,
+        ) -> c_int,
+    >,
+    pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
+    pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
+    pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+}
+
+pub type Section5OutputContext = MuxerPriv;
+
+unsafe extern "C" fn section5_open(
+    c: *mut Section5OutputContext,
+    file: *const c_char,
+    _p: *const Dav1dPictureParameters,
+    _fps: *const c_uint,
+) -> c_int {
+    if strcmp(file, b"-\0" as *const u8 as *const c_char) == 0 {
+        (*c).f = stdout;
+    } else {
+        (*c).f = fopen(file, b"wb\0" as *const u8 as *const c_char);
+        if ((*c).f).is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                file,
+                strerror(*errno_location()),
+            );
+            return -1;
+        }
+    }
+    return 0 as c_int;
+}
+
+/// The temporal units of the remuxer are already in the low-overhead format.
+unsafe extern "C" fn section5_write(
+    c: *mut Section5OutputContext,
+    data: *const Dav1dData,
+) -> c_int {
+    if fwrite(
+        (*data).data.unwrap().as_ptr() as *const c_void,
+        (*data).sz,
+        1,
+        (*c).f,
+    ) != 1
+    {
+        fprintf(
+            stderr,
+            b"Failed to write frame data: %s\n\0" as *const u8 as *const c_char,
+            strerror(*errno_location()),
+        );
+        return -1;
+    }
+    return 0 as c_int;
+}
+
+unsafe extern "C" fn section5_close(c: *mut Section5OutputContext) {
+    if (*c).f != stdout {
+        fclose((*c).f);
+    }
+}
+
+#[no_mangle]
+pub static mut section5_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<Section5OutputContext>() as c_ulong as c_int,
+    name: b"section5\0" as *const u8 as *const c_char,
+    extension: b"obu\0" as *const u8 as *const c_char,
+    write_header: Some(section5_open),
+    write_picture: None,
+    write_data: Some(section5_write),
+    write_trailer: Some(section5_close),
+    verify: None,
+    verify_open: None,
+};
@@ -0,0 +1,608 @@
+//! An XXH3-128 checksum muxer, compatible with the `xxh3` muxer of the C tool,
+//! which uses `xxhash.h` with the default secret and seed.
+
+use crate::compat::errno::errno_location;
+use crate::compat::stdio::stderr;
+use crate::compat::stdio::stdout;
+use libc::fclose;
+use libc::fopen;
+use libc::fprintf;
+use libc::strcmp;
+use libc::strerror;
+use libc::strlen;
+use rav1d::include::dav1d::data::Dav1dData;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
+use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
+use rav1d::include::dav1d::picture::Dav1dPicture;
+use rav1d::include::dav1d::picture::Dav1dPictureParameters;
+use rav1d::src::lib::dav1d_picture_unref;
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_uint;
+use std::ffi::c_ulong;
+use std::ffi::CStr;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+
+const PRIME32_1: u64 = 0x9e3779b1;
+const PRIME32_2: u64 = 0x85ebca77;
+const PRIME32_3: u64 = 0xc2b2ae3d;
+const PRIME64_1: u64 = 0x9e3779b185ebca87;
+const PRIME64_2: u64 = 0xc2b2ae3d27d4eb4f;
+const PRIME64_3: u64 = 0x165667b19e3779f9;
+const PRIME64_4: u64 = 0x85ebca77c2b2ae63;
+const PRIME64_5: u64 = 0x27d4eb2f165667c5;
+const PRIME_MX1: u64 = 0x165667919e3779f9;
+const PRIME_MX2: u64 = 0x9fb21c651e98df25;
+
+const STRIPE_LEN: usize = 64;
+const SECRET_CONSUME_RATE: usize = 8;
+const SECRET_MERGEACCS_START: usize = 11;
+const SECRET_LASTACC_START: usize = 7;
+const MIDSIZE_MAX: usize = 240;
+const MIDSIZE_STARTOFFSET: usize = 3;
+const MIDSIZE_LASTOFFSET: usize = 17;
+const SECRET_SIZE_MIN: usize = 136;
+const SECRET_SIZE: usize = 192;
+const STRIPES_PER_BLOCK: usize = (SECRET_SIZE - STRIPE_LEN) / SECRET_CONSUME_RATE;
+const INTERNAL_BUFFER_SIZE: usize = 256;
+
+static SECRET: [u8; SECRET_SIZE] = [
+    0xb8, 0xfe, 0x6c, 0x39, 0x23, 0xa4, 0x4b, 0xbe, 0x7c, 0x01, 0x81, 0x2c, 0xf7, 0x21, 0xad, 0x1c,
+    0xde, 0xd4, 0x6d, 0xe9, 0x83, 0x90, 0x97, 0xdb, 0x72, 0x40, 0xa4, 0xa4, 0xb7, 0xb3, 0x67, 0x1f,
+    0xcb, 0x79, 0xe6, 0x4e, 0xcc, 0xc0, 0xe5, 0x78, 0x82, 0x5a, 0xd0, 0x7d, 0xcc, 0xff, 0x72, 0x21,
+    0xb8, 0x08, 0x46, 0x74, 0xf7, 0x43, 0x24, 0x8e, 0xe0, 0x35, 0x90, 0xe6, 0x81, 0x3a, 0x26, 0x4c,
+    0x3c, 0x28, 0x52, 0xbb, 0x91, 0xc3, 0x00, 0xcb, 0x88, 0xd0, 0x65, 0x8b, 0x1b, 0x53, 0x2e, 0xa3,
+    0x71, 0x64, 0x48, 0x97, 0xa2, 0x0d, 0xf9, 0x4e, 0x38, 0x19, 0xef, 0x46, 0xa9, 0xde, 0xac, 0xd8,
+    0xa8, 0xfa, 0x76, 0x3f, 0xe3, 0x9c, 0x34, 0x3f, 0xf9, 0xdc, 0xbb, 0xc7, 0xc7, 0x0b, 0x4f, 0x1d,
+    0x8a, 0x51, 0xe0, 0x4b, 0xcd, 0xb4, 0x59, 0x31, 0xc8, 0x9f, 0x7e, 0xc9, 0xd9, 0x78, 0x73, 0x64,
+    0xea, 0xc5, 0xac, 0x83, 0x34, 0xd3, 0xeb, 0xc3, 0xc5, 0x81, 0xa0, 0xff, 0xfa, 0x13, 0x63, 0xeb,
+    0x17, 0x0d, 0xdd, 0x51, 0xb7, 0xf0, 0xda, 0x49, 0xd3, 0x16, 0x55, 0x26, 0x29, 0xd4, 0x68, 0x9e,
+    0x2b, 0x16, 0xbe, 0x58, 0x7d, 0x47, 0xa1, 0xfc, 0x8f, 0xf8, 0xb8, 0xd1, 0x7a, 0xd0, 0x31, 0xce,
+    0x45, 0xcb, 0x3a, 0x8f, 0x95, 0x16, 0x04, 0x28, 0xaf, 0xd7, 0xfb, 0xca, 0xbb, 0x4b, 0x40, 0x7e,
+];
+
+const INIT_ACC: [u64; 8] = [
+    PRIME32_3, PRIME64_1, PRIME64_2, PRIME64_3, PRIME64_4, PRIME32_2, PRIME64_5, PRIME32_1,
+];
+
+fn read32(p: &[u8]) -> u64 {
+    u32::from_le_bytes(p[..4].try_into().unwrap()) as u64
+}
+
+fn read64(p: &[u8]) -> u64 {
+    u64::from_le_bytes(p[..8].try_into().unwrap())
+}
+
+fn mult64to128(a: u64, b: u64) -> (u64, u64) {
+    let product = a as u128 * b as u128;
+    (product as u64, (product >> 64) as u64)
+}
+
+fn mul128_fold64(a: u64, b: u64) -> u64 {
+    let (lo, hi) = mult64to128(a, b);
+    lo ^ hi
+}
+
+fn xxh64_avalanche(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(PRIME64_2);
+    h ^= h >> 29;
+    h = h.wrapping_mul(PRIME64_3);
+    h ^ h >> 32
+}
+
+fn avalanche(mut h: u64) -> u64 {
+    h ^= h >> 37;
+    h = h.wrapping_mul(PRIME_MX1);
+    h ^ h >> 32
+}
+
+fn mix16b(input: &[u8], secret: &[u8]) -> u64 {
+    mul128_fold64(
+        read64(input) ^ read64(secret),
+        read64(&input[8..]) ^ read64(&secret[8..]),
+    )
+}
+
+fn mix32b(acc: &mut (u64, u64), input_1: &[u8], input_2: &[u8], secret: &[u8]) {
+    acc.0 = acc.0.wrapping_add(mix16b(input_1, secret));
+    acc.0 ^= read64(input_2).wrapping_add(read64(&input_2[8..]));
+    acc.1 = acc.1.wrapping_add(mix16b(input_2, &secret[16..]));
+    acc.1 ^= read64(input_1).wrapping_add(read64(&input_1[8..]));
+}
+
+fn len_1to3(input: &[u8]) -> (u64, u64) {
+    let len = input.len();
+    let combined_lo = (input[0] as u32) << 16
+        | (input[len >> 1] as u32) << 24
+        | input[len - 1] as u32
+        | (len as u32) << 8;
+    let combined_hi = combined_lo.swap_bytes().rotate_left(13);
+    let bitflip_lo = read32(&SECRET[0..]) ^ read32(&SECRET[4..]);
+    let bitflip_hi = read32(&SECRET[8..]) ^ read32(&SECRET[12..]);
+    (
+        xxh64_avalanche(combined_lo as u64 ^ bitflip_lo),
+        xxh64_avalanche(combined_hi as u64 ^ bitflip_hi),
+    )
+}
+
+fn len_4to8(input: &[u8]) -> (u64, u64) {
+    let len = input.len();
+    let input_64 = read32(input).wrapping_add(read32(&input[len - 4..]) << 32);
+    let bitflip = read64(&SECRET[16..]) ^ read64(&SECRET[24..]);
+    let (mut lo, mut hi) = mult64to128(
+        input_64 ^ bitflip,
+        PRIME64_1.wrapping_add((len as u64) << 2),
+    );
+    hi = hi.wrapping_add(lo << 1);
+    lo ^= hi >> 3;
+    lo ^= lo >> 35;
+    lo = lo.wrapping_mul(PRIME_MX2);
+    lo ^= lo >> 28;
+    (lo, avalanche(hi))
+}
+
+fn len_9to16(input: &[u8]) -> (u64, u64) {
+    let len = input.len();
+    let bitflip_lo = read64(&SECRET[32..]) ^ read64(&SECRET[40..]);
+    let bitflip_hi = read64(&SECRET[48..]) ^ read64(&SECRET[56..]);
+    let input_lo = read64(input);
+    let mut input_hi = read64(&input[len - 8..]);
+    let (mut m_lo, mut m_hi) = mult64to128(input_lo ^ input_hi ^ bitflip_lo, PRIME64_1);
+    m_lo = m_lo.wrapping_add((len as u64 - 1) << 54);
+    input_hi ^= bitflip_hi;
+    m_hi = m_hi
+        .wrapping_add(input_hi)
+        .wrapping_add((input_hi as u32 as u64).wrapping_mul(PRIME32_2 - 1));
+    m_lo ^= m_hi.swap_bytes();
+    let (h_lo, h_hi) = mult64to128(m_lo, PRIME64_2);
+    (
+        avalanche(h_lo),
+        avalanche(h_hi.wrapping_add(m_hi.wrapping_mul(PRIME64_2))),
+    )
+}
+
+fn len_0to16(input: &[u8]) -> (u64, u64) {
+    match input.len() {
+        9.. => len_9to16(input),
+        4.. => len_4to8(input),
+        1.. => len_1to3(input),
+        0 => (
+            xxh64_avalanche(read64(&SECRET[64..]) ^ read64(&SECRET[72..])),
+            xxh64_avalanche(read64(&SECRET[80..]) ^ read64(&SECRET[88..])),
+        ),
+    }
+}
+
+fn finalize_mid(acc: (u64, u64), len: usize) -> (u64, u64) {
+    let (lo, hi) = acc;
+    let h_lo = lo.wrapping_add(hi);
+    let h_hi = lo
+        .wrapping_mul(PRIME64_1)
+        .wrapping_add(hi.wrapping_mul(PRIME64_4))
+        .wrapping_add((len as u64).wrapping_mul(PRIME64_2));
+    (avalanche(h_lo), avalanche(h_hi).wrapping_neg())
+}
+
+fn len_17to128(input: &[u8]) -> (u64, u64) {
+    let len = input.len();
+    let mut acc = ((len as u64).wrapping_mul(PRIME64_1), 0);
+    let mut i = (len - 1) / 32;
+    loop {
+        mix32b(
+            &mut acc,
+            &input[16 * i..],
+            &input[len - 16 * (i + 1)..],
+            &SECRET[32 * i..],
+        );
+        if i == 0 {
+            break;
+        }
+        i -= 1;
+    }
+    finalize_mid(acc, len)
+}
+
+fn len_129to240(input: &[u8]) -> (u64, u64) {
+    let len = input.len();
+    let mut acc = ((len as u64).wrapping_mul(PRIME64_1), 0);
+    for i in 0..4 {
+        mix32b(
+            &mut acc,
+            &input[32 * i..],
+            &input[32 * i + 16..],
+            &SECRET[32 * i..],
+        );
+    }
+    acc = (avalanche(acc.0), avalanche(acc.1));
+    for i in 4..len / 32 {
+        mix32b(
+            &mut acc,
+            &input[32 * i..],
+            &input[32 * i + 16..],
+            &SECRET[MIDSIZE_STARTOFFSET + 32 * (i - 4)..],
+        );
+    }
+    mix32b(
+        &mut acc,
+        &input[len - 16..],
+        &input[len - 32..],
+        &SECRET[SECRET_SIZE_MIN - MIDSIZE_LASTOFFSET - 16..],
+    );
+    finalize_mid(acc, len)
+}
+
+fn accumulate_512(acc: &mut [u64; 8], input: &[u8], secret: &[u8]) {
+    for i in 0..8 {
+        let data_val = read64(&input[8 * i..]);
+        let data_key = data_val ^ read64(&secret[8 * i..]);
+        acc[i ^ 1] = acc[i ^ 1].wrapping_add(data_val);
+        acc[i] = acc[i].wrapping_add((data_key & 0xffffffff).wrapping_mul(data_key >> 32));
+    }
+}
+
+fn scramble_acc(acc: &mut [u64; 8], secret: &[u8]) {
+    for i in 0..8 {
+        acc[i] = (acc[i] ^ acc[i] >> 47 ^ read64(&secret[8 * i..])).wrapping_mul(PRIME32_1);
+    }
+}
+
+fn accumulate(acc: &mut [u64; 8], input: &[u8], secret: &[u8], nb_stripes: usize) {
+    for n in 0..nb_stripes {
+        accumulate_512(
+            acc,
+            &input[n * STRIPE_LEN..],
+            &secret[n * SECRET_CONSUME_RATE..],
+        );
+    }
+}
+
+fn merge_accs(acc: &[u64; 8], secret: &[u8], start: u64) -> u64 {
+    let mut result = start;
+    for i in 0..4 {
+        result = result.wrapping_add(mul128_fold64(
+            acc[2 * i] ^ read64(&secret[16 * i..]),
+            acc[2 * i + 1] ^ read64(&secret[16 * i + 8..]),
+        ));
+    }
+    avalanche(result)
+}
+
+/// The streaming state of `XXH3_128bits_*`, limited to the default secret and seed.
+#[repr(C)]
+pub struct Xxh3State {
+    acc: [u64; 8],
+    buffer: [u8; INTERNAL_BUFFER_SIZE],
+    buffered_size: usize,
+    nb_stripes_so_far: usize,
+    total_len: u64,
+}
+
+impl Xxh3State {
+    pub const fn new() -> Self {
+        Self {
+            acc: INIT_ACC,
+            buffer: [0; INTERNAL_BUFFER_SIZE],
+            buffered_size: 0,
+            nb_stripes_so_far: 0,
+            total_len: 0,
+        }
+    }
+
+    fn consume_stripes(
+        acc: &mut [u64; 8],
+        nb_stripes_so_far: &mut usize,
+        input: &[u8],
+        nb_stripes: usize,
+    ) {
+        let stripes_to_end = STRIPES_PER_BLOCK - *nb_stripes_so_far;
+        let secret = &SECRET[*nb_stripes_so_far * SECRET_CONSUME_RATE..];
+        if nb_stripes >= stripes_to_end {
+            accumulate(acc, input, secret, stripes_to_end);
+            scramble_acc(acc, &SECRET[SECRET.len() - STRIPE_LEN..]);
+            let nb_stripes = nb_stripes - stripes_to_end;
+            accumulate(
+                acc,
+                &input[stripes_to_end * STRIPE_LEN..],
+                &SECRET,
+                nb_stripes,
+            );
+            *nb_stripes_so_far = nb_stripes;
+        } else {
+            accumulate(acc, input, secret, nb_stripes);
+            *nb_stripes_so_far += nb_stripes;
+        }
+    }
+
+    pub fn update(&mut self, mut input: &[u8]) {
+        const BUFFER_STRIPES: usize = INTERNAL_BUFFER_SIZE / STRIPE_LEN;
+
+        self.total_len += input.len() as u64;
+        if input.len() <= INTERNAL_BUFFER_SIZE - self.buffered_size {
+            self.buffer[self.buffered_size..][..input.len()].copy_from_slice(input);
+            self.buffered_size += input.len();
+            return;
+        }
+
+        // At least one byte is always kept in the buffer,
+        // as the last stripe is treated differently by `digest`.
+        if self.buffered_size != 0 {
+            let (head, tail) = input.split_at(INTERNAL_BUFFER_SIZE - self.buffered_size);
+            self.buffer[self.buffered_size..].copy_from_slice(head);
+            input = tail;
+            Self::consume_stripes(
+                &mut self.acc,
+                &mut self.nb_stripes_so_far,
+                &self.buffer,
+                BUFFER_STRIPES,
+            );
+            self.buffered_size = 0;
+        }
+        if input.len() > INTERNAL_BUFFER_SIZE {
+            let mut consumed = 0;
+            while input.len() - consumed > INTERNAL_BUFFER_SIZE {
+                Self::consume_stripes(
+                    &mut self.acc,
+                    &mut self.nb_stripes_so_far,
+                    &input[consumed..],
+                    BUFFER_STRIPES,
+                );
+                consumed += INTERNAL_BUFFER_SIZE;
+            }
+            // Needed by `digest` if less than a stripe is left.
+            self.buffer[INTERNAL_BUFFER_SIZE - STRIPE_LEN..]
+                .copy_from_slice(&input[consumed - STRIPE_LEN..consumed]);
+            input = &input[consumed..];
+        }
+        self.buffer[..input.len()].copy_from_slice(input);
+        self.buffered_size = input.len();
+    }
+
+    /// Returns the hash as `(low64, high64)`.
+    fn digest(&self) -> (u64, u64) {
+        let len = self.total_len as usize;
+        if len > MIDSIZE_MAX {
+            let mut acc = self.acc;
+            let mut nb_stripes_so_far = self.nb_stripes_so_far;
+            let mut stripe = [0; STRIPE_LEN];
+            let last_stripe = if self.buffered_size >= STRIPE_LEN {
+                let nb_stripes = (self.buffered_size - 1) / STRIPE_LEN;
+                Self::consume_stripes(&mut acc, &mut nb_stripes_so_far, &self.buffer, nb_stripes);
+                &self.buffer[self.buffered_size - STRIPE_LEN..self.buffered_size]
+            } else {
+                let catchup_size = STRIPE_LEN - self.buffered_size;
+                stripe[..catchup_size]
+                    .copy_from_slice(&self.buffer[INTERNAL_BUFFER_SIZE - catchup_size..]);
+                stripe[catchup_size..].copy_from_slice(&self.buffer[..self.buffered_size]);
+                &stripe[..]
+            };
+            accumulate_512(
+                &mut acc,
+                last_stripe,
+                &SECRET[SECRET.len() - STRIPE_LEN - SECRET_LASTACC_START..],
+            );
+            (
+                merge_accs(
+                    &acc,
+                    &SECRET[SECRET_MERGEACCS_START..],
+                    (len as u64).wrapping_mul(PRIME64_1),
+                ),
+                merge_accs(
+                    &acc,
+                    &SECRET[SECRET.len() - 64 - SECRET_MERGEACCS_START..],
+                    !(len as u64).wrapping_mul(PRIME64_2),
+                ),
+            )
+        } else {
+            let input = &self.buffer[..len];
+            match len {
+                0..=16 => len_0to16(input),
+                17..=128 => len_17to128(input),
+                _ => len_129to240(input),
+            }
+        }
+    }
+
+    /// The canonical, big endian representation of the hash, as printed by `xxhsum`.
+    pub fn canonical(&self) -> [u8; 16] {
+        let (lo, hi) = self.digest();
+        let mut c = [0; 16];
+        c[..8].copy_from_slice(&hi.to_be_bytes());
+        c[8..].copy_from_slice(&lo.to_be_bytes());
+        c
+    }
+}
+
+#[repr(C)]
+pub struct MuxerPriv {
+    pub state: Xxh3State,
+    pub f: *mut libc::FILE,
+}
+
+#[repr(C)]
+pub struct Muxer {
+    pub priv_data_size: c_int,
+    pub name: *const c_char,
+    pub extension: *const c_char,
+    pub write_header: Option<
+        unsafe extern "C" fn(
+            *mut MuxerPriv,
+            *const c_char,
+            *const Dav1dPictureParameters,
+            *const c_uint,
+        ) -> c_int,
+    >,
+    pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
+    pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
+    pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+}
+
+pub type Xxh3Context = MuxerPriv;
+
+unsafe extern "C" fn xxh3_open(
+    xxh3: *mut Xxh3Context,
+    file: *const c_char,
+    _p: *const Dav1dPictureParameters,
+    _fps: *const c_uint,
+) -> c_int {
+    (*xxh3).state = Xxh3State::new();
+    if strcmp(file, b"-\0" as *const u8 as *const c_char) == 0 {
+        (*xxh3).f = stdout;
+    } else {
+        (*xxh3).f = fopen(file, b"wb\0" as *const u8 as *const c_char);
+        if (*xxh3).f.is_null() {
+            fprintf(
+                stderr,
+                b"Failed to open %s: %s\n\0" as *const u8 as *const c_char,
+                file,
+                strerror(*errno_location()),
+            );
+            return -1;
+        }
+    }
+    0
+}
+
+unsafe extern "C" fn xxh3_write(xxh3: *mut Xxh3Context, p: *mut Dav1dPicture) -> c_int {
+    let hbd = ((*p).p.bpc > 8) as c_int;
+    let w = (*p).p.w;
+    let h = (*p).p.h;
+    let mut planes = vec![(0, w, h)];
+    if (*p).p.layout != DAV1D_PIXEL_LAYOUT_I400 {
+        let ss_ver = ((*p).p.layout == DAV1D_PIXEL_LAYOUT_I420) as c_int;
+        let ss_hor = ((*p).p.layout != DAV1D_PIXEL_LAYOUT_I444) as c_int;
+        let cw = w + ss_hor >> ss_hor;
+        let ch = h + ss_ver >> ss_ver;
+        planes.extend([(1, cw, ch), (2, cw, ch)]);
+    }
+    for (pl, pw, ph) in planes {
+        let stride = (*p).stride[(pl != 0) as usize];
+        let mut ptr = (*p).data[pl].map_or_else(ptr::null_mut, NonNull::as_ptr) as *const u8;
+        for _ in 0..ph {
+            (*xxh3)
+                .state
+                .update(slice::from_raw_parts(ptr, (pw << hbd) as usize));
+            ptr = ptr.wrapping_offset(stride);
+        }
+    }
+    dav1d_picture_unref(p);
+    0
+}
+
+unsafe extern "C" fn xxh3_close(xxh3: *mut Xxh3Context) {
+    for byte in (*xxh3).state.canonical() {
+        fprintf(
+            (*xxh3).f,
+            b"%2.2x\0" as *const u8 as *const c_char,
+            byte as c_uint,
+        );
+    }
+    fprintf((*xxh3).f, b"\n\0" as *const u8 as *const c_char);
+    if (*xxh3).f != stdout {
+        fclose((*xxh3).f);
+    }
+}
+
+unsafe extern "C" fn xxh3_verify(xxh3: *mut Xxh3Context, xxh3_str: *const c_char) -> c_int {
+    if strlen(xxh3_str) < 32 {
+        return -1;
+    }
+    let hex = &CStr::from_ptr(xxh3_str).to_bytes()[..32];
+    let mut expected = [0; 16];
+    for (byte, digits) in expected.iter_mut().zip(hex.chunks(2)) {
+        // Like `strtoul`, stop at the first invalid digit.
+        let digits = std::str::from_utf8(digits).unwrap_or("");
+        let end = digits
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(digits.len());
+        *byte = u8::from_str_radix(&digits[..end], 16).unwrap_or(0);
+    }
+    (expected != (*xxh3).state.canonical()) as c_int
+}
+
+#[no_mangle]
+pub static mut xxh3_muxer: Muxer = Muxer {
+    priv_data_size: ::core::mem::size_of::<Xxh3Context>() as c_ulong as c_int,
+    name: b"xxh3\0" as *const u8 as *const c_char,
+    extension: b"xxh3\0" as *const u8 as *const c_char,
+    write_header: Some(xxh3_open),
+    write_picture: Some(xxh3_write),
+    write_data: None,
+    write_trailer: Some(xxh3_close),
+    verify: Some(xxh3_verify),
+    verify_open: None,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::Xxh3State;
+
+    /// The `(len, low64, high64)` test vectors of `XXH128` with the default secret and seed 0,
+    /// from xxHash's `xsum_sanity_check.c`, covering each of the length ranges.
+    const TEST_VECTORS: [(usize, u64, u64); 13] = [
+        (0, 0x6001C324468D497F, 0x99AA06D3014798D8),
+        (1, 0xC44BDFF4074EECDB, 0xA6CD5E9392000F6A),
+        (6, 0x3E7039BDDA43CFC6, 0x082AFE0B8162D12A),
+        (12, 0x061A192713F69AD9, 0x6E3EFD8FC7802B18),
+        (24, 0x1E7044D28B1B901D, 0x0CE966E4678D3761),
+        (48, 0xF942219AED80F67B, 0xA002AC4E5478227E),
+        (81, 0x5E8BAFB9F95FB803, 0x4952F58181AB0042),
+        (222, 0xF1AEBD597CEC6B3A, 0x337E09641B948717),
+        (403, 0xCDEB804D65C6DEA4, 0x1B6DE21E332DD73D),
+        (512, 0x617E49599013CB6B, 0x18D2D110DCC9BCA1),
+        (2048, 0xDD59E2C3A5F038E0, 0xF736557FD47073A5),
+        (2240, 0x6E73A90539CF2948, 0xCCB134FBFA7CE49D),
+        (2367, 0xCB37AEB9E5D361ED, 0xE89C0F6FF369B427),
+    ];
+
+    /// The input the test vectors are hashed from.
+    fn sanity_buffer(len: usize) -> Vec<u8> {
+        const PRIME32: u64 = 2654435761;
+        const PRIME64: u64 = 11400714785074694797;
+        let mut byte_gen = PRIME32;
+        (0..len)
+            .map(|_| {
+                let byte = (byte_gen >> 56) as u8;
+                byte_gen = byte_gen.wrapping_mul(PRIME64);
+                byte
+            })
+            .collect()
+    }
+
+    #[test]
+    fn known_answers() {
+        let buf = sanity_buffer(2367);
+        for (len, lo, hi) in TEST_VECTORS {
+            let mut state = Xxh3State::new();
+            state.update(&buf[..len]);
+            assert_eq!(state.digest(), (lo, hi), "len {len}");
+        }
+    }
+
+    #[test]
+    fn known_answers_streaming() {
+        let buf = sanity_buffer(2367);
+        for (len, lo, hi) in TEST_VECTORS {
+            for chunk in [1, 7, 63, 64, 65, 255, 256, 257, 1000] {
+                let mut state = Xxh3State::new();
+                for chunk in buf[..len].chunks(chunk) {
+                    state.update(chunk);
+                }
+                assert_eq!(state.digest(), (lo, hi), "len {len}, chunks of {chunk}");
+            }
+        }
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(
+            Xxh3State::new().canonical(),
+            0x99AA06D3014798D86001C324468D497Fu128.to_be_bytes(),
+        );
+    }
+}
@@ -7,6 +7,7 @@ use libc::fprintf;
 use libc::fwrite;
 use libc::strcmp;
 use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
 use rav1d::include::dav1d::headers::DAV1D_CHR_UNKNOWN;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
@@ -43,8 +44,10 @@ pub struct Muxer {
         ) -> c_int,
     >,
     pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
     pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
     pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
 }
 
 pub type Y4m2OutputContext = MuxerPriv;
@@ -236,6 +239,8 @@ pub static mut y4m2_muxer: Muxer = Muxer {
     extension: b"y4m\0" as *const u8 as *const c_char,
     write_header: Some(y4m2_open),
     write_picture: Some(y4m2_write),
+    write_data: None,
     write_trailer: Some(y4m2_close),
     verify: None,
+    verify_open: None,
 };
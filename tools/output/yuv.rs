@@ -7,6 +7,7 @@ use libc::fprintf;
 use libc::fwrite;
 use libc::strcmp;
 use libc::strerror;
+use rav1d::include::dav1d::data::Dav1dData;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I400;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I420;
 use rav1d::include::dav1d::headers::DAV1D_PIXEL_LAYOUT_I444;
@@ -40,8 +41,10 @@ pub struct Muxer {
         ) -> c_int,
     >,
     pub write_picture: Option<unsafe extern "C" fn(*mut MuxerPriv, *mut Dav1dPicture) -> c_int>,
+    pub write_data: Option<unsafe extern "C" fn(*mut MuxerPriv, *const Dav1dData) -> c_int>,
     pub write_trailer: Option<unsafe extern "C" fn(*mut MuxerPriv) -> ()>,
     pub verify: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
+    pub verify_open: Option<unsafe extern "C" fn(*mut MuxerPriv, *const c_char) -> c_int>,
 }
 
 pub type YuvOutputContext = MuxerPriv;
@@ -150,6 +153,8 @@ pub static mut yuv_muxer: Muxer = Muxer {
     extension: b"yuv\0" as *const u8 as *const c_char,
     write_header: Some(yuv_open),
     write_picture: Some(yuv_write),
+    write_data: None,
     write_trailer: Some(yuv_close),
     verify: None,
+    verify_open: None,
 };